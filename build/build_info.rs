@@ -1,57 +1,117 @@
-pub const TOKEN_TYPES_AND_PARSE_RULES: [&str; 28] = [
+pub const TOKEN_TYPES_AND_PARSE_RULES: [&str; 74] = [
     // Single-character tokens
-    "LeftParen              = { grouping,           None,               PrecNone       }",
+    "LeftParen              = { grouping,           call,               PrecCall       }",
     "RightParen             = { None,               None,               PrecNone       }",
     "LeftCurlyBrace         = { block,              None,               PrecNone       }",
     "RightCurlyBrace        = { None,               None,               PrecNone       }",
-    "LeftSquareBracket      = { None,               None,               PrecNone       }",
+    "LeftSquareBracket      = { array,              index,              PrecCall       }",
     "RightSquareBracket     = { None,               None,               PrecNone       }",
     "Semicolon              = { None,               None,               PrecNone       }",
     "Bang                   = { unary,              None,               PrecNone       }",
     "Assign                 = { None,               None,               PrecNone       }",
     "Comma                  = { None,               None,               PrecNone       }",
+    "Dot                    = { None,               dot,                PrecCall       }",
+    "Colon                  = { None,               None,               PrecNone       }",
+    "Question               = { None,               None,               PrecNone       }",
 
     // Arithmetic operators
     "Minus                  = { unary,              binary,             PrecTerm       }",
     "Plus                   = { None,               binary,             PrecTerm       }",
     "Slash                  = { None,               binary,             PrecFactor     }",
     "Star                   = { None,               binary,             PrecFactor     }",
-    // "Power                  = { None,          binary, PrecFactor     }",
+    "Percent                = { None,               binary,             PrecFactor     }",
+    "StarStar               = { None,               binary,             PrecPower      }",
+
+    // Compound assignment operators
+    "PlusEqual              = { None,               None,               PrecNone       }",
+    "MinusEqual             = { None,               None,               PrecNone       }",
+    "StarEqual              = { None,               None,               PrecNone       }",
+    "SlashEqual             = { None,               None,               PrecNone       }",
+
+    // Bitwise operators
+    "Ampersand              = { None,               binary,             PrecBitAnd     }",
+    "Pipe                   = { None,               binary,             PrecBitOr      }",
+    "Caret                  = { None,               binary,             PrecBitXor     }",
+    "LessLess               = { None,               binary,             PrecShift      }",
+    "GreaterGreater         = { None,               binary,             PrecShift      }",
+    "Tilde                  = { unary,              None,               PrecNone       }",
+
+    // Comparison operators
+    "EqualEqual             = { None,               binary,             PrecEquality   }",
+    "BangEqual              = { None,               binary,             PrecEquality   }",
+    "Less                   = { None,               binary,             PrecComparison }",
+    "LessEqual              = { None,               binary,             PrecComparison }",
+    "Greater                = { None,               binary,             PrecComparison }",
+    "GreaterEqual           = { None,               binary,             PrecComparison }",
+
+    // Logical operators
+    "And                    = { None,               logical,            PrecAnd        }",
+    "Or                     = { None,               logical,            PrecOr         }",
+    "QuestionQuestion       = { None,               logical,            PrecCoalesce   }",
 
     // Literals
     "Number                 = { number,             None,               PrecNone       }",
+    "String                 = { string,             None,               PrecNone       }",
+    "StringInterpolation    = { string_interpolation, None,             PrecNone       }",
     "Identifier             = { identifier,         None,               PrecNone       }",
 
     // Types
     "True                   = { literal,            None,               PrecNone       }",
     "False                  = { literal,            None,               PrecNone       }",
+    "NoneValue              = { literal,            None,               PrecNone       }",
     "Int32                  = { None,               None,               PrecNone       }",
     "Bool                   = { None,               None,               PrecNone       }",
 
     // Double-character tokens
     "Define                 = { None,               None,               PrecNone       }",
+    "DotDot                 = { None,               None,               PrecNone       }",
+    "FatArrow               = { None,               None,               PrecNone       }",
 
     // Keywords
     "Mutable                = { mut_var_def,        None,               PrecNone       }",
     "Function               = { function,           None,               PrecNone       }",
     "Typing                 = { typing,             None,               PrecNone       }",
-    "Print                  = { None,               None,               PrecNone       }",
-    "Return                 = { None,               None,               PrecNone       }",
+    "Print                  = { print_statement,    None,               PrecNone       }",
+    "Return                 = { return_statement,   None,               PrecNone       }",
+    "If                     = { if_statement,       None,               PrecNone       }",
+    "Else                   = { None,               None,               PrecNone       }",
+    "While                  = { while_statement,    None,               PrecNone       }",
+    "For                    = { for_statement,      None,               PrecNone       }",
+    "In                     = { None,               None,               PrecNone       }",
+    "As                     = { None,               cast,               PrecCast       }",
+    "Break                  = { break_statement,    None,               PrecNone       }",
+    "Continue               = { continue_statement, None,               PrecNone       }",
+    "Class                  = { class_statement,    None,               PrecNone       }",
+    "Enum                   = { enum_statement,     None,               PrecNone       }",
+    "Match                  = { match_expression,   None,               PrecNone       }",
+    "Trait                  = { trait_statement,    None,               PrecNone       }",
+    "Impl                   = { impl_statement,     None,               PrecNone       }",
+    "Import                 = { import_statement,   None,               PrecNone       }",
+    "Public                 = { public,             None,               PrecNone       }",
+    "SelfValue              = { self_value,         None,               PrecNone       }",
+    "SelfType               = { None,               None,               PrecNone       }",
 
     "Error                  = { error,              None,               PrecNone       }",
     "EOF                    = { None,               None,               PrecNone       }",
 ];
 
-pub const PRECEDENCE: [&str; 11] = [
+pub const PRECEDENCE: [&str; 18] = [
     "PrecNone",
     "PrecAssignment",
+    "PrecCoalesce",
     "PrecOr",
     "PrecAnd",
     "PrecEquality",
     "PrecComparison",
+    "PrecBitOr",
+    "PrecBitXor",
+    "PrecBitAnd",
+    "PrecShift",
     "PrecTerm",
     "PrecFactor",
+    "PrecCast",
     "PrecUnary",
+    "PrecPower",
     "PrecCall",
     "PrecPrimary",
 ];
@@ -63,23 +123,67 @@ pub const INSTRUCTION_SRC: [&str; 3] = [
     "IR     VariableRegister(InstructionRegister)",
 ];
 
-pub const BYTECODE_INSTRUCTIONS: [&str; 12] = [
+pub const BYTECODE_INSTRUCTIONS: [&str; 51] = [
     "Halt",
 
     "StartScope",
     "EndScope",
+    "EndScopes          {   count: usize        }",
 
-    // "Print  {   reg: usize      }",
+    "Jump               {   target: usize       }",
+    "JumpIfFalse        {   src: T,     target: usize       }",
+
+    "Call               {   dest: InstructionRegister,      function: usize,        args: Vec<T>        }",
+    "CallTraitMethod    {   dest: InstructionRegister,      method: usize,      args: Vec<T>        }",
+    "CallNative         {   dest: InstructionRegister,      method: NativeMethod,       receiver: T,        args: Vec<T>        }",
+    "CallValue          {   dest: InstructionRegister,      callee: T,      args: Vec<T>        }",
+    "Return             {   src: Option<T>      }",
+
+    "NewClosure         {   dest: InstructionRegister,      function: usize,        function_type: FunctionType,        captures: Vec<Capture>      }",
+    "GetUpvalue         {   dest: InstructionRegister,      index: usize        }",
+    "SetUpvalue         {   index: usize,       src: T      }",
+
+    "Print              {   src: T      }",
 
     "Load               {   reg: InstructionRegister,       src: T      }",
 
+    "NewArray           {   dest: InstructionRegister,      elements: Vec<T>        }",
+    "NewMap             {   dest: InstructionRegister,      entries: Vec<(T, T)>        }",
+    "NewTuple           {   dest: InstructionRegister,      elements: Vec<T>        }",
+    "Index              {   dest: InstructionRegister,      src: T,     index: T        }",
+    "SetIndex           {   array: T,       index: T,       src: T      }",
+    "CallBuiltin        {   dest: InstructionRegister,      function: NativeFunction,       args: Vec<T>        }",
+
+    "Cast               {   dest: InstructionRegister,      src: T,     value_type: ValueType       }",
+
+    "NewInstance        {   dest: InstructionRegister,      layout: Rc<ClassLayout>,       fields: Vec<T>      }",
+    "GetField           {   dest: InstructionRegister,      src: T,     field: usize        }",
+    "SetField           {   object: T,      field: usize,       src: T      }",
+    "NewEnum            {   dest: InstructionRegister,      layout: Rc<EnumLayout>,     tag: usize,     payload: Vec<T>     }",
+    "GetTag             {   dest: InstructionRegister,      src: T      }",
+    "GetPayload         {   dest: InstructionRegister,      src: T,     index: usize        }",
+
     "BINARY     Add    {   dest: InstructionRegister,      src1: T,        src2: T     }",
     "BINARY     Sub    {   dest: InstructionRegister,      src1: T,        src2: T     }",
     "BINARY     Mul    {   dest: InstructionRegister,      src1: T,        src2: T     }",
     "BINARY     Div    {   dest: InstructionRegister,      src1: T,        src2: T     }",
+    "BINARY     Mod    {   dest: InstructionRegister,      src1: T,        src2: T     }",
+    "BINARY     Pow    {   dest: InstructionRegister,      src1: T,        src2: T     }",
+    "BINARY     BitAnd          {   dest: InstructionRegister,      src1: T,        src2: T     }",
+    "BINARY     BitOr           {   dest: InstructionRegister,      src1: T,        src2: T     }",
+    "BINARY     BitXor          {   dest: InstructionRegister,      src1: T,        src2: T     }",
+    "BINARY     Shl             {   dest: InstructionRegister,      src1: T,        src2: T     }",
+    "BINARY     Shr             {   dest: InstructionRegister,      src1: T,        src2: T     }",
+    "BINARY     Equal           {   dest: InstructionRegister,      src1: T,        src2: T     }",
+    "BINARY     NotEqual        {   dest: InstructionRegister,      src1: T,        src2: T     }",
+    "BINARY     Less            {   dest: InstructionRegister,      src1: T,        src2: T     }",
+    "BINARY     LessEqual       {   dest: InstructionRegister,      src1: T,        src2: T     }",
+    "BINARY     Greater         {   dest: InstructionRegister,      src1: T,        src2: T     }",
+    "BINARY     GreaterEqual    {   dest: InstructionRegister,      src1: T,        src2: T     }",
 
     "UNARY      Neg    {   dest: InstructionRegister,      src: T      }",
     "UNARY      Truthy {   dest: InstructionRegister,      src: T      }",
+    "UNARY      BitNot {   dest: InstructionRegister,      src: T      }",
 
     "DEFINEMENT Define {   dest: InstructionRegister,      src: T      }",
     "ASSIGNMENT Assign {   dest: InstructionRegister,      src: T      }",
@@ -1,16 +1,38 @@
+use std::rc::Rc;
+
 use crate::{
     compiler::cfg::dag::{ DAGNode, DAGOp, DAG },
-    operations::{ BinaryOp, UnaryOp },
-    parser::{ ast_generator::AstEnvironment, token::TokenMetadata },
-    value::{ Value, ValueType },
+    operations::{ BinaryOp, LogicalOp, UnaryOp },
+    parser::{
+        ast_generator::{ AstEnvironment, ClassMethod, FunctionSignature, InitState },
+        token::TokenMetadata,
+    },
+    value::{ ClassLayout, ClassType, EnumLayout, EnumType, FunctionType, Value, ValueType },
+    value_v2::{ Builtin, TO_STRING },
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Expr {
     BinaryExpr(BinaryExpr),
     UnaryExpr(UnaryExpr),
+    LogicalExpr(LogicalExpr),
     Literal(AstValue),
     IdentifierLookup(AstIdentifier),
+    CallExpr(CallExpr),
+    CallValueExpr(CallValueExpr),
+    BuiltinCallExpr(BuiltinCallExpr),
+    ClosureExpr(ClosureExpr),
+    ArrayExpr(ArrayExpr),
+    MapExpr(MapExpr),
+    InterpolationExpr(InterpolationExpr),
+    TupleExpr(TupleExpr),
+    IndexExpr(IndexExpr),
+    InstanceExpr(InstanceExpr),
+    MemberAccessExpr(MemberAccessExpr),
+    MethodCallExpr(MethodCallExpr),
+    CastExpr(CastExpr),
+    EnumVariantExpr(EnumVariantExpr),
+    MatchExpr(MatchExpr),
 }
 
 impl Expr {
@@ -28,8 +50,24 @@ impl Expr {
         match self {
             Expr::BinaryExpr(expr) => expr.compile_to_dag_node(dag),
             Expr::UnaryExpr(expr) => expr.compile_to_dag_node(dag),
+            Expr::LogicalExpr(expr) => expr.compile_to_dag_node(dag),
             Expr::Literal(ast_value) => ast_value.compile_to_dag_node(dag),
             Expr::IdentifierLookup(ast_identifier) => ast_identifier.compile_to_dag_node(dag),
+            Expr::CallExpr(expr) => expr.compile_to_dag_node(dag),
+            Expr::CallValueExpr(expr) => expr.compile_to_dag_node(dag),
+            Expr::BuiltinCallExpr(expr) => expr.compile_to_dag_node(dag),
+            Expr::ClosureExpr(expr) => expr.compile_to_dag_node(dag),
+            Expr::ArrayExpr(expr) => expr.compile_to_dag_node(dag),
+            Expr::MapExpr(expr) => expr.compile_to_dag_node(dag),
+            Expr::InterpolationExpr(expr) => expr.compile_to_dag_node(dag),
+            Expr::TupleExpr(expr) => expr.compile_to_dag_node(dag),
+            Expr::IndexExpr(expr) => expr.compile_to_dag_node(dag),
+            Expr::InstanceExpr(expr) => expr.compile_to_dag_node(dag),
+            Expr::MemberAccessExpr(expr) => expr.compile_to_dag_node(dag),
+            Expr::MethodCallExpr(expr) => expr.compile_to_dag_node(dag),
+            Expr::CastExpr(expr) => expr.compile_to_dag_node(dag),
+            Expr::EnumVariantExpr(expr) => expr.compile_to_dag_node(dag),
+            Expr::MatchExpr(expr) => expr.compile_to_dag_node(dag),
         }
     }
 
@@ -41,15 +79,52 @@ impl Expr {
         match self {
             Expr::BinaryExpr(expr) => expr.type_check(ast_environemtn, token_vec),
             Expr::UnaryExpr(expr) => expr.type_check(ast_environemtn, token_vec),
+            Expr::LogicalExpr(expr) => expr.type_check(ast_environemtn, token_vec),
             Expr::Literal(ast_value) => Ok(ast_value.value.to_value_type()),
             Expr::IdentifierLookup(ast_identifier) => {
-                if let Some((value_type, _, _)) = ast_environemtn.get(&ast_identifier.lexeme) {
-                    Ok(value_type)
-                } else {
-                    token_vec.push(ast_identifier.token_metadata);
-                    return Err(format!("Undefined variable: '{}'", ast_identifier.lexeme));
+                match ast_environemtn.get(&ast_identifier.lexeme) {
+                    Some((value_type, _, true)) => Ok(value_type),
+                    Some(_) => {
+                        token_vec.push(ast_identifier.token_metadata);
+
+                        match ast_environemtn.get_init_state(&ast_identifier.lexeme) {
+                            Some(InitState::MaybeInitialized) =>
+                                Err(
+                                    format!(
+                                        "Variable '{}' might not be initialized here",
+                                        ast_identifier.lexeme
+                                    )
+                                ),
+                            _ =>
+                                Err(
+                                    format!(
+                                        "Variable '{}' is used before it's initialized",
+                                        ast_identifier.lexeme
+                                    )
+                                ),
+                        }
+                    }
+                    None => {
+                        token_vec.push(ast_identifier.token_metadata);
+                        Err(format!("Undefined variable: '{}'", ast_identifier.lexeme))
+                    }
                 }
             }
+            Expr::CallExpr(expr) => expr.type_check(ast_environemtn, token_vec),
+            Expr::CallValueExpr(expr) => expr.type_check(ast_environemtn, token_vec),
+            Expr::BuiltinCallExpr(expr) => expr.type_check(ast_environemtn, token_vec),
+            Expr::ClosureExpr(expr) => Ok(ValueType::Function(expr.function_type.clone())),
+            Expr::ArrayExpr(expr) => expr.type_check(ast_environemtn, token_vec),
+            Expr::MapExpr(expr) => expr.type_check(ast_environemtn, token_vec),
+            Expr::InterpolationExpr(expr) => expr.type_check(ast_environemtn, token_vec),
+            Expr::TupleExpr(expr) => expr.type_check(ast_environemtn, token_vec),
+            Expr::IndexExpr(expr) => expr.type_check(ast_environemtn, token_vec),
+            Expr::InstanceExpr(expr) => expr.type_check(ast_environemtn, token_vec),
+            Expr::MemberAccessExpr(expr) => expr.type_check(ast_environemtn, token_vec),
+            Expr::MethodCallExpr(expr) => expr.type_check(ast_environemtn, token_vec),
+            Expr::CastExpr(expr) => expr.type_check(ast_environemtn, token_vec),
+            Expr::EnumVariantExpr(expr) => expr.type_check(ast_environemtn, token_vec),
+            Expr::MatchExpr(expr) => expr.type_check(ast_environemtn, token_vec),
         }
     }
 
@@ -57,13 +132,194 @@ impl Expr {
         match self {
             Expr::BinaryExpr(expr) => expr.push_to_token_vec(token_vec),
             Expr::UnaryExpr(expr) => expr.push_to_token_vec(token_vec),
+            Expr::LogicalExpr(expr) => expr.push_to_token_vec(token_vec),
             Expr::Literal(ast_value) => ast_value.push_to_token_vec(token_vec),
             Expr::IdentifierLookup(ast_identifier) => ast_identifier.push_to_token_vec(token_vec),
+            Expr::CallExpr(expr) => expr.push_to_token_vec(token_vec),
+            Expr::CallValueExpr(expr) => expr.push_to_token_vec(token_vec),
+            Expr::BuiltinCallExpr(expr) => expr.push_to_token_vec(token_vec),
+            Expr::ClosureExpr(expr) => expr.push_to_token_vec(token_vec),
+            Expr::ArrayExpr(expr) => expr.push_to_token_vec(token_vec),
+            Expr::MapExpr(expr) => expr.push_to_token_vec(token_vec),
+            Expr::InterpolationExpr(expr) => expr.push_to_token_vec(token_vec),
+            Expr::TupleExpr(expr) => expr.push_to_token_vec(token_vec),
+            Expr::IndexExpr(expr) => expr.push_to_token_vec(token_vec),
+            Expr::InstanceExpr(expr) => expr.push_to_token_vec(token_vec),
+            Expr::MemberAccessExpr(expr) => expr.push_to_token_vec(token_vec),
+            Expr::MethodCallExpr(expr) => expr.push_to_token_vec(token_vec),
+            Expr::CastExpr(expr) => expr.push_to_token_vec(token_vec),
+            Expr::EnumVariantExpr(expr) => expr.push_to_token_vec(token_vec),
+            Expr::MatchExpr(expr) => expr.push_to_token_vec(token_vec),
+        }
+    }
+
+    pub fn any_sub_expr(&self, predicate: &dyn Fn(&Expr) -> bool) -> bool {
+        if predicate(self) {
+            return true;
+        }
+
+        match self {
+            Expr::BinaryExpr(expr) => {
+                expr.left.any_sub_expr(predicate) || expr.right.any_sub_expr(predicate)
+            }
+            Expr::UnaryExpr(expr) => expr.right.any_sub_expr(predicate),
+            Expr::LogicalExpr(expr) => {
+                expr.left.any_sub_expr(predicate) || expr.right.any_sub_expr(predicate)
+            }
+            Expr::CallExpr(expr) => {
+                expr.args.iter().chain(&expr.trait_impls).any(|arg| arg.any_sub_expr(predicate))
+            }
+            Expr::CallValueExpr(expr) => {
+                expr.callee.any_sub_expr(predicate) ||
+                    expr.args.iter().any(|arg| arg.any_sub_expr(predicate))
+            }
+            Expr::BuiltinCallExpr(expr) => {
+                expr.args.iter().any(|arg| arg.any_sub_expr(predicate))
+            }
+            Expr::ArrayExpr(expr) => {
+                expr.elements.iter().any(|element| element.any_sub_expr(predicate))
+            }
+            Expr::MapExpr(expr) => {
+                expr.entries
+                    .iter()
+                    .any(|(key, value)| key.any_sub_expr(predicate) || value.any_sub_expr(predicate))
+            }
+            Expr::InterpolationExpr(expr) => expr.parts.iter().any(|part| part.any_sub_expr(predicate)),
+            Expr::TupleExpr(expr) => {
+                expr.elements.iter().any(|element| element.any_sub_expr(predicate))
+            }
+            Expr::IndexExpr(expr) => {
+                expr.target.any_sub_expr(predicate) || expr.index.any_sub_expr(predicate)
+            }
+            Expr::InstanceExpr(expr) => expr.fields.iter().any(|field| field.any_sub_expr(predicate)),
+            Expr::MemberAccessExpr(expr) => expr.target.any_sub_expr(predicate),
+            Expr::MethodCallExpr(expr) => {
+                expr.receiver.any_sub_expr(predicate) ||
+                    expr.args.iter().chain(&expr.trait_impls).any(|arg| arg.any_sub_expr(predicate))
+            }
+            Expr::CastExpr(expr) => expr.value.any_sub_expr(predicate),
+            Expr::EnumVariantExpr(expr) => {
+                expr.payload.iter().any(|value| value.any_sub_expr(predicate))
+            }
+            Expr::MatchExpr(expr) => {
+                expr.value.any_sub_expr(predicate) ||
+                    expr.arms.iter().any(|arm| arm.body.any_sub_expr(predicate))
+            }
+            Expr::Literal(_) | Expr::IdentifierLookup(_) | Expr::ClosureExpr(_) => false,
+        }
+    }
+
+    pub fn is_number_literal(&self) -> bool {
+        match self {
+            Expr::Literal(ast_value) => ast_value.value.to_value_type().is_numeric(),
+            Expr::UnaryExpr(UnaryExpr { operator: UnaryOp::Neg, right }) => {
+                right.is_number_literal()
+            }
+            _ => false,
+        }
+    }
+
+    // Literals that don't fit are left as they are, and reported as a type mismatch
+    pub fn coerce_literal(&mut self, value_type: &ValueType) {
+        match (&mut *self, value_type) {
+            (_, ValueType::Optional(inner_type)) => self.coerce_literal(inner_type),
+            (Expr::Literal(ast_value), value_type) => {
+                if let Some(value) = ast_value.value.convert_literal(value_type) {
+                    ast_value.value = value;
+                }
+            }
+            (Expr::UnaryExpr(UnaryExpr { operator: UnaryOp::Neg, right }), value_type) => {
+                let Expr::Literal(ast_value) = right.as_mut() else {
+                    right.coerce_literal(value_type);
+                    return;
+                };
+
+                // The literal is negated here, since `-128` fits in an i8 even though `128` doesn't
+                let negated = ast_value.value
+                    .neg()
+                    .ok()
+                    .and_then(|value| value.convert_literal(value_type));
+
+                if let Some(value) = negated {
+                    let mut token_metadata = ast_value.token_metadata;
+                    for _ in 0..UnaryOp::Neg.get_op_len() {
+                        token_metadata.decrement_start();
+                        token_metadata.increment_length();
+                    }
+
+                    *self = Expr::Literal(AstValue::new(value, token_metadata));
+                }
+            }
+            (Expr::BinaryExpr(binary_expr), value_type) if binary_expr.operator.is_arithmetic() => {
+                binary_expr.left.coerce_literal(value_type);
+                binary_expr.right.coerce_literal(value_type);
+            }
+            (Expr::ArrayExpr(array_expr), ValueType::Array(element_type)) => {
+                for element in array_expr.elements.iter_mut() {
+                    element.coerce_literal(element_type);
+                }
+            }
+            (Expr::MapExpr(map_expr), ValueType::Map(key_type, value_type)) => {
+                for (key, value) in map_expr.entries.iter_mut() {
+                    key.coerce_literal(key_type);
+                    value.coerce_literal(value_type);
+                }
+            }
+            (Expr::TupleExpr(tuple_expr), ValueType::Tuple(element_types)) => {
+                for (element, element_type) in tuple_expr.elements.iter_mut().zip(element_types) {
+                    element.coerce_literal(element_type);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // For `a.b[0]` it's `a`
+    pub fn get_root_identifier(&self) -> Option<&AstIdentifier> {
+        match self {
+            Expr::IdentifierLookup(ast_identifier) => Some(ast_identifier),
+            Expr::IndexExpr(index_expr) => index_expr.target.get_root_identifier(),
+            Expr::MemberAccessExpr(member_access_expr) => {
+                member_access_expr.target.get_root_identifier()
+            }
+            _ => None,
+        }
+    }
+
+    pub fn type_check_class(
+        &self,
+        member: &AstIdentifier,
+        ast_environment: &AstEnvironment,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ClassType, String> {
+        let value_type = self.type_check(ast_environment, token_vec)?;
+        self.expect_class(value_type, member, token_vec)
+    }
+
+    fn expect_class(
+        &self,
+        value_type: ValueType,
+        member: &AstIdentifier,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ClassType, String> {
+        match value_type {
+            ValueType::Class(class_type) => Ok(class_type),
+            value_type => {
+                member.push_to_token_vec(token_vec);
+                self.push_to_token_vec(token_vec);
+                Err(
+                    format!(
+                        "Cannot access member '{}' on a value of type {}",
+                        member.lexeme,
+                        value_type.to_type_string()
+                    )
+                )
+            }
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BinaryExpr {
     pub left: Box<Expr>,
     pub operator: BinaryOp,
@@ -77,14 +333,19 @@ impl BinaryExpr {
 
         let op = DAGOp::BinaryOp(self.operator);
 
-        let mut dag_node = DAGNode::new(op, Some(vec![left_dag, right_dag]));
+        let mut token_vec = Vec::new();
+        self.push_to_token_vec(&mut token_vec);
+
+        let dag_node = DAGNode::new(op, Some(vec![left_dag, right_dag])).with_location(
+            TokenMetadata::span(&token_vec)
+        );
 
         dag.add_node(dag_node)
     }
 
     pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
-        self.left.push_to_token_vec(token_vec);
         self.right.push_to_token_vec(token_vec);
+        self.left.push_to_token_vec(token_vec);
     }
 
     pub fn type_check(
@@ -98,15 +359,14 @@ impl BinaryExpr {
         match left_type.type_check_binary(&right_type, self.operator) {
             Ok(v) => Ok(v),
             Err(e) => {
-                self.left.push_to_token_vec(token_vec);
-                self.right.push_to_token_vec(token_vec);
+                self.push_to_token_vec(token_vec);
                 Err(e)
             }
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct UnaryExpr {
     pub operator: UnaryOp,
     pub right: Box<Expr>,
@@ -118,7 +378,18 @@ impl UnaryExpr {
 
         let op = DAGOp::UnaryOp(self.operator);
 
-        let mut dag_node = DAGNode::new(op, Some(vec![right_dag]));
+        let mut token_vec = Vec::new();
+        self.right.push_to_token_vec(&mut token_vec);
+        // The span starts at the operator, which isn't part of the token vector
+        let location = TokenMetadata::span(&token_vec).map(|mut token_metadata| {
+            for _ in 0..self.operator.get_op_len() {
+                token_metadata.decrement_start();
+                token_metadata.increment_length();
+            }
+            token_metadata
+        });
+
+        let dag_node = DAGNode::new(op, Some(vec![right_dag])).with_location(location);
 
         dag.add_node(dag_node)
     }
@@ -151,34 +422,1045 @@ impl UnaryExpr {
     }
 }
 
+// Lowered to control flow, since the right operand is only evaluated when needed
 #[derive(Debug, Clone)]
-pub struct AstValue {
-    pub value: Value,
+pub struct LogicalExpr {
+    pub left: Box<Expr>,
+    pub operator: LogicalOp,
+    pub right: Box<Expr>,
     pub token_metadata: TokenMetadata,
 }
 
-impl AstValue {
+impl LogicalExpr {
+    pub fn compile_to_dag_node(&self, _dag: &mut DAG) -> usize {
+        unreachable!("Logical expressions are lowered before DAGs are generated")
+    }
+
+    pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
+        self.right.push_to_token_vec(token_vec);
+        self.left.push_to_token_vec(token_vec);
+    }
+
+    pub fn type_check(
+        &self,
+        ast_environment: &AstEnvironment,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ValueType, String> {
+        let left_type = self.left.type_check(ast_environment, token_vec)?;
+        let right_type = self.right.type_check(ast_environment, token_vec)?;
+
+        if let LogicalOp::Coalesce = self.operator {
+            return self.type_check_coalesce(left_type, right_type, token_vec);
+        }
+
+        if left_type.is(&ValueType::Bool) && right_type.is(&ValueType::Bool) {
+            Ok(ValueType::Bool)
+        } else {
+            self.push_to_token_vec(token_vec);
+            Err(
+                format!(
+                    "{} is not defined for {} and {}",
+                    self.operator.to_op_string(),
+                    left_type.to_type_string(),
+                    right_type.to_type_string()
+                )
+            )
+        }
+    }
+
+    fn type_check_coalesce(
+        &self,
+        left_type: ValueType,
+        right_type: ValueType,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ValueType, String> {
+        let ValueType::Optional(inner_type) = &left_type else {
+            self.left.push_to_token_vec(token_vec);
+            return Err(
+                format!("Expected an optional before '??' but got {}", left_type.to_type_string())
+            );
+        };
+
+        if right_type.is(inner_type) {
+            Ok(*inner_type.clone())
+        } else if right_type.is(&left_type) {
+            Ok(left_type)
+        } else {
+            self.push_to_token_vec(token_vec);
+            Err(
+                format!(
+                    "{} is not defined for {} and {}",
+                    self.operator.to_op_string(),
+                    left_type.to_type_string(),
+                    right_type.to_type_string()
+                )
+            )
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CallExpr {
+    pub callee: AstIdentifier,
+    pub args: Vec<Expr>,
+    pub signature: Option<FunctionSignature>,
+    // The trait bound implementations for the type arguments, passed after the arguments
+    pub trait_impls: Vec<Expr>,
+}
+
+impl CallExpr {
+    pub fn new(
+        callee: AstIdentifier,
+        args: Vec<Expr>,
+        signature: Option<FunctionSignature>,
+        trait_impls: Vec<Expr>
+    ) -> Self {
+        Self {
+            callee,
+            args,
+            signature,
+            trait_impls,
+        }
+    }
+
     pub fn compile_to_dag_node(&self, dag: &mut DAG) -> usize {
-        let op = DAGOp::Const(self.value.clone());
+        let arg_dags = self.args
+            .iter()
+            .chain(&self.trait_impls)
+            .map(|arg| arg.compile_to_dag_node(dag))
+            .collect::<Vec<_>>();
 
-        let mut dag_node = DAGNode::new(op, None);
+        let function_id = self.signature.as_ref().expect("Call to undefined function").id;
+
+        let op = DAGOp::Call(function_id);
+
+        let dag_node = DAGNode::new(op, Some(arg_dags));
 
         dag.add_node(dag_node)
     }
 
-    pub fn new(value: Value, token_metadata: TokenMetadata) -> Self {
-        Self {
-            value,
-            token_metadata,
+    pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
+        for arg in self.args.iter().rev() {
+            arg.push_to_token_vec(token_vec);
         }
+        self.callee.push_to_token_vec(token_vec);
     }
 
-    pub fn get_value(&self) -> Value {
-        self.value.clone()
+    pub fn type_check(
+        &self,
+        ast_environment: &AstEnvironment,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ValueType, String> {
+        let signature = match &self.signature {
+            Some(signature) => signature,
+            None => {
+                token_vec.push(self.callee.token_metadata);
+                return Err(format!("Undefined function: '{}'", self.callee.lexeme));
+            }
+        };
+
+        if self.args.len() != signature.parameters.len() {
+            self.push_to_token_vec(token_vec);
+            return Err(
+                format!(
+                    "Function '{}' expects {} argument(s) but got {}",
+                    self.callee.lexeme,
+                    signature.parameters.len(),
+                    self.args.len()
+                )
+            );
+        }
+
+        for (arg, parameter_type) in self.args.iter().zip(&signature.parameters) {
+            let arg_type = arg.type_check(ast_environment, token_vec)?;
+
+            if !arg_type.is(parameter_type) {
+                arg.push_to_token_vec(token_vec);
+                return Err(
+                    format!(
+                        "Function '{}' expects an argument of type {} but got {}",
+                        self.callee.lexeme,
+                        parameter_type.to_type_string(),
+                        arg_type.to_type_string()
+                    )
+                );
+            }
+        }
+
+        Ok(signature.return_type.clone())
     }
+}
 
-    pub fn get_token_metadata(&self) -> TokenMetadata {
-        self.token_metadata
+#[derive(Debug, Clone)]
+pub struct CallValueExpr {
+    pub callee: Box<Expr>,
+    pub args: Vec<Expr>,
+    pub function_type: FunctionType,
+    pub paren_metadata: TokenMetadata,
+}
+
+impl CallValueExpr {
+    pub fn compile_to_dag_node(&self, dag: &mut DAG) -> usize {
+        let mut operand_dags = vec![self.callee.compile_to_dag_node(dag)];
+        operand_dags.extend(self.args.iter().map(|arg| arg.compile_to_dag_node(dag)));
+
+        let dag_node = DAGNode::new(DAGOp::CallValue, Some(operand_dags));
+
+        dag.add_node(dag_node)
+    }
+
+    pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
+        for arg in self.args.iter().rev() {
+            arg.push_to_token_vec(token_vec);
+        }
+        token_vec.push(self.paren_metadata);
+        self.callee.push_to_token_vec(token_vec);
+    }
+
+    pub fn type_check(
+        &self,
+        ast_environment: &AstEnvironment,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ValueType, String> {
+        self.callee.type_check(ast_environment, token_vec)?;
+
+        let parameters = &self.function_type.parameters;
+
+        if self.args.len() != parameters.len() {
+            self.push_to_token_vec(token_vec);
+            return Err(
+                format!(
+                    "Function of type {} expects {} argument(s) but got {}",
+                    self.function_type.to_type_string(),
+                    parameters.len(),
+                    self.args.len()
+                )
+            );
+        }
+
+        for (arg, parameter_type) in self.args.iter().zip(parameters) {
+            let arg_type = arg.type_check(ast_environment, token_vec)?;
+
+            if !arg_type.is(parameter_type) {
+                arg.push_to_token_vec(token_vec);
+                return Err(
+                    format!(
+                        "Function of type {} expects an argument of type {} but got {}",
+                        self.function_type.to_type_string(),
+                        parameter_type.to_type_string(),
+                        arg_type.to_type_string()
+                    )
+                );
+            }
+        }
+
+        Ok(*self.function_type.return_type.clone())
+    }
+}
+
+// The body is compiled as its own function
+#[derive(Debug, Clone)]
+pub struct ClosureExpr {
+    pub function: usize,
+    pub captures: Vec<String>,
+    pub function_type: FunctionType,
+    pub token_metadata: TokenMetadata,
+}
+
+impl ClosureExpr {
+    pub fn compile_to_dag_node(&self, dag: &mut DAG) -> usize {
+        let op = DAGOp::Closure {
+            function: self.function,
+            function_type: self.function_type.clone(),
+            captures: self.captures.clone(),
+        };
+
+        dag.add_node(DAGNode::new(op, None))
+    }
+
+    pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
+        token_vec.push(self.token_metadata);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BuiltinCallExpr {
+    pub builtin: Builtin,
+    pub args: Vec<Expr>,
+    pub token_metadata: TokenMetadata,
+}
+
+impl BuiltinCallExpr {
+    pub fn compile_to_dag_node(&self, dag: &mut DAG) -> usize {
+        let arg_dags = self.args
+            .iter()
+            .map(|arg| arg.compile_to_dag_node(dag))
+            .collect::<Vec<_>>();
+
+        // Builtins can fail at runtime, in which case the error points at the name
+        let dag_node = DAGNode::new(DAGOp::Builtin(self.builtin.native), Some(arg_dags)).with_location(
+            Some(self.token_metadata)
+        );
+
+        dag.add_node(dag_node)
+    }
+
+    pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
+        for arg in self.args.iter().rev() {
+            arg.push_to_token_vec(token_vec);
+        }
+        token_vec.push(self.token_metadata);
+    }
+
+    pub fn type_check(
+        &self,
+        ast_environment: &AstEnvironment,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ValueType, String> {
+        let name = self.builtin.get_name();
+
+        if self.args.len() != self.builtin.parameters.len() {
+            self.push_to_token_vec(token_vec);
+            return Err(
+                format!(
+                    "Function '{}' expects {} argument(s) but got {}",
+                    name,
+                    self.builtin.parameters.len(),
+                    self.args.len()
+                )
+            );
+        }
+
+        let arg_types = self.args
+            .iter()
+            .map(|arg| arg.type_check(ast_environment, token_vec))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if self.builtin.mutates_first_arg {
+            if let Some(root_identifier) = self.args[0].get_root_identifier() {
+                if let Some((_, false, _)) = ast_environment.get(&root_identifier.lexeme) {
+                    self.args[0].push_to_token_vec(token_vec);
+                    return Err(
+                        format!(
+                            "Cannot pass immutable variable '{}' to '{}', since it changes the argument",
+                            root_identifier.lexeme,
+                            name
+                        )
+                    );
+                }
+            }
+        }
+
+        // `T` stays unknown if it can't be inferred, like from an empty array
+        let type_arg = self.builtin
+            .infer_type_arg(arg_types.iter().enumerate())
+            .unwrap_or(ValueType::Empty);
+
+        if type_arg != ValueType::Empty && !self.builtin.type_bound.allows(&type_arg) {
+            let arg_index = self.builtin.parameters
+                .iter()
+                .position(|parameter| parameter.contains_type_param())
+                .unwrap_or(0);
+
+            self.args[arg_index].push_to_token_vec(token_vec);
+            return Err(
+                format!(
+                    "Function '{}' expects {} but got {}",
+                    name,
+                    self.builtin.type_bound.get_description(),
+                    type_arg.to_type_string()
+                )
+            );
+        }
+
+        let (parameters, return_type) = self.builtin.resolve_types(&type_arg);
+
+        for ((arg, arg_type), parameter_type) in self.args.iter().zip(&arg_types).zip(&parameters) {
+            if !arg_type.is(parameter_type) {
+                arg.push_to_token_vec(token_vec);
+                return Err(
+                    format!(
+                        "Function '{}' expects an argument of type {} but got {}",
+                        name,
+                        parameter_type.to_type_string(),
+                        arg_type.to_type_string()
+                    )
+                );
+            }
+        }
+
+        Ok(return_type)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ArrayExpr {
+    pub elements: Vec<Expr>,
+    pub token_metadata: TokenMetadata,
+}
+
+impl ArrayExpr {
+    pub fn compile_to_dag_node(&self, dag: &mut DAG) -> usize {
+        let element_dags = self.elements
+            .iter()
+            .map(|element| element.compile_to_dag_node(dag))
+            .collect::<Vec<_>>();
+
+        let dag_node = DAGNode::new(DAGOp::Array, Some(element_dags));
+
+        dag.add_node(dag_node)
+    }
+
+    pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
+        for element in self.elements.iter().rev() {
+            element.push_to_token_vec(token_vec);
+        }
+        token_vec.push(self.token_metadata);
+    }
+
+    // An empty array has the element type empty, which fits any array type
+    pub fn type_check(
+        &self,
+        ast_environment: &AstEnvironment,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ValueType, String> {
+        let mut element_type = ValueType::Empty;
+
+        for (i, element) in self.elements.iter().enumerate() {
+            let next_element_type = element.type_check(ast_environment, token_vec)?;
+
+            if i > 0 && !next_element_type.is(&element_type) {
+                element.push_to_token_vec(token_vec);
+                return Err(
+                    format!(
+                        "Array elements must be of the same type, but got {} and {}",
+                        element_type.to_type_string(),
+                        next_element_type.to_type_string()
+                    )
+                );
+            }
+
+            // Nested empty arrays don't tell the full element type
+            if i == 0 || element_type.has_unknown_element_type() {
+                element_type = next_element_type;
+            }
+        }
+
+        Ok(ValueType::Array(Box::new(element_type)))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MapExpr {
+    pub entries: Vec<(Expr, Expr)>,
+    pub token_metadata: TokenMetadata,
+}
+
+impl MapExpr {
+    pub fn compile_to_dag_node(&self, dag: &mut DAG) -> usize {
+        let entry_dags = self.entries
+            .iter()
+            .flat_map(|(key, value)| [key.compile_to_dag_node(dag), value.compile_to_dag_node(dag)])
+            .collect::<Vec<_>>();
+
+        let dag_node = DAGNode::new(DAGOp::Map, Some(entry_dags));
+
+        dag.add_node(dag_node)
+    }
+
+    pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
+        for (key, value) in self.entries.iter().rev() {
+            value.push_to_token_vec(token_vec);
+            key.push_to_token_vec(token_vec);
+        }
+        token_vec.push(self.token_metadata);
+    }
+
+    pub fn type_check(
+        &self,
+        ast_environment: &AstEnvironment,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ValueType, String> {
+        let mut key_type = ValueType::Empty;
+        let mut value_type = ValueType::Empty;
+
+        for (i, (key, value)) in self.entries.iter().enumerate() {
+            let next_key_type = key.type_check(ast_environment, token_vec)?;
+            let next_value_type = value.type_check(ast_environment, token_vec)?;
+
+            if !next_key_type.is_hashable() {
+                key.push_to_token_vec(token_vec);
+                return Err(
+                    format!(
+                        "Map keys must be integers, bools or strings, but got {}",
+                        next_key_type.to_type_string()
+                    )
+                );
+            }
+
+            if i > 0 && !next_key_type.is(&key_type) {
+                key.push_to_token_vec(token_vec);
+                return Err(
+                    format!(
+                        "Map keys must be of the same type, but got {} and {}",
+                        key_type.to_type_string(),
+                        next_key_type.to_type_string()
+                    )
+                );
+            }
+
+            if i > 0 && !next_value_type.is(&value_type) {
+                value.push_to_token_vec(token_vec);
+                return Err(
+                    format!(
+                        "Map values must be of the same type, but got {} and {}",
+                        value_type.to_type_string(),
+                        next_value_type.to_type_string()
+                    )
+                );
+            }
+
+            key_type = next_key_type;
+            // Nested empty arrays and maps don't tell the full value type
+            if i == 0 || value_type.has_unknown_element_type() {
+                value_type = next_value_type;
+            }
+        }
+
+        Ok(ValueType::Map(Box::new(key_type), Box::new(value_type)))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InterpolationExpr {
+    pub parts: Vec<Expr>,
+    pub token_metadata: TokenMetadata,
+}
+
+impl InterpolationExpr {
+    pub fn compile_to_dag_node(&self, dag: &mut DAG) -> usize {
+        let mut part_dags = self.parts
+            .iter()
+            .map(|part| {
+                let part_dag = part.compile_to_dag_node(dag);
+
+                match part {
+                    Expr::Literal(AstValue { value: Value::String(_), .. }) => part_dag,
+                    _ => dag.add_node(DAGNode::new(DAGOp::Builtin(TO_STRING), Some(vec![part_dag]))),
+                }
+            })
+            .collect::<Vec<_>>()
+            .into_iter();
+
+        let Some(first_dag) = part_dags.next() else {
+            return dag.add_node(DAGNode::new(DAGOp::Const(Value::String("".into())), None));
+        };
+
+        part_dags.fold(first_dag, |left_dag, right_dag| {
+            dag.add_node(
+                DAGNode::new(DAGOp::BinaryOp(BinaryOp::Add), Some(vec![left_dag, right_dag]))
+            )
+        })
+    }
+
+    pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
+        for part in self.parts.iter().rev() {
+            part.push_to_token_vec(token_vec);
+        }
+        token_vec.push(self.token_metadata);
+    }
+
+    pub fn type_check(
+        &self,
+        ast_environment: &AstEnvironment,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ValueType, String> {
+        for part in &self.parts {
+            if part.type_check(ast_environment, token_vec)? == ValueType::Void {
+                part.push_to_token_vec(token_vec);
+                return Err("Cannot interpolate a value of type void".to_string());
+            }
+        }
+
+        Ok(ValueType::String)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TupleExpr {
+    pub elements: Vec<Expr>,
+    pub token_metadata: TokenMetadata,
+}
+
+impl TupleExpr {
+    pub fn compile_to_dag_node(&self, dag: &mut DAG) -> usize {
+        let element_dags = self.elements
+            .iter()
+            .map(|element| element.compile_to_dag_node(dag))
+            .collect::<Vec<_>>();
+
+        let dag_node = DAGNode::new(DAGOp::Tuple, Some(element_dags));
+
+        dag.add_node(dag_node)
+    }
+
+    pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
+        for element in self.elements.iter().rev() {
+            element.push_to_token_vec(token_vec);
+        }
+        token_vec.push(self.token_metadata);
+    }
+
+    pub fn type_check(
+        &self,
+        ast_environment: &AstEnvironment,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ValueType, String> {
+        let mut element_types = Vec::with_capacity(self.elements.len());
+
+        for element in self.elements.iter() {
+            let element_type = element.type_check(ast_environment, token_vec)?;
+
+            if element_type.is(&ValueType::Void) {
+                element.push_to_token_vec(token_vec);
+                return Err("Tuple elements cannot be of type void".to_string());
+            }
+
+            element_types.push(element_type);
+        }
+
+        Ok(ValueType::Tuple(element_types))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexExpr {
+    pub target: Box<Expr>,
+    pub index: Box<Expr>,
+    pub token_metadata: TokenMetadata,
+}
+
+impl IndexExpr {
+    pub fn compile_to_dag_node(&self, dag: &mut DAG) -> usize {
+        let target_dag = self.target.compile_to_dag_node(dag);
+        let index_dag = self.index.compile_to_dag_node(dag);
+
+        let mut token_vec = Vec::new();
+        self.push_to_token_vec(&mut token_vec);
+
+        let dag_node = DAGNode::new(DAGOp::Index, Some(vec![target_dag, index_dag])).with_location(
+            TokenMetadata::span(&token_vec)
+        );
+
+        dag.add_node(dag_node)
+    }
+
+    pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
+        self.index.push_to_token_vec(token_vec);
+        token_vec.push(self.token_metadata);
+        self.target.push_to_token_vec(token_vec);
+    }
+
+    pub fn type_check(
+        &self,
+        ast_environment: &AstEnvironment,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ValueType, String> {
+        let target_type = self.target.type_check(ast_environment, token_vec)?;
+        let index_type = self.index.type_check(ast_environment, token_vec)?;
+
+        let element_type = match target_type {
+            ValueType::Array(element_type) => *element_type,
+            ValueType::Map(key_type, value_type) => {
+                if !index_type.is(&key_type) {
+                    self.index.push_to_token_vec(token_vec);
+                    return Err(
+                        format!(
+                            "Map key must be of type {} but got {}",
+                            key_type.to_type_string(),
+                            index_type.to_type_string()
+                        )
+                    );
+                }
+
+                return Ok(*value_type);
+            }
+            target_type => {
+                self.target.push_to_token_vec(token_vec);
+                return Err(format!("Cannot index into a value of type {}", target_type.to_type_string()));
+            }
+        };
+
+        if !index_type.is(&ValueType::Int32) {
+            self.index.push_to_token_vec(token_vec);
+            return Err(
+                format!("Array index must be of type i32 but got {}", index_type.to_type_string())
+            );
+        }
+
+        Ok(element_type)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CastExpr {
+    pub value: Box<Expr>,
+    pub value_type: ValueType,
+    pub token_metadata: TokenMetadata,
+}
+
+impl CastExpr {
+    pub fn compile_to_dag_node(&self, dag: &mut DAG) -> usize {
+        let value_dag = self.value.compile_to_dag_node(dag);
+
+        let mut token_vec = Vec::new();
+        self.push_to_token_vec(&mut token_vec);
+
+        let dag_node = DAGNode::new(
+            DAGOp::Cast(self.value_type.clone()),
+            Some(vec![value_dag])
+        ).with_location(TokenMetadata::span(&token_vec));
+
+        dag.add_node(dag_node)
+    }
+
+    pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
+        token_vec.push(self.token_metadata);
+        self.value.push_to_token_vec(token_vec);
+    }
+
+    pub fn type_check(
+        &self,
+        ast_environment: &AstEnvironment,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ValueType, String> {
+        let value_type = self.value.type_check(ast_environment, token_vec)?;
+
+        match value_type.try_cast(&self.value_type) {
+            Ok(value_type) => Ok(value_type),
+            Err(e) => {
+                self.push_to_token_vec(token_vec);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InstanceExpr {
+    pub class_type: ClassType,
+    pub layout: Rc<ClassLayout>,
+    pub fields: Vec<Expr>,
+    pub token_metadata: TokenMetadata,
+}
+
+impl InstanceExpr {
+    pub fn compile_to_dag_node(&self, dag: &mut DAG) -> usize {
+        let field_dags = self.fields
+            .iter()
+            .map(|field| field.compile_to_dag_node(dag))
+            .collect::<Vec<_>>();
+
+        let dag_node = DAGNode::new(DAGOp::Instance(self.layout.clone()), Some(field_dags));
+
+        dag.add_node(dag_node)
+    }
+
+    pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
+        token_vec.push(self.token_metadata);
+    }
+
+    pub fn type_check(
+        &self,
+        _ast_environment: &AstEnvironment,
+        _token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ValueType, String> {
+        Ok(ValueType::Class(self.class_type.clone()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumVariantExpr {
+    pub enum_type: EnumType,
+    pub layout: Rc<EnumLayout>,
+    pub enum_name: AstIdentifier,
+    pub variant: AstIdentifier,
+    pub tag: usize,
+    pub payload: Vec<Expr>,
+    pub payload_types: Vec<ValueType>,
+}
+
+impl EnumVariantExpr {
+    pub fn compile_to_dag_node(&self, dag: &mut DAG) -> usize {
+        let payload_dags = self.payload
+            .iter()
+            .map(|value| value.compile_to_dag_node(dag))
+            .collect::<Vec<_>>();
+
+        let op = DAGOp::EnumVariant(self.layout.clone(), self.tag);
+
+        let dag_node = DAGNode::new(op, Some(payload_dags));
+
+        dag.add_node(dag_node)
+    }
+
+    pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
+        for value in self.payload.iter().rev() {
+            value.push_to_token_vec(token_vec);
+        }
+        self.variant.push_to_token_vec(token_vec);
+        self.enum_name.push_to_token_vec(token_vec);
+    }
+
+    pub fn type_check(
+        &self,
+        ast_environment: &AstEnvironment,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ValueType, String> {
+        if self.payload.len() != self.payload_types.len() {
+            self.push_to_token_vec(token_vec);
+            return Err(
+                format!(
+                    "Variant '{}.{}' expects {} value(s) but got {}",
+                    self.enum_type.name,
+                    self.variant.lexeme,
+                    self.payload_types.len(),
+                    self.payload.len()
+                )
+            );
+        }
+
+        for (value, payload_type) in self.payload.iter().zip(&self.payload_types) {
+            let value_type = value.type_check(ast_environment, token_vec)?;
+
+            if !value_type.is(payload_type) {
+                value.push_to_token_vec(token_vec);
+                return Err(
+                    format!(
+                        "Variant '{}.{}' expects a value of type {} but got {}",
+                        self.enum_type.name,
+                        self.variant.lexeme,
+                        payload_type.to_type_string(),
+                        value_type.to_type_string()
+                    )
+                );
+            }
+        }
+
+        Ok(ValueType::Enum(self.enum_type.clone()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub tag: Option<usize>,
+    pub bindings: Vec<AstIdentifier>,
+    pub body: Expr,
+}
+
+// Lowered to control flow like `&&` and `||`. The arms are type checked while parsed
+#[derive(Debug, Clone)]
+pub struct MatchExpr {
+    pub value: Box<Expr>,
+    pub arms: Vec<MatchArm>,
+    pub value_type: ValueType,
+    pub token_metadata: TokenMetadata,
+}
+
+impl MatchExpr {
+    pub fn compile_to_dag_node(&self, _dag: &mut DAG) -> usize {
+        unreachable!("Match expressions are lowered before DAGs are generated")
+    }
+
+    pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
+        self.value.push_to_token_vec(token_vec);
+        token_vec.push(self.token_metadata);
+    }
+
+    pub fn type_check(
+        &self,
+        ast_environment: &AstEnvironment,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ValueType, String> {
+        self.value.type_check(ast_environment, token_vec)?;
+
+        Ok(self.value_type.clone())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MemberAccessExpr {
+    pub target: Box<Expr>,
+    pub member: AstIdentifier,
+    pub field_index: usize,
+}
+
+impl MemberAccessExpr {
+    pub fn compile_to_dag_node(&self, dag: &mut DAG) -> usize {
+        let target_dag = self.target.compile_to_dag_node(dag);
+
+        let dag_node = DAGNode::new(DAGOp::GetField(self.field_index), Some(vec![target_dag]));
+
+        dag.add_node(dag_node)
+    }
+
+    pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
+        self.member.push_to_token_vec(token_vec);
+        self.target.push_to_token_vec(token_vec);
+    }
+
+    pub fn type_check(
+        &self,
+        ast_environment: &AstEnvironment,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ValueType, String> {
+        let class_type = match self.target.type_check(ast_environment, token_vec)? {
+            ValueType::Tuple(element_types) => {
+                return Ok(element_types[self.field_index].clone());
+            }
+            value_type => self.target.expect_class(value_type, &self.member, token_vec)?,
+        };
+
+        match ast_environment.get_field(&class_type, &self.member.lexeme) {
+            Ok((_, field)) => Ok(ast_environment.specialize_member_type(&class_type, &field.value_type)),
+            Err(e) => {
+                self.push_to_token_vec(token_vec);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MethodCallExpr {
+    pub receiver: Box<Expr>,
+    pub method: AstIdentifier,
+    pub args: Vec<Expr>,
+    pub class_method: Box<ClassMethod>,
+    // The implementation of a trait bound method, which the generic got as a hidden parameter
+    pub bound_impl: Option<Box<Expr>>,
+    pub trait_impls: Vec<Expr>,
+}
+
+impl MethodCallExpr {
+    // The receiver is passed as the first argument, which is `self` in the method
+    pub fn compile_to_dag_node(&self, dag: &mut DAG) -> usize {
+        let mut arg_dags = self.bound_impl
+            .iter()
+            .map(|bound_impl| bound_impl.compile_to_dag_node(dag))
+            .collect::<Vec<_>>();
+        arg_dags.push(self.receiver.compile_to_dag_node(dag));
+        arg_dags.extend(
+            self.args
+                .iter()
+                .chain(&self.trait_impls)
+                .map(|arg| arg.compile_to_dag_node(dag))
+        );
+
+        let op = match (self.class_method.native, &self.bound_impl) {
+            (Some(native), _) => DAGOp::CallNative(native),
+            (None, Some(_)) => DAGOp::CallValue,
+            (None, None) => DAGOp::Call(self.class_method.signature.id),
+        };
+
+        // Native methods can fail at runtime, in which case the error points at the method
+        let dag_node = DAGNode::new(op, Some(arg_dags)).with_location(
+            Some(self.method.token_metadata)
+        );
+
+        dag.add_node(dag_node)
+    }
+
+    pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
+        for arg in self.args.iter().rev() {
+            arg.push_to_token_vec(token_vec);
+        }
+        self.method.push_to_token_vec(token_vec);
+        self.receiver.push_to_token_vec(token_vec);
+    }
+
+    pub fn type_check(
+        &self,
+        ast_environment: &AstEnvironment,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<ValueType, String> {
+        // The method is resolved when the call is parsed
+        self.receiver.type_check(ast_environment, token_vec)?;
+
+        let signature = &self.class_method.signature;
+
+        if self.class_method.is_self_mutable {
+            if let Some(root_identifier) = self.receiver.get_root_identifier() {
+                if let Some((_, false, _)) = ast_environment.get(&root_identifier.lexeme) {
+                    self.method.push_to_token_vec(token_vec);
+                    self.receiver.push_to_token_vec(token_vec);
+                    return Err(
+                        format!(
+                            "Cannot call method '{}' on immutable variable '{}', since it takes 'mut self'",
+                            self.method.lexeme,
+                            root_identifier.lexeme
+                        )
+                    );
+                }
+            }
+        }
+
+        if self.args.len() != signature.parameters.len() {
+            self.push_to_token_vec(token_vec);
+            return Err(
+                format!(
+                    "Method '{}' expects {} argument(s) but got {}",
+                    self.method.lexeme,
+                    signature.parameters.len(),
+                    self.args.len()
+                )
+            );
+        }
+
+        for (arg, parameter_type) in self.args.iter().zip(&signature.parameters) {
+            let arg_type = arg.type_check(ast_environment, token_vec)?;
+
+            if !arg_type.is(parameter_type) {
+                arg.push_to_token_vec(token_vec);
+                return Err(
+                    format!(
+                        "Method '{}' expects an argument of type {} but got {}",
+                        self.method.lexeme,
+                        parameter_type.to_type_string(),
+                        arg_type.to_type_string()
+                    )
+                );
+            }
+        }
+
+        Ok(signature.return_type.clone())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AstValue {
+    pub value: Value,
+    pub token_metadata: TokenMetadata,
+}
+
+impl AstValue {
+    pub fn compile_to_dag_node(&self, dag: &mut DAG) -> usize {
+        let op = DAGOp::Const(self.value.clone());
+
+        let mut dag_node = DAGNode::new(op, None);
+
+        dag.add_node(dag_node)
+    }
+
+    pub fn new(value: Value, token_metadata: TokenMetadata) -> Self {
+        Self {
+            value,
+            token_metadata,
+        }
+    }
+
+    pub fn get_value(&self) -> Value {
+        self.value.clone()
     }
 
     pub fn push_to_token_vec(&self, token_vec: &mut Vec<TokenMetadata>) {
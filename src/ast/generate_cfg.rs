@@ -1,34 +1,168 @@
-use std::cell::Cell;
+use std::borrow::Cow;
 
 use crate::{
-    ast::stmt::Stmt,
+    ast::{
+        expr::{
+            ArrayExpr,
+            AstIdentifier,
+            AstValue,
+            BinaryExpr,
+            BuiltinCallExpr,
+            CallExpr,
+            CallValueExpr,
+            CastExpr,
+            EnumVariantExpr,
+            Expr,
+            IndexExpr,
+            InstanceExpr,
+            InterpolationExpr,
+            LogicalExpr,
+            MapExpr,
+            MatchExpr,
+            MemberAccessExpr,
+            MethodCallExpr,
+            TupleExpr,
+            UnaryExpr,
+        },
+        stmt::{
+            DestructuredVariable,
+            ForIterable,
+            ForStmt,
+            FunctionStmt,
+            IfStmt,
+            ScopeStmt,
+            Stmt,
+            VariableAssignmentStmt,
+            VariableDefinitionStmt,
+            WhileStmt,
+        },
+    },
     compiler::cfg::{
-        cfg_node::{ CFGNodeState, CFGProcessNode },
+        cfg_node::{
+            CFGDecisionNode,
+            CFGJumpNode,
+            CFGNodeState,
+            CFGProcessNode,
+            CFGReturnNode,
+        },
         dag::{ DAGNode, DAGOp, DAG },
         CFGNode,
         CFG,
     },
+    operations::{ BinaryOp, LogicalOp, UnaryOp },
+    parser::token::TokenMetadata,
+    value::{ Value, ValueType },
+    value_v2::{ self, NativeFunction, MAP_HAS_ENTRY_AT, MAP_KEY_AT, MAP_VALUE_AT },
 };
 
 use super::Ast;
 
+// Hidden variables, which can't collide with user variables since they aren't valid identifiers
+const FOR_RANGE_END: &str = "@range_end";
+
+// Each iteration defines the loop variable from this, so closures capture their own variable
+const FOR_COUNTER: &str = "@counter";
+
+// The map, its size when the loop started, and the index of the current entry
+const FOR_MAP: &str = "@map";
+const FOR_MAP_SIZE: &str = "@map_size";
+const FOR_INDEX: &str = "@index";
+
+struct LoopContext {
+    scope_depth: usize,
+    break_node_ids: Vec<usize>,
+    continue_node_ids: Vec<usize>,
+}
+
+struct CFGContext {
+    scope_depth: usize,
+    loops: Vec<LoopContext>,
+    temporary_count: usize,
+    temporaries: Vec<String>,
+}
+
+impl CFGContext {
+    fn new() -> Self {
+        Self { scope_depth: 0, loops: Vec::new(), temporary_count: 0, temporaries: Vec::new() }
+    }
+
+    fn new_temporary(&mut self) -> String {
+        self.temporary_count += 1;
+        let lexeme = format!("@temp{}", self.temporary_count);
+        self.temporaries.push(lexeme.clone());
+        lexeme
+    }
+}
+
 impl Ast {
     pub fn generate_cfg(&self) -> CFG {
         let mut cfg = CFG::new();
+        let mut context = CFGContext::new();
 
-        self.type_check();
+        cfg.add_node(CFGNode::ProgramStart(cfg.get_next_node_id()));
 
-        let next_cfg_node_id: Cell<usize> = Cell::new(1);
-        let get_next_node_id = || {
-            let next_node_id = next_cfg_node_id.get();
-            next_cfg_node_id.set(next_node_id + 1);
-            next_node_id
-        };
+        for stmt in &self.main_scope.cf_stmts {
+            self.generate_stmt_cfg(stmt, &mut cfg, &mut context);
+        }
 
-        cfg.add_node(CFGNode::ProgramStart(get_next_node_id()));
+        cfg.add_node(CFGNode::ProgramEnd);
 
-        for stmt in &self.main_scope.cf_stmts {
-            self.generate_cfg_node(stmt, &mut cfg, &get_next_node_id);
+        cfg
+    }
+
+    pub fn generate_function_cfgs(&self) -> Vec<CFG> {
+        let mut function_stmts = Vec::new();
+        Self::collect_function_stmts(&self.main_scope, &mut function_stmts);
+
+        function_stmts.sort_by_key(|function_stmt| function_stmt.id);
+
+        function_stmts
+            .into_iter()
+            .map(|function_stmt| self.generate_function_cfg(function_stmt))
+            .collect()
+    }
+
+    fn collect_function_stmts<'a>(scope_stmt: &'a ScopeStmt, function_stmts: &mut Vec<&'a FunctionStmt>) {
+        for stmt in &scope_stmt.forwards_declarations {
+            if let Stmt::FunctionStmt(function_stmt) = stmt {
+                function_stmts.push(function_stmt);
+                Self::collect_function_stmts(&function_stmt.body, function_stmts);
+            }
+        }
+
+        for stmt in &scope_stmt.cf_stmts {
+            match stmt {
+                Stmt::ScopeStmt(scope_stmt) => Self::collect_function_stmts(scope_stmt, function_stmts),
+                Stmt::IfStmt(if_stmt) => {
+                    Self::collect_function_stmts(&if_stmt.true_block, function_stmts);
+                    if let Some(false_block) = &if_stmt.false_block {
+                        Self::collect_function_stmts(false_block, function_stmts);
+                    }
+                }
+                Stmt::WhileStmt(while_stmt) => {
+                    Self::collect_function_stmts(&while_stmt.body, function_stmts);
+                }
+                Stmt::ForStmt(for_stmt) => {
+                    Self::collect_function_stmts(&for_stmt.body, function_stmts);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn generate_function_cfg(&self, function_stmt: &FunctionStmt) -> CFG {
+        let parameters = function_stmt.args
+            .iter()
+            .map(|arg| arg.name.clone())
+            .collect();
+
+        let mut cfg = CFG::new_function(parameters, function_stmt.captures.clone());
+        let mut context = CFGContext::new();
+
+        cfg.add_node(CFGNode::ProgramStart(cfg.get_next_node_id()));
+
+        for stmt in &function_stmt.body.cf_stmts {
+            self.generate_stmt_cfg(stmt, &mut cfg, &mut context);
         }
 
         cfg.add_node(CFGNode::ProgramEnd);
@@ -36,85 +170,1028 @@ impl Ast {
         cfg
     }
 
-    fn generate_cfg_node(&self, stmt: &Stmt, cfg: &mut CFG, get_next_node_id: &impl Fn() -> usize) {
+    fn generate_stmt_cfg(&self, stmt: &Stmt, cfg: &mut CFG, context: &mut CFGContext) {
+        let temporaries_start = context.temporaries.len();
+
+        self.generate_cfg_node(stmt, cfg, context);
+
+        let temporaries = context.temporaries.split_off(temporaries_start);
+        if !temporaries.is_empty() {
+            cfg.add_node(CFGNode::FreeTemporaries(temporaries, cfg.get_next_node_id()));
+        }
+    }
+
+    fn generate_cfg_node(&self, stmt: &Stmt, cfg: &mut CFG, context: &mut CFGContext) {
         let temp_default_state = CFGNodeState::Alive;
 
         match stmt {
-            Stmt::TypeDefStmt(_) => {}
-            Stmt::FunctionStmt(_) => { panic!("FunctionStmt is not supported yet") }
+            Stmt::FunctionStmt(_) => {} // Functions get their own CFG in generate_function_cfgs
+            Stmt::VariableDefinition(
+                VariableDefinitionStmt {
+                    destructured: Some(variables),
+                    value: Some(value),
+                    token_metadata,
+                    ..
+                },
+            ) => {
+                self.lower_destructuring_definition(variables, value, *token_metadata, cfg, context);
+            }
             Stmt::VariableDefinition(variable_definition) => {
-                let mut dag = DAG::new();
+                let value = variable_definition.value
+                    .as_ref()
+                    .map(|value| self.lower_control_flow_exprs(value, cfg, context));
 
-                let value_id = match &variable_definition.value {
-                    Some(value) => Some(value.compile_to_dag_node(&mut dag)),
-                    None => None,
-                };
-
-                let lexeme_id = dag.add_node(
-                    DAGNode::new(DAGOp::Identifier(variable_definition.name.clone()), None)
+                let dag = Self::variable_dag(
+                    DAGOp::Define,
+                    &variable_definition.name,
+                    value.as_deref()
                 );
 
-                let operands = match value_id {
-                    Some(value_id) => vec![lexeme_id, value_id],
-                    None => vec![lexeme_id],
-                };
-
-                let entry_node_id = dag.add_node(DAGNode::new(DAGOp::Define, Some(operands)));
-                dag.set_entry_node_id(entry_node_id);
-
                 let cfg_process_node = CFGProcessNode::new(
                     dag,
-                    get_next_node_id(),
+                    cfg.get_next_node_id(),
                     temp_default_state
                 );
 
                 cfg.add_node(CFGNode::Process(cfg_process_node));
             }
             Stmt::ExprStmt(expr_stmt) => {
-                let dag = expr_stmt.compile_to_dag();
+                let dag = self.lower_control_flow_exprs(expr_stmt, cfg, context).compile_to_dag();
 
                 let cfg_process_node = CFGProcessNode::new(
                     dag,
-                    get_next_node_id(),
+                    cfg.get_next_node_id(),
                     temp_default_state
                 );
 
                 cfg.add_node(CFGNode::Process(cfg_process_node));
             }
-            Stmt::VariableAssignment(variable_assignment) => {
+            Stmt::PrintStmt(expr) => {
                 let mut dag = DAG::new();
 
-                let value_id = variable_assignment.value.compile_to_dag_node(&mut dag);
+                let value_id = self.lower_control_flow_exprs(expr, cfg, context).compile_to_dag_node(&mut dag);
+                let entry_node_id = dag.add_node(DAGNode::new(DAGOp::Print, Some(vec![value_id])));
+                dag.set_entry_node_id(entry_node_id);
 
-                let lexeme_id = dag.add_node(
-                    DAGNode::new(
-                        DAGOp::Identifier(variable_assignment.field.get_lexeme().clone()),
-                        None
+                cfg.add_node(
+                    CFGNode::Process(
+                        CFGProcessNode::new(dag, cfg.get_next_node_id(), temp_default_state)
                     )
                 );
+            }
+            Stmt::VariableAssignment(
+                VariableAssignmentStmt {
+                    target_expr: Some(target_expr),
+                    field,
+                    value,
+                    field_index: Some(field_index),
+                    is_compound,
+                },
+            ) => {
+                let operands = [target_expr, value];
 
+                let lowered_operands;
+                let operands = if *is_compound {
+                    lowered_operands = self.lower_compound_assignment(
+                        &[target_expr],
+                        value,
+                        |mut place_operands| {
+                            Expr::MemberAccessExpr(MemberAccessExpr {
+                                target: Box::new(place_operands.remove(0)),
+                                member: field.clone(),
+                                field_index: *field_index,
+                            })
+                        },
+                        cfg,
+                        context
+                    );
+                    lowered_operands.iter().collect()
+                } else if operands.iter().any(|operand| operand.any_sub_expr(&Self::is_control_flow_expr)) {
+                    lowered_operands = self.lower_operands(&operands, cfg, context);
+                    lowered_operands.iter().collect()
+                } else {
+                    operands.to_vec()
+                };
+
+                let mut dag = DAG::new();
+
+                let operand_ids = operands
+                    .iter()
+                    .map(|operand| operand.compile_to_dag_node(&mut dag))
+                    .collect::<Vec<_>>();
                 let entry_node_id = dag.add_node(
-                    DAGNode::new(DAGOp::Assign, Some(vec![lexeme_id, value_id]))
+                    DAGNode::new(DAGOp::SetField(*field_index), Some(operand_ids))
                 );
                 dag.set_entry_node_id(entry_node_id);
 
+                cfg.add_node(
+                    CFGNode::Process(
+                        CFGProcessNode::new(dag, cfg.get_next_node_id(), temp_default_state)
+                    )
+                );
+            }
+            Stmt::VariableAssignment(variable_assignment) => {
+                let value = self.lower_control_flow_exprs(&variable_assignment.value, cfg, context);
+
+                let dag = Self::variable_dag(
+                    DAGOp::Assign,
+                    &variable_assignment.field.get_lexeme(),
+                    Some(&value)
+                );
+
                 let cfg_process_node = CFGProcessNode::new(
                     dag,
-                    get_next_node_id(),
+                    cfg.get_next_node_id(),
                     temp_default_state
                 );
 
                 cfg.add_node(CFGNode::Process(cfg_process_node));
             }
+            Stmt::IndexAssignment(index_assignment) => {
+                let target = &index_assignment.target;
+                let operands = [target.target.as_ref(), target.index.as_ref(), &index_assignment.value];
+
+                let lowered_operands;
+                let operands = if index_assignment.is_compound {
+                    lowered_operands = self.lower_compound_assignment(
+                        &operands[..2],
+                        &index_assignment.value,
+                        |mut place_operands| {
+                            let index = place_operands.pop().unwrap();
+                            Expr::IndexExpr(IndexExpr {
+                                target: Box::new(place_operands.pop().unwrap()),
+                                index: Box::new(index),
+                                token_metadata: target.token_metadata,
+                            })
+                        },
+                        cfg,
+                        context
+                    );
+                    lowered_operands.iter().collect()
+                } else if operands.iter().any(|operand| operand.any_sub_expr(&Self::is_control_flow_expr)) {
+                    lowered_operands = self.lower_operands(&operands, cfg, context);
+                    lowered_operands.iter().collect()
+                } else {
+                    operands.to_vec()
+                };
+
+                let mut dag = DAG::new();
+
+                let operand_ids = operands
+                    .iter()
+                    .map(|operand| operand.compile_to_dag_node(&mut dag))
+                    .collect::<Vec<_>>();
+                let mut token_vec = Vec::new();
+                target.push_to_token_vec(&mut token_vec);
+
+                let entry_node_id = dag.add_node(
+                    DAGNode::new(DAGOp::SetIndex, Some(operand_ids)).with_location(
+                        TokenMetadata::span(&token_vec)
+                    )
+                );
+                dag.set_entry_node_id(entry_node_id);
+
+                cfg.add_node(
+                    CFGNode::Process(
+                        CFGProcessNode::new(dag, cfg.get_next_node_id(), temp_default_state)
+                    )
+                );
+            }
             Stmt::ScopeStmt(scope_stmt) => {
-                cfg.add_node(CFGNode::ScopeStart(get_next_node_id()));
+                self.generate_scope_cfg(scope_stmt, cfg, context);
+            }
+            Stmt::IfStmt(if_stmt) => {
+                self.generate_if_cfg(if_stmt, cfg, context);
+            }
+            Stmt::WhileStmt(while_stmt) => {
+                self.generate_while_cfg(while_stmt, cfg, context);
+            }
+            Stmt::ForStmt(for_stmt) => {
+                self.generate_for_cfg(for_stmt, cfg, context);
+            }
+            Stmt::ReturnStmt(return_stmt) => {
+                let value = return_stmt.value
+                    .as_ref()
+                    .map(|value| self.lower_control_flow_exprs(value, cfg, context).compile_to_dag());
+                cfg.add_node(CFGNode::Return(CFGReturnNode::new(value)));
+            }
+            Stmt::BreakStmt | Stmt::ContinueStmt => {
+                let loop_context = context.loops
+                    .last_mut()
+                    .expect("break and continue are checked to be inside a loop when parsing");
+
+                let node_id = cfg.get_current_node_id();
+                if let Stmt::BreakStmt = stmt {
+                    loop_context.break_node_ids.push(node_id);
+                } else {
+                    loop_context.continue_node_ids.push(node_id);
+                }
+
+                // The jump target is patched when the loop has been generated
+                let ended_scopes = context.scope_depth - loop_context.scope_depth;
+                cfg.add_node(CFGNode::Jump(CFGJumpNode::new(0, ended_scopes)));
+            }
+        }
+    }
+
+    fn generate_scope_cfg(&self, scope_stmt: &ScopeStmt, cfg: &mut CFG, context: &mut CFGContext) {
+        self.generate_scope_cfg_with_definitions(scope_stmt, Vec::new(), cfg, context);
+    }
+
+    fn generate_scope_cfg_with_definitions(
+        &self,
+        scope_stmt: &ScopeStmt,
+        definitions: Vec<DAG>,
+        cfg: &mut CFG,
+        context: &mut CFGContext
+    ) {
+        cfg.add_node(CFGNode::ScopeStart(cfg.get_next_node_id()));
+        context.scope_depth += 1;
+
+        for definition in definitions {
+            Self::add_process_node(definition, cfg);
+        }
+
+        for stmt in &scope_stmt.cf_stmts {
+            self.generate_stmt_cfg(stmt, cfg, context);
+        }
+
+        context.scope_depth -= 1;
+        cfg.add_node(CFGNode::ScopeEnd(cfg.get_next_node_id()));
+    }
+
+    fn generate_if_cfg(&self, if_stmt: &IfStmt, cfg: &mut CFG, context: &mut CFGContext) {
+        let condition = self.lower_control_flow_exprs(&if_stmt.condition, cfg, context).compile_to_dag();
+        let decision_node_id = cfg.get_current_node_id();
+
+        // The false branch id is patched when the true branch has been generated
+        cfg.add_node(
+            CFGNode::Decision(
+                CFGDecisionNode::new(
+                    condition,
+                    cfg.get_next_node_id(),
+                    0,
+                    CFGNodeState::Alive
+                )
+            )
+        );
+
+        self.generate_scope_cfg(&if_stmt.true_block, cfg, context);
+
+        let true_branch_end_id = cfg.get_current_node_id() - 1;
+        let false_branch_id = cfg.get_current_node_id();
+
+        if let Some(false_block) = &if_stmt.false_block {
+            self.generate_scope_cfg(false_block, cfg, context);
+
+            let next_id = cfg.get_current_node_id();
+            cfg.get_node_mut(true_branch_end_id).set_next_id(next_id);
+        }
+
+        if let CFGNode::Decision(decision_node) = cfg.get_node_mut(decision_node_id) {
+            decision_node.false_branch_id = false_branch_id;
+        }
+    }
+
+    fn generate_while_cfg(&self, while_stmt: &WhileStmt, cfg: &mut CFG, context: &mut CFGContext) {
+        // The condition may be lowered into several nodes, which must all run on every iteration
+        let condition_start_id = cfg.get_current_node_id();
+        let condition = self.lower_control_flow_exprs(&while_stmt.condition, cfg, context).compile_to_dag();
+        let decision_node_id = cfg.get_current_node_id();
+
+        cfg.add_node(
+            CFGNode::Decision(
+                CFGDecisionNode::new(
+                    condition,
+                    cfg.get_next_node_id(),
+                    0,
+                    CFGNodeState::Alive
+                )
+            )
+        );
+
+        let loop_context = self.generate_loop_body_cfg(&while_stmt.body, Vec::new(), cfg, context);
+
+        let body_end_id = cfg.get_current_node_id() - 1;
+        cfg.get_node_mut(body_end_id).set_next_id(condition_start_id);
+
+        let exit_id = cfg.get_current_node_id();
+        if let CFGNode::Decision(decision_node) = cfg.get_node_mut(decision_node_id) {
+            decision_node.false_branch_id = exit_id;
+        }
+
+        Self::patch_loop_jumps(loop_context, condition_start_id, exit_id, cfg);
+    }
+
+    // A map is looped over by entry index, with the key and value defined in each iteration
+    fn generate_for_cfg(&self, for_stmt: &ForStmt, cfg: &mut CFG, context: &mut CFGContext) {
+        cfg.add_node(CFGNode::ScopeStart(cfg.get_next_node_id()));
+        context.scope_depth += 1;
+
+        let mut condition = DAG::new();
+        let counter = match &for_stmt.iterable {
+            ForIterable::Range(range_start, range_end) => {
+                let definitions = [
+                    (FOR_COUNTER, range_start),
+                    (FOR_RANGE_END, range_end),
+                ];
+                for (lexeme, value) in definitions {
+                    let value = self.lower_control_flow_exprs(value, cfg, context);
+                    Self::add_process_node(Self::variable_dag(DAGOp::Define, lexeme, Some(&value)), cfg);
+                }
+
+                let counter_id = condition.add_node(
+                    DAGNode::new(DAGOp::Identifier(FOR_COUNTER.to_string()), None)
+                );
+                let range_end_id = condition.add_node(
+                    DAGNode::new(DAGOp::Identifier(FOR_RANGE_END.to_string()), None)
+                );
+                let entry_node_id = condition.add_node(
+                    DAGNode::new(DAGOp::BinaryOp(BinaryOp::Less), Some(vec![counter_id, range_end_id]))
+                );
+                condition.set_entry_node_id(entry_node_id);
+
+                FOR_COUNTER.to_string()
+            }
+            ForIterable::Map(map) => {
+                let map = self.lower_control_flow_exprs(map, cfg, context);
+                Self::add_process_node(Self::variable_dag(DAGOp::Define, FOR_MAP, Some(&map)), cfg);
+
+                let mut index = DAG::new();
+                let index_id = index.add_node(
+                    DAGNode::new(DAGOp::Identifier(FOR_INDEX.to_string()), None)
+                );
+                let zero_id = index.add_node(DAGNode::new(DAGOp::Const(Value::Int32(0)), None));
+                let entry_node_id = index.add_node(
+                    DAGNode::new(DAGOp::Define, Some(vec![index_id, zero_id]))
+                );
+                index.set_entry_node_id(entry_node_id);
+                Self::add_process_node(index, cfg);
+
+                let len = value_v2::get_builtin("len").expect("len is a builtin").native;
+                let mut size = DAG::new();
+                let size_id = size.add_node(
+                    DAGNode::new(DAGOp::Identifier(FOR_MAP_SIZE.to_string()), None)
+                );
+                let map_id = size.add_node(DAGNode::new(DAGOp::Identifier(FOR_MAP.to_string()), None));
+                let len_id = size.add_node(DAGNode::new(DAGOp::Builtin(len), Some(vec![map_id])));
+                let entry_node_id = size.add_node(
+                    DAGNode::new(DAGOp::Define, Some(vec![size_id, len_id]))
+                );
+                size.set_entry_node_id(entry_node_id);
+                Self::add_process_node(size, cfg);
+
+                let operands = [FOR_MAP, FOR_INDEX, FOR_MAP_SIZE]
+                    .iter()
+                    .map(|lexeme| {
+                        condition.add_node(DAGNode::new(DAGOp::Identifier(lexeme.to_string()), None))
+                    })
+                    .collect();
+                let entry_node_id = condition.add_node(
+                    DAGNode::new(DAGOp::Builtin(MAP_HAS_ENTRY_AT), Some(operands))
+                );
+                condition.set_entry_node_id(entry_node_id);
+
+                FOR_INDEX.to_string()
+            }
+        };
+
+        let decision_node_id = cfg.get_current_node_id();
+        cfg.add_node(
+            CFGNode::Decision(
+                CFGDecisionNode::new(condition, cfg.get_next_node_id(), 0, CFGNodeState::Alive)
+            )
+        );
+
+        // The loop variables are defined in the body, so their scope ends with each iteration
+        let definitions = match &for_stmt.iterable {
+            ForIterable::Range(_, _) => vec![Self::copy_variable_dag(&for_stmt.variable, FOR_COUNTER)],
+            ForIterable::Map(_) => {
+                std::iter
+                    ::once(Self::map_entry_dag(&for_stmt.variable, MAP_KEY_AT))
+                    .chain(
+                        for_stmt.value_variable
+                            .iter()
+                            .map(|value_variable| Self::map_entry_dag(value_variable, MAP_VALUE_AT))
+                    )
+                    .collect()
+            }
+        };
+
+        let loop_context = self.generate_loop_body_cfg(&for_stmt.body, definitions, cfg, context);
+
+        let mut increment = DAG::new();
+        let target_id = increment.add_node(DAGNode::new(DAGOp::Identifier(counter.clone()), None));
+        let counter_id = increment.add_node(DAGNode::new(DAGOp::Identifier(counter), None));
+        let one_id = increment.add_node(DAGNode::new(DAGOp::Const(Value::Int32(1)), None));
+        let sum_id = increment.add_node(
+            DAGNode::new(DAGOp::BinaryOp(BinaryOp::Add), Some(vec![counter_id, one_id]))
+        );
+        let entry_node_id = increment.add_node(
+            DAGNode::new(DAGOp::Assign, Some(vec![target_id, sum_id]))
+        );
+        increment.set_entry_node_id(entry_node_id);
+
+        let increment_node_id = cfg.get_current_node_id();
+        cfg.add_node(
+            CFGNode::Process(CFGProcessNode::new(increment, decision_node_id, CFGNodeState::Alive))
+        );
+
+        let exit_id = cfg.get_current_node_id();
+        if let CFGNode::Decision(decision_node) = cfg.get_node_mut(decision_node_id) {
+            decision_node.false_branch_id = exit_id;
+        }
 
-                for stmt in &scope_stmt.cf_stmts {
-                    self.generate_cfg_node(stmt, cfg, get_next_node_id);
+        context.scope_depth -= 1;
+        cfg.add_node(CFGNode::ScopeEnd(cfg.get_next_node_id()));
+
+        Self::patch_loop_jumps(loop_context, increment_node_id, exit_id, cfg);
+    }
+
+    fn add_process_node(dag: DAG, cfg: &mut CFG) {
+        cfg.add_node(
+            CFGNode::Process(CFGProcessNode::new(dag, cfg.get_next_node_id(), CFGNodeState::Alive))
+        );
+    }
+
+    fn copy_variable_dag(variable: &str, source: &str) -> DAG {
+        let mut dag = DAG::new();
+
+        let lexeme_id = dag.add_node(DAGNode::new(DAGOp::Identifier(variable.to_string()), None));
+        let source_id = dag.add_node(DAGNode::new(DAGOp::Identifier(source.to_string()), None));
+
+        let entry_node_id = dag.add_node(DAGNode::new(DAGOp::Define, Some(vec![lexeme_id, source_id])));
+        dag.set_entry_node_id(entry_node_id);
+
+        dag
+    }
+
+    fn map_entry_dag(variable: &str, native: NativeFunction) -> DAG {
+        let mut dag = DAG::new();
+
+        let map_id = dag.add_node(DAGNode::new(DAGOp::Identifier(FOR_MAP.to_string()), None));
+        let index_id = dag.add_node(DAGNode::new(DAGOp::Identifier(FOR_INDEX.to_string()), None));
+        let value_id = dag.add_node(DAGNode::new(DAGOp::Builtin(native), Some(vec![map_id, index_id])));
+
+        let lexeme_id = dag.add_node(DAGNode::new(DAGOp::Identifier(variable.to_string()), None));
+
+        let entry_node_id = dag.add_node(DAGNode::new(DAGOp::Define, Some(vec![lexeme_id, value_id])));
+        dag.set_entry_node_id(entry_node_id);
+
+        dag
+    }
+
+    fn variable_dag(op: DAGOp, lexeme: &str, value: Option<&Expr>) -> DAG {
+        let mut dag = DAG::new();
+
+        let value_id = value.map(|value| value.compile_to_dag_node(&mut dag));
+
+        let lexeme_id = dag.add_node(DAGNode::new(DAGOp::Identifier(lexeme.to_string()), None));
+
+        let operands = match value_id {
+            Some(value_id) => vec![lexeme_id, value_id],
+            None => vec![lexeme_id],
+        };
+
+        let entry_node_id = dag.add_node(DAGNode::new(op, Some(operands)));
+        dag.set_entry_node_id(entry_node_id);
+
+        dag
+    }
+
+    fn lower_control_flow_exprs<'a>(
+        &self,
+        expr: &'a Expr,
+        cfg: &mut CFG,
+        context: &mut CFGContext
+    ) -> Cow<'a, Expr> {
+        if expr.any_sub_expr(&Self::is_control_flow_expr) {
+            Cow::Owned(self.lower_expr(expr, cfg, context))
+        } else {
+            Cow::Borrowed(expr)
+        }
+    }
+
+    fn is_control_flow_expr(expr: &Expr) -> bool {
+        matches!(expr, Expr::LogicalExpr(_) | Expr::MatchExpr(_))
+    }
+
+    fn lower_expr(&self, expr: &Expr, cfg: &mut CFG, context: &mut CFGContext) -> Expr {
+        match expr {
+            Expr::Literal(_) | Expr::IdentifierLookup(_) | Expr::ClosureExpr(_) => expr.clone(),
+            Expr::UnaryExpr(unary_expr) =>
+                Expr::UnaryExpr(UnaryExpr {
+                    operator: unary_expr.operator,
+                    right: Box::new(self.lower_expr(&unary_expr.right, cfg, context)),
+                }),
+            Expr::BinaryExpr(binary_expr) => {
+                let mut operands = self.lower_operands(
+                    &[&binary_expr.left, &binary_expr.right],
+                    cfg,
+                    context
+                );
+                let right = operands.pop().unwrap();
+                let left = operands.pop().unwrap();
+
+                Expr::BinaryExpr(BinaryExpr {
+                    left: Box::new(left),
+                    operator: binary_expr.operator,
+                    right: Box::new(right),
+                })
+            }
+            Expr::CallExpr(call_expr) => {
+                let args = call_expr.args.iter().collect::<Vec<_>>();
+
+                Expr::CallExpr(
+                    CallExpr::new(
+                        call_expr.callee.clone(),
+                        self.lower_operands(&args, cfg, context),
+                        call_expr.signature.clone(),
+                        call_expr.trait_impls.clone()
+                    )
+                )
+            }
+            Expr::CallValueExpr(call_value_expr) => {
+                let operands = std::iter
+                    ::once(call_value_expr.callee.as_ref())
+                    .chain(&call_value_expr.args)
+                    .collect::<Vec<_>>();
+                let mut operands = self.lower_operands(&operands, cfg, context);
+                let args = operands.split_off(1);
+                let callee = operands.pop().unwrap();
+
+                Expr::CallValueExpr(CallValueExpr {
+                    callee: Box::new(callee),
+                    args,
+                    function_type: call_value_expr.function_type.clone(),
+                    paren_metadata: call_value_expr.paren_metadata,
+                })
+            }
+            Expr::BuiltinCallExpr(builtin_call_expr) => {
+                let args = builtin_call_expr.args.iter().collect::<Vec<_>>();
+
+                Expr::BuiltinCallExpr(BuiltinCallExpr {
+                    builtin: builtin_call_expr.builtin.clone(),
+                    args: self.lower_operands(&args, cfg, context),
+                    token_metadata: builtin_call_expr.token_metadata,
+                })
+            }
+            Expr::ArrayExpr(array_expr) => {
+                let elements = array_expr.elements.iter().collect::<Vec<_>>();
+
+                Expr::ArrayExpr(ArrayExpr {
+                    elements: self.lower_operands(&elements, cfg, context),
+                    token_metadata: array_expr.token_metadata,
+                })
+            }
+            Expr::MapExpr(map_expr) => {
+                let operands = map_expr.entries
+                    .iter()
+                    .flat_map(|(key, value)| [key, value])
+                    .collect::<Vec<_>>();
+                let mut operands = self.lower_operands(&operands, cfg, context).into_iter();
+
+                let mut entries = Vec::with_capacity(map_expr.entries.len());
+                while let (Some(key), Some(value)) = (operands.next(), operands.next()) {
+                    entries.push((key, value));
                 }
 
-                cfg.add_node(CFGNode::ScopeEnd(get_next_node_id()));
+                Expr::MapExpr(MapExpr {
+                    entries,
+                    token_metadata: map_expr.token_metadata,
+                })
+            }
+            Expr::InterpolationExpr(interpolation_expr) => {
+                let parts = interpolation_expr.parts.iter().collect::<Vec<_>>();
+
+                Expr::InterpolationExpr(InterpolationExpr {
+                    parts: self.lower_operands(&parts, cfg, context),
+                    token_metadata: interpolation_expr.token_metadata,
+                })
+            }
+            Expr::TupleExpr(tuple_expr) => {
+                let elements = tuple_expr.elements.iter().collect::<Vec<_>>();
+
+                Expr::TupleExpr(TupleExpr {
+                    elements: self.lower_operands(&elements, cfg, context),
+                    token_metadata: tuple_expr.token_metadata,
+                })
             }
+            Expr::IndexExpr(index_expr) => {
+                let mut operands = self.lower_operands(
+                    &[&index_expr.target, &index_expr.index],
+                    cfg,
+                    context
+                );
+                let index = operands.pop().unwrap();
+                let target = operands.pop().unwrap();
+
+                Expr::IndexExpr(IndexExpr {
+                    target: Box::new(target),
+                    index: Box::new(index),
+                    token_metadata: index_expr.token_metadata,
+                })
+            }
+            Expr::InstanceExpr(instance_expr) => {
+                let fields = instance_expr.fields.iter().collect::<Vec<_>>();
+
+                Expr::InstanceExpr(InstanceExpr {
+                    class_type: instance_expr.class_type.clone(),
+                    layout: instance_expr.layout.clone(),
+                    fields: self.lower_operands(&fields, cfg, context),
+                    token_metadata: instance_expr.token_metadata,
+                })
+            }
+            Expr::CastExpr(cast_expr) =>
+                Expr::CastExpr(CastExpr {
+                    value: Box::new(self.lower_expr(&cast_expr.value, cfg, context)),
+                    value_type: cast_expr.value_type.clone(),
+                    token_metadata: cast_expr.token_metadata,
+                }),
+            Expr::MemberAccessExpr(member_access_expr) =>
+                Expr::MemberAccessExpr(MemberAccessExpr {
+                    target: Box::new(self.lower_expr(&member_access_expr.target, cfg, context)),
+                    member: member_access_expr.member.clone(),
+                    field_index: member_access_expr.field_index,
+                }),
+            Expr::MethodCallExpr(method_call_expr) => {
+                let operands = std::iter
+                    ::once(method_call_expr.receiver.as_ref())
+                    .chain(&method_call_expr.args)
+                    .collect::<Vec<_>>();
+                let mut operands = self.lower_operands(&operands, cfg, context);
+                let args = operands.split_off(1);
+                let receiver = operands.pop().unwrap();
+
+                Expr::MethodCallExpr(MethodCallExpr {
+                    receiver: Box::new(receiver),
+                    method: method_call_expr.method.clone(),
+                    args,
+                    class_method: method_call_expr.class_method.clone(),
+                    bound_impl: method_call_expr.bound_impl.clone(),
+                    trait_impls: method_call_expr.trait_impls.clone(),
+                })
+            }
+            Expr::EnumVariantExpr(enum_variant_expr) => {
+                let payload = enum_variant_expr.payload.iter().collect::<Vec<_>>();
+
+                Expr::EnumVariantExpr(EnumVariantExpr {
+                    payload: self.lower_operands(&payload, cfg, context),
+                    ..enum_variant_expr.clone()
+                })
+            }
+            Expr::LogicalExpr(logical_expr) => self.lower_logical_expr(logical_expr, cfg, context),
+            Expr::MatchExpr(match_expr) => self.lower_match_expr(match_expr, cfg, context),
+        }
+    }
+
+    // When a later operand is lowered, earlier calls are evaluated into hidden variables first
+    fn lower_operands(&self, operands: &[&Expr], cfg: &mut CFG, context: &mut CFGContext) -> Vec<Expr> {
+        let mut lowered_operands = Vec::with_capacity(operands.len());
+
+        for (i, operand) in operands.iter().enumerate() {
+            let mut lowered_operand = self.lower_expr(operand, cfg, context);
+
+            let is_followed_by_control_flow_expr = operands[i + 1..]
+                .iter()
+                .any(|operand| operand.any_sub_expr(&Self::is_control_flow_expr));
+
+            if is_followed_by_control_flow_expr && lowered_operand.any_sub_expr(&Self::is_call_expr) {
+                let mut token_vec = Vec::new();
+                operand.push_to_token_vec(&mut token_vec);
+                let token_metadata = token_vec[0];
+
+                lowered_operand = self.define_temporary(lowered_operand, token_metadata, cfg, context);
+            }
+
+            lowered_operands.push(lowered_operand);
+        }
+
+        lowered_operands
+    }
+
+    // The operands of the place, like `a` and `f()` in `a[f()] += 1`, are only evaluated once
+    fn lower_compound_assignment(
+        &self,
+        place_operands: &[&Expr],
+        value: &Expr,
+        read_place: impl Fn(Vec<Expr>) -> Expr,
+        cfg: &mut CFG,
+        context: &mut CFGContext
+    ) -> Vec<Expr> {
+        let Expr::BinaryExpr(binary_expr) = value else {
+            unreachable!("The value of a compound assignment is a binary expression");
+        };
+
+        let mut lowered_operands = Vec::with_capacity(place_operands.len() + 1);
+
+        for operand in place_operands {
+            let lowered_operand = match operand {
+                Expr::Literal(_) | Expr::IdentifierLookup(_) => (*operand).clone(),
+                _ => {
+                    let mut token_vec = Vec::new();
+                    operand.push_to_token_vec(&mut token_vec);
+
+                    let lowered_operand = self.lower_expr(operand, cfg, context);
+                    self.define_temporary(lowered_operand, token_vec[0], cfg, context)
+                }
+            };
+
+            lowered_operands.push(lowered_operand);
+        }
+
+        let value = Expr::BinaryExpr(BinaryExpr {
+            left: Box::new(read_place(lowered_operands.clone())),
+            operator: binary_expr.operator,
+            right: binary_expr.right.clone(),
+        });
+        lowered_operands.push(self.lower_expr(&value, cfg, context));
+
+        lowered_operands
+    }
+
+    fn is_call_expr(expr: &Expr) -> bool {
+        matches!(expr, Expr::CallExpr(_) | Expr::CallValueExpr(_) | Expr::MethodCallExpr(_))
+    }
+
+    fn define_temporary(
+        &self,
+        value: Expr,
+        token_metadata: TokenMetadata,
+        cfg: &mut CFG,
+        context: &mut CFGContext
+    ) -> Expr {
+        let lexeme = context.new_temporary();
+
+        cfg.add_node(
+            CFGNode::Process(
+                CFGProcessNode::new(
+                    Self::variable_dag(DAGOp::Define, &lexeme, Some(&value)),
+                    cfg.get_next_node_id(),
+                    CFGNodeState::Alive
+                )
+            )
+        );
+
+        Expr::IdentifierLookup(AstIdentifier::new(lexeme, token_metadata))
+    }
+
+    // Variables named `_` aren't defined
+    fn lower_destructuring_definition(
+        &self,
+        variables: &[DestructuredVariable],
+        value: &Expr,
+        token_metadata: TokenMetadata,
+        cfg: &mut CFG,
+        context: &mut CFGContext
+    ) {
+        let value = self.lower_expr(value, cfg, context);
+        let tuple = self.define_temporary(value, token_metadata, cfg, context);
+
+        for (index, variable) in variables.iter().enumerate() {
+            if variable.name == "_" {
+                continue;
+            }
+
+            let element = Expr::MemberAccessExpr(MemberAccessExpr {
+                target: Box::new(tuple.clone()),
+                member: AstIdentifier::new(index.to_string(), variable.token_metadata),
+                field_index: index,
+            });
+
+            cfg.add_node(
+                CFGNode::Process(
+                    CFGProcessNode::new(
+                        Self::variable_dag(DAGOp::Define, &variable.name, Some(&element)),
+                        cfg.get_next_node_id(),
+                        CFGNodeState::Alive
+                    )
+                )
+            );
+        }
+    }
+
+    // The condition is the left operand for &&, its negation for || and `== none` for ??
+    fn lower_logical_expr(
+        &self,
+        logical_expr: &LogicalExpr,
+        cfg: &mut CFG,
+        context: &mut CFGContext
+    ) -> Expr {
+        let left = self.lower_expr(&logical_expr.left, cfg, context);
+        let result = self.define_temporary(left, logical_expr.token_metadata, cfg, context);
+
+        let condition = match logical_expr.operator {
+            LogicalOp::And => result.clone(),
+            LogicalOp::Or =>
+                Expr::UnaryExpr(UnaryExpr {
+                    operator: UnaryOp::Truthy,
+                    right: Box::new(result.clone()),
+                }),
+            LogicalOp::Coalesce =>
+                Expr::BinaryExpr(BinaryExpr {
+                    left: Box::new(result.clone()),
+                    operator: BinaryOp::Equal,
+                    right: Box::new(
+                        Expr::Literal(AstValue::new(Value::None, logical_expr.token_metadata))
+                    ),
+                }),
+        };
+
+        let decision_node_id = cfg.get_current_node_id();
+        cfg.add_node(
+            CFGNode::Decision(
+                CFGDecisionNode::new(
+                    condition.compile_to_dag(),
+                    cfg.get_next_node_id(),
+                    0,
+                    CFGNodeState::Alive
+                )
+            )
+        );
+
+        let right = self.lower_expr(&logical_expr.right, cfg, context);
+        let Expr::IdentifierLookup(result_identifier) = &result else {
+            unreachable!("Temporaries are identifier lookups");
+        };
+
+        cfg.add_node(
+            CFGNode::Process(
+                CFGProcessNode::new(
+                    Self::variable_dag(DAGOp::Assign, &result_identifier.lexeme, Some(&right)),
+                    cfg.get_next_node_id(),
+                    CFGNodeState::Alive
+                )
+            )
+        );
+
+        let next_id = cfg.get_current_node_id();
+        if let CFGNode::Decision(decision_node) = cfg.get_node_mut(decision_node_id) {
+            decision_node.false_branch_id = next_id;
+        }
+
+        result
+    }
+
+    // The last arm needs no decision, since the match is exhaustive
+    fn lower_match_expr(&self, match_expr: &MatchExpr, cfg: &mut CFG, context: &mut CFGContext) -> Expr {
+        let token_metadata = match_expr.token_metadata;
+
+        let value = self.lower_expr(&match_expr.value, cfg, context);
+        let value = self.define_temporary(value, token_metadata, cfg, context);
+        let Expr::IdentifierLookup(value_identifier) = &value else {
+            unreachable!("Temporaries are identifier lookups");
+        };
+
+        let result = match match_expr.value_type {
+            ValueType::Void => None,
+            _ => {
+                let lexeme = context.new_temporary();
+                cfg.add_node(
+                    CFGNode::Process(
+                        CFGProcessNode::new(
+                            Self::variable_dag(DAGOp::Define, &lexeme, None),
+                            cfg.get_next_node_id(),
+                            CFGNodeState::Alive
+                        )
+                    )
+                );
+                Some(lexeme)
+            }
+        };
+
+        let mut arm_end_ids = Vec::new();
+
+        for (i, arm) in match_expr.arms.iter().enumerate() {
+            let is_last_arm = i == match_expr.arms.len() - 1;
+
+            // A wildcard arm is always last, so it never needs a decision
+            let decision_node_id = match arm.tag {
+                Some(tag) if !is_last_arm => {
+                    let mut condition = DAG::new();
+                    let value_id = condition.add_node(
+                        DAGNode::new(DAGOp::Identifier(value_identifier.lexeme.clone()), None)
+                    );
+                    let value_tag_id = condition.add_node(
+                        DAGNode::new(DAGOp::GetTag, Some(vec![value_id]))
+                    );
+                    let tag_id = condition.add_node(
+                        DAGNode::new(DAGOp::Const(Value::Int32(tag as i32)), None)
+                    );
+                    let entry_node_id = condition.add_node(
+                        DAGNode::new(DAGOp::BinaryOp(BinaryOp::Equal), Some(vec![value_tag_id, tag_id]))
+                    );
+                    condition.set_entry_node_id(entry_node_id);
+
+                    let decision_node_id = cfg.get_current_node_id();
+                    cfg.add_node(
+                        CFGNode::Decision(
+                            CFGDecisionNode::new(
+                                condition,
+                                cfg.get_next_node_id(),
+                                0,
+                                CFGNodeState::Alive
+                            )
+                        )
+                    );
+                    Some(decision_node_id)
+                }
+                _ => None,
+            };
+
+            cfg.add_node(CFGNode::ScopeStart(cfg.get_next_node_id()));
+            context.scope_depth += 1;
+
+            for (index, binding) in arm.bindings.iter().enumerate() {
+                if binding.lexeme == "_" {
+                    continue;
+                }
+
+                let mut dag = DAG::new();
+                let value_id = dag.add_node(
+                    DAGNode::new(DAGOp::Identifier(value_identifier.lexeme.clone()), None)
+                );
+                let payload_id = dag.add_node(
+                    DAGNode::new(DAGOp::GetPayload(index), Some(vec![value_id]))
+                );
+                let lexeme_id = dag.add_node(
+                    DAGNode::new(DAGOp::Identifier(binding.lexeme.clone()), None)
+                );
+                let entry_node_id = dag.add_node(
+                    DAGNode::new(DAGOp::Define, Some(vec![lexeme_id, payload_id]))
+                );
+                dag.set_entry_node_id(entry_node_id);
+
+                cfg.add_node(
+                    CFGNode::Process(
+                        CFGProcessNode::new(dag, cfg.get_next_node_id(), CFGNodeState::Alive)
+                    )
+                );
+            }
+
+            let body = self.lower_expr(&arm.body, cfg, context);
+            let dag = match &result {
+                Some(result) => Self::variable_dag(DAGOp::Assign, result, Some(&body)),
+                None => body.compile_to_dag(),
+            };
+            cfg.add_node(
+                CFGNode::Process(
+                    CFGProcessNode::new(dag, cfg.get_next_node_id(), CFGNodeState::Alive)
+                )
+            );
+
+            context.scope_depth -= 1;
+            cfg.add_node(CFGNode::ScopeEnd(cfg.get_next_node_id()));
+            arm_end_ids.push(cfg.get_current_node_id() - 1);
+
+            if let Some(decision_node_id) = decision_node_id {
+                let next_arm_id = cfg.get_current_node_id();
+                if let CFGNode::Decision(decision_node) = cfg.get_node_mut(decision_node_id) {
+                    decision_node.false_branch_id = next_arm_id;
+                }
+            }
+        }
+
+        let next_id = cfg.get_current_node_id();
+        for arm_end_id in arm_end_ids {
+            cfg.get_node_mut(arm_end_id).set_next_id(next_id);
+        }
+
+        match result {
+            Some(result) => Expr::IdentifierLookup(AstIdentifier::new(result, token_metadata)),
+            None => Expr::Literal(AstValue::new(Value::Empty, token_metadata)),
+        }
+    }
+
+    fn generate_loop_body_cfg(
+        &self,
+        body: &ScopeStmt,
+        definitions: Vec<DAG>,
+        cfg: &mut CFG,
+        context: &mut CFGContext
+    ) -> LoopContext {
+        context.loops.push(LoopContext {
+            scope_depth: context.scope_depth,
+            break_node_ids: Vec::new(),
+            continue_node_ids: Vec::new(),
+        });
+
+        self.generate_scope_cfg_with_definitions(body, definitions, cfg, context);
+
+        context.loops.pop().unwrap()
+    }
+
+    fn patch_loop_jumps(
+        loop_context: LoopContext,
+        continue_id: usize,
+        break_id: usize,
+        cfg: &mut CFG
+    ) {
+        for node_id in loop_context.continue_node_ids {
+            cfg.get_node_mut(node_id).set_next_id(continue_id);
+        }
+        for node_id in loop_context.break_node_ids {
+            cfg.get_node_mut(node_id).set_next_id(break_id);
         }
     }
 }
@@ -1,16 +1,14 @@
 use crate::{
     operations::{ BinaryOp, UnaryOp },
     parser::{ ast_generator::AstEnvironment, token::TokenMetadata },
-    value::ValueType,
 };
 
-use self::{ expr::Expr, stmt::{ FunctionArgument, FunctionStmt, ScopeStmt, Stmt } };
+use self::{ expr::Expr, stmt::{ ForStmt, FunctionStmt, IfStmt, ScopeStmt, Stmt, WhileStmt } };
 
 mod generate_cfg;
 pub mod expr;
 pub mod stmt;
 mod symbol_table;
-mod type_check;
 
 #[derive(Debug)]
 pub struct Ast {
@@ -35,12 +33,7 @@ impl Ast {
         }
     }
 
-    pub fn start_function(
-        &mut self,
-        name: String,
-        args: Vec<FunctionArgument>,
-        return_type: Option<ValueType>
-    ) {
+    pub fn start_function(&mut self, function_stmt: FunctionStmt) {
         if self.current_scope_ptr.is_none() {
             self.current_scope_ptr = Some(&mut self.main_scope);
         }
@@ -49,60 +42,84 @@ impl Ast {
 
         unsafe {
             self.path_to_parent_scope.push(current_scope);
-            let new_index = (*current_scope).forwards_declarations.len();
-            (*current_scope).forwards_declarations.push(
-                Stmt::FunctionStmt(FunctionStmt {
-                    name,
-                    args,
-                    return_type,
-                    body: ScopeStmt::new(),
-                })
-            );
-
-            let new_scope = match &mut (*current_scope).forwards_declarations[new_index] {
+            let forwards_declarations = &mut (*current_scope).forwards_declarations;
+            forwards_declarations.push(Stmt::FunctionStmt(function_stmt));
+
+            let new_scope = match forwards_declarations.last_mut().unwrap() {
                 Stmt::FunctionStmt(FunctionStmt { ref mut body, .. }) => { body }
 
-                _ =>
-                    panic!(
-                        "Expected scope stmt: {:?}",
-                        (*current_scope).forwards_declarations[new_index]
-                    ),
+                stmt => panic!("Expected scope stmt: {:?}", stmt),
             };
             self.current_scope_ptr = Some(new_scope);
         }
     }
 
     pub fn end_function(&mut self) {
-        if self.current_scope_ptr.is_none() {
-            println!("No scope to end, so must be main scope");
-        } else {
-            if let Some(parent_scope) = self.path_to_parent_scope.pop() {
-                self.current_scope_ptr = Some(parent_scope);
-            } else {
-                self.current_scope_ptr = None;
-            }
-        }
+        self.end_scope();
+    }
 
-        println!("{:#?}", self)
+    pub fn get_current_scope_mut(&mut self) -> &mut ScopeStmt {
+        match self.current_scope_ptr {
+            Some(scope) => unsafe { &mut *scope },
+            None => &mut self.main_scope,
+        }
     }
 
     pub fn start_scope(&mut self) {
+        self.push_stmt_and_enter(Stmt::ScopeStmt(ScopeStmt::new()));
+    }
+
+    pub fn start_if(&mut self, condition: Expr) {
+        self.push_stmt_and_enter(Stmt::IfStmt(IfStmt::new(condition)));
+    }
+
+    pub fn start_while(&mut self, condition: Expr) {
+        self.push_stmt_and_enter(Stmt::WhileStmt(WhileStmt::new(condition)));
+    }
+
+    pub fn start_for(&mut self, for_stmt: ForStmt) {
+        self.push_stmt_and_enter(Stmt::ForStmt(for_stmt));
+    }
+
+    fn push_stmt_and_enter(&mut self, stmt: Stmt) {
         if self.current_scope_ptr.is_none() {
             self.current_scope_ptr = Some(&mut self.main_scope);
-        } else {
-            let current_scope = self.current_scope_ptr.unwrap();
-            unsafe {
-                self.path_to_parent_scope.push(current_scope);
-                let new_index = (*current_scope).cf_stmts.len();
-                (*current_scope).cf_stmts.push(Stmt::ScopeStmt(ScopeStmt::new()));
-
-                let new_scope = match (*current_scope).cf_stmts[new_index] {
-                    Stmt::ScopeStmt(ref mut scope) => scope,
-                    _ => panic!("Expected scope stmt: {:?}", (*current_scope).cf_stmts[new_index]),
-                };
-                self.current_scope_ptr = Some(new_scope);
-            }
         }
+
+        let current_scope = self.current_scope_ptr.unwrap();
+        unsafe {
+            self.path_to_parent_scope.push(current_scope);
+            let cf_stmts = &mut (*current_scope).cf_stmts;
+            cf_stmts.push(stmt);
+
+            let new_scope = match cf_stmts.last_mut().unwrap() {
+                Stmt::ScopeStmt(ref mut scope) => scope,
+                Stmt::IfStmt(IfStmt { ref mut true_block, .. }) => true_block,
+                Stmt::WhileStmt(WhileStmt { ref mut body, .. }) => body,
+                Stmt::ForStmt(ForStmt { ref mut body, .. }) => body,
+                stmt => panic!("Expected stmt with a block: {:?}", stmt),
+            };
+            self.current_scope_ptr = Some(new_scope);
+        }
+    }
+
+    pub fn start_else(&mut self) {
+        let parent_scope = *self.path_to_parent_scope.last().expect("No if statement to else");
+
+        unsafe {
+            let cf_stmts = &mut (*parent_scope).cf_stmts;
+            let new_scope = match cf_stmts.last_mut().unwrap() {
+                Stmt::IfStmt(IfStmt { ref mut false_block, .. }) => {
+                    false_block.insert(ScopeStmt::new())
+                }
+                stmt => panic!("Expected if stmt: {:?}", stmt),
+            };
+            self.current_scope_ptr = Some(new_scope);
+        }
+    }
+
+    pub fn end_if(&mut self) {
+        self.end_scope();
     }
 
     pub fn end_scope(&mut self) {
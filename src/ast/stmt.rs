@@ -2,16 +2,23 @@ use std::rc::Rc;
 
 use crate::{ parser::{ self, token::{ Token, TokenMetadata } }, value::ValueType };
 
-use super::{ expr::{ AstIdentifier, Expr }, type_check::{ AstEnvironment, SymbolTypeDef } };
+use super::expr::{ AstIdentifier, Expr, IndexExpr };
 
 #[derive(Debug)]
 pub enum Stmt {
     ExprStmt(Expr),
     VariableDefinition(VariableDefinitionStmt),
     VariableAssignment(VariableAssignmentStmt),
+    IndexAssignment(IndexAssignmentStmt),
     ScopeStmt(ScopeStmt),
     FunctionStmt(FunctionStmt),
-    TypeDefStmt(TypeDefStmt),
+    IfStmt(IfStmt),
+    WhileStmt(WhileStmt),
+    ForStmt(ForStmt),
+    BreakStmt,
+    ContinueStmt,
+    ReturnStmt(ReturnStmt),
+    PrintStmt(Expr),
 }
 
 impl Stmt {
@@ -21,27 +28,97 @@ impl Stmt {
         token_vec: &mut Vec<TokenMetadata>
     ) -> Result<(), String> {
         match self {
-            Stmt::TypeDefStmt(_) => { Ok(()) }
-            Stmt::FunctionStmt(_) => {
-                // let mut function_environment = AstEnvironment::new();
-                // for parameter in parameters {
-                //     function_environment.insert(
-                //         parameter.name.to_string(),
-                //         parameter.value_type,
-                //         parameter.is_mutable,
-                //         true
-                //     );
-                // }
-
-                // body.type_check(&mut function_environment, token_vec)?;
-
-                Ok(())
-            }
+            Stmt::FunctionStmt(_) => { Ok(()) } // The body is checked while it's parsed
             Stmt::ScopeStmt(_) => { Ok(()) }
+            Stmt::IfStmt(_) => { Ok(()) } // The condition is checked in AstGenerator::start_if
+            Stmt::WhileStmt(_) => { Ok(()) } // The condition is checked in AstGenerator::start_while
+            Stmt::ForStmt(_) => { Ok(()) } // The iterable is checked in AstGenerator::start_for
+            Stmt::BreakStmt | Stmt::ContinueStmt => { Ok(()) }
+            Stmt::ReturnStmt(_) => { Ok(()) } // The value is checked in AstGenerator::emit_return
             Stmt::ExprStmt(expr) => {
                 expr.type_check(ast_environment, token_vec)?;
                 Ok(())
             }
+            Stmt::PrintStmt(expr) => {
+                let value_type = expr.type_check(ast_environment, token_vec)?;
+
+                if value_type.is(&ValueType::Void) {
+                    expr.push_to_token_vec(token_vec);
+                    return Err("Cannot print a value of type void".to_string());
+                }
+
+                Ok(())
+            }
+            Stmt::VariableDefinition(
+                VariableDefinitionStmt { destructured: Some(variables), value: Some(value), .. },
+            ) => {
+                let element_types = match value.type_check(ast_environment, token_vec) {
+                    Ok(ValueType::Tuple(element_types)) if element_types.len() == variables.len() => {
+                        Ok(element_types)
+                    }
+                    Ok(value_type) => {
+                        value.push_to_token_vec(token_vec);
+
+                        Err(match value_type {
+                            ValueType::Tuple(element_types) =>
+                                format!(
+                                    "Cannot destructure a tuple of {} elements into {} variables",
+                                    element_types.len(),
+                                    variables.len()
+                                ),
+                            value_type =>
+                                format!(
+                                    "Cannot destructure a value of type {}, since it's not a tuple",
+                                    value_type.to_type_string()
+                                ),
+                        })
+                    }
+                    Err(e) => Err(e),
+                };
+
+                let element_types = match element_types {
+                    Ok(element_types) => element_types,
+                    Err(e) => {
+                        for variable in variables.iter() {
+                            ast_environment.insert(
+                                variable.name.to_string(),
+                                ValueType::Unkown,
+                                variable.is_mutable,
+                                true
+                            );
+                        }
+
+                        return Err(e);
+                    }
+                };
+
+                let mut result = Ok(());
+
+                for (variable, element_type) in variables.iter().zip(element_types) {
+                    if variable.name == "_" {
+                        continue;
+                    }
+
+                    if element_type.has_unknown_element_type() && result.is_ok() {
+                        value.push_to_token_vec(token_vec);
+                        result = Err(
+                            format!(
+                                "Cannot infer the element type of an empty array, so it can't be bound to variable '{}'",
+                                variable.name
+                            )
+                        );
+                    }
+
+                    ast_environment.insert(
+                        variable.name.to_string(),
+                        element_type,
+                        variable.is_mutable,
+                        true
+                    );
+                }
+
+                result
+            }
             Stmt::VariableDefinition(variable_definition) => {
                 let resulted_value_type = match &variable_definition.value {
                     Some(value) => {
@@ -62,6 +139,47 @@ impl Stmt {
                     None => ValueType::Empty,
                 };
 
+                if
+                    variable_definition.value_type.is_none() &&
+                    resulted_value_type.has_unknown_element_type()
+                {
+                    if let Some(value) = &variable_definition.value {
+                        value.push_to_token_vec(token_vec);
+                    }
+                    token_vec.push(variable_definition.token_metadata);
+
+                    ast_environment.insert(
+                        variable_definition.name.to_string(),
+                        ValueType::Unkown,
+                        variable_definition.is_mutable,
+                        true
+                    );
+
+                    return Err(match &resulted_value_type {
+                        ValueType::Class(_) =>
+                            format!(
+                                "Cannot infer the type arguments of {}, so variable '{}' needs a type annotation",
+                                resulted_value_type.to_type_string(),
+                                variable_definition.name
+                            ),
+                        ValueType::Optional(_) =>
+                            format!(
+                                "Cannot infer the type of 'none', so variable '{}' needs a type annotation",
+                                variable_definition.name
+                            ),
+                        ValueType::Map(key_type, _) if **key_type == ValueType::Empty =>
+                            format!(
+                                "Cannot infer the key and value types of an empty map, so variable '{}' needs a type annotation",
+                                variable_definition.name
+                            ),
+                        _ =>
+                            format!(
+                                "Cannot infer the element type of an empty array, so variable '{}' needs a type annotation",
+                                variable_definition.name
+                            ),
+                    });
+                }
+
                 let value_type = match &variable_definition.value_type {
                     Some(value_type) => {
                         if
@@ -95,6 +213,13 @@ impl Stmt {
                     None => &resulted_value_type,
                 };
 
+                let shared_check = match (&variable_definition.value, variable_definition.is_mutable) {
+                    (Some(value), true) => {
+                        check_shared_value(value, &variable_definition.name, value_type, ast_environment, token_vec)
+                    }
+                    _ => Ok(()),
+                };
+
                 ast_environment.insert(
                     variable_definition.name.to_string(),
                     value_type.clone(),
@@ -104,6 +229,50 @@ impl Stmt {
 
                 variable_definition.value_type = Some(value_type.clone());
 
+                shared_check
+            }
+            Stmt::VariableAssignment(
+                VariableAssignmentStmt { target_expr: Some(target_expr), field, value, .. },
+            ) => {
+                let class_type = target_expr.type_check_class(field, ast_environment, token_vec)?;
+                let value_type = value.type_check(ast_environment, token_vec)?;
+
+                let field_type = match ast_environment.get_field(&class_type, &field.lexeme) {
+                    Ok((_, class_field)) => {
+                        ast_environment.specialize_member_type(&class_type, &class_field.value_type)
+                    }
+                    Err(e) => {
+                        field.push_to_token_vec(token_vec);
+                        target_expr.push_to_token_vec(token_vec);
+                        return Err(e);
+                    }
+                };
+
+                if let Some(root_identifier) = target_expr.get_root_identifier() {
+                    if let Some((_, false, _)) = ast_environment.get(&root_identifier.lexeme) {
+                        field.push_to_token_vec(token_vec);
+                        target_expr.push_to_token_vec(token_vec);
+
+                        return Err(
+                            format!("Cannot mutate immutable variable '{}'", root_identifier.lexeme)
+                        );
+                    }
+                }
+
+                if !value_type.is(&field_type) {
+                    value.push_to_token_vec(token_vec);
+                    field.push_to_token_vec(token_vec);
+
+                    return Err(
+                        format!(
+                            "Field '{}' is of type {} but the assignment value is of type {}",
+                            field.lexeme,
+                            field_type.to_type_string(),
+                            value_type.to_type_string()
+                        )
+                    );
+                }
+
                 Ok(())
             }
             Stmt::VariableAssignment(variable_assignment) => {
@@ -118,44 +287,44 @@ impl Stmt {
 
                 let variable = ast_environment.get(&variable_assignment.field.get_lexeme());
 
-                if let Some((value_type, is_mutable, is_initialized)) = variable {
-                    if !is_initialized {
-                        println!(
-                            "Found non-initialized variable: '{}'.\nTODO: Implement checks when conditional blocks are implemented.",
-                            variable_assignment.field.get_lexeme()
-                        );
+                if let Some((value_type, is_mutable, _)) = variable {
+                    if is_mutable {
+                        check_shared_value(
+                            &variable_assignment.value,
+                            &variable_assignment.field.lexeme,
+                            &value_type,
+                            ast_environment,
+                            token_vec
+                        )?;
                     }
-                    if !is_mutable && is_initialized {
+
+                    if !&resulted_value_type.is(&value_type) {
                         variable_assignment.value.push_to_token_vec(token_vec);
                         variable_assignment.field.push_to_token_vec(token_vec);
 
-                        return Err(
+                        let error_message = if value_type.is(&ValueType::Unkown) {
                             format!(
-                                "Cannot mutate immutable variable '{}'",
+                                "The tye of variable '{}' is unknown and cannot be assigned to",
                                 variable_assignment.field.lexeme
                             )
-                        );
-                    } else {
-                        if !&resulted_value_type.is(&value_type) {
-                            variable_assignment.value.push_to_token_vec(token_vec);
-                            variable_assignment.field.push_to_token_vec(token_vec);
+                        } else {
+                            format!(
+                                "Variable '{}' is of type {} but the assignment value is of type {}",
+                                variable_assignment.field.lexeme,
+                                value_type.to_type_string(),
+                                resulted_value_type.to_type_string()
+                            )
+                        };
 
-                            let error_message = if value_type.is(&ValueType::Unkown) {
-                                format!(
-                                    "The tye of variable '{}' is unknown and cannot be assigned to",
-                                    variable_assignment.field.lexeme
-                                )
-                            } else {
-                                format!(
-                                    "Variable '{}' is of type {} but the assignment value is of type {}",
-                                    variable_assignment.field.lexeme,
-                                    value_type.to_type_string(),
-                                    resulted_value_type.to_type_string()
-                                )
-                            };
+                        return Err(error_message);
+                    }
 
-                            return Err(error_message);
-                        }
+                    // Immutable variables can only be assigned if they're uninitialized
+                    if let Err(e) = ast_environment.initialize(&variable_assignment.field.lexeme) {
+                        variable_assignment.value.push_to_token_vec(token_vec);
+                        variable_assignment.field.push_to_token_vec(token_vec);
+
+                        return Err(e);
                     }
                 } else {
                     variable_assignment.value.push_to_token_vec(token_vec);
@@ -171,6 +340,82 @@ impl Stmt {
 
                 Ok(())
             }
+            Stmt::IndexAssignment(index_assignment) => {
+                let element_type = index_assignment.target.type_check(ast_environment, token_vec)?;
+                let value_type = index_assignment.value.type_check(ast_environment, token_vec)?;
+
+                if let Some(root_identifier) = index_assignment.target.target.get_root_identifier() {
+                    if let Some((_, false, _)) = ast_environment.get(&root_identifier.lexeme) {
+                        index_assignment.target.push_to_token_vec(token_vec);
+
+                        return Err(
+                            format!("Cannot mutate immutable variable '{}'", root_identifier.lexeme)
+                        );
+                    }
+                }
+
+                if !value_type.is(&element_type) {
+                    index_assignment.value.push_to_token_vec(token_vec);
+                    index_assignment.target.push_to_token_vec(token_vec);
+
+                    return Err(
+                        format!(
+                            "Array elements are of type {} but the assignment value is of type {}",
+                            element_type.to_type_string(),
+                            value_type.to_type_string()
+                        )
+                    );
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+// Arrays, maps and instances are shared when assigned, so mutability can't be added by copying
+fn check_shared_value(
+    value: &Expr,
+    variable_name: &str,
+    value_type: &ValueType,
+    ast_environment: &parser::ast_generator::AstEnvironment,
+    token_vec: &mut Vec<TokenMetadata>
+) -> Result<(), String> {
+    let Some(root_identifier) = value.get_root_identifier() else {
+        return Ok(());
+    };
+
+    match ast_environment.get(&root_identifier.lexeme) {
+        Some((_, false, _)) if value_type.is_shared() => {
+            value.push_to_token_vec(token_vec);
+
+            Err(
+                format!(
+                    "Cannot give mutable variable '{}' the value of immutable variable '{}', since values of type {} are shared rather than copied",
+                    variable_name,
+                    root_identifier.lexeme,
+                    value_type.to_type_string()
+                )
+            )
+        }
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug)]
+pub struct IndexAssignmentStmt {
+    pub target: IndexExpr,
+    pub value: Expr,
+    // The value is then `target[index] + b`, which reads the element being assigned
+    pub is_compound: bool,
+}
+
+impl IndexAssignmentStmt {
+    pub fn new(target: IndexExpr, value: Expr, is_compound: bool) -> Self {
+        Self {
+            target,
+            value,
+            is_compound,
         }
     }
 }
@@ -180,6 +425,8 @@ pub struct VariableAssignmentStmt {
     pub target_expr: Option<Expr>,
     pub field: AstIdentifier,
     pub value: Expr,
+    pub field_index: Option<usize>,
+    pub is_compound: bool,
 }
 
 impl VariableAssignmentStmt {
@@ -188,6 +435,24 @@ impl VariableAssignmentStmt {
             target_expr,
             field,
             value,
+            field_index: None,
+            is_compound: false,
+        }
+    }
+
+    pub fn new_field_assignment(
+        target_expr: Expr,
+        field: AstIdentifier,
+        field_index: usize,
+        value: Expr,
+        is_compound: bool
+    ) -> Self {
+        Self {
+            target_expr: Some(target_expr),
+            field,
+            value,
+            field_index: Some(field_index),
+            is_compound,
         }
     }
 }
@@ -199,6 +464,15 @@ pub struct VariableDefinitionStmt {
     pub is_mutable: bool,
     pub value: Option<Expr>,
     pub token_metadata: TokenMetadata,
+    // `name` is empty for a destructuring definition like `(a, mut b) := pair()`
+    pub destructured: Option<Vec<DestructuredVariable>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DestructuredVariable {
+    pub name: String,
+    pub is_mutable: bool,
+    pub token_metadata: TokenMetadata,
 }
 
 impl VariableDefinitionStmt {
@@ -215,6 +489,22 @@ impl VariableDefinitionStmt {
             is_mutable,
             value,
             token_metadata,
+            destructured: None,
+        }
+    }
+
+    pub fn new_destructuring(
+        variables: Vec<DestructuredVariable>,
+        value: Expr,
+        token_metadata: TokenMetadata
+    ) -> Self {
+        Self {
+            name: String::new(),
+            value_type: None,
+            is_mutable: false,
+            value: Some(value),
+            token_metadata,
+            destructured: Some(variables),
         }
     }
 }
@@ -243,34 +533,88 @@ impl ScopeStmt {
     //     }
     // }
 
-    pub fn forward_declare(&self, ast_environment: &mut AstEnvironment) {
-        for stmt in &self.cf_stmts {
+    pub fn push_stmt(&mut self, stmt: Stmt) {
+        self.cf_stmts.push(stmt);
+    }
+
+    pub fn diverges(&self) -> bool {
+        self.always_returns() ||
+            self.cf_stmts.iter().any(|stmt| matches!(stmt, Stmt::BreakStmt | Stmt::ContinueStmt))
+    }
+
+    pub fn always_returns(&self) -> bool {
+        self.cf_stmts.iter().any(|stmt| {
             match stmt {
-                Stmt::TypeDefStmt(type_def_stmt) => {
-                    let type_name = type_def_stmt.type_name.clone();
-                    let typing_value = match &type_def_stmt.typing.typing_value {
-                        TypingValue::Custom(_) => { panic!("Custom types not supported yet") }
-                        TypingValue::ValueType(value_type) => { value_type.clone() }
-                    };
-
-                    ast_environment.scopes[ast_environment.scope_depth].type_definitions.insert(
-                        type_name,
-                        SymbolTypeDef {
-                            value_type: typing_value,
-                        }
-                    );
+                Stmt::ReturnStmt(_) => true,
+                Stmt::ScopeStmt(scope_stmt) => scope_stmt.always_returns(),
+                Stmt::IfStmt(IfStmt { true_block, false_block: Some(false_block), .. }) => {
+                    true_block.always_returns() && false_block.always_returns()
                 }
-                _ => {}
+                _ => false,
             }
+        })
+    }
+
+    pub fn push_forward_stmt(&mut self, stmt: Stmt) {
+        self.forwards_declarations.push(stmt)
+    }
+}
+
+#[derive(Debug)]
+pub struct IfStmt {
+    pub condition: Expr,
+    pub true_block: ScopeStmt,
+    pub false_block: Option<ScopeStmt>,
+}
+
+impl IfStmt {
+    pub fn new(condition: Expr) -> Self {
+        Self {
+            condition,
+            true_block: ScopeStmt::new(),
+            false_block: None,
         }
     }
+}
 
-    pub fn push_stmt(&mut self, stmt: Stmt) {
-        self.cf_stmts.push(stmt);
+#[derive(Debug)]
+pub struct WhileStmt {
+    pub condition: Expr,
+    pub body: ScopeStmt,
+}
+
+impl WhileStmt {
+    pub fn new(condition: Expr) -> Self {
+        Self {
+            condition,
+            body: ScopeStmt::new(),
+        }
     }
+}
 
-    pub fn push_forward_stmt(&mut self, stmt: Stmt) {
-        self.forwards_declarations.push(stmt)
+#[derive(Debug)]
+pub enum ForIterable {
+    Range(Box<Expr>, Box<Expr>),
+    Map(Box<Expr>),
+}
+
+// Looping over a map with a single variable only gives the keys
+#[derive(Debug)]
+pub struct ForStmt {
+    pub variable: String,
+    pub value_variable: Option<String>,
+    pub iterable: ForIterable,
+    pub body: ScopeStmt,
+}
+
+impl ForStmt {
+    pub fn new(variable: String, value_variable: Option<String>, iterable: ForIterable) -> Self {
+        Self {
+            variable,
+            value_variable,
+            iterable,
+            body: ScopeStmt::new(),
+        }
     }
 }
 
@@ -283,24 +627,42 @@ pub struct FunctionArgument {
 
 #[derive(Debug)]
 pub struct FunctionStmt {
+    pub id: usize,
     pub name: String,
     pub args: Vec<FunctionArgument>,
-    pub return_type: Option<ValueType>,
+    pub return_type: ValueType,
     pub body: ScopeStmt,
+    pub captures: Vec<String>,
 }
 
 impl FunctionStmt {
     pub fn new(
+        id: usize,
         name: String,
         args: Vec<FunctionArgument>,
-        return_type: Option<ValueType>,
+        return_type: ValueType,
         body: ScopeStmt
     ) -> Self {
         Self {
+            id,
             name,
             args,
             return_type,
             body,
+            captures: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ReturnStmt {
+    pub value: Option<Expr>,
+}
+
+impl ReturnStmt {
+    pub fn new(value: Option<Expr>) -> Self {
+        Self {
+            value,
         }
     }
 }
@@ -308,25 +670,40 @@ impl FunctionStmt {
 #[derive(Debug)]
 pub struct TypeDefStmt {
     pub type_name: String,
+    pub type_params: Vec<AstIdentifier>,
     pub typing: Typing,
+    pub token_metadata: TokenMetadata,
 }
 
 impl TypeDefStmt {
-    pub fn new(type_name: String, typing: Typing) -> Self {
+    pub fn new(
+        type_name: String,
+        type_params: Vec<AstIdentifier>,
+        typing: Typing,
+        token_metadata: TokenMetadata
+    ) -> Self {
         Self {
             type_name,
+            type_params,
             typing,
+            token_metadata,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum TypingValue {
     ValueType(ValueType),
     Custom(String),
+    Array,
+    Map,
+    Optional,
+    Tuple,
+    Function,
 }
 
-#[derive(Debug)]
+// Unlike a `ValueType`, it can refer to types by name
+#[derive(Debug, Clone)]
 pub struct Typing {
     pub typing_value: TypingValue,
     pub token_metadata: TokenMetadata,
@@ -362,11 +739,76 @@ impl Typing {
         }
     }
 
-    pub fn new_custom(custom_name: String, token_metadata: TokenMetadata) -> Self {
+    pub fn new_array(element_typing: Typing, token_metadata: TokenMetadata) -> Self {
         Self {
-            typing_value: TypingValue::Custom(custom_name),
+            typing_value: TypingValue::Array,
             token_metadata,
-            type_args: None,
+            type_args: Some(vec![element_typing]),
+        }
+    }
+
+    pub fn new_map(key_typing: Typing, value_typing: Typing, token_metadata: TokenMetadata) -> Self {
+        Self {
+            typing_value: TypingValue::Map,
+            token_metadata,
+            type_args: Some(vec![key_typing, value_typing]),
+        }
+    }
+
+    pub fn new_optional(inner_typing: Typing, token_metadata: TokenMetadata) -> Self {
+        Self {
+            typing_value: TypingValue::Optional,
+            token_metadata,
+            type_args: Some(vec![inner_typing]),
+        }
+    }
+
+    pub fn new_tuple(element_typings: Vec<Typing>, token_metadata: TokenMetadata) -> Self {
+        Self {
+            typing_value: TypingValue::Tuple,
+            token_metadata,
+            type_args: Some(element_typings),
+        }
+    }
+
+    pub fn new_function(
+        mut parameter_typings: Vec<Typing>,
+        return_typing: Typing,
+        token_metadata: TokenMetadata
+    ) -> Self {
+        parameter_typings.push(return_typing);
+
+        Self {
+            typing_value: TypingValue::Function,
+            token_metadata,
+            type_args: Some(parameter_typings),
+        }
+    }
+
+    // The type parameters are names, or already resolved in an alias imported from a module
+    pub fn substitute(&self, type_params: &[Rc<str>], type_args: &[ValueType]) -> Self {
+        let typing_value = match &self.typing_value {
+            TypingValue::Custom(name) if self.type_args.is_none() => {
+                match type_params.iter().position(|type_param| &**type_param == name) {
+                    Some(index) => TypingValue::ValueType(type_args[index].clone()),
+                    None => self.typing_value.clone(),
+                }
+            }
+            TypingValue::ValueType(value_type) => {
+                TypingValue::ValueType(value_type.substitute(type_params, type_args))
+            }
+            typing_value => typing_value.clone(),
+        };
+
+        Self {
+            typing_value,
+            token_metadata: self.token_metadata,
+            type_args: self.type_args.as_ref().map(|type_args_typings| {
+                type_args_typings
+                    .iter()
+                    .map(|typing| typing.substitute(type_params, type_args))
+                    .collect()
+            }),
         }
     }
 }
@@ -26,8 +26,53 @@ impl CFGProcessNode {
 
 #[derive(Debug)]
 pub struct CFGDecisionNode {
-    pub condition: usize,
+    pub condition: DAG,
     pub true_branch_id: usize,
     pub false_branch_id: usize,
     pub state: CFGNodeState,
 }
+
+impl CFGDecisionNode {
+    pub fn new(
+        condition: DAG,
+        true_branch_id: usize,
+        false_branch_id: usize,
+        node_state: CFGNodeState
+    ) -> Self {
+        Self {
+            condition,
+            true_branch_id,
+            false_branch_id,
+            state: node_state,
+        }
+    }
+}
+
+// Ends the scopes it jumps out of
+#[derive(Debug)]
+pub struct CFGJumpNode {
+    pub next_id: usize,
+    pub ended_scopes: usize,
+}
+
+impl CFGJumpNode {
+    pub fn new(next_id: usize, ended_scopes: usize) -> Self {
+        Self {
+            next_id,
+            ended_scopes,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CFGReturnNode {
+    pub value: Option<DAG>,
+}
+
+impl CFGReturnNode {
+    pub fn new(value: Option<DAG>) -> Self {
+        Self {
+            value,
+        }
+    }
+}
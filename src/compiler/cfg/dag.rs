@@ -1,9 +1,13 @@
 use ahash::AHashMap;
 
+use std::rc::Rc;
+
 use crate::{
     operations::{ BinaryOp, UnaryOp },
-    value::Value,
-    vm::instructions::{ Instruction, InstructionRegister, InstructionSrc },
+    value::{ ClassLayout, EnumLayout, FunctionType, Value, ValueType },
+    value_v2::{ NativeFunction, NativeMethod },
+    parser::token::TokenMetadata,
+    vm::instructions::{ Bytecode, Capture, Instruction, InstructionRegister, InstructionSrc },
 };
 
 use super::{ ChangedState, DefinitionState, IREnvironment, RegistersMap };
@@ -16,12 +20,35 @@ pub enum DAGOp {
     Assign,
     Const(Value),
     Identifier(String),
+    Call(usize), // The operands are the arguments
+    CallNative(NativeMethod), // The operands are the receiver and the arguments
+    CallValue, // The operands are the function value and the arguments
+    Closure {
+        function: usize,
+        function_type: FunctionType,
+        captures: Vec<String>,
+    },
+    Builtin(NativeFunction), // The operands are the arguments
+    Print,
+    Array, // The operands are the elements
+    Map, // The operands are the keys, each followed by its value
+    Tuple, // The operands are the elements
+    Index, // The operands are the array and the index
+    SetIndex, // The operands are the array, the index and the value
+    Instance(Rc<ClassLayout>), // The operands are the fields
+    GetField(usize), // The operand is the instance
+    SetField(usize), // The operands are the instance and the value
+    Cast(ValueType), // The operand is the value
+    EnumVariant(Rc<EnumLayout>, usize), // The operands are the payload
+    GetTag, // The operand is the enum value
+    GetPayload(usize), // The operand is the enum value
 }
 
 #[derive(Debug, Clone)]
 pub struct DAGNode {
     pub op: DAGOp,
     pub operands: Option<Vec<usize>>,
+    pub token_metadata: Option<TokenMetadata>,
 }
 
 impl DAGNode {
@@ -29,8 +56,14 @@ impl DAGNode {
         Self {
             op,
             operands,
+            token_metadata: None,
         }
     }
+
+    pub fn with_location(mut self, token_metadata: Option<TokenMetadata>) -> Self {
+        self.token_metadata = token_metadata;
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -66,38 +99,92 @@ impl DAG {
         self.nodes.remove(&node_id);
     }
 
-    pub fn generate_bytecode(&self, registers_maps: &mut RegistersMap) -> Vec<Instruction> {
-        let mut bytecode = vec![];
+    // The result isn't used, so its register is freed right away
+    pub fn generate_bytecode(&self, registers_maps: &mut RegistersMap) -> Bytecode {
+        let mut bytecode = Bytecode::new();
 
         match self.generate_node_bytecode(self.entry_node_id, registers_maps, &mut bytecode) {
             InstructionSrc::Constant(value) => {
-                if bytecode.len() == 0 {
+                if bytecode.is_empty() {
                     let (register, scope) = registers_maps.assign_register();
                     bytecode.push(Instruction::Load {
                         reg: InstructionRegister::new(register, scope, false),
                         src: InstructionSrc::Constant(value),
                     });
+                    registers_maps.free_register(register, scope);
                 }
             }
+            InstructionSrc::Register(register) if !register.is_variable => {
+                registers_maps.free_register(register.register, register.scope);
+            }
             _ => {}
         }
 
         bytecode
     }
 
+    pub fn generate_src_bytecode(
+        &self,
+        registers_maps: &mut RegistersMap
+    ) -> (Bytecode, InstructionSrc) {
+        let mut bytecode = Bytecode::new();
+
+        let condition = self.generate_node_bytecode(
+            self.entry_node_id,
+            registers_maps,
+            &mut bytecode
+        );
+
+        (bytecode, condition)
+    }
+
+    pub fn get_assigned_variable(&self) -> Option<String> {
+        let entry_node = self.nodes.get(&self.entry_node_id)?;
+
+        match (&entry_node.op, &entry_node.operands) {
+            (DAGOp::Assign, Some(operands)) => {
+                match &self.nodes.get(&operands[0])?.op {
+                    DAGOp::Identifier(lexeme) => Some(lexeme.clone()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn get_closure_captures(&self) -> Vec<String> {
+        self.nodes
+            .values()
+            .flat_map(|node| {
+                match &node.op {
+                    DAGOp::Closure { captures, .. } => captures.clone(),
+                    _ => Vec::new(),
+                }
+            })
+            .collect()
+    }
+
     fn generate_node_bytecode(
         &self,
         node_id: usize,
         registers_maps: &mut RegistersMap,
-        bytecode: &mut Vec<Instruction>
+        bytecode: &mut Bytecode
     ) -> InstructionSrc {
         let node = self.nodes.get(&node_id).cloned().unwrap();
 
         match &node.op {
             DAGOp::Const(value) => { InstructionSrc::Constant(value.clone()) }
             DAGOp::Identifier(lexeme) => {
-                let (register, scope) = registers_maps.get_register(lexeme).unwrap();
-                InstructionSrc::Register(InstructionRegister::new(register, scope, true))
+                if let Some((register, scope)) = registers_maps.get_register(lexeme) {
+                    return InstructionSrc::Register(InstructionRegister::new(register, scope, true));
+                }
+
+                let index = registers_maps.get_upvalue(lexeme).expect("Undefined variable");
+                let (register, scope) = registers_maps.assign_register();
+                let dest = InstructionRegister::new(register, scope, false);
+
+                bytecode.push(Instruction::GetUpvalue { dest, index });
+                InstructionSrc::Register(dest)
             }
             DAGOp::UnaryOp(unary_op) => {
                 let operand = node.operands.unwrap()[0];
@@ -130,9 +217,14 @@ impl DAG {
                             dest,
                             src: right,
                         },
+                    UnaryOp::BitNot =>
+                        Instruction::BitNot {
+                            dest,
+                            src: right,
+                        },
                 };
 
-                bytecode.push(instruction);
+                bytecode.push_with_location(instruction, node.token_metadata);
                 InstructionSrc::Register(dest)
             }
             DAGOp::BinaryOp(binary_op) => {
@@ -168,36 +260,303 @@ impl DAG {
 
                 let dest = InstructionRegister::new(register, scope, false);
 
-                let instruction = match binary_op {
-                    BinaryOp::Add =>
-                        Instruction::Add {
-                            dest,
-                            src1: left,
-                            src2: right,
-                        },
-                    BinaryOp::Sub =>
-                        Instruction::Sub {
-                            dest,
-                            src1: left,
-                            src2: right,
-                        },
-                    BinaryOp::Mul =>
-                        Instruction::Mul {
-                            dest,
-                            src1: left,
-                            src2: right,
-                        },
-                    BinaryOp::Div =>
-                        Instruction::Div {
-                            dest,
-                            src1: left,
-                            src2: right,
-                        },
-                };
+                let instruction = Instruction::new_binary(binary_op, dest, left, right);
 
-                bytecode.push(instruction);
+                bytecode.push_with_location(instruction, node.token_metadata);
+                InstructionSrc::Register(dest)
+            }
+            DAGOp::Call(function) => {
+                let args = node.operands
+                    .unwrap()
+                    .iter()
+                    .map(|arg| self.generate_node_bytecode(*arg, registers_maps, bytecode))
+                    .collect::<Vec<_>>();
+
+                for arg in &args {
+                    if let InstructionSrc::Register(arg_register) = arg {
+                        if !arg_register.is_variable {
+                            registers_maps.free_register(arg_register.register, arg_register.scope);
+                        }
+                    }
+                }
+
+                let (register, scope) = registers_maps.assign_register();
+
+                let dest = InstructionRegister::new(register, scope, false);
+
+                bytecode.push(Instruction::Call {
+                    dest,
+                    function: *function,
+                    args,
+                });
+                InstructionSrc::Register(dest)
+            }
+            DAGOp::CallNative(method) => {
+                let mut args = self.generate_operands_bytecode(
+                    node.operands.unwrap(),
+                    registers_maps,
+                    bytecode
+                );
+                let receiver = args.remove(0);
+
+                let (register, scope) = registers_maps.assign_register();
+
+                let dest = InstructionRegister::new(register, scope, false);
+
+                let instruction = Instruction::CallNative { dest, method: *method, receiver, args };
+
+                bytecode.push_with_location(instruction, node.token_metadata);
+                InstructionSrc::Register(dest)
+            }
+            DAGOp::CallValue => {
+                let mut args = self.generate_operands_bytecode(
+                    node.operands.unwrap(),
+                    registers_maps,
+                    bytecode
+                );
+                let callee = args.remove(0);
+
+                let (register, scope) = registers_maps.assign_register();
+
+                let dest = InstructionRegister::new(register, scope, false);
+
+                bytecode.push(Instruction::CallValue { dest, callee, args });
+                InstructionSrc::Register(dest)
+            }
+            DAGOp::Closure { function, function_type, captures } => {
+                let captures = captures
+                    .iter()
+                    .map(|lexeme| {
+                        match registers_maps.get_register(lexeme) {
+                            Some((register, scope)) =>
+                                Capture::Local(InstructionRegister::new(register, scope, true)),
+                            None =>
+                                Capture::Upvalue(
+                                    registers_maps.get_upvalue(lexeme).expect("Undefined variable")
+                                ),
+                        }
+                    })
+                    .collect();
+
+                let (register, scope) = registers_maps.assign_register();
+
+                let dest = InstructionRegister::new(register, scope, false);
+
+                bytecode.push(Instruction::NewClosure {
+                    dest,
+                    function: *function,
+                    function_type: function_type.clone(),
+                    captures,
+                });
+                InstructionSrc::Register(dest)
+            }
+            DAGOp::Builtin(function) => {
+                let args = self.generate_operands_bytecode(
+                    node.operands.unwrap(),
+                    registers_maps,
+                    bytecode
+                );
+
+                let (register, scope) = registers_maps.assign_register();
+
+                let dest = InstructionRegister::new(register, scope, false);
+
+                let instruction = Instruction::CallBuiltin { dest, function: *function, args };
+
+                bytecode.push_with_location(instruction, node.token_metadata);
+                InstructionSrc::Register(dest)
+            }
+            DAGOp::Array => {
+                let elements = self.generate_operands_bytecode(
+                    node.operands.unwrap(),
+                    registers_maps,
+                    bytecode
+                );
+
+                let (register, scope) = registers_maps.assign_register();
+
+                let dest = InstructionRegister::new(register, scope, false);
+
+                bytecode.push(Instruction::NewArray { dest, elements });
+                InstructionSrc::Register(dest)
+            }
+            DAGOp::Map => {
+                let operands = self.generate_operands_bytecode(
+                    node.operands.unwrap(),
+                    registers_maps,
+                    bytecode
+                );
+                let entries = operands
+                    .chunks(2)
+                    .map(|entry| (entry[0].clone(), entry[1].clone()))
+                    .collect();
+
+                let (register, scope) = registers_maps.assign_register();
+
+                let dest = InstructionRegister::new(register, scope, false);
+
+                bytecode.push(Instruction::NewMap { dest, entries });
+                InstructionSrc::Register(dest)
+            }
+            DAGOp::Tuple => {
+                let elements = self.generate_operands_bytecode(
+                    node.operands.unwrap(),
+                    registers_maps,
+                    bytecode
+                );
+
+                let (register, scope) = registers_maps.assign_register();
+
+                let dest = InstructionRegister::new(register, scope, false);
+
+                bytecode.push(Instruction::NewTuple { dest, elements });
+                InstructionSrc::Register(dest)
+            }
+            DAGOp::Index => {
+                let mut operands = self.generate_operands_bytecode(
+                    node.operands.unwrap(),
+                    registers_maps,
+                    bytecode
+                );
+                let index = operands.pop().unwrap();
+                let src = operands.pop().unwrap();
+
+                let (register, scope) = registers_maps.assign_register();
+
+                let dest = InstructionRegister::new(register, scope, false);
+
+                bytecode.push_with_location(Instruction::Index { dest, src, index }, node.token_metadata);
+                InstructionSrc::Register(dest)
+            }
+            DAGOp::SetIndex => {
+                let mut operands = self.generate_operands_bytecode(
+                    node.operands.unwrap(),
+                    registers_maps,
+                    bytecode
+                );
+                let src = operands.pop().unwrap();
+                let index = operands.pop().unwrap();
+                let array = operands.pop().unwrap();
+
+                bytecode.push_with_location(Instruction::SetIndex { array, index, src }, node.token_metadata);
+                // Like printing, assigning to an element produces no value
+                InstructionSrc::Constant(Value::Empty)
+            }
+            DAGOp::Instance(layout) => {
+                let fields = self.generate_operands_bytecode(
+                    node.operands.unwrap(),
+                    registers_maps,
+                    bytecode
+                );
+
+                let (register, scope) = registers_maps.assign_register();
+
+                let dest = InstructionRegister::new(register, scope, false);
+
+                bytecode.push(Instruction::NewInstance { dest, layout: layout.clone(), fields });
+                InstructionSrc::Register(dest)
+            }
+            DAGOp::Cast(value_type) => {
+                let mut operands = self.generate_operands_bytecode(
+                    node.operands.unwrap(),
+                    registers_maps,
+                    bytecode
+                );
+                let src = operands.pop().unwrap();
+
+                let (register, scope) = registers_maps.assign_register();
+
+                let dest = InstructionRegister::new(register, scope, false);
+
+                bytecode.push_with_location(Instruction::Cast { dest, src, value_type: value_type.clone() }, node.token_metadata);
+                InstructionSrc::Register(dest)
+            }
+            DAGOp::GetField(field) => {
+                let mut operands = self.generate_operands_bytecode(
+                    node.operands.unwrap(),
+                    registers_maps,
+                    bytecode
+                );
+                let src = operands.pop().unwrap();
+
+                let (register, scope) = registers_maps.assign_register();
+
+                let dest = InstructionRegister::new(register, scope, false);
+
+                bytecode.push(Instruction::GetField { dest, src, field: *field });
+                InstructionSrc::Register(dest)
+            }
+            DAGOp::SetField(field) => {
+                let mut operands = self.generate_operands_bytecode(
+                    node.operands.unwrap(),
+                    registers_maps,
+                    bytecode
+                );
+                let src = operands.pop().unwrap();
+                let object = operands.pop().unwrap();
+
+                bytecode.push(Instruction::SetField { object, field: *field, src });
+                // Like assigning to an element, assigning to a field produces no value
+                InstructionSrc::Constant(Value::Empty)
+            }
+            DAGOp::EnumVariant(layout, tag) => {
+                let payload = self.generate_operands_bytecode(
+                    node.operands.unwrap(),
+                    registers_maps,
+                    bytecode
+                );
+
+                let (register, scope) = registers_maps.assign_register();
+
+                let dest = InstructionRegister::new(register, scope, false);
+
+                bytecode.push(Instruction::NewEnum { dest, layout: layout.clone(), tag: *tag, payload });
                 InstructionSrc::Register(dest)
             }
+            DAGOp::GetTag => {
+                let mut operands = self.generate_operands_bytecode(
+                    node.operands.unwrap(),
+                    registers_maps,
+                    bytecode
+                );
+                let src = operands.pop().unwrap();
+
+                let (register, scope) = registers_maps.assign_register();
+
+                let dest = InstructionRegister::new(register, scope, false);
+
+                bytecode.push(Instruction::GetTag { dest, src });
+                InstructionSrc::Register(dest)
+            }
+            DAGOp::GetPayload(index) => {
+                let mut operands = self.generate_operands_bytecode(
+                    node.operands.unwrap(),
+                    registers_maps,
+                    bytecode
+                );
+                let src = operands.pop().unwrap();
+
+                let (register, scope) = registers_maps.assign_register();
+
+                let dest = InstructionRegister::new(register, scope, false);
+
+                bytecode.push(Instruction::GetPayload { dest, src, index: *index });
+                InstructionSrc::Register(dest)
+            }
+            DAGOp::Print => {
+                let operand = node.operands.unwrap()[0];
+                let src = self.generate_node_bytecode(operand, registers_maps, bytecode);
+
+                if let InstructionSrc::Register(register) = &src {
+                    if !register.is_variable {
+                        registers_maps.free_register(register.register, register.scope);
+                    }
+                }
+
+                bytecode.push(Instruction::Print { src });
+                // Printing produces no value
+                InstructionSrc::Constant(Value::Empty)
+            }
             DAGOp::Define => {
                 let operands = node.operands.unwrap();
 
@@ -212,6 +571,13 @@ impl DAG {
                     None => InstructionSrc::Constant(Value::Empty),
                 };
 
+                // The value is read before the variable is stored, like an operand
+                if let InstructionSrc::Register(register) = &value {
+                    if !register.is_variable {
+                        registers_maps.free_register(register.register, register.scope);
+                    }
+                }
+
                 let (register, scope) = registers_maps.assign_variable_register(lexeme);
 
                 let dest = InstructionRegister::new(register, scope, true);
@@ -238,7 +604,21 @@ impl DAG {
                     None => InstructionSrc::Constant(Value::Empty),
                 };
 
-                let (register, scope) = registers_maps.get_register(&lexeme).unwrap();
+                let (register, scope) = match registers_maps.get_register(&lexeme) {
+                    Some(register) => register,
+                    None => {
+                        let index = registers_maps.get_upvalue(&lexeme).expect("Undefined variable");
+
+                        bytecode.push(Instruction::SetUpvalue { index, src: value.clone() });
+                        return value;
+                    }
+                };
+
+                if let InstructionSrc::Register(register) = &value {
+                    if !register.is_variable {
+                        registers_maps.free_register(register.register, register.scope);
+                    }
+                }
 
                 let dest = InstructionRegister::new(register, scope, true);
 
@@ -255,8 +635,35 @@ impl DAG {
         }
     }
 
-    pub fn constant_folding(&mut self, environment: &mut IREnvironment, scope: usize) {
-        self.eval(self.nodes.len() - 1, environment, scope);
+    // Temporary operands are freed right away, since the instruction reads them first
+    fn generate_operands_bytecode(
+        &self,
+        operands: Vec<usize>,
+        registers_maps: &mut RegistersMap,
+        bytecode: &mut Bytecode
+    ) -> Vec<InstructionSrc> {
+        let srcs = operands
+            .iter()
+            .map(|operand| self.generate_node_bytecode(*operand, registers_maps, bytecode))
+            .collect::<Vec<_>>();
+
+        for src in &srcs {
+            if let InstructionSrc::Register(register) = src {
+                if !register.is_variable {
+                    registers_maps.free_register(register.register, register.scope);
+                }
+            }
+        }
+
+        srcs
+    }
+
+    pub fn constant_folding(
+        &mut self,
+        environment: &mut IREnvironment,
+        scope: usize
+    ) -> Option<Value> {
+        self.eval(self.entry_node_id, environment, scope)
     }
 
     #[profiler::function_tracker]
@@ -270,16 +677,20 @@ impl DAG {
 
         match &node.op {
             DAGOp::Const(value) => { Some(value.clone()) }
+            // Closures can change captured variables whenever they're called
+            DAGOp::Identifier(lexeme) if environment.is_captured(lexeme) => None,
             DAGOp::Identifier(lexeme) => {
                 let (value, changed_state, _) = match environment.get(lexeme) {
                     Some(value) => value,
                     None => panic!("Variable not found"),
                 };
 
-                if changed_state == &ChangedState::Unchanged {
-                    return value.clone();
-                } else {
-                    return None;
+                match (value.clone(), changed_state) {
+                    (Some(value), ChangedState::Unchanged) => {
+                        self.add_node_at(DAGNode::new(DAGOp::Const(value.clone()), None), node_id);
+                        Some(value)
+                    }
+                    _ => None,
                 }
             }
             DAGOp::BinaryOp(op) => {
@@ -296,6 +707,78 @@ impl DAG {
                     None
                 }
             }
+            | DAGOp::Call(_)
+            | DAGOp::CallNative(_)
+            | DAGOp::CallValue => {
+                // Only the arguments can be folded, since the result isn't known until runtime
+                for arg in node.operands.unwrap() {
+                    self.eval(arg, environment, scope);
+                }
+                None
+            }
+            DAGOp::Print => {
+                self.eval(node.operands.unwrap()[0], environment, scope);
+                None
+            }
+            DAGOp::Builtin(function) => {
+                let operands = node.operands.unwrap();
+                let args = operands
+                    .iter()
+                    .map(|operand| self.eval(*operand, environment, scope))
+                    .collect::<Vec<_>>();
+
+                if !function.is_pure {
+                    return None;
+                }
+
+                // Arrays are never folded into constants, since each evaluation creates a new one
+                match args.into_iter().collect::<Option<Vec<_>>>().map(|args| function.call(&args)) {
+                    Some(Ok(evaluated)) if !matches!(evaluated, Value::Array(_)) => {
+                        for operand in operands {
+                            self.remove_node(operand);
+                        }
+                        self.add_node_at(
+                            DAGNode::new(DAGOp::Const(evaluated.clone()), None),
+                            node_id
+                        );
+                        Some(evaluated)
+                    }
+                    _ => None,
+                }
+            }
+            DAGOp::Cast(value_type) => {
+                let operand = node.operands.unwrap()[0];
+
+                match self.eval(operand, environment, scope).map(|value| value.cast(value_type)) {
+                    Some(Ok(evaluated)) => {
+                        self.remove_node(operand);
+                        self.add_node_at(
+                            DAGNode::new(DAGOp::Const(evaluated.clone()), None),
+                            node_id
+                        );
+                        Some(evaluated)
+                    }
+                    _ => None,
+                }
+            }
+            DAGOp::Closure { .. } => None,
+            | DAGOp::Array
+            | DAGOp::Map
+            | DAGOp::Tuple
+            | DAGOp::Index
+            | DAGOp::SetIndex
+            | DAGOp::Instance(_)
+            | DAGOp::GetField(_)
+            | DAGOp::SetField(_)
+            | DAGOp::EnumVariant(_, _)
+            | DAGOp::GetTag
+            | DAGOp::GetPayload(_) => {
+                // Literals and constructors of shared values are never folded, only their operands
+                for operand in node.operands.unwrap() {
+                    self.eval(operand, environment, scope);
+                }
+                None
+            }
             DAGOp::Define | DAGOp::Assign => {
                 if let Some(operands) = node.operands {
                     let is_definition = match &node.op {
@@ -347,7 +830,16 @@ impl DAG {
             Some(rhs) => {
                 match op {
                     UnaryOp::Neg => {
-                        let evaluated = rhs.neg().unwrap();
+                        let evaluated = rhs.neg().ok()?;
+                        self.remove_node(operand);
+                        self.add_node_at(
+                            DAGNode::new(DAGOp::Const(evaluated.clone()), None),
+                            node_id
+                        );
+                        Some(evaluated)
+                    }
+                    UnaryOp::BitNot => {
+                        let evaluated = rhs.bit_not().ok()?;
                         self.remove_node(operand);
                         self.add_node_at(
                             DAGNode::new(DAGOp::Const(evaluated.clone()), None),
@@ -383,51 +875,14 @@ impl DAG {
 
         match (left, right) {
             (Some(lhs), Some(rhs)) => {
-                match op {
-                    BinaryOp::Add => {
-                        let evaluated = lhs.add(&rhs).unwrap();
-
-                        self.remove_node(operands[0]);
-                        self.remove_node(operands[1]);
+                // Operations that fail, like a division by zero, are left for the VM to report
+                let evaluated = lhs.binary_op(&rhs, *op).ok()?;
 
-                        self.add_node_at(
-                            DAGNode::new(DAGOp::Const(evaluated.clone()), None),
-                            node_id
-                        );
+                self.remove_node(operands[0]);
+                self.remove_node(operands[1]);
+                self.add_node_at(DAGNode::new(DAGOp::Const(evaluated.clone()), None), node_id);
 
-                        Some(evaluated)
-                    }
-                    BinaryOp::Sub => {
-                        let evaluated = lhs.sub(&rhs).unwrap();
-                        self.remove_node(operands[0]);
-                        self.remove_node(operands[1]);
-                        self.add_node_at(
-                            DAGNode::new(DAGOp::Const(evaluated.clone()), None),
-                            node_id
-                        );
-                        Some(evaluated)
-                    }
-                    BinaryOp::Mul => {
-                        let evaluated = lhs.mul(&rhs).unwrap();
-                        self.remove_node(operands[0]);
-                        self.remove_node(operands[1]);
-                        self.add_node_at(
-                            DAGNode::new(DAGOp::Const(evaluated.clone()), None),
-                            node_id
-                        );
-                        Some(evaluated)
-                    }
-                    BinaryOp::Div => {
-                        let evaluated = lhs.div(&rhs).unwrap();
-                        self.remove_node(operands[0]);
-                        self.remove_node(operands[1]);
-                        self.add_node_at(
-                            DAGNode::new(DAGOp::Const(evaluated.clone()), None),
-                            node_id
-                        );
-                        Some(evaluated)
-                    }
-                }
+                Some(evaluated)
             }
             _ => None,
         }
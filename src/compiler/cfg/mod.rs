@@ -1,21 +1,76 @@
-use ahash::AHashMap;
+use ahash::{ AHashMap, AHashSet };
 
-use crate::{ constants::REGISTERS, value::Value, vm::instructions::Instruction };
+use crate::{
+    constants::REGISTERS,
+    value::Value,
+    vm::instructions::{ Bytecode, Instruction, InstructionSrc },
+};
 
-use self::cfg_node::CFGProcessNode;
+use self::cfg_node::{ CFGDecisionNode, CFGJumpNode, CFGProcessNode, CFGReturnNode };
 pub mod cfg_node;
 pub mod dag;
 
 #[derive(Debug)]
 pub enum CFGNode {
     Process(CFGProcessNode), // This is essentially a statement e.g. "mut i32 a := 8"
-    // Decision, // This is an if-statement (maybe other conditional statements as well)
+    Decision(CFGDecisionNode), // This is an if-statement or the head of a loop
+    Jump(CFGJumpNode), // This is a break or continue
+    Return(CFGReturnNode),
     ScopeStart(usize),
     ScopeEnd(usize),
+    // No code is generated for it
+    FreeTemporaries(Vec<String>, usize),
     ProgramStart(usize),
     ProgramEnd,
 }
 
+impl CFGNode {
+    pub fn get_next_id(&self) -> Option<usize> {
+        match self {
+            CFGNode::Process(process_node) => Some(process_node.next_id),
+            CFGNode::Decision(decision_node) => Some(decision_node.true_branch_id),
+            CFGNode::Jump(jump_node) => Some(jump_node.next_id),
+            CFGNode::ScopeStart(next_id) | CFGNode::ScopeEnd(next_id) => Some(*next_id),
+            CFGNode::FreeTemporaries(_, next_id) => Some(*next_id),
+            CFGNode::ProgramStart(next_id) => Some(*next_id),
+            CFGNode::Return(_) | CFGNode::ProgramEnd => None,
+        }
+    }
+
+    pub fn set_next_id(&mut self, new_next_id: usize) {
+        match self {
+            CFGNode::Process(process_node) => {
+                process_node.next_id = new_next_id;
+            }
+            CFGNode::Decision(decision_node) => {
+                decision_node.true_branch_id = new_next_id;
+            }
+            CFGNode::Jump(jump_node) => {
+                jump_node.next_id = new_next_id;
+            }
+            CFGNode::ScopeStart(next_id) | CFGNode::ScopeEnd(next_id) => {
+                *next_id = new_next_id;
+            }
+            CFGNode::FreeTemporaries(_, next_id) => {
+                *next_id = new_next_id;
+            }
+            CFGNode::ProgramStart(next_id) => {
+                *next_id = new_next_id;
+            }
+            CFGNode::Return(_) => panic!("Return has no next node"),
+            CFGNode::ProgramEnd => panic!("ProgramEnd has no next node"),
+        }
+    }
+
+    pub fn get_successors(&self) -> Vec<usize> {
+        match self {
+            CFGNode::Decision(decision_node) =>
+                vec![decision_node.true_branch_id, decision_node.false_branch_id],
+            _ => self.get_next_id().into_iter().collect(),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum ChangedState {
     MaybeChanged,
@@ -31,6 +86,8 @@ pub enum DefinitionState {
 #[derive(Debug)]
 pub struct RegistersMap {
     registers_maps: Vec<(AHashMap<String, usize>, Vec<usize>)>,
+    upvalues: Vec<String>,
+    is_out_of_registers: bool,
 }
 
 impl RegistersMap {
@@ -39,9 +96,21 @@ impl RegistersMap {
 
         Self {
             registers_maps: vec![(AHashMap::default(), available_registers)],
+            upvalues: Vec::new(),
+            is_out_of_registers: false,
         }
     }
 
+    pub fn with_upvalues(mut self, upvalues: Vec<String>) -> Self {
+        self.upvalues = upvalues;
+        self
+    }
+
+    // Only used when the variable isn't in a register, since locals shadow captured ones
+    pub fn get_upvalue(&self, variable: &String) -> Option<usize> {
+        self.upvalues.iter().position(|upvalue| upvalue == variable)
+    }
+
     pub fn start_scope(&mut self) {
         let available_registers = (0..REGISTERS).rev().collect::<Vec<_>>();
 
@@ -56,7 +125,10 @@ impl RegistersMap {
         let scope = self.registers_maps.len() - 1;
         let (_, available_registers) = self.registers_maps.last_mut().unwrap();
 
-        let register = available_registers.pop().unwrap();
+        let register = available_registers.pop().unwrap_or_else(|| {
+            self.is_out_of_registers = true;
+            0
+        });
 
         (register, scope)
     }
@@ -65,7 +137,10 @@ impl RegistersMap {
         let scope = self.registers_maps.len() - 1;
         let (current_scope, available_registers) = self.registers_maps.last_mut().unwrap();
 
-        let register = available_registers.pop().unwrap();
+        let register = available_registers.pop().unwrap_or_else(|| {
+            self.is_out_of_registers = true;
+            0
+        });
 
         current_scope.insert(variable, register);
 
@@ -89,20 +164,37 @@ impl RegistersMap {
 
         available_registers.push(register);
     }
+
+    // Nothing happens if the scope of the variable has already ended
+    pub fn free_variable_register(&mut self, variable: &String) {
+        for (current_scope, available_registers) in self.registers_maps.iter_mut().rev() {
+            if let Some(register) = current_scope.remove(variable) {
+                available_registers.push(register);
+                return;
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct IREnvironment {
     definitions: Vec<AHashMap<(String, usize), (Option<Value>, ChangedState, DefinitionState)>>,
+    // Closures can change these whenever one is called
+    captured_variables: AHashSet<String>,
 }
 
 impl IREnvironment {
-    pub fn new() -> Self {
+    pub fn new(captured_variables: AHashSet<String>) -> Self {
         Self {
             definitions: vec![],
+            captured_variables,
         }
     }
 
+    pub fn is_captured(&self, lexeme: &String) -> bool {
+        self.captured_variables.contains(lexeme)
+    }
+
     pub fn start_scope(&mut self) {
         self.definitions.push(AHashMap::default())
     }
@@ -111,36 +203,42 @@ impl IREnvironment {
         self.definitions.pop();
     }
 
-    pub fn get(&self, lexeme: &String) -> Option<&(Option<Value>, ChangedState, DefinitionState)> {
-        for scope in self.definitions.iter().rev() {
-            let scope_values = scope.iter().collect::<Vec<_>>();
-            for i in (0..scope_values.len()).rev() {
-                let ((name, _), value) = scope_values[i];
+    fn get_latest_key(&self, lexeme: &String) -> Option<(usize, (String, usize))> {
+        for (scope, definitions) in self.definitions.iter().enumerate().rev() {
+            let latest_key = definitions
+                .keys()
+                .filter(|(name, _)| name == lexeme)
+                .max_by_key(|(_, subscript)| *subscript);
 
-                if name == lexeme {
-                    return Some(value);
-                }
+            if let Some(key) = latest_key {
+                return Some((scope, key.clone()));
             }
         }
 
         None
     }
 
+    pub fn get(&self, lexeme: &String) -> Option<&(Option<Value>, ChangedState, DefinitionState)> {
+        let (scope, key) = self.get_latest_key(lexeme)?;
+        self.definitions[scope].get(&key)
+    }
+
     pub fn overwrite(&mut self, lexeme: &String, new_value: Option<Value>) {
-        for scope in self.definitions.iter_mut().rev() {
-            let mut scope_values = scope.iter_mut().collect::<Vec<_>>();
-            for i in (0..scope_values.len()).rev() {
-                let ((name, _), (value, changed_state, definition_state)) = scope_values
-                    .get_mut(i)
-                    .unwrap();
-
-                if name == lexeme {
-                    *value = new_value;
-                    *changed_state = ChangedState::Unchanged;
-                    *definition_state = DefinitionState::IsAssignment;
-                    return;
-                }
-            }
+        if let Some((scope, key)) = self.get_latest_key(lexeme) {
+            let (value, changed_state, definition_state) = self.definitions[scope]
+                .get_mut(&key)
+                .unwrap();
+
+            *value = new_value;
+            *changed_state = ChangedState::Unchanged;
+            *definition_state = DefinitionState::IsAssignment;
+        }
+    }
+
+    pub fn mark_maybe_changed(&mut self, lexeme: &String) {
+        if let Some((scope, key)) = self.get_latest_key(lexeme) {
+            let (_, changed_state, _) = self.definitions[scope].get_mut(&key).unwrap();
+            *changed_state = ChangedState::MaybeChanged;
         }
     }
 
@@ -159,31 +257,41 @@ impl IREnvironment {
     }
 
     fn get_new_subscript(&self, lexeme: &str) -> usize {
-        for scope in self.definitions.iter().rev() {
-            let scope_keys = scope.keys().collect::<Vec<_>>();
-
-            for i in (0..scope_keys.len()).rev() {
-                let (name, subscript) = scope_keys[i];
-
-                if name == lexeme {
-                    return *subscript + 1;
-                }
-            }
-        }
-
-        0
+        self.definitions
+            .iter()
+            .flat_map(|scope| scope.keys())
+            .filter(|(name, _)| name == lexeme)
+            .map(|(_, subscript)| *subscript + 1)
+            .max()
+            .unwrap_or(0)
     }
 }
 
 #[derive(Debug)]
 pub struct CFG {
     nodes: Vec<CFGNode>,
+    // Placed in the first registers by the caller
+    parameters: Vec<String>,
+    upvalues: Vec<String>,
+    is_function: bool,
 }
 
 impl CFG {
     pub fn new() -> Self {
         Self {
             nodes: Vec::new(),
+            parameters: Vec::new(),
+            upvalues: Vec::new(),
+            is_function: false,
+        }
+    }
+
+    pub fn new_function(parameters: Vec<String>, upvalues: Vec<String>) -> Self {
+        Self {
+            nodes: Vec::new(),
+            parameters,
+            upvalues,
+            is_function: true,
         }
     }
 
@@ -191,49 +299,97 @@ impl CFG {
         self.nodes.push(node);
     }
 
+    pub fn get_next_node_id(&self) -> usize {
+        self.nodes.len() + 1
+    }
+
+    pub fn get_current_node_id(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn get_node_mut(&mut self, node_id: usize) -> &mut CFGNode {
+        self.nodes.get_mut(node_id).unwrap()
+    }
+
     // Implement iterator for CFG
-    fn for_each<F: FnMut(&mut CFGNode)>(&mut self, mut callback: F) {
-        let mut next_node_id = 0;
-
-        loop {
-            match self.nodes.get_mut(next_node_id) {
-                Some(node) => {
-                    callback(node);
-                    match node {
-                        CFGNode::Process(process_node) => {
-                            next_node_id = process_node.next_id;
-                        }
-                        CFGNode::ProgramStart(next_id) => {
-                            next_node_id = *next_id;
-                        }
-                        CFGNode::ProgramEnd => {
-                            break;
-                        }
-                        CFGNode::ScopeStart(next_id) => {
-                            next_node_id = *next_id;
-                        }
-                        CFGNode::ScopeEnd(next_id) => {
-                            next_node_id = *next_id;
+    // Nodes are visited in layout order, while the ids they point to describe the control flow
+    fn for_each<F: FnMut(usize, &mut CFGNode)>(&mut self, mut callback: F) {
+        for (node_id, node) in self.nodes.iter_mut().enumerate() {
+            callback(node_id, node);
+        }
+    }
+
+    // `dead_nodes` are left out, as they can never be reached (see `constant_folding`)
+    pub fn generate_bytecode(&mut self, dead_nodes: &[bool]) -> Result<Bytecode, String> {
+        let mut registers_maps = RegistersMap::new().with_upvalues(self.upvalues.clone());
+        let mut instructions = Bytecode::new();
+
+        for parameter in &self.parameters {
+            registers_maps.assign_variable_register(parameter.clone());
+        }
+        let is_function = self.is_function;
+
+        // The first instruction of each node, and the jumps that should be patched to point at one
+        let mut node_locations: Vec<usize> = Vec::with_capacity(self.nodes.len());
+        let mut jumps: Vec<(usize, usize)> = Vec::new();
+
+        self.for_each(|node_id, node| {
+            node_locations.push(instructions.len());
+
+            if dead_nodes[node_id] {
+                // The registers maps still follow the scopes, as in constant folding
+                match node {
+                    CFGNode::ScopeStart(_) => registers_maps.start_scope(),
+                    CFGNode::ScopeEnd(_) => registers_maps.end_scope(),
+                    CFGNode::FreeTemporaries(temporaries, _) => {
+                        for temporary in temporaries.iter() {
+                            registers_maps.free_variable_register(temporary);
                         }
                     }
+                    _ => {}
                 }
-                None => {
-                    panic!("Invalid next_node_id: {}", next_node_id);
-                }
+                return;
             }
-        }
-    }
 
-    pub fn generate_bytecode(&mut self) -> Vec<Instruction> {
-        let mut registers_maps = RegistersMap::new();
-        let mut instructions: Vec<Instruction> = Vec::with_capacity(64);
-
-        self.for_each(|node| {
             match node {
                 CFGNode::Process(ref mut process_node) => {
                     let node_instructions = process_node.dag.generate_bytecode(&mut registers_maps);
                     instructions.extend(node_instructions)
                 }
+                CFGNode::Decision(ref mut decision_node) => {
+                    let (node_instructions, condition) =
+                        decision_node.condition.generate_src_bytecode(&mut registers_maps);
+                    instructions.extend(node_instructions);
+
+                    if let InstructionSrc::Register(register) = &condition {
+                        if !register.is_variable {
+                            registers_maps.free_register(register.register, register.scope);
+                        }
+                    }
+
+                    match condition {
+                        InstructionSrc::Constant(Value::Bool(true)) => {}
+                        InstructionSrc::Constant(Value::Bool(false)) => {
+                            let false_branch_id = decision_node.false_branch_id;
+                            if !Self::is_fallthrough(dead_nodes, node_id, false_branch_id) {
+                                jumps.push((instructions.len(), false_branch_id));
+                                instructions.push(Instruction::Jump { target: 0 });
+                            }
+                        }
+                        condition => {
+                            jumps.push((instructions.len(), decision_node.false_branch_id));
+                            instructions.push(Instruction::JumpIfFalse { src: condition, target: 0 });
+                        }
+                    }
+                }
+                CFGNode::Jump(jump_node) => {
+                    // The registers maps are kept, as the next nodes are still in these scopes
+                    if jump_node.ended_scopes > 0 {
+                        instructions.push(Instruction::EndScopes {
+                            count: jump_node.ended_scopes,
+                        });
+                    }
+                }
                 CFGNode::ScopeStart(_) => {
                     registers_maps.start_scope();
                     instructions.push(Instruction::StartScope)
@@ -242,14 +398,60 @@ impl CFG {
                     registers_maps.end_scope();
                     instructions.push(Instruction::EndScope)
                 }
+                CFGNode::FreeTemporaries(temporaries, _) => {
+                    for temporary in temporaries.iter() {
+                        registers_maps.free_variable_register(temporary);
+                    }
+                }
+                CFGNode::Return(return_node) => {
+                    let src = match &return_node.value {
+                        Some(value) => {
+                            let (node_instructions, src) =
+                                value.generate_src_bytecode(&mut registers_maps);
+                            instructions.extend(node_instructions);
+                            Some(src)
+                        }
+                        None => None,
+                    };
+
+                    instructions.push(Instruction::Return { src });
+                }
                 CFGNode::ProgramStart(_) => {}
                 CFGNode::ProgramEnd => {
-                    instructions.push(Instruction::Halt);
+                    if is_function {
+                        instructions.push(Instruction::Return { src: None });
+                    } else {
+                        instructions.push(Instruction::Halt);
+                    }
+                }
+            }
+
+            if let Some(next_id) = node.get_next_id() {
+                if !Self::is_fallthrough(dead_nodes, node_id, next_id) {
+                    jumps.push((instructions.len(), next_id));
+                    instructions.push(Instruction::Jump { target: 0 });
                 }
             }
         });
 
-        instructions
+        for (instruction_index, node_id) in jumps {
+            let location = node_locations[node_id];
+
+            match &mut instructions.instructions[instruction_index] {
+                Instruction::Jump { target } | Instruction::JumpIfFalse { target, .. } => {
+                    *target = location;
+                }
+                instruction => panic!("Expected jump instruction: {:?}", instruction),
+            }
+        }
+
+        if registers_maps.is_out_of_registers {
+            return Err(
+                format!("Too many values in one scope: a scope can use at most {} registers", REGISTERS)
+            );
+        }
+
+        Ok(instructions)
 
         /*
         MUL S0:R0 1 4
@@ -290,31 +492,95 @@ impl CFG {
         */
     }
 
+    fn is_fallthrough(dead_nodes: &[bool], node_id: usize, target_id: usize) -> bool {
+        target_id > node_id && dead_nodes[node_id + 1..target_id].iter().all(|dead| *dead)
+    }
+
     #[profiler::function_tracker]
-    pub fn optimize_and_generate_bytecode(&mut self) -> Vec<Instruction> {
-        self.constant_folding();
+    pub fn optimize_and_generate_bytecode(&mut self) -> Result<Bytecode, String> {
+        let dead_nodes = self.constant_folding();
 
         // self.eliminate_dead_code();
 
-        self.generate_bytecode()
+        self.generate_bytecode(&dead_nodes)
     }
 
-    fn constant_folding(&mut self) {
-        let mut environment = IREnvironment::new();
+    fn get_captured_variables(&self) -> AHashSet<String> {
+        let mut captured_variables = self.upvalues.iter().cloned().collect::<AHashSet<_>>();
+
+        for node in &self.nodes {
+            let dag = match node {
+                CFGNode::Process(process_node) => &process_node.dag,
+                CFGNode::Decision(decision_node) => &decision_node.condition,
+                CFGNode::Return(CFGReturnNode { value: Some(value), .. }) => value,
+                _ => {
+                    continue;
+                }
+            };
+
+            captured_variables.extend(dag.get_closure_captures());
+        }
+
+        captured_variables
+    }
+
+    fn constant_folding(&mut self) -> Vec<bool> {
+        let mut environment = IREnvironment::new(self.get_captured_variables());
+        let mut reachability = Reachability::new(&self.nodes);
+        let assigned_variables = self.nodes
+            .iter()
+            .map(|node| {
+                match node {
+                    CFGNode::Process(process_node) => process_node.dag.get_assigned_variable(),
+                    _ => None,
+                }
+            })
+            .collect::<Vec<_>>();
 
         let mut scope = 0;
+        let parameters = self.parameters.clone();
+
+        self.for_each(|node_id, node| {
+            let is_reachable = reachability.is_reachable(node_id);
 
-        self.for_each(|node| {
+            if is_reachable {
+                for lexeme in reachability.get_maybe_changed_variables(node_id, &assigned_variables) {
+                    environment.mark_maybe_changed(lexeme);
+                }
+            } else {
+                reachability.dead_nodes[node_id] = true;
+            }
+
+            // Scopes are tracked even when dead, since a dead node can end a live scope
             match node {
                 CFGNode::ProgramStart(_) => {
                     environment.start_scope();
+
+                    for parameter in &parameters {
+                        environment.push(parameter, None, DefinitionState::IsDefinition, scope);
+                    }
                 }
                 CFGNode::ProgramEnd => {
                     environment.end_scope();
                 }
-                CFGNode::Process(ref mut process_node) => {
+                CFGNode::Process(ref mut process_node) if is_reachable => {
                     process_node.dag.constant_folding(&mut environment, scope);
                 }
+                CFGNode::Decision(ref mut decision_node) if is_reachable => {
+                    let condition = decision_node.condition.constant_folding(
+                        &mut environment,
+                        scope
+                    );
+
+                    if let Some(Value::Bool(condition)) = condition {
+                        reachability.folded_conditions[node_id] = Some(condition);
+                    }
+                }
+                CFGNode::Return(ref mut return_node) if is_reachable => {
+                    if let Some(value) = &mut return_node.value {
+                        value.constant_folding(&mut environment, scope);
+                    }
+                }
                 CFGNode::ScopeStart(_) => {
                     environment.start_scope();
                     scope += 1;
@@ -323,7 +589,91 @@ impl CFG {
                     environment.end_scope();
                     scope -= 1;
                 }
+                _ => {}
             }
         });
+
+        reachability.dead_nodes
+    }
+}
+
+// A decision whose condition folds to a constant only ever takes one of its branches
+struct Reachability {
+    successors: Vec<Vec<usize>>,
+    predecessors: Vec<Vec<usize>>,
+    dead_nodes: Vec<bool>,
+    folded_conditions: Vec<Option<bool>>,
+}
+
+impl Reachability {
+    fn new(nodes: &[CFGNode]) -> Self {
+        let successors = nodes
+            .iter()
+            .map(|node| node.get_successors())
+            .collect::<Vec<_>>();
+
+        let mut predecessors = vec![Vec::new(); nodes.len()];
+        for (node_id, node_successors) in successors.iter().enumerate() {
+            for successor_id in node_successors {
+                predecessors[*successor_id].push(node_id);
+            }
+        }
+
+        Self {
+            successors,
+            predecessors,
+            dead_nodes: vec![false; nodes.len()],
+            folded_conditions: vec![None; nodes.len()],
+        }
+    }
+
+    // Backward edges (loops) are always taken, since their source hasn't been folded yet
+    fn is_taken(&self, source_id: usize, target_id: usize) -> bool {
+        if source_id >= target_id {
+            return true;
+        }
+        if self.dead_nodes[source_id] {
+            return false;
+        }
+
+        match self.folded_conditions[source_id] {
+            Some(true) => self.successors[source_id][0] == target_id,
+            Some(false) => self.successors[source_id][1] == target_id,
+            None => true,
+        }
+    }
+
+    fn is_reachable(&self, node_id: usize) -> bool {
+        node_id == 0 ||
+            self.predecessors[node_id]
+                .iter()
+                .any(|predecessor_id| self.is_taken(*predecessor_id, node_id))
+    }
+
+    // Variables assigned in nodes that are jumped over or back across can't be trusted
+    fn get_maybe_changed_variables<'a>(
+        &self,
+        node_id: usize,
+        assigned_variables: &'a [Option<String>]
+    ) -> Vec<&'a String> {
+        let mut lexemes = Vec::new();
+
+        for predecessor_id in &self.predecessors[node_id] {
+            let predecessor_id = *predecessor_id;
+
+            if predecessor_id >= node_id {
+                lexemes.extend(assigned_variables[node_id..predecessor_id + 1].iter().flatten());
+            } else if predecessor_id + 1 < node_id && self.is_taken(predecessor_id, node_id) {
+                // Dead nodes are never executed, so they can't change anything
+                let skipped_nodes = predecessor_id + 1..node_id;
+                lexemes.extend(
+                    skipped_nodes
+                        .filter(|skipped_id| !self.dead_nodes[*skipped_id])
+                        .filter_map(|skipped_id| assigned_variables[skipped_id].as_ref())
+                );
+            }
+        }
+
+        lexemes
     }
 }
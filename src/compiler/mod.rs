@@ -1,6 +1,10 @@
 use std::{ collections::HashMap, fmt::format };
 
-use crate::{ ast::Ast, error_handler::ErrorHandler, vm::instructions::Instruction };
+use crate::{
+    ast::Ast,
+    error_handler::ErrorHandler,
+    vm::{ instructions::{ Bytecode, Instruction }, Program },
+};
 
 pub mod cfg;
 // pub mod ir_graph;
@@ -19,55 +23,77 @@ impl<'a> Compiler<'a> {
     }
 
     #[profiler::function_tracker]
-    pub fn compile(&mut self, ast: Ast) -> Option<Vec<Instruction>> {
+    pub fn compile(&mut self, ast: Ast) -> Option<Program> {
         let mut cfg = ast.generate_cfg();
-
-        let instructions = cfg.optimize_and_generate_bytecode();
+        let function_cfgs = ast.generate_function_cfgs();
+
+        let bytecode = cfg.optimize_and_generate_bytecode();
+        let functions = function_cfgs
+            .into_iter()
+            .map(|mut function_cfg| function_cfg.optimize_and_generate_bytecode())
+            .collect::<Result<Vec<_>, _>>();
+
+        let (instructions, functions) = match (bytecode, functions) {
+            (Ok(instructions), Ok(functions)) => (instructions, functions),
+            (Err(message), _) | (_, Err(message)) => {
+                self.error_handler.report_compile_error(message, Vec::new());
+                return None;
+            }
+        };
 
         #[cfg(debug_assertions)]
         {
-            let mut indentation_level = 0;
-            let indentation_size = 4;
-
             if !self.error_handler.has_error() {
                 println!("Optimized instructions:");
                 println!("----------------------\n");
-                for instruction in &instructions {
-                    match instruction {
-                        Instruction::EndScope => {
-                            indentation_level -= 1;
-                        }
-                        _ => {}
-                    }
-
-                    let print_string = format!(
-                        "{}{}",
-                        " ".repeat(indentation_level * indentation_size),
-                        instruction.dissassemble()
-                    );
-
-                    match instruction {
-                        Instruction::StartScope => {
-                            println!("{}\n", print_string);
-                        }
-                        Instruction::EndScope => {
-                            println!("\n{}", print_string);
-                        }
-                        _ => println!("{}", print_string),
-                    }
-
-                    match instruction {
-                        Instruction::StartScope => {
-                            indentation_level += 1;
-                        }
-                        _ => {}
-                    }
+                Self::print_instructions(&instructions);
+                for (function, function_bytecode) in functions.iter().enumerate() {
+                    println!("\nFunction {}:\n", function);
+                    Self::print_instructions(function_bytecode);
                 }
                 println!("\n----------------------")
             }
         }
 
-        Some(instructions)
+        Some(Program { instructions, functions })
+    }
+
+    #[cfg(debug_assertions)]
+    fn print_instructions(bytecode: &Bytecode) {
+        let mut indentation_level = 0;
+        let indentation_size = 4;
+
+        for instruction in &bytecode.instructions {
+            match instruction {
+                Instruction::EndScope => {
+                    indentation_level -= 1;
+                }
+                _ => {}
+            }
+
+            let print_string = format!(
+                "{}{}",
+                " ".repeat(indentation_level * indentation_size),
+                instruction.dissassemble()
+            );
+
+            match instruction {
+                Instruction::StartScope => {
+                    println!("{}\n", print_string);
+                }
+                Instruction::EndScope => {
+                    println!("\n{}", print_string);
+                }
+                _ => println!("{}", print_string),
+            }
+
+            match instruction {
+                Instruction::StartScope => {
+                    indentation_level += 1;
+                }
+                _ => {}
+            }
+        }
     }
 }
 
@@ -1 +1,3 @@
 pub const REGISTERS: usize = 256;
+
+pub const MAX_CALL_DEPTH: usize = 10_000;
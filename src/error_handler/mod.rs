@@ -1,4 +1,6 @@
-use crate::parser::token::TokenMetadata;
+use std::path::{ Path, PathBuf };
+
+use crate::{ parser::token::TokenMetadata, vm::runtime_error::RuntimeError };
 use colored::Colorize;
 
 // const AT_STR: &str = "at: ";
@@ -9,53 +11,66 @@ enum _ErrorType {
     Warning,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct CompileError {
     message: String,
     error_metadata: Vec<TokenMetadata>,
 }
 
+#[derive(Debug)]
+struct SourceFile {
+    path: PathBuf,
+    source: String,
+}
+
 #[derive(Debug)]
 pub struct ErrorHandler {
     _compile_warnings: Vec<CompileError>,
     compile_errors: Vec<CompileError>,
+    files: Vec<SourceFile>,
 }
 
 impl ErrorHandler {
     pub fn new() -> Self {
-        Self { _compile_warnings: Vec::new(), compile_errors: Vec::new() }
+        Self { _compile_warnings: Vec::new(), compile_errors: Vec::new(), files: Vec::new() }
     }
 
+    pub fn add_file(&mut self, path: PathBuf, source: String) -> usize {
+        self.files.push(SourceFile { path, source });
+        self.files.len() - 1
+    }
+
+    pub fn get_file_path(&self, file_id: usize) -> Option<&Path> {
+        self.files.get(file_id).map(|file| file.path.as_path())
+    }
+
+    // An error already reported at the same place is skipped
     pub fn report_compile_error(&mut self, message: String, error_metadata: Vec<TokenMetadata>) {
-        self.compile_errors.push(CompileError { message, error_metadata });
+        let error = CompileError { message, error_metadata };
+
+        if !self.compile_errors.contains(&error) {
+            self.compile_errors.push(error);
+        }
     }
 
     pub fn has_error(&self) -> bool {
         !self.compile_errors.is_empty()
     }
 
-    pub fn print_errors(&self, src: &str) {
+    pub fn print_errors(&self) {
         eprintln!("{}", "Errors:\n".red().underline().bold());
         for error in &self.compile_errors {
             let error_metadata = &error.error_metadata;
-            if error.error_metadata.len() == 1 {
+            if error_metadata.is_empty() {
+                eprintln!("{}", error.message);
+            } else if error.error_metadata.len() == 1 {
                 let metadata = &error.error_metadata[0];
-                eprintln!("[line {}] {}", metadata.get_line(), error.message);
-                eprintln!("-------> {}", self.get_five_char_context(src, metadata));
+                self.print_error(&error.message, metadata);
                 // eprintln!("{}", self.get_arrows_up_to_error_token(metadata, ErrorType::Error));
             } else {
-                let first = error_metadata.last().unwrap();
-                let last = error_metadata.first().unwrap();
-
-                let combined_metadata = TokenMetadata::new(
-                    first.get_start(),
-                    last.get_start() - first.get_start() + last.get_len(),
-                    last.get_line(),
-                    last.get_ttype()
-                );
-
-                eprintln!("[line {}] {}", combined_metadata.get_line(), error.message);
-                eprintln!("-------> {}", self.get_five_char_context(src, &combined_metadata));
+                let combined_metadata = TokenMetadata::span(error_metadata).unwrap();
+
+                self.print_error(&error.message, &combined_metadata);
                 // eprintln!(
                 //     "{}",
                 //     self.get_arrows_up_to_error_token(&combined_metadata, ErrorType::Error)
@@ -64,6 +79,24 @@ impl ErrorHandler {
         }
     }
 
+    pub fn print_runtime_error(&self, error: &RuntimeError) {
+        eprintln!("{}", "Runtime error:\n".red().underline().bold());
+        match &error.token_metadata {
+            Some(metadata) => self.print_error(&error.message, metadata),
+            None => eprintln!("{}", error.message),
+        }
+    }
+
+    fn print_error(&self, message: &str, metadata: &TokenMetadata) {
+        match self.files.get(metadata.get_file_id()) {
+            Some(file) => {
+                eprintln!("[{}:{}] {}", file.path.display(), metadata.get_line(), message);
+                eprintln!("-------> {}", self.get_five_char_context(&file.source, metadata));
+            }
+            None => eprintln!("[line {}] {}", metadata.get_line(), message),
+        }
+    }
+
     fn get_five_char_context(&self, src: &str, token: &TokenMetadata) -> String {
         let start = token.get_start() as isize;
         let length = token.get_len() as isize;
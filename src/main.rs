@@ -12,6 +12,7 @@ mod vm;
 mod compiler;
 mod ast;
 // mod value;
+mod value_v2;
 mod constants;
 mod operations;
 mod util;
@@ -69,26 +70,30 @@ fn main() {
     let src = file_str;
 
     let error_handler = &mut ErrorHandler::new();
+    let file_id = error_handler.add_file(args[1].clone().into(), src.to_string());
 
     let src_chars = src.chars().collect::<Vec<_>>();
-    let mut parser = Parser::new(&src_chars, error_handler);
+    let mut parser = Parser::new(&src_chars, file_id, error_handler);
 
     let ast = parser.parse_to_ast();
 
     if error_handler.has_error() {
-        error_handler.print_errors(src);
+        error_handler.print_errors();
         std::process::exit(1);
     }
 
     let mut compiler = Compiler::new(error_handler);
 
-    let instructions = compiler.compile(ast);
+    let program = compiler.compile(ast);
 
-    if let Some(instructions) = instructions {
-        let mut vm = VM::new(instructions);
+    if let Some(program) = program {
+        let mut vm = VM::new(program);
 
-        vm.run();
+        if let Err(error) = vm.run() {
+            error_handler.print_runtime_error(&error);
+            std::process::exit(1);
+        }
     } else {
-        error_handler.print_errors(src);
+        error_handler.print_errors();
     }
 }
@@ -1,4 +1,4 @@
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Op {
     BinaryOp(BinaryOp),
     UnaryOp(UnaryOp),
@@ -9,12 +9,25 @@ pub enum Op {
     NoOp,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BinaryOp {
     Add,
     Sub,
     Mul,
     Div,
+    Mod,
+    Pow,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
 }
 
 impl BinaryOp {
@@ -24,9 +37,39 @@ impl BinaryOp {
             BinaryOp::Sub => 1,
             BinaryOp::Mul => 1,
             BinaryOp::Div => 1,
+            BinaryOp::Mod => 1,
+            BinaryOp::Pow => 2,
+            BinaryOp::BitAnd => 1,
+            BinaryOp::BitOr => 1,
+            BinaryOp::BitXor => 1,
+            BinaryOp::Shl => 2,
+            BinaryOp::Shr => 2,
+            BinaryOp::Equal => 2,
+            BinaryOp::NotEqual => 2,
+            BinaryOp::Less => 1,
+            BinaryOp::LessEqual => 2,
+            BinaryOp::Greater => 1,
+            BinaryOp::GreaterEqual => 2,
         }
     }
 
+    // Whether the result has the same type as the operands
+    pub fn is_arithmetic(&self) -> bool {
+        !self.is_comparison()
+    }
+
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            | BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::Less
+            | BinaryOp::LessEqual
+            | BinaryOp::Greater
+            | BinaryOp::GreaterEqual
+        )
+    }
+
     pub fn to_op_string(&self) -> String {
         (
             match self {
@@ -34,15 +77,49 @@ impl BinaryOp {
                 Self::Sub => "Subtraction",
                 Self::Mul => "Multiplication",
                 Self::Div => "Division",
+                Self::Mod => "Modulo",
+                Self::Pow => "Exponentiation",
+                Self::BitAnd => "Bitwise and",
+                Self::BitOr => "Bitwise or",
+                Self::BitXor => "Bitwise xor",
+                Self::Shl => "Left shift",
+                Self::Shr => "Right shift",
+                Self::Equal => "Equality",
+                Self::NotEqual => "Inequality",
+                Self::Less => "Less than comparison",
+                Self::LessEqual => "Less than or equal comparison",
+                Self::Greater => "Greater than comparison",
+                Self::GreaterEqual => "Greater than or equal comparison",
             }
         ).to_string()
     }
 }
 
+// Operators that only evaluate their right operand when needed
 #[derive(Debug, Clone, Copy)]
+pub enum LogicalOp {
+    And,
+    Or,
+    Coalesce,
+}
+
+impl LogicalOp {
+    pub fn to_op_string(&self) -> String {
+        (
+            match self {
+                Self::And => "Logical and",
+                Self::Or => "Logical or",
+                Self::Coalesce => "Coalescing",
+            }
+        ).to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum UnaryOp {
     Neg,
     Truthy,
+    BitNot,
 }
 
 impl UnaryOp {
@@ -50,6 +127,7 @@ impl UnaryOp {
         match self {
             UnaryOp::Neg => 1,
             UnaryOp::Truthy => 1,
+            UnaryOp::BitNot => 1,
         }
     }
 
@@ -58,6 +136,7 @@ impl UnaryOp {
             match self {
                 Self::Neg => "Negation",
                 Self::Truthy => "Truthy",
+                Self::BitNot => "Bitwise not",
             }
         ).to_string()
     }
@@ -72,11 +151,25 @@ impl Op {
                     BinaryOp::Sub => "subtraction".to_string(),
                     BinaryOp::Mul => "multiplication".to_string(),
                     BinaryOp::Div => "division".to_string(),
+                    BinaryOp::Mod => "modulo".to_string(),
+                    BinaryOp::Pow => "exponentiation".to_string(),
+                    BinaryOp::BitAnd => "bitwise and".to_string(),
+                    BinaryOp::BitOr => "bitwise or".to_string(),
+                    BinaryOp::BitXor => "bitwise xor".to_string(),
+                    BinaryOp::Shl => "left shift".to_string(),
+                    BinaryOp::Shr => "right shift".to_string(),
+                    BinaryOp::Equal => "equality".to_string(),
+                    BinaryOp::NotEqual => "inequality".to_string(),
+                    BinaryOp::Less => "less than comparison".to_string(),
+                    BinaryOp::LessEqual => "less than or equal comparison".to_string(),
+                    BinaryOp::Greater => "greater than comparison".to_string(),
+                    BinaryOp::GreaterEqual => "greater than or equal comparison".to_string(),
                 }
             Op::UnaryOp(unary_op) =>
                 match unary_op {
                     UnaryOp::Neg => "negation".to_string(),
                     UnaryOp::Truthy => "truthy".to_string(),
+                    UnaryOp::BitNot => "bitwise not".to_string(),
                 }
 
             Op::Define => "definement".to_string(),
@@ -1,169 +1,3974 @@
-use std::collections::HashMap;
+use std::{ cell::RefCell, collections::{ HashMap, HashSet }, path::{ Path, PathBuf }, rc::Rc };
 
 use crate::{
     ast::{
-        expr::{ AstIdentifier, AstValue, BinaryExpr, Expr, UnaryExpr },
+        expr::{
+            ArrayExpr,
+            AstIdentifier,
+            AstValue,
+            BinaryExpr,
+            BuiltinCallExpr,
+            CallExpr,
+            CallValueExpr,
+            CastExpr,
+            ClosureExpr,
+            EnumVariantExpr,
+            Expr,
+            IndexExpr,
+            InstanceExpr,
+            InterpolationExpr,
+            LogicalExpr,
+            MapExpr,
+            MatchArm,
+            MatchExpr,
+            MemberAccessExpr,
+            MethodCallExpr,
+            TupleExpr,
+            UnaryExpr,
+        },
         stmt::{
+            DestructuredVariable,
+            ForIterable,
+            ForStmt,
             FunctionArgument,
+            FunctionStmt,
+            IndexAssignmentStmt,
+            ReturnStmt,
             ScopeStmt,
             Stmt,
             TypeDefStmt,
+            Typing,
+            TypingValue,
             VariableAssignmentStmt,
             VariableDefinitionStmt,
         },
         Ast,
     },
-    operations::{ BinaryOp, UnaryOp },
-    value::ValueType,
+    operations::{ BinaryOp, LogicalOp, Op, UnaryOp },
+    value::{
+        ClassLayout,
+        ClassType,
+        Closure,
+        EnumLayout,
+        EnumType,
+        FunctionType,
+        ImplTarget,
+        Value,
+        ValueType,
+    },
+    value_v2::{ self, Method, MethodImpl, NativeMethod, ValueTypeAttrs },
 };
 use super::token::{ token_type::TokenType, Token, TokenMetadata };
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InitState {
+    Uninitialized,
+    // Initialized on some paths only, like inside an if without an else
+    MaybeInitialized,
+    Initialized,
+}
+
 #[derive(Debug)]
 struct AstVariableValue {
     value_type: ValueType,
     is_mutable: bool,
-    is_initialized: bool,
+    init_state: InitState,
+}
+
+impl AstVariableValue {
+    pub fn to_tuple(&self) -> (ValueType, bool, bool) {
+        (self.value_type.clone(), self.is_mutable, self.init_state == InitState::Initialized)
+    }
+}
+
+// A block that might not run, like the body of an if statement or a loop
+struct AstBranch {
+    scope_depth: usize,
+    is_loop: bool,
+    // The outside variables initialized in the block, with their state before it
+    initializations: Vec<(usize, String, InitState)>,
+}
+
+pub struct EndedBranch {
+    initializations: Vec<(usize, String)>,
+    diverges: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionSignature {
+    pub id: usize,
+    pub type_params: Vec<Rc<str>>,
+    pub parameters: Vec<ValueType>,
+    pub return_type: ValueType,
+}
+
+impl FunctionSignature {
+    // The compiled function is shared by every type argument, since values carry their type
+    pub fn substitute(&self, type_params: &[Rc<str>], type_args: &[ValueType]) -> Self {
+        Self {
+            id: self.id,
+            type_params: self.type_params
+                .iter()
+                .filter(|type_param| !type_params.contains(type_param))
+                .cloned()
+                .collect(),
+            parameters: self.parameters
+                .iter()
+                .map(|parameter| parameter.substitute(type_params, type_args))
+                .collect(),
+            return_type: self.return_type.substitute(type_params, type_args),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GenericId {
+    Function(usize),
+    Class(usize),
+}
+
+// An operator used on a type parameter, which every type argument must support
+#[derive(Debug, Clone)]
+pub struct TypeParamRequirement {
+    pub type_param: Rc<str>,
+    pub operation: Op,
+    pub token_metadata: TokenMetadata,
+}
+
+// `T: Show + Eq`
+#[derive(Debug)]
+pub struct TypeParamDefinition {
+    pub name: AstIdentifier,
+    pub bounds: Vec<AstIdentifier>,
+}
+
+#[derive(Debug)]
+struct TypeParamScope {
+    type_params: Vec<Rc<str>>,
+    bounds: Vec<Vec<usize>>,
+    generic_id: Option<GenericId>,
+    requirements: Vec<TypeParamRequirement>,
+}
+
+// Checked again when the generic gets a requirement after the use
+#[derive(Debug, Clone)]
+struct GenericUse {
+    generic_id: GenericId,
+    generic_name: String,
+    type_params: Vec<Rc<str>>,
+    type_args: Vec<ValueType>,
+    use_metadata: TokenMetadata,
+    type_param_generics: Vec<(Rc<str>, GenericId)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassField {
+    pub name: String,
+    pub value_type: ValueType,
+    pub is_public: bool,
+    pub default_value: Expr,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClassMethod {
+    pub is_public: bool,
+    pub is_self_mutable: bool,
+    // The parameters don't include `self`
+    pub signature: FunctionSignature,
+    // Set for calls through a trait bound, where the receiver picks the implementation
+    pub trait_method_id: Option<usize>,
+    // Set for the methods the VM implements itself
+    pub native: Option<NativeMethod>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TraitMethod {
+    pub id: usize,
+    pub name: String,
+    pub is_self_mutable: bool,
+    pub parameters: Vec<ValueType>,
+    pub return_type: ValueType,
+}
+
+#[derive(Debug)]
+pub struct TraitSignature {
+    pub name: Rc<str>,
+    pub methods: Vec<TraitMethod>,
+    // The ids of the functions implementing the methods, for each implementing type
+    pub impls: HashMap<ImplTarget, Vec<usize>>,
+}
+
+#[derive(Debug)]
+pub struct ClassSignature {
+    pub class_type: ClassType,
+    pub type_params: Vec<Rc<str>>,
+    pub fields: Vec<ClassField>,
+    pub methods: HashMap<String, ClassMethod>,
+    pub layout: Rc<ClassLayout>,
+}
+
+impl ClassSignature {
+    pub fn new(class_type: ClassType, type_params: Vec<Rc<str>>) -> Self {
+        let layout = Rc::new(ClassLayout {
+            id: class_type.id,
+            name: class_type.name.clone(),
+            field_names: Vec::new(),
+        });
+
+        Self {
+            class_type,
+            type_params,
+            fields: Vec::new(),
+            methods: HashMap::new(),
+            layout,
+        }
+    }
+
+    pub fn add_field(&mut self, field: ClassField) {
+        self.fields.push(field);
+
+        self.layout = Rc::new(ClassLayout {
+            id: self.class_type.id,
+            name: self.class_type.name.clone(),
+            field_names: self.fields
+                .iter()
+                .map(|field| field.name.as_str().into())
+                .collect(),
+        });
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumVariantSignature {
+    pub name: String,
+    pub payload_types: Vec<ValueType>,
+}
+
+#[derive(Debug)]
+pub struct EnumSignature {
+    pub enum_type: EnumType,
+    // The tag of a variant is its index
+    pub variants: Vec<EnumVariantSignature>,
+    pub layout: Rc<EnumLayout>,
+}
+
+impl EnumSignature {
+    pub fn new(enum_type: EnumType) -> Self {
+        let layout = Rc::new(EnumLayout {
+            id: enum_type.id,
+            name: enum_type.name.clone(),
+            variant_names: Vec::new(),
+        });
+
+        Self {
+            enum_type,
+            variants: Vec::new(),
+            layout,
+        }
+    }
+
+    pub fn add_variant(&mut self, variant: EnumVariantSignature) {
+        self.variants.push(variant);
+
+        self.layout = Rc::new(EnumLayout {
+            id: self.enum_type.id,
+            name: self.enum_type.name.clone(),
+            variant_names: self.variants
+                .iter()
+                .map(|variant| variant.name.as_str().into())
+                .collect(),
+        });
+    }
+
+    pub fn get_variant(&self, name: &str) -> Result<(usize, &EnumVariantSignature), String> {
+        match self.variants.iter().enumerate().find(|(_, variant)| variant.name == name) {
+            Some(variant) => Ok(variant),
+            None => Err(format!("Enum '{}' has no variant '{}'", self.enum_type.name, name)),
+        }
+    }
+}
+
+// `type Name<T> = typing`
+#[derive(Debug, Clone)]
+pub struct TypeAlias {
+    pub type_params: Vec<Rc<str>>,
+    pub typing: Typing,
+}
+
+#[derive(Debug)]
+pub struct AstScope {
+    definitions: HashMap<String, AstVariableValue>,
+    functions: HashMap<String, FunctionSignature>,
+    // Declared ahead, and not defined yet
+    hoisted_functions: HashSet<String>,
+    declared_types: HashSet<String>,
+    classes: HashMap<String, usize>,
+    enums: HashMap<String, usize>,
+    traits: HashMap<String, usize>,
+    // Resolved when used, so they can refer to aliases defined after them
+    type_aliases: HashMap<String, TypeAlias>,
+    // Declared with `pub`, so they're exported from a module
+    public_names: Vec<String>,
+    imports: HashMap<String, PathBuf>,
+}
+
+impl AstScope {
+    pub fn new() -> Self {
+        Self {
+            definitions: HashMap::new(),
+            functions: HashMap::new(),
+            hoisted_functions: HashSet::new(),
+            declared_types: HashSet::new(),
+            classes: HashMap::new(),
+            enums: HashMap::new(),
+            traits: HashMap::new(),
+            type_aliases: HashMap::new(),
+            public_names: Vec::new(),
+            imports: HashMap::new(),
+        }
+    }
+
+    pub fn insert(
+        &mut self,
+        lexeme: String,
+        value_type: ValueType,
+        is_mutable: bool,
+        is_initialized: bool
+    ) {
+        let init_state = if is_initialized {
+            InitState::Initialized
+        } else {
+            InitState::Uninitialized
+        };
+
+        self.definitions.insert(lexeme, AstVariableValue {
+            value_type,
+            is_mutable,
+            init_state,
+        });
+    }
+
+    pub fn get(&self, lexeme: &String) -> Option<(ValueType, bool, bool)> {
+        match self.definitions.get(lexeme) {
+            Some(v) => Some(v.to_tuple()),
+            None => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ModuleExports {
+    functions: HashMap<String, FunctionSignature>,
+    classes: HashMap<String, usize>,
+    enums: HashMap<String, usize>,
+    traits: HashMap<String, usize>,
+    // Resolved in the module, since their names might not be visible where they're imported
+    type_aliases: HashMap<String, TypeAlias>,
+}
+
+fn import_names<T: Clone>(
+    names: &mut HashMap<String, T>,
+    exports: &HashMap<String, T>,
+    taken: &HashSet<String>,
+    imports: &mut HashMap<String, PathBuf>,
+    path: &Path
+) {
+    for (name, export) in exports {
+        if !taken.contains(name) {
+            names.insert(name.clone(), export.clone());
+            imports.insert(name.clone(), path.to_path_buf());
+        }
+    }
+}
+
+pub struct AstEnvironment {
+    scopes: Vec<AstScope>,
+    scope_depth: usize,
+    // Nothing outside the innermost module is visible inside it, except its imports
+    module_scope_depths: Vec<usize>,
+    function_scope_depths: Vec<usize>,
+    // None for functions that aren't closures
+    closure_captures: Vec<Option<Vec<String>>>,
+    classes: Vec<ClassSignature>,
+    class_ids: Vec<usize>,
+    // Only usable as types until they're defined
+    declared_classes: HashSet<usize>,
+    enums: Vec<EnumSignature>,
+    traits: Vec<TraitSignature>,
+    trait_method_count: usize,
+    // The methods from trait implementations, added to the builtin methods of each type
+    type_attrs: HashMap<ImplTarget, ValueTypeAttrs>,
+    branches: Vec<AstBranch>,
+    // One for each function and class being parsed, even if it isn't generic
+    type_param_scopes: Vec<TypeParamScope>,
+    requirements: HashMap<GenericId, Vec<TypeParamRequirement>>,
+    bounds: HashMap<GenericId, Vec<Vec<usize>>>,
+    // Declared ahead, so their requirements aren't known yet
+    pending_generics: HashSet<GenericId>,
+    deferred_uses: RefCell<Vec<GenericUse>>,
+    generic_uses: RefCell<Vec<GenericUse>>,
+    type_alias_errors: Vec<(String, Vec<TokenMetadata>)>,
 }
 
-impl AstVariableValue {
-    pub fn to_tuple(&self) -> (ValueType, bool, bool) {
-        (self.value_type.clone(), self.is_mutable, self.is_initialized)
+impl AstEnvironment {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![AstScope::new()],
+            scope_depth: 0,
+            module_scope_depths: Vec::new(),
+            function_scope_depths: Vec::new(),
+            closure_captures: Vec::new(),
+            classes: Vec::new(),
+            class_ids: Vec::new(),
+            declared_classes: HashSet::new(),
+            enums: Vec::new(),
+            traits: Vec::new(),
+            trait_method_count: 0,
+            type_attrs: HashMap::new(),
+            branches: Vec::new(),
+            type_param_scopes: Vec::new(),
+            requirements: HashMap::new(),
+            bounds: HashMap::new(),
+            pending_generics: HashSet::new(),
+            deferred_uses: RefCell::new(Vec::new()),
+            generic_uses: RefCell::new(Vec::new()),
+            type_alias_errors: Vec::new(),
+        }
+    }
+
+    pub fn get_scope_depth(&self) -> usize {
+        self.scope_depth
+    }
+
+    pub fn start_scope(&mut self) {
+        self.scope_depth += 1;
+        self.scopes.push(AstScope::new());
+    }
+
+    pub fn end_scope(&mut self) {
+        self.check_type_aliases();
+
+        if self.scope_depth > 0 {
+            self.scope_depth -= 1;
+        }
+        self.scopes.pop();
+    }
+
+    pub fn insert(
+        &mut self,
+        lexeme: String,
+        value_type: ValueType,
+        is_mutable: bool,
+        is_initialized: bool
+    ) {
+        self.scopes[self.scope_depth].insert(lexeme, value_type, is_mutable, is_initialized);
+    }
+
+    pub fn start_function(&mut self, is_closure: bool) {
+        self.start_scope();
+        self.function_scope_depths.push(self.scope_depth);
+        self.closure_captures.push(if is_closure { Some(Vec::new()) } else { None });
+    }
+
+    pub fn end_function(&mut self) -> Vec<String> {
+        self.function_scope_depths.pop();
+        self.end_scope();
+        self.closure_captures.pop().flatten().unwrap_or_default()
+    }
+
+    fn get_visible_scope_start(&self) -> usize {
+        let function_scopes = self.function_scope_depths.iter().zip(&self.closure_captures).rev();
+
+        for (scope_depth, captures) in function_scopes {
+            if captures.is_none() {
+                return *scope_depth;
+            }
+        }
+
+        0
+    }
+
+    fn is_captured(&self, scope_index: usize) -> bool {
+        self.function_scope_depths.last().is_some_and(|scope_depth| scope_index < *scope_depth)
+    }
+
+    // Each closure between the variable and the innermost closure captures it
+    pub fn capture(&mut self, lexeme: &String) {
+        let scope = match self.find_variable(lexeme) {
+            Some(scope) if self.is_captured(scope) => scope,
+            _ => {
+                return;
+            }
+        };
+
+        let function_scopes = self.function_scope_depths.iter().zip(self.closure_captures.iter_mut()).rev();
+
+        for (scope_depth, captures) in function_scopes {
+            if scope >= *scope_depth {
+                break;
+            }
+
+            if let Some(captures) = captures {
+                if !captures.contains(lexeme) {
+                    captures.push(lexeme.clone());
+                }
+            }
+        }
+    }
+
+    // Parsed like a function that isn't a closure, so the importer's variables aren't visible
+    pub fn start_module(&mut self) {
+        self.start_function(false);
+        self.module_scope_depths.push(self.scope_depth);
+    }
+
+    pub fn end_module(&mut self) -> ModuleExports {
+        let scope = &self.scopes[self.scope_depth];
+        let is_public = |name: &&String| scope.public_names.contains(name);
+
+        let exports = ModuleExports {
+            functions: scope.functions
+                .iter()
+                .filter(|(name, _)| is_public(name))
+                .map(|(name, signature)| (name.clone(), signature.clone()))
+                .collect(),
+            classes: scope.classes
+                .iter()
+                .filter(|(name, _)| is_public(name))
+                .map(|(name, id)| (name.clone(), *id))
+                .collect(),
+            enums: scope.enums
+                .iter()
+                .filter(|(name, _)| is_public(name))
+                .map(|(name, id)| (name.clone(), *id))
+                .collect(),
+            traits: scope.traits
+                .iter()
+                .filter(|(name, _)| is_public(name))
+                .map(|(name, id)| (name.clone(), *id))
+                .collect(),
+            type_aliases: scope.type_aliases
+                .iter()
+                .filter(|(name, _)| is_public(name))
+                .filter_map(|(name, type_alias)| {
+                    // Invalid aliases are reported when the module scope ends
+                    let value_type = self.resolve_type_alias(name, type_alias).ok()?;
+                    let typing = Typing::new(
+                        TypingValue::ValueType(value_type),
+                        type_alias.typing.token_metadata,
+                        None
+                    );
+
+                    Some((name.clone(), TypeAlias { type_params: type_alias.type_params.clone(), typing }))
+                })
+                .collect(),
+        };
+
+        self.module_scope_depths.pop();
+        self.end_function();
+
+        exports
+    }
+
+    fn get_module_scope_start(&self) -> usize {
+        self.module_scope_depths.last().copied().unwrap_or(0)
+    }
+
+    pub fn is_module_top_level(&self) -> bool {
+        self.scope_depth == self.get_module_scope_start()
+    }
+
+    // Names already declared are kept, and returned with the module they came from
+    pub fn import(&mut self, exports: &ModuleExports, path: &Path) -> Vec<(String, Option<PathBuf>)> {
+        let scope = &self.scopes[self.scope_depth];
+
+        let taken_functions = exports.functions.keys().filter(|name| scope.functions.contains_key(*name));
+        let taken_types = exports.classes
+            .keys()
+            .chain(exports.enums.keys())
+            .chain(exports.traits.keys())
+            .chain(exports.type_aliases.keys())
+            .filter(|name| self.is_type_defined_in_scope(name));
+        let taken = taken_functions.chain(taken_types).cloned().collect::<HashSet<_>>();
+
+        // Importing the same module again isn't a conflict
+        let mut conflicts = taken
+            .iter()
+            .filter_map(|name| {
+                match scope.imports.get(name) {
+                    Some(import_path) if import_path == path => None,
+                    import_path => Some((name.clone(), import_path.cloned())),
+                }
+            })
+            .collect::<Vec<_>>();
+        conflicts.sort();
+
+        let scope = &mut self.scopes[self.scope_depth];
+        import_names(&mut scope.functions, &exports.functions, &taken, &mut scope.imports, path);
+        import_names(&mut scope.classes, &exports.classes, &taken, &mut scope.imports, path);
+        import_names(&mut scope.enums, &exports.enums, &taken, &mut scope.imports, path);
+        import_names(&mut scope.traits, &exports.traits, &taken, &mut scope.imports, path);
+        import_names(&mut scope.type_aliases, &exports.type_aliases, &taken, &mut scope.imports, path);
+
+        conflicts
+    }
+
+    pub fn set_public(&mut self, name: String) {
+        self.scopes[self.scope_depth].public_names.push(name);
+    }
+
+    pub fn insert_function(&mut self, name: String, signature: FunctionSignature) {
+        self.scopes[self.scope_depth].functions.insert(name, signature);
+    }
+
+    pub fn hoist_function(&mut self, name: String, signature: FunctionSignature) -> bool {
+        let scope = &mut self.scopes[self.scope_depth];
+        if scope.functions.contains_key(&name) {
+            return false;
+        }
+
+        scope.hoisted_functions.insert(name.clone());
+        scope.functions.insert(name, signature);
+        true
+    }
+
+    pub fn take_hoisted_function(&mut self, name: &String) -> Option<usize> {
+        let scope = &mut self.scopes[self.scope_depth];
+        if !scope.hoisted_functions.remove(name) {
+            return None;
+        }
+
+        scope.functions.get(name).map(|signature| signature.id)
+    }
+
+    pub fn is_function_defined_in_scope(&self, name: &String) -> bool {
+        self.scopes[self.scope_depth].functions.contains_key(name)
+    }
+
+    pub fn get_function(&self, name: &String) -> Option<FunctionSignature> {
+        for i in (self.get_module_scope_start()..self.scope_depth + 1).rev() {
+            if let Some(signature) = self.scopes[i].functions.get(name) {
+                return Some(signature.clone());
+            }
+        }
+
+        None
+    }
+
+    pub fn insert_class(&mut self, name: String, declared_id: Option<usize>) -> ClassType {
+        let type_params = self.get_current_type_params();
+        let class_type = ClassType {
+            id: declared_id.unwrap_or(self.classes.len()),
+            name: name.as_str().into(),
+            type_args: type_params
+                .iter()
+                .map(|type_param| ValueType::TypeParam(type_param.clone()))
+                .collect(),
+        };
+
+        let signature = ClassSignature::new(class_type.clone(), type_params);
+        match declared_id {
+            Some(id) => {
+                self.classes[id] = signature;
+                self.declared_classes.remove(&id);
+            }
+            None => self.classes.push(signature),
+        }
+        self.set_generic_id(GenericId::Class(class_type.id));
+        self.scopes[self.scope_depth].classes.insert(name, class_type.id);
+
+        class_type
+    }
+
+    pub fn get_class_type(&self, name: &String) -> Option<ClassType> {
+        for i in (self.get_module_scope_start()..self.scope_depth + 1).rev() {
+            if let Some(id) = self.scopes[i].classes.get(name) {
+                return Some(self.classes[*id].class_type.clone());
+            }
+        }
+
+        None
+    }
+
+    pub fn get_class(&self, class_type: &ClassType) -> &ClassSignature {
+        &self.classes[class_type.id]
+    }
+
+    pub fn get_class_mut(&mut self, class_type: &ClassType) -> &mut ClassSignature {
+        &mut self.classes[class_type.id]
+    }
+
+    pub fn insert_enum(&mut self, name: String, declared_id: Option<usize>) -> EnumType {
+        let enum_type = EnumType { id: declared_id.unwrap_or(self.enums.len()), name: name.as_str().into() };
+
+        let signature = EnumSignature::new(enum_type.clone());
+        match declared_id {
+            Some(id) => {
+                self.enums[id] = signature;
+            }
+            None => self.enums.push(signature),
+        }
+        self.scopes[self.scope_depth].enums.insert(name, enum_type.id);
+
+        enum_type
+    }
+
+    pub fn declare_class(&mut self, name: String) {
+        if self.is_type_defined_in_scope(&name) {
+            return;
+        }
+
+        let class_type = self.insert_class(name.clone(), None);
+        self.declared_classes.insert(class_type.id);
+        self.scopes[self.scope_depth].declared_types.insert(name);
+    }
+
+    pub fn declare_enum(&mut self, name: String) {
+        if self.is_type_defined_in_scope(&name) {
+            return;
+        }
+
+        self.insert_enum(name.clone(), None);
+        self.scopes[self.scope_depth].declared_types.insert(name);
+    }
+
+    pub fn take_declared_class(&mut self, name: &String) -> Option<usize> {
+        let scope = &mut self.scopes[self.scope_depth];
+        if !scope.classes.contains_key(name) || !scope.declared_types.remove(name) {
+            return None;
+        }
+
+        scope.classes.get(name).copied()
+    }
+
+    pub fn take_declared_enum(&mut self, name: &String) -> Option<usize> {
+        let scope = &mut self.scopes[self.scope_depth];
+        if !scope.enums.contains_key(name) || !scope.declared_types.remove(name) {
+            return None;
+        }
+
+        scope.enums.get(name).copied()
+    }
+
+    pub fn get_declared_enum(&self, name: &String) -> Option<EnumType> {
+        let scope = &self.scopes[self.scope_depth];
+        if !scope.declared_types.contains(name) {
+            return None;
+        }
+
+        scope.enums.get(name).map(|id| self.enums[*id].enum_type.clone())
+    }
+
+    pub fn is_class_declared_only(&self, class_type: &ClassType) -> bool {
+        self.declared_classes.contains(&class_type.id)
+    }
+
+    pub fn class_used_before_definition(class_type: &ClassType) -> String {
+        format!("Class '{}' cannot be constructed or have its members used before its definition", class_type.name)
+    }
+
+    pub fn get_enum_type(&self, name: &String) -> Option<EnumType> {
+        for i in (self.get_module_scope_start()..self.scope_depth + 1).rev() {
+            if let Some(id) = self.scopes[i].enums.get(name) {
+                return Some(self.enums[*id].enum_type.clone());
+            }
+        }
+
+        None
+    }
+
+    pub fn get_enum(&self, enum_type: &EnumType) -> &EnumSignature {
+        &self.enums[enum_type.id]
+    }
+
+    pub fn get_enum_mut(&mut self, enum_type: &EnumType) -> &mut EnumSignature {
+        &mut self.enums[enum_type.id]
+    }
+
+    pub fn insert_trait(&mut self, name: String) -> usize {
+        let id = self.traits.len();
+
+        self.traits.push(TraitSignature {
+            name: name.as_str().into(),
+            methods: Vec::new(),
+            impls: HashMap::new(),
+        });
+        self.scopes[self.scope_depth].traits.insert(name, id);
+
+        id
+    }
+
+    pub fn get_trait_id(&self, name: &String) -> Option<usize> {
+        for i in (self.get_module_scope_start()..self.scope_depth + 1).rev() {
+            if let Some(id) = self.scopes[i].traits.get(name) {
+                return Some(*id);
+            }
+        }
+
+        None
+    }
+
+    pub fn get_trait(&self, id: usize) -> &TraitSignature {
+        &self.traits[id]
+    }
+
+    pub fn get_trait_mut(&mut self, id: usize) -> &mut TraitSignature {
+        &mut self.traits[id]
+    }
+
+    pub fn next_trait_method_id(&mut self) -> usize {
+        self.trait_method_count += 1;
+        self.trait_method_count - 1
+    }
+
+    pub fn implements(&self, value_type: &ValueType, trait_id: usize) -> bool {
+        match value_type.get_impl_target() {
+            Some(target) => self.traits[trait_id].impls.contains_key(&target),
+            None => false,
+        }
+    }
+
+    // Keeps the existing method and returns false if the name is taken
+    pub fn insert_type_method(&mut self, self_type: &ValueType, name: &str, method: Method) -> bool {
+        let Some(target) = self_type.get_impl_target() else {
+            return false;
+        };
+
+        self.type_attrs
+            .entry(target)
+            .or_insert_with(|| {
+                value_v2::get_type_attrs(self_type)
+                    .map(|attrs| (*attrs).clone())
+                    .unwrap_or_default()
+            })
+            .insert_method(name, method)
+    }
+
+    pub fn is_type_defined_in_scope(&self, name: &String) -> bool {
+        let scope = &self.scopes[self.scope_depth];
+
+        scope.type_aliases.contains_key(name) ||
+            scope.classes.contains_key(name) ||
+            scope.enums.contains_key(name) ||
+            scope.traits.contains_key(name)
+    }
+
+    pub fn start_type_params(&mut self, type_params: Vec<Rc<str>>, bounds: Vec<Vec<usize>>) {
+        self.type_param_scopes.push(TypeParamScope {
+            type_params,
+            bounds,
+            generic_id: None,
+            requirements: Vec::new(),
+        });
+    }
+
+    // Returns the errors found in the uses of the generic before its definition
+    pub fn end_type_params(&mut self) -> Vec<(String, Vec<TokenMetadata>)> {
+        let scope = self.type_param_scopes.pop().expect("No type parameters to end");
+
+        let generic_id = match (scope.generic_id, scope.type_params.is_empty()) {
+            (Some(generic_id), false) => generic_id,
+            _ => {
+                return Vec::new();
+            }
+        };
+        self.requirements.insert(generic_id, scope.requirements);
+        self.bounds.insert(generic_id, scope.bounds);
+
+        match self.pending_generics.remove(&generic_id) {
+            true => self.check_deferred_type_args(generic_id),
+            false => Vec::new(),
+        }
+    }
+
+    // Only the bounds are known until the definition is parsed
+    pub fn end_declared_type_params(&mut self) {
+        let scope = self.type_param_scopes.pop().expect("No type parameters to end");
+
+        if let (Some(generic_id), false) = (scope.generic_id, scope.type_params.is_empty()) {
+            self.bounds.insert(generic_id, scope.bounds);
+            self.pending_generics.insert(generic_id);
+        }
+    }
+
+    fn check_deferred_type_args(&mut self, generic_id: GenericId) -> Vec<(String, Vec<TokenMetadata>)> {
+        let (uses, rest): (Vec<_>, Vec<_>) = self.deferred_uses
+            .take()
+            .into_iter()
+            .partition(|generic_use| generic_use.generic_id == generic_id);
+        self.deferred_uses.replace(rest);
+
+        let mut errors = Vec::new();
+        for generic_use in uses {
+            let result = self.check_requirements(
+                generic_use.generic_id,
+                &generic_use.generic_name,
+                &generic_use.type_params,
+                &generic_use.type_args,
+                generic_use.use_metadata
+            );
+
+            match result {
+                Ok(passed_on) => {
+                    for requirement in passed_on {
+                        errors.extend(self.pass_on_requirement(requirement, &generic_use.type_param_generics));
+                    }
+                }
+                Err(error) => errors.push(error),
+            }
+            self.generic_uses.borrow_mut().push(generic_use);
+        }
+
+        errors
+    }
+
+    fn pass_on_requirement(
+        &mut self,
+        requirement: TypeParamRequirement,
+        type_param_generics: &[(Rc<str>, GenericId)]
+    ) -> Vec<(String, Vec<TokenMetadata>)> {
+        let generic_id = type_param_generics
+            .iter()
+            .rev()
+            .find(|(type_param, _)| *type_param == requirement.type_param)
+            .map(|(_, generic_id)| *generic_id);
+        let generic_id = match generic_id {
+            Some(generic_id) => generic_id,
+            None => {
+                return Vec::new();
+            }
+        };
+
+        match self.type_param_scopes.iter_mut().find(|scope| scope.generic_id == Some(generic_id)) {
+            Some(scope) => {
+                scope.requirements.push(requirement);
+                Vec::new()
+            }
+            None => self.add_late_requirement(generic_id, requirement),
+        }
+    }
+
+    // Checks the uses found so far against the new requirement
+    fn add_late_requirement(
+        &mut self,
+        generic_id: GenericId,
+        requirement: TypeParamRequirement
+    ) -> Vec<(String, Vec<TokenMetadata>)> {
+        let requirements = self.requirements.entry(generic_id).or_default();
+        let is_known = requirements
+            .iter()
+            .any(|other| other.type_param == requirement.type_param && other.operation == requirement.operation);
+        if is_known {
+            return Vec::new();
+        }
+        requirements.push(requirement.clone());
+
+        let uses = self.generic_uses
+            .borrow()
+            .iter()
+            .filter(|generic_use| generic_use.generic_id == generic_id)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut errors = Vec::new();
+        for generic_use in uses {
+            let result = self.check_requirement(
+                &requirement,
+                &generic_use.generic_name,
+                &generic_use.type_params,
+                &generic_use.type_args,
+                generic_use.use_metadata
+            );
+
+            match result {
+                Ok(Some(passed_on)) => {
+                    errors.extend(self.pass_on_requirement(passed_on, &generic_use.type_param_generics));
+                }
+                Ok(None) => {}
+                Err(error) => errors.push(error),
+            }
+        }
+
+        errors
+    }
+
+    pub fn get_type_param_bounds(&self, name: &str) -> &[usize] {
+        for scope in self.type_param_scopes.iter().rev() {
+            if let Some(index) = scope.type_params.iter().position(|type_param| &**type_param == name) {
+                return &scope.bounds[index];
+            }
+        }
+
+        &[]
+    }
+
+    fn get_generic_bounds(&self, generic_id: GenericId) -> Vec<Vec<usize>> {
+        if let Some(bounds) = self.bounds.get(&generic_id) {
+            return bounds.clone();
+        }
+
+        self.type_param_scopes
+            .iter()
+            .find(|scope| scope.generic_id == Some(generic_id))
+            .map(|scope| scope.bounds.clone())
+            .unwrap_or_default()
+    }
+
+    // The trait methods a generic gets as hidden parameters after its arguments
+    pub fn get_bound_methods(&self, generic_id: GenericId) -> Vec<(usize, usize, usize)> {
+        let mut bound_methods = Vec::new();
+
+        for (type_param_index, trait_ids) in self.get_generic_bounds(generic_id).iter().enumerate() {
+            for trait_id in trait_ids {
+                for method_index in 0..self.traits[*trait_id].methods.len() {
+                    bound_methods.push((type_param_index, *trait_id, method_index));
+                }
+            }
+        }
+
+        bound_methods
+    }
+
+    pub fn set_generic_id(&mut self, generic_id: GenericId) {
+        if let Some(scope) = self.type_param_scopes.last_mut() {
+            scope.generic_id = Some(generic_id);
+        }
+    }
+
+    pub fn get_current_type_params(&self) -> Vec<Rc<str>> {
+        self.type_param_scopes
+            .last()
+            .map(|scope| scope.type_params.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn is_type_param(&self, name: &str) -> bool {
+        self.type_param_scopes
+            .iter()
+            .any(|scope| scope.type_params.iter().any(|type_param| &**type_param == name))
+    }
+
+    pub fn has_type_params(&self) -> bool {
+        self.type_param_scopes.iter().any(|scope| !scope.type_params.is_empty())
+    }
+
+    pub fn add_requirement(&mut self, requirement: TypeParamRequirement) {
+        let scope = self.type_param_scopes
+            .iter_mut()
+            .rev()
+            .find(|scope| scope.type_params.contains(&requirement.type_param));
+
+        if let Some(scope) = scope {
+            scope.requirements.push(requirement);
+        }
+    }
+
+    // Requirements on type arguments that are type parameters themselves are returned
+    pub fn check_type_args(
+        &self,
+        generic_id: GenericId,
+        generic_name: &str,
+        type_params: &[Rc<str>],
+        type_args: &[ValueType],
+        use_metadata: TokenMetadata
+    ) -> Result<Vec<TypeParamRequirement>, (String, Vec<TokenMetadata>)> {
+        let bounds = self.bounds.get(&generic_id).into_iter().flatten();
+        for ((type_param, type_arg), trait_ids) in type_params.iter().zip(type_args).zip(bounds) {
+            for trait_id in trait_ids {
+                let trait_name = &self.traits[*trait_id].name;
+
+                let message = match type_arg {
+                    ValueType::Empty | ValueType::Unkown => None,
+                    ValueType::TypeParam(name) if !self.get_type_param_bounds(name).contains(trait_id) => {
+                        Some(
+                            format!(
+                                "Type parameter '{}' needs the bound '{}: {}' to be used for '{}' in '{}'",
+                                name,
+                                name,
+                                trait_name,
+                                type_param,
+                                generic_name
+                            )
+                        )
+                    }
+                    ValueType::TypeParam(_) => None,
+                    type_arg if !self.implements(type_arg, *trait_id) => {
+                        Some(
+                            format!(
+                                "Type {} doesn't implement trait '{}', which '{}' in '{}' requires",
+                                type_arg.to_type_string(),
+                                trait_name,
+                                type_param,
+                                generic_name
+                            )
+                        )
+                    }
+                    _ => None,
+                };
+
+                if let Some(message) = message {
+                    return Err((message, vec![use_metadata]));
+                }
+            }
+        }
+
+        let generic_use = GenericUse {
+            generic_id,
+            generic_name: generic_name.to_string(),
+            type_params: type_params.to_vec(),
+            type_args: type_args.to_vec(),
+            use_metadata,
+            type_param_generics: self.type_param_scopes
+                .iter()
+                .filter_map(|scope| Some((scope.generic_id?, &scope.type_params)))
+                .flat_map(|(id, type_params)| type_params.iter().map(move |type_param| (type_param.clone(), id)))
+                .collect(),
+        };
+
+        if self.pending_generics.contains(&generic_id) {
+            self.deferred_uses.borrow_mut().push(generic_use);
+            return Ok(Vec::new());
+        }
+
+        let passed_on = self.check_requirements(generic_id, generic_name, type_params, type_args, use_metadata)?;
+        self.generic_uses.borrow_mut().push(generic_use);
+
+        Ok(passed_on)
+    }
+
+    fn check_requirements(
+        &self,
+        generic_id: GenericId,
+        generic_name: &str,
+        type_params: &[Rc<str>],
+        type_args: &[ValueType],
+        use_metadata: TokenMetadata
+    ) -> Result<Vec<TypeParamRequirement>, (String, Vec<TokenMetadata>)> {
+        let mut passed_on = Vec::new();
+
+        for requirement in self.requirements.get(&generic_id).into_iter().flatten() {
+            let result = self.check_requirement(requirement, generic_name, type_params, type_args, use_metadata)?;
+            passed_on.extend(result);
+        }
+
+        Ok(passed_on)
+    }
+
+    fn check_requirement(
+        &self,
+        requirement: &TypeParamRequirement,
+        generic_name: &str,
+        type_params: &[Rc<str>],
+        type_args: &[ValueType],
+        use_metadata: TokenMetadata
+    ) -> Result<Option<TypeParamRequirement>, (String, Vec<TokenMetadata>)> {
+        let type_arg = match type_params.iter().position(|type_param| *type_param == requirement.type_param) {
+            Some(index) => &type_args[index],
+            None => {
+                return Ok(None);
+            }
+        };
+
+        let is_supported = match requirement.operation {
+            Op::BinaryOp(op) => type_arg.type_check_binary(type_arg, op).is_ok(),
+            Op::UnaryOp(op) => type_arg.type_check_unary(op).is_ok(),
+            _ => true,
+        };
+
+        match type_arg {
+            ValueType::TypeParam(name) => {
+                Ok(
+                    Some(TypeParamRequirement {
+                        type_param: name.clone(),
+                        operation: requirement.operation,
+                        token_metadata: use_metadata,
+                    })
+                )
+            }
+            ValueType::Empty | ValueType::Unkown => Ok(None),
+            _ if !is_supported => {
+                Err((
+                    format!(
+                        "Type argument {} for '{}' in '{}' doesn't support {}, which is used on line {}",
+                        type_arg.to_type_string(),
+                        requirement.type_param,
+                        generic_name,
+                        requirement.operation.to_op_string(),
+                        requirement.token_metadata.get_line()
+                    ),
+                    vec![use_metadata],
+                ))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn specialize_member_type(&self, class_type: &ClassType, value_type: &ValueType) -> ValueType {
+        value_type.substitute(&self.get_class(class_type).type_params, &class_type.type_args)
+    }
+
+    pub fn insert_type_alias(&mut self, name: String, type_alias: TypeAlias) {
+        self.scopes[self.scope_depth].type_aliases.insert(name, type_alias);
+    }
+
+    pub fn resolve_typing(&self, typing: &Typing) -> Result<ValueType, (String, Vec<TokenMetadata>)> {
+        self.resolve_typing_in_scope(typing, self.scope_depth, &mut Vec::new())
+    }
+
+    // Done when the scope ends, since an alias can refer to types defined after it
+    fn check_type_aliases(&mut self) {
+        let mut errors = Vec::new();
+
+        for (name, type_alias) in &self.scopes[self.scope_depth].type_aliases {
+            if let Err(error) = self.resolve_type_alias(name, type_alias) {
+                errors.push(error);
+            }
+        }
+
+        // The aliases aren't kept in order, so the errors are sorted by where they are
+        errors.sort_by_key(|(_, token_vec)| {
+            token_vec.first().map(|metadata| (metadata.get_file_id(), metadata.get_start()))
+        });
+        self.type_alias_errors.extend(errors);
+    }
+
+    fn resolve_type_alias(
+        &self,
+        name: &str,
+        type_alias: &TypeAlias
+    ) -> Result<ValueType, (String, Vec<TokenMetadata>)> {
+        let type_args = type_alias.type_params
+            .iter()
+            .map(|type_param| ValueType::TypeParam(type_param.clone()))
+            .collect::<Vec<_>>();
+        let typing = type_alias.typing.substitute(&type_alias.type_params, &type_args);
+
+        self.resolve_typing_in_scope(&typing, self.scope_depth, &mut vec![name.to_string()])
+    }
+
+    pub fn end_program(&mut self) -> Vec<(String, Vec<TokenMetadata>)> {
+        self.check_type_aliases();
+
+        std::mem::take(&mut self.type_alias_errors)
+    }
+
+    // `alias_path` holds the aliases being resolved, so cycles are found
+    fn resolve_typing_in_scope(
+        &self,
+        typing: &Typing,
+        scope_depth: usize,
+        alias_path: &mut Vec<String>
+    ) -> Result<ValueType, (String, Vec<TokenMetadata>)> {
+        match &typing.typing_value {
+            TypingValue::ValueType(value_type) if typing.type_args.is_some() => {
+                Err((
+                    format!("Type {} doesn't take type arguments", value_type.to_type_string()),
+                    vec![typing.token_metadata],
+                ))
+            }
+            TypingValue::ValueType(value_type) => Ok(value_type.clone()),
+            TypingValue::Array => {
+                let element_typing = typing.type_args
+                    .as_ref()
+                    .and_then(|type_args| type_args.first())
+                    .expect("Array typings have an element type");
+
+                let element_type = self.resolve_typing_in_scope(
+                    element_typing,
+                    scope_depth,
+                    alias_path
+                )?;
+
+                Ok(ValueType::Array(Box::new(element_type)))
+            }
+            TypingValue::Map => {
+                let typings = typing.type_args.as_ref().expect("Map typings have a key and a value type");
+
+                let key_type = self.resolve_typing_in_scope(
+                    &typings[0],
+                    scope_depth,
+                    alias_path
+                )?;
+                let value_type = self.resolve_typing_in_scope(
+                    &typings[1],
+                    scope_depth,
+                    alias_path
+                )?;
+
+                if !key_type.is_hashable() {
+                    return Err((
+                        format!(
+                            "Map keys must be integers, bools or strings, but got {}",
+                            key_type.to_type_string()
+                        ),
+                        vec![typings[0].token_metadata],
+                    ));
+                }
+
+                Ok(ValueType::Map(Box::new(key_type), Box::new(value_type)))
+            }
+            TypingValue::Optional => {
+                let inner_typing = typing.type_args
+                    .as_ref()
+                    .and_then(|type_args| type_args.first())
+                    .expect("Optional typings have an inner type");
+
+                let inner_type = self.resolve_typing_in_scope(
+                    inner_typing,
+                    scope_depth,
+                    alias_path
+                )?;
+
+                // An alias of an optional can be made optional again, which changes nothing
+                match inner_type {
+                    ValueType::Optional(_) => Ok(inner_type),
+                    _ => Ok(ValueType::Optional(Box::new(inner_type))),
+                }
+            }
+            TypingValue::Tuple => {
+                let element_typings = typing.type_args
+                    .as_ref()
+                    .expect("Tuple typings have element types");
+
+                let element_types = element_typings
+                    .iter()
+                    .map(|element_typing| {
+                        self.resolve_typing_in_scope(
+                            element_typing,
+                            scope_depth,
+                            alias_path
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(ValueType::Tuple(element_types))
+            }
+            TypingValue::Function => {
+                let typings = typing.type_args
+                    .as_ref()
+                    .expect("Function typings have a return type");
+
+                let mut parameters = typings
+                    .iter()
+                    .map(|typing| {
+                        self.resolve_typing_in_scope(typing, scope_depth, alias_path)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let return_type = parameters.pop().expect("Function typings have a return type");
+
+                Ok(
+                    ValueType::Function(FunctionType {
+                        parameters,
+                        return_type: Box::new(return_type),
+                    })
+                )
+            }
+            TypingValue::Custom(name) if self.is_type_param(name) => {
+                match typing.type_args {
+                    Some(_) => {
+                        Err((
+                            format!("Type parameter '{}' doesn't take type arguments", name),
+                            vec![typing.token_metadata],
+                        ))
+                    }
+                    None => Ok(ValueType::TypeParam(name.as_str().into())),
+                }
+            }
+            TypingValue::Custom(name) => {
+                for i in (self.get_module_scope_start()..scope_depth + 1).rev() {
+                    let scope = &self.scopes[i];
+
+                    if typing.type_args.is_some() && scope.enums.contains_key(name) {
+                        return Err((
+                            format!("Type '{}' doesn't take type arguments", name),
+                            vec![typing.token_metadata],
+                        ));
+                    }
+
+                    if let Some(type_alias) = scope.type_aliases.get(name) {
+                        if let Some(cycle_start) = alias_path.iter().position(|alias| alias == name) {
+                            let mut cycle = alias_path[cycle_start..].to_vec();
+                            cycle.push(name.clone());
+
+                            return Err((
+                                format!(
+                                    "Type alias '{}' refers to itself: {}",
+                                    name,
+                                    cycle.join(" -> ")
+                                ),
+                                vec![typing.token_metadata],
+                            ));
+                        }
+
+                        // The type arguments are resolved where the alias is used
+                        let type_args = typing.type_args
+                            .iter()
+                            .flatten()
+                            .map(|type_arg| self.resolve_typing_in_scope(type_arg, scope_depth, alias_path))
+                            .collect::<Result<Vec<_>, _>>()?;
+
+                        if type_args.len() != type_alias.type_params.len() {
+                            return Err((
+                                format!(
+                                    "Type alias '{}' expects {} type argument(s) but got {}",
+                                    name,
+                                    type_alias.type_params.len(),
+                                    type_args.len()
+                                ),
+                                vec![typing.token_metadata],
+                            ));
+                        }
+
+                        alias_path.push(name.clone());
+                        let result = self.resolve_typing_in_scope(
+                            &type_alias.typing.substitute(&type_alias.type_params, &type_args),
+                            i,
+                            alias_path
+                        );
+                        alias_path.pop();
+
+                        return result;
+                    }
+
+                    if let Some(id) = scope.classes.get(name) {
+                        return self.resolve_class_typing(
+                            &self.classes[*id],
+                            typing,
+                            scope_depth,
+                            alias_path
+                        );
+                    }
+
+                    if let Some(id) = scope.enums.get(name) {
+                        return Ok(ValueType::Enum(self.enums[*id].enum_type.clone()));
+                    }
+                }
+
+                Err((format!("Unknown type '{}'", name), vec![typing.token_metadata]))
+            }
+        }
+    }
+
+    fn resolve_class_typing(
+        &self,
+        class: &ClassSignature,
+        typing: &Typing,
+        scope_depth: usize,
+        alias_path: &mut Vec<String>
+    ) -> Result<ValueType, (String, Vec<TokenMetadata>)> {
+        let type_args = typing.type_args
+            .iter()
+            .flatten()
+            .map(|type_arg| {
+                self.resolve_typing_in_scope(type_arg, scope_depth, alias_path)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let class_type = &class.class_type;
+
+        if type_args.len() != class.type_params.len() {
+            return Err((
+                format!(
+                    "Class '{}' expects {} type argument(s) but got {}",
+                    class_type.name,
+                    class.type_params.len(),
+                    type_args.len()
+                ),
+                vec![typing.token_metadata],
+            ));
+        }
+
+        // Requirements on type parameters are passed on where the generic calls a function
+        if !type_args.iter().any(|type_arg| type_arg.contains_type_param()) {
+            self.check_type_args(
+                GenericId::Class(class_type.id),
+                &class_type.name,
+                &class.type_params,
+                &type_args,
+                typing.token_metadata
+            )?;
+        }
+
+        Ok(
+            ValueType::Class(ClassType {
+                id: class_type.id,
+                name: class_type.name.clone(),
+                type_args,
+            })
+        )
+    }
+
+    pub fn start_class(&mut self, class_type: &ClassType) {
+        self.start_function(false);
+        self.class_ids.push(class_type.id);
+    }
+
+    pub fn end_class(&mut self) {
+        self.class_ids.pop();
+        self.end_function();
+    }
+
+    fn is_inside_class(&self, class_type: &ClassType) -> bool {
+        self.class_ids.contains(&class_type.id)
+    }
+
+    pub fn get_field(&self, class_type: &ClassType, name: &str) -> Result<(usize, &ClassField), String> {
+        if self.is_class_declared_only(class_type) {
+            return Err(Self::class_used_before_definition(class_type));
+        }
+        let class = self.get_class(class_type);
+
+        match class.fields.iter().enumerate().find(|(_, field)| field.name == name) {
+            Some((_, field)) if !field.is_public && !self.is_inside_class(class_type) => {
+                Err(format!("Field '{}' of class '{}' is private", name, class_type.name))
+            }
+            Some(field) => Ok(field),
+            None => Err(format!("Class '{}' has no field '{}'", class_type.name, name)),
+        }
+    }
+
+    // Methods declared in a class come before those from trait implementations
+    pub fn get_method(&self, value_type: &ValueType, name: &str) -> Result<ClassMethod, String> {
+        if let ValueType::Class(class_type) = value_type {
+            if self.is_class_declared_only(class_type) {
+                return Err(Self::class_used_before_definition(class_type));
+            }
+
+            match self.get_class(class_type).methods.get(name) {
+                Some(method) if !method.is_public && !self.is_inside_class(class_type) => {
+                    return Err(format!("Method '{}' of class '{}' is private", name, class_type.name));
+                }
+                Some(method) => {
+                    return Ok(method.clone());
+                }
+                None => {}
+            }
+        }
+
+        if let ValueType::TypeParam(type_param) = value_type {
+            return self.get_bound_method(type_param, name);
+        }
+
+        match (self.get_type_method(value_type, name), value_type) {
+            (Some(method), _) => Ok(method),
+            (None, ValueType::Class(class_type)) => {
+                Err(format!("Class '{}' has no method '{}'", class_type.name, name))
+            }
+            (None, value_type) => {
+                Err(format!("Type {} has no method '{}'", value_type.to_type_string(), name))
+            }
+        }
+    }
+
+    fn get_type_method(&self, value_type: &ValueType, name: &str) -> Option<ClassMethod> {
+        let method = match value_type.get_impl_target().and_then(|target| self.type_attrs.get(&target)) {
+            Some(attrs) => attrs.methods.get(name)?.clone(),
+            None => value_v2::get_type_attrs(value_type)?.methods.get(name)?.clone(),
+        };
+
+        let (parameters, return_type) = method.resolve_types(value_type);
+        let (id, native) = match method.implementation {
+            MethodImpl::Native(native) => (0, Some(native)),
+            MethodImpl::Function(id) => (id, None),
+        };
+
+        Some(ClassMethod {
+            is_public: true,
+            is_self_mutable: method.is_self_mutable,
+            signature: FunctionSignature {
+                id,
+                type_params: Vec::new(),
+                parameters,
+                return_type,
+            },
+            trait_method_id: None,
+            native,
+        })
+    }
+
+    fn get_bound_method(&self, type_param: &str, name: &str) -> Result<ClassMethod, String> {
+        let mut found: Option<(&TraitSignature, &TraitMethod)> = None;
+
+        for trait_id in self.get_type_param_bounds(type_param) {
+            let signature = &self.traits[*trait_id];
+
+            if let Some(method) = signature.methods.iter().find(|method| method.name == name) {
+                if let Some((other, _)) = found {
+                    return Err(
+                        format!(
+                            "Method '{}' of type parameter '{}' is ambiguous, since both '{}' and '{}' have it",
+                            name,
+                            type_param,
+                            other.name,
+                            signature.name
+                        )
+                    );
+                }
+                found = Some((signature, method));
+            }
+        }
+
+        match found {
+            Some((_, method)) => {
+                Ok(ClassMethod {
+                    is_public: true,
+                    is_self_mutable: method.is_self_mutable,
+                    signature: FunctionSignature {
+                        id: method.id,
+                        type_params: Vec::new(),
+                        parameters: method.parameters.clone(),
+                        return_type: method.return_type.clone(),
+                    },
+                    trait_method_id: Some(method.id),
+                    native: None,
+                })
+            }
+            None => {
+                Err(
+                    format!(
+                        "Type parameter '{}' has no method '{}', since none of its trait bounds have it",
+                        type_param,
+                        name
+                    )
+                )
+            }
+        }
+    }
+
+    pub fn get(&self, lexeme: &String) -> Option<(ValueType, bool, bool)> {
+        for i in (self.get_visible_scope_start()..self.scope_depth + 1).rev() {
+            match self.scopes[i].get(lexeme) {
+                Some(v) => {
+                    return Some(v);
+                }
+                None => {
+                    continue;
+                }
+            }
+        }
+
+        None
+    }
+
+    fn find_variable(&self, lexeme: &String) -> Option<usize> {
+        (self.get_visible_scope_start()..self.scope_depth + 1)
+            .rev()
+            .find(|i| self.scopes[*i].definitions.contains_key(lexeme))
+    }
+
+    pub fn get_init_state(&self, lexeme: &String) -> Option<InitState> {
+        self.find_variable(lexeme).map(|i| self.scopes[i].definitions[lexeme].init_state)
+    }
+
+    // An immutable variable must be uninitialized on every path, and outside loops in its scope
+    pub fn initialize(&mut self, lexeme: &String) -> Result<(), String> {
+        let Some(i) = self.find_variable(lexeme) else {
+            return Ok(());
+        };
+        let variable = &self.scopes[i].definitions[lexeme];
+
+        // The closure might never be called, so it can't be what initializes the variable
+        if self.is_captured(i) && variable.init_state != InitState::Initialized {
+            return Err(format!("Variable '{}' must be initialized before a closure can assign to it", lexeme));
+        }
+
+        if !variable.is_mutable {
+            match variable.init_state {
+                InitState::Initialized => {
+                    return Err(format!("Cannot mutate immutable variable '{}'", lexeme));
+                }
+                InitState::MaybeInitialized => {
+                    return Err(format!("Immutable variable '{}' might already be initialized", lexeme));
+                }
+                InitState::Uninitialized => {
+                    if self.branches.iter().any(|branch| branch.is_loop && i <= branch.scope_depth) {
+                        return Err(
+                            format!(
+                                "Cannot initialize immutable variable '{}' inside a loop, since the loop might run more than once",
+                                lexeme
+                            )
+                        );
+                    }
+                }
+            }
+        }
+
+        self.set_init_state(i, lexeme, InitState::Initialized);
+
+        Ok(())
+    }
+
+    fn set_init_state(&mut self, scope_index: usize, lexeme: &String, init_state: InitState) {
+        let variable = self.scopes[scope_index].definitions.get_mut(lexeme).unwrap();
+        if variable.init_state == init_state {
+            return;
+        }
+
+        // Variables defined inside the block are dropped with it, so they aren't recorded
+        if let Some(branch) = self.branches.last_mut() {
+            if scope_index <= branch.scope_depth {
+                branch.initializations.push((scope_index, lexeme.clone(), variable.init_state));
+            }
+        }
+
+        variable.init_state = init_state;
+    }
+
+    pub fn start_branch(&mut self, is_loop: bool) {
+        self.branches.push(AstBranch {
+            scope_depth: self.scope_depth,
+            is_loop,
+            initializations: Vec::new(),
+        });
+    }
+
+    // Undoes the initializations, since the block might not run. Merging redoes them
+    pub fn end_branch(&mut self, diverges: bool) -> EndedBranch {
+        let branch = self.branches.pop().expect("No branch to end");
+
+        let mut initializations = Vec::new();
+        for (scope_index, lexeme, init_state) in branch.initializations.into_iter().rev() {
+            if let Some(variable) = self.scopes[scope_index].definitions.get_mut(&lexeme) {
+                variable.init_state = init_state;
+            }
+            if !initializations.contains(&(scope_index, lexeme.clone())) {
+                initializations.push((scope_index, lexeme));
+            }
+        }
+
+        EndedBranch { initializations, diverges }
+    }
+
+    // Initialized if every branch reaching its end initialized it, and none can be skipped
+    pub fn merge_branches(&mut self, branches: Vec<EndedBranch>, is_exhaustive: bool) {
+        let reaching_branches = branches
+            .iter()
+            .filter(|branch| !branch.diverges)
+            .collect::<Vec<_>>();
+
+        for branch in &reaching_branches {
+            for variable in &branch.initializations {
+                let is_initialized_everywhere =
+                    is_exhaustive &&
+                    reaching_branches.iter().all(|branch| branch.initializations.contains(variable));
+
+                let init_state = if is_initialized_everywhere {
+                    InitState::Initialized
+                } else {
+                    InitState::MaybeInitialized
+                };
+
+                let (scope_index, lexeme) = variable;
+                self.set_init_state(*scope_index, lexeme, init_state);
+            }
+        }
+    }
+}
+
+struct AstClass {
+    class_type: ClassType,
+    // Functions defined directly in the class body are its methods
+    function_depth: usize,
+}
+
+struct AstImpl {
+    trait_id: usize,
+    self_type: ValueType,
+    // None if the type can't implement traits, which is already reported
+    target: Option<ImplTarget>,
+    target_metadata: TokenMetadata,
+    function_depth: usize,
+    methods: Vec<(String, TokenMetadata, ClassMethod)>,
+}
+
+struct AstFunction {
+    name: String,
+    name_metadata: TokenMetadata,
+    return_type: ValueType,
+    outer_loop_depth: usize,
+    // The expressions before the body, which belong to the expression containing the closure
+    outer_expr_count: usize,
+}
+
+struct AstMatch {
+    value: Expr,
+    enum_type: Option<EnumType>,
+    arms: Vec<MatchArm>,
+    matched_tags: Vec<usize>,
+    arm_pattern: Option<(Option<usize>, Vec<AstIdentifier>)>,
+    has_wildcard: bool,
+    value_type: Option<ValueType>,
+    expr_count: usize,
+    token_metadata: TokenMetadata,
+}
+
+pub struct AstGenerator {
+    ast: Option<Ast>,
+    stmts: Vec<Stmt>,
+    exprs: Vec<Expr>,
+    panic_mode: bool,
+    ast_environment: AstEnvironment,
+    loop_depth: usize,
+    functions: Vec<AstFunction>,
+    function_count: usize,
+    classes: Vec<AstClass>,
+    current_enum: Option<EnumType>,
+    current_trait: Option<usize>,
+    impls: Vec<AstImpl>,
+    matches: Vec<AstMatch>,
+    if_branches: Vec<Option<EndedBranch>>,
+    modules: HashMap<PathBuf, ModuleExports>,
+}
+
+impl AstGenerator {
+    pub fn new() -> Self {
+        Self {
+            ast: Some(Ast::new()),
+            stmts: Vec::new(),
+            exprs: Vec::new(),
+            panic_mode: false,
+            ast_environment: AstEnvironment::new(),
+            loop_depth: 0,
+            functions: Vec::new(),
+            function_count: 0,
+            classes: Vec::new(),
+            current_enum: None,
+            current_trait: None,
+            impls: Vec::new(),
+            matches: Vec::new(),
+            if_branches: Vec::new(),
+            modules: HashMap::new(),
+        }
+    }
+
+    fn get_scope_depth(&self) -> usize {
+        self.ast_environment.get_scope_depth()
+    }
+
+    pub fn start_function(
+        &mut self,
+        name: String,
+        name_metadata: TokenMetadata,
+        args: Vec<FunctionArgument>,
+        return_type: Option<ValueType>,
+        is_public: bool
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let is_method = self.get_method_class_type().is_some() || self.get_method_impl_mut().is_some();
+        let hoisted_id = match is_method {
+            true => None,
+            false => self.ast_environment.take_hoisted_function(&name),
+        };
+
+        // Like for classes, the function is still defined when the name is taken
+        let error = match hoisted_id.is_none() && !is_method {
+            true if self.ast_environment.is_function_defined_in_scope(&name) =>
+                Some((format!("Function '{}' is already defined in this scope", name), vec![name_metadata])),
+            _ => None,
+        };
+
+        let id = hoisted_id.unwrap_or_else(|| {
+            self.function_count += 1;
+            self.function_count - 1
+        });
+
+        let return_type = return_type.unwrap_or(ValueType::Void);
+
+        let method = ClassMethod {
+            is_public,
+            is_self_mutable: args.first().is_some_and(|arg| arg.is_mutable),
+            signature: FunctionSignature {
+                id,
+                type_params: Vec::new(),
+                parameters: args
+                    .iter()
+                    .skip(1)
+                    .map(|arg| arg.value_type.clone())
+                    .collect(),
+                return_type: return_type.clone(),
+            },
+            trait_method_id: None,
+            native: None,
+        };
+
+        // The function is declared before its body, so it can call itself
+        let bound_impl_params = match (self.get_method_class_type(), self.get_method_impl_mut()) {
+            (Some(class_type), _) => {
+                let class = self.ast_environment.get_class_mut(&class_type);
+                class.methods.insert(name.clone(), method);
+
+                let type_params = class.type_params.clone();
+                self.get_bound_impl_params(GenericId::Class(class_type.id), &type_params)
+            }
+            (None, Some(ast_impl)) => {
+                // Methods of trait implementations are always public
+                let method = ClassMethod { is_public: true, ..method };
+                ast_impl.methods.push((name.clone(), name_metadata, method.clone()));
+
+                if ast_impl.target.is_some() {
+                    let self_type = ast_impl.self_type.clone();
+                    let method = Method::from_function(
+                        id,
+                        args.iter().skip(1).cloned().collect(),
+                        return_type.clone(),
+                        method.is_self_mutable
+                    );
+                    self.ast_environment.insert_type_method(&self_type, &name, method);
+                }
+
+                // Traits can't be implemented for generic classes
+                Vec::new()
+            }
+            (None, None) => {
+                let type_params = self.ast_environment.get_current_type_params();
+
+                self.ast_environment.set_generic_id(GenericId::Function(id));
+                self.ast_environment.insert_function(name.clone(), FunctionSignature {
+                    id,
+                    type_params: type_params.clone(),
+                    parameters: args
+                        .iter()
+                        .map(|arg| arg.value_type.clone())
+                        .collect(),
+                    return_type: return_type.clone(),
+                });
+
+                self.get_bound_impl_params(GenericId::Function(id), &type_params)
+            }
+        };
+
+        let args = args.into_iter().chain(bound_impl_params).collect();
+        self.start_function_body(id, name, name_metadata, args, return_type, false);
+
+        match error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    // The hidden parameters holding the functions that implement the trait bound methods
+    fn get_bound_impl_params(&self, generic_id: GenericId, type_params: &[Rc<str>]) -> Vec<FunctionArgument> {
+        self.ast_environment
+            .get_bound_methods(generic_id)
+            .into_iter()
+            .map(|(type_param_index, trait_id, method_index)| {
+                let type_param = &type_params[type_param_index];
+                let method = &self.ast_environment.get_trait(trait_id).methods[method_index];
+
+                FunctionArgument {
+                    name: Self::get_bound_impl_name(type_param, method.id),
+                    value_type: ValueType::Function(
+                        self.get_impl_function_type(trait_id, method_index, ValueType::TypeParam(type_param.clone()))
+                    ),
+                    is_mutable: false,
+                }
+            })
+            .collect()
+    }
+
+    // Not a valid identifier, so programs can't refer to it
+    fn get_bound_impl_name(type_param: &str, trait_method_id: usize) -> String {
+        format!("@{}.{}", type_param, trait_method_id)
+    }
+
+    fn get_impl_function_type(&self, trait_id: usize, method_index: usize, self_type: ValueType) -> FunctionType {
+        let method = &self.ast_environment.get_trait(trait_id).methods[method_index];
+
+        FunctionType {
+            parameters: std::iter::once(self_type).chain(method.parameters.iter().cloned()).collect(),
+            return_type: Box::new(method.return_type.clone()),
+        }
+    }
+
+    fn get_bound_impl(
+        &mut self,
+        type_param: &str,
+        trait_method_id: usize,
+        token_metadata: TokenMetadata
+    ) -> Result<Expr, (String, Vec<TokenMetadata>)> {
+        let lexeme = Self::get_bound_impl_name(type_param, trait_method_id);
+
+        if self.ast_environment.get(&lexeme).is_none() {
+            return Err((
+                format!(
+                    "The trait bounds of type parameter '{}' can only be used in the functions and methods declaring it, or in the closures inside them",
+                    type_param
+                ),
+                vec![token_metadata],
+            ));
+        }
+
+        self.ast_environment.capture(&lexeme);
+        Ok(Expr::IdentifierLookup(AstIdentifier::new(lexeme, token_metadata)))
+    }
+
+    // Resolves calls through trait bounds to the implementations for the type arguments
+    fn get_trait_impls(
+        &mut self,
+        generic_id: GenericId,
+        type_args: &[ValueType],
+        token_metadata: TokenMetadata
+    ) -> Result<Vec<Expr>, (String, Vec<TokenMetadata>)> {
+        let mut trait_impls = Vec::new();
+
+        for (type_param_index, trait_id, method_index) in self.ast_environment.get_bound_methods(generic_id) {
+            let type_arg = &type_args[type_param_index];
+            let signature = self.ast_environment.get_trait(trait_id);
+
+            let function_id = match type_arg {
+                ValueType::TypeParam(type_param) => {
+                    let trait_method_id = signature.methods[method_index].id;
+                    trait_impls.push(self.get_bound_impl(type_param, trait_method_id, token_metadata)?);
+                    continue;
+                }
+                type_arg =>
+                    type_arg
+                        .get_impl_target()
+                        .and_then(|target| signature.impls.get(&target))
+                        .map(|function_ids| function_ids[method_index]),
+            };
+
+            // Only missing when the bounds check already reported an error
+            let value = match function_id {
+                Some(function) => {
+                    let closure = Closure {
+                        function,
+                        function_type: self.get_impl_function_type(trait_id, method_index, type_arg.clone()),
+                        upvalues: Vec::new(),
+                    };
+                    Value::Function(Rc::new(closure))
+                }
+                None => Value::None,
+            };
+            trait_impls.push(Expr::Literal(AstValue::new(value, token_metadata)));
+        }
+
+        Ok(trait_impls)
+    }
+
+    pub fn hoist_function(&mut self, name: String, args: &[FunctionArgument], return_type: Option<ValueType>) {
+        let signature = FunctionSignature {
+            id: self.function_count,
+            type_params: self.ast_environment.get_current_type_params(),
+            parameters: args
+                .iter()
+                .map(|arg| arg.value_type.clone())
+                .collect(),
+            return_type: return_type.unwrap_or(ValueType::Void),
+        };
+
+        if self.ast_environment.hoist_function(name, signature) {
+            self.ast_environment.set_generic_id(GenericId::Function(self.function_count));
+            self.function_count += 1;
+        }
+    }
+
+    pub fn start_closure(
+        &mut self,
+        fn_metadata: TokenMetadata,
+        args: Vec<FunctionArgument>,
+        return_type: Option<ValueType>
+    ) {
+        let id = self.function_count;
+        self.function_count += 1;
+
+        let return_type = return_type.unwrap_or(ValueType::Void);
+        self.start_function_body(id, String::new(), fn_metadata, args, return_type, true);
+    }
+
+    fn start_function_body(
+        &mut self,
+        id: usize,
+        name: String,
+        name_metadata: TokenMetadata,
+        args: Vec<FunctionArgument>,
+        return_type: ValueType,
+        is_closure: bool
+    ) {
+        self.ast_environment.start_function(is_closure);
+        for arg in &args {
+            self.ast_environment.insert(
+                arg.name.clone(),
+                arg.value_type.clone(),
+                arg.is_mutable,
+                true
+            );
+        }
+
+        self.functions.push(AstFunction {
+            name: name.clone(),
+            name_metadata,
+            return_type: return_type.clone(),
+            outer_loop_depth: self.loop_depth,
+            outer_expr_count: self.exprs.len(),
+        });
+        self.loop_depth = 0;
+
+        self.ast
+            .as_mut()
+            .unwrap()
+            .start_function(FunctionStmt::new(id, name, args, return_type, ScopeStmt::new()));
+    }
+
+    // Started even without type parameters, so they're only visible to the generic declaring them
+    pub fn start_type_params(
+        &mut self,
+        type_params: Vec<TypeParamDefinition>
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let mut names: Vec<Rc<str>> = Vec::new();
+        let mut bounds = Vec::new();
+        let mut error = None;
+
+        for type_param in &type_params {
+            let name = type_param.name.lexeme.as_str();
+
+            let message = if names.iter().any(|other| &**other == name) {
+                Some(format!("Type parameter '{}' is declared more than once", name))
+            } else if self.ast_environment.is_type_param(name) {
+                Some(format!("Type parameter '{}' shadows a type parameter of the enclosing generic", name))
+            } else if ValueType::from_name(name).is_some() {
+                Some(format!("Cannot use the builtin type '{}' as a type parameter", name))
+            } else {
+                None
+            };
+
+            let mut trait_ids = Vec::new();
+            for bound in &type_param.bounds {
+                match self.ast_environment.get_trait_id(&bound.lexeme) {
+                    Some(trait_id) => trait_ids.push(trait_id),
+                    None if error.is_none() => {
+                        error = Some((
+                            format!("Unknown trait '{}'", bound.lexeme),
+                            vec![bound.token_metadata],
+                        ));
+                    }
+                    None => {}
+                }
+            }
+
+            match message {
+                Some(message) if error.is_none() => {
+                    error = Some((message, vec![type_param.name.token_metadata]));
+                }
+                Some(_) => {}
+                None => {
+                    names.push(name.into());
+                    bounds.push(trait_ids);
+                }
+            }
+        }
+
+        self.ast_environment.start_type_params(names, bounds);
+
+        match error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    pub fn end_type_params(&mut self) -> Vec<(String, Vec<TokenMetadata>)> {
+        self.ast_environment.end_type_params()
+    }
+
+    pub fn end_declared_type_params(&mut self) {
+        self.ast_environment.end_declared_type_params();
+    }
+
+    pub fn declare_class(&mut self, name: String) {
+        if ValueType::from_name(&name).is_none() {
+            self.ast_environment.declare_class(name);
+        }
+    }
+
+    pub fn declare_enum(&mut self, name: String) {
+        if ValueType::from_name(&name).is_none() {
+            self.ast_environment.declare_enum(name);
+        }
+    }
+
+    // Whether `name<` starts a call with type arguments rather than a comparison
+    pub fn is_generic(&self, name: &String) -> bool {
+        if self.ast_environment.get(name).is_some() {
+            return false;
+        }
+
+        match self.ast_environment.get_function(name) {
+            Some(signature) => !signature.type_params.is_empty(),
+            None =>
+                self.get_class_type(name).is_some_and(|class_type| !class_type.type_args.is_empty()),
+        }
+    }
+
+    pub fn start_class(
+        &mut self,
+        name: String,
+        name_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let declared_id = self.ast_environment.take_declared_class(&name);
+        let error = if ValueType::from_name(&name).is_some() {
+            Some(format!("Cannot redefine the builtin type '{}'", name))
+        } else if declared_id.is_none() && self.ast_environment.is_type_defined_in_scope(&name) {
+            Some(format!("Type '{}' is already defined in this scope", name))
+        } else {
+            None
+        };
+
+        // Like for enums, the body is still parsed when the name is taken
+        let class_type = self.ast_environment.insert_class(name, declared_id);
+
+        self.ast_environment.start_class(&class_type);
+        self.classes.push(AstClass {
+            class_type,
+            function_depth: self.functions.len(),
+        });
+
+        match error {
+            Some(message) => Err((message, vec![name_metadata])),
+            None => Ok(()),
+        }
+    }
+
+    pub fn end_class(&mut self) {
+        self.classes.pop().expect("No class to end");
+        self.ast_environment.end_class();
+    }
+
+    fn get_method_impl_mut(&mut self) -> Option<&mut AstImpl> {
+        match self.impls.last_mut() {
+            Some(ast_impl) if ast_impl.function_depth == self.functions.len() => Some(ast_impl),
+            _ => None,
+        }
+    }
+
+    // In a trait body, the implementing type isn't known
+    pub fn get_method_self_type(&self) -> Option<ValueType> {
+        if let Some(class_type) = self.get_method_class_type() {
+            return Some(ValueType::Class(class_type));
+        }
+
+        match (self.impls.last(), self.current_trait) {
+            (Some(ast_impl), _) if ast_impl.function_depth == self.functions.len() => {
+                Some(ast_impl.self_type.clone())
+            }
+            (_, Some(_)) => Some(ValueType::TypeParam("Self".into())),
+            _ => None,
+        }
+    }
+
+    pub fn start_trait(
+        &mut self,
+        name: String,
+        name_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let error = if ValueType::from_name(&name).is_some() {
+            Some(format!("Cannot redefine the builtin type '{}'", name))
+        } else if self.ast_environment.is_type_defined_in_scope(&name) {
+            Some(format!("Type '{}' is already defined in this scope", name))
+        } else {
+            None
+        };
+
+        // Like for enums, the body is still parsed when the name is taken
+        self.current_trait = Some(self.ast_environment.insert_trait(name));
+
+        match error {
+            Some(message) => Err((message, vec![name_metadata])),
+            None => Ok(()),
+        }
+    }
+
+    pub fn emit_trait_method(
+        &mut self,
+        name: String,
+        name_metadata: TokenMetadata,
+        args: Vec<FunctionArgument>,
+        return_type: Option<ValueType>
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let trait_id = self.current_trait.expect("Trait methods are parsed in trait bodies");
+
+        if self.ast_environment.get_trait(trait_id).methods.iter().any(|method| method.name == name) {
+            return Err((format!("Method '{}' is already declared", name), vec![name_metadata]));
+        }
+
+        let id = self.ast_environment.next_trait_method_id();
+        self.ast_environment.get_trait_mut(trait_id).methods.push(TraitMethod {
+            id,
+            name,
+            is_self_mutable: args.first().is_some_and(|arg| arg.is_mutable),
+            parameters: args
+                .iter()
+                .skip(1)
+                .map(|arg| arg.value_type.clone())
+                .collect(),
+            return_type: return_type.unwrap_or(ValueType::Void),
+        });
+
+        Ok(())
+    }
+
+    pub fn end_trait(&mut self) {
+        self.current_trait = None;
+    }
+
+    // The body is parsed even if this fails
+    pub fn start_impl(
+        &mut self,
+        trait_name: AstIdentifier,
+        self_type: ValueType,
+        target_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let trait_id = self.ast_environment.get_trait_id(&trait_name.lexeme);
+
+        let target = match &self_type {
+            ValueType::Class(class_type) if !class_type.type_args.is_empty() => None,
+            self_type => self_type.get_impl_target(),
+        };
+
+        self.impls.push(AstImpl {
+            trait_id: trait_id.unwrap_or(0),
+            self_type: self_type.clone(),
+            target: target.clone().filter(|_| trait_id.is_some()),
+            target_metadata,
+            function_depth: self.functions.len(),
+            methods: Vec::new(),
+        });
+
+        let Some(trait_id) = trait_id else {
+            return Err((format!("Unknown trait '{}'", trait_name.lexeme), vec![trait_name.token_metadata]));
+        };
+
+        let message = match (&self_type, target) {
+            (ValueType::Class(class_type), None) => {
+                format!(
+                    "Cannot implement trait '{}' for generic class '{}'",
+                    trait_name.lexeme,
+                    class_type.name
+                )
+            }
+            (self_type, None) => {
+                format!("Cannot implement a trait for type {}", self_type.to_type_string())
+            }
+            (self_type, Some(target)) => {
+                if !self.ast_environment.get_trait(trait_id).impls.contains_key(&target) {
+                    return Ok(());
+                }
+
+                format!(
+                    "Trait '{}' is already implemented for {}",
+                    trait_name.lexeme,
+                    self_type.to_type_string()
+                )
+            }
+        };
+
+        // The methods are still parsed and checked, but not registered
+        if let Some(ast_impl) = self.impls.last_mut() {
+            ast_impl.target = None;
+        }
+
+        Err((message, vec![target_metadata]))
+    }
+
+    pub fn end_impl(&mut self) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let ast_impl = self.impls.pop().expect("No trait implementation to end");
+
+        let Some(target) = ast_impl.target else {
+            return Ok(());
+        };
+        let signature = self.ast_environment.get_trait(ast_impl.trait_id);
+
+        for (name, name_metadata, method) in &ast_impl.methods {
+            let Some(trait_method) = signature.methods.iter().find(|trait_method| trait_method.name == *name) else {
+                return Err((
+                    format!("Method '{}' is not a member of trait '{}'", name, signature.name),
+                    vec![*name_metadata],
+                ));
+            };
+
+            let is_matching =
+                method.is_self_mutable == trait_method.is_self_mutable &&
+                method.signature.return_type == trait_method.return_type &&
+                method.signature.parameters == trait_method.parameters;
+
+            if !is_matching {
+                return Err((
+                    format!(
+                        "Method '{}' doesn't match its declaration in trait '{}'",
+                        name,
+                        signature.name
+                    ),
+                    vec![*name_metadata],
+                ));
+            }
+
+            if ast_impl.methods.iter().filter(|(other, _, _)| other == name).count() > 1 {
+                return Err((format!("Method '{}' is already defined", name), vec![*name_metadata]));
+            }
+
+            // Another trait or the type itself has a method with the same name
+            let is_registered = self.ast_environment
+                .get_method(&ast_impl.self_type, name)
+                .is_ok_and(|registered| {
+                    registered.native.is_none() && registered.signature.id == method.signature.id
+                });
+            if !is_registered {
+                return Err((
+                    format!(
+                        "Type {} already has a method '{}'",
+                        ast_impl.self_type.to_type_string(),
+                        name
+                    ),
+                    vec![*name_metadata],
+                ));
+            }
+        }
+
+        let mut function_ids = Vec::new();
+        let mut missing_methods = Vec::new();
+
+        for trait_method in &signature.methods {
+            match ast_impl.methods.iter().find(|(name, _, _)| *name == trait_method.name) {
+                Some((_, _, method)) => function_ids.push(method.signature.id),
+                None => missing_methods.push(trait_method.name.clone()),
+            }
+        }
+
+        if !missing_methods.is_empty() {
+            return Err((
+                format!(
+                    "Implementation of trait '{}' for {} is missing method(s): {}",
+                    signature.name,
+                    ast_impl.self_type.to_type_string(),
+                    missing_methods.join(", ")
+                ),
+                vec![ast_impl.target_metadata],
+            ));
+        }
+
+        self.ast_environment.get_trait_mut(ast_impl.trait_id).impls.insert(target, function_ids);
+
+        Ok(())
+    }
+
+    // Only when the parser is directly inside the class body
+    pub fn get_method_class_type(&self) -> Option<ClassType> {
+        match self.classes.last() {
+            Some(class) if class.function_depth == self.functions.len() => {
+                Some(class.class_type.clone())
+            }
+            _ => None,
+        }
+    }
+
+    // Anywhere inside the class body. This is what `Self` refers to
+    pub fn get_current_class_type(&self) -> Option<ClassType> {
+        self.classes.last().map(|class| class.class_type.clone())
+    }
+
+    pub fn get_class_type(&self, name: &String) -> Option<ClassType> {
+        self.ast_environment.get_class_type(name)
+    }
+
+    // Usable right away, so a variant can hold a value of its own enum
+    pub fn start_enum(
+        &mut self,
+        name: String,
+        name_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let declared_id = self.ast_environment.take_declared_enum(&name);
+        let error = if ValueType::from_name(&name).is_some() {
+            Some(format!("Cannot redefine the builtin type '{}'", name))
+        } else if declared_id.is_none() && self.ast_environment.is_type_defined_in_scope(&name) {
+            Some(format!("Type '{}' is already defined in this scope", name))
+        } else {
+            None
+        };
+
+        // The body is still parsed when the name is taken, so its errors are reported too
+        let enum_type = self.ast_environment.insert_enum(name, declared_id);
+        self.current_enum = Some(enum_type);
+
+        match error {
+            Some(message) => Err((message, vec![name_metadata])),
+            None => Ok(()),
+        }
+    }
+
+    pub fn emit_enum_variant(
+        &mut self,
+        name: String,
+        name_metadata: TokenMetadata,
+        payload_types: Vec<ValueType>
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let enum_type = self.current_enum.clone().expect("No enum to add a variant to");
+        let enum_signature = self.ast_environment.get_enum_mut(&enum_type);
+
+        if enum_signature.get_variant(&name).is_ok() {
+            return Err((
+                format!("Enum '{}' already has a variant '{}'", enum_type.name, name),
+                vec![name_metadata],
+            ));
+        }
+
+        enum_signature.add_variant(EnumVariantSignature { name, payload_types });
+
+        Ok(())
+    }
+
+    pub fn start_declared_enum(&mut self, name: &String) -> bool {
+        self.current_enum = self.ast_environment.get_declared_enum(name);
+        self.current_enum.is_some()
+    }
+
+    pub fn end_enum(&mut self) {
+        self.current_enum = None;
+    }
+
+    // Variables shadow enums
+    fn get_enum_receiver(&self, receiver: &Expr) -> Option<(AstIdentifier, EnumType)> {
+        match receiver {
+            Expr::IdentifierLookup(identifier) if self.ast_environment.get(&identifier.lexeme).is_none() => {
+                self.ast_environment
+                    .get_enum_type(&identifier.lexeme)
+                    .map(|enum_type| (identifier.clone(), enum_type))
+            }
+            _ => None,
+        }
+    }
+
+    fn emit_enum_variant_expr(
+        &mut self,
+        enum_name: AstIdentifier,
+        enum_type: EnumType,
+        variant: AstIdentifier,
+        mut payload: Vec<Expr>
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let enum_signature = self.ast_environment.get_enum(&enum_type);
+
+        let (tag, payload_types) = match enum_signature.get_variant(&variant.lexeme) {
+            Ok((tag, variant_signature)) => (tag, variant_signature.payload_types.clone()),
+            Err(e) => {
+                return Err((e, vec![variant.token_metadata]));
+            }
+        };
+
+        Self::coerce_args(&mut payload, &payload_types);
+
+        self.exprs.push(
+            Expr::EnumVariantExpr(EnumVariantExpr {
+                enum_type,
+                layout: enum_signature.layout.clone(),
+                enum_name,
+                variant,
+                tag,
+                payload,
+                payload_types,
+            })
+        );
+
+        Ok(())
+    }
+
+    pub fn start_match(
+        &mut self,
+        match_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let value = match self.exprs.pop() {
+            Some(value) => value,
+            None => Expr::Literal(AstValue::new(Value::Empty, match_metadata)),
+        };
+
+        let mut token_vec = Vec::new();
+        let result = match value.type_check(&self.ast_environment, &mut token_vec) {
+            Ok(ValueType::Enum(enum_type)) => Ok(Some(enum_type)),
+            Ok(value_type) => {
+                value.push_to_token_vec(&mut token_vec);
+                Err((
+                    format!("Cannot match on a value of type {}", value_type.to_type_string()),
+                    token_vec,
+                ))
+            }
+            Err(e) => Err((e, token_vec)),
+        };
+
+        // The arms are still parsed after an error, so the parser gets past the match
+        self.matches.push(AstMatch {
+            value,
+            enum_type: result.clone().unwrap_or(None),
+            arms: Vec::new(),
+            matched_tags: Vec::new(),
+            arm_pattern: None,
+            has_wildcard: false,
+            value_type: None,
+            expr_count: self.exprs.len(),
+            token_metadata: match_metadata,
+        });
+
+        result.map(|_| ())
+    }
+
+    // The pattern is `_` when `variant` is None
+    pub fn start_match_arm(
+        &mut self,
+        enum_name: Option<AstIdentifier>,
+        variant: Option<AstIdentifier>,
+        bindings: Vec<AstIdentifier>,
+        pattern_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        self.ast_environment.start_scope();
+
+        let ast_match = self.matches.last_mut().expect("No match to add an arm to");
+        ast_match.arm_pattern = None;
+
+        let Some(enum_type) = ast_match.enum_type.clone() else {
+            return Ok(());
+        };
+
+        if ast_match.has_wildcard {
+            return Err((
+                "Match arm is unreachable, since '_' matches every variant".to_string(),
+                vec![pattern_metadata],
+            ));
+        }
+
+        let Some(variant) = variant else {
+            ast_match.has_wildcard = true;
+            ast_match.arm_pattern = Some((None, Vec::new()));
+            return Ok(());
+        };
+
+        if let Some(enum_name) = enum_name {
+            if self.ast_environment.get_enum_type(&enum_name.lexeme) != Some(enum_type.clone()) {
+                return Err((
+                    format!(
+                        "Expected a variant of enum '{}' but got '{}.{}'",
+                        enum_type.name,
+                        enum_name.lexeme,
+                        variant.lexeme
+                    ),
+                    vec![pattern_metadata],
+                ));
+            }
+        }
+
+        let enum_signature = self.ast_environment.get_enum(&enum_type);
+        let (tag, payload_types) = match enum_signature.get_variant(&variant.lexeme) {
+            Ok((tag, variant_signature)) => (tag, variant_signature.payload_types.clone()),
+            Err(e) => {
+                return Err((e, vec![variant.token_metadata]));
+            }
+        };
+
+        if ast_match.matched_tags.contains(&tag) {
+            return Err((
+                format!("Variant '{}' is already matched", variant.lexeme),
+                vec![pattern_metadata],
+            ));
+        }
+
+        ast_match.matched_tags.push(tag);
+
+        if bindings.len() != payload_types.len() {
+            return Err((
+                format!(
+                    "Variant '{}.{}' holds {} value(s) but the pattern binds {}",
+                    enum_type.name,
+                    variant.lexeme,
+                    payload_types.len(),
+                    bindings.len()
+                ),
+                vec![pattern_metadata],
+            ));
+        }
+
+        // `_` ignores a value of the payload
+        for (binding, payload_type) in bindings.iter().zip(payload_types) {
+            if binding.lexeme != "_" {
+                self.ast_environment.insert(binding.lexeme.clone(), payload_type, false, true);
+            }
+        }
+
+        ast_match.arm_pattern = Some((Some(tag), bindings));
+
+        Ok(())
+    }
+
+    pub fn end_match_arm(&mut self) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let ast_match = self.matches.last_mut().expect("No match arm to end");
+
+        let body = if self.exprs.len() > ast_match.expr_count { self.exprs.pop() } else { None };
+
+        let result = match (body, ast_match.arm_pattern.take()) {
+            (Some(mut body), Some((tag, bindings))) => {
+                if let Some(value_type) = &ast_match.value_type {
+                    body.coerce_literal(value_type);
+                }
+
+                let mut token_vec = Vec::new();
+                match body.type_check(&self.ast_environment, &mut token_vec) {
+                    Ok(body_type) => {
+                        match &ast_match.value_type {
+                            Some(value_type) if !body_type.is(value_type) => {
+                                body.push_to_token_vec(&mut token_vec);
+                                Err((
+                                    format!(
+                                        "Match arms must have the same type, but got {} and {}",
+                                        value_type.to_type_string(),
+                                        body_type.to_type_string()
+                                    ),
+                                    token_vec,
+                                ))
+                            }
+                            _ => {
+                                ast_match.value_type = Some(body_type);
+                                ast_match.arms.push(MatchArm { tag, bindings, body });
+                                Ok(())
+                            }
+                        }
+                    }
+                    Err(e) => Err((e, token_vec)),
+                }
+            }
+            _ => Ok(()),
+        };
+
+        self.ast_environment.end_scope();
+
+        result
+    }
+
+    pub fn end_match(&mut self) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let ast_match = self.matches.pop().expect("No match to end");
+
+        self.exprs.truncate(ast_match.expr_count);
+        self.exprs.push(
+            Expr::MatchExpr(MatchExpr {
+                value: Box::new(ast_match.value),
+                arms: ast_match.arms.clone(),
+                value_type: ast_match.value_type.unwrap_or(ValueType::Void),
+                token_metadata: ast_match.token_metadata,
+            })
+        );
+
+        let Some(enum_type) = ast_match.enum_type else {
+            return Ok(());
+        };
+        if ast_match.has_wildcard {
+            return Ok(());
+        }
+
+        let missing_variants = self.ast_environment
+            .get_enum(&enum_type)
+            .variants.iter()
+            .enumerate()
+            .filter(|(tag, _)| !ast_match.matched_tags.contains(tag))
+            .map(|(_, variant)| variant.name.clone())
+            .collect::<Vec<_>>();
+
+        if missing_variants.is_empty() {
+            Ok(())
+        } else {
+            Err((
+                format!(
+                    "Match on enum '{}' is not exhaustive, missing variant(s): {}",
+                    enum_type.name,
+                    missing_variants.join(", ")
+                ),
+                vec![ast_match.token_metadata],
+            ))
+        }
+    }
+
+    pub fn emit_field(
+        &mut self,
+        name: String,
+        name_metadata: TokenMetadata,
+        value_type: ValueType,
+        is_public: bool
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let class_type = self.get_method_class_type().expect("Fields are parsed in class bodies");
+
+        let mut default_value = match self.exprs.pop() {
+            Some(default_value) => default_value,
+            None => {
+                return Err((
+                    format!("Expected a default value for field '{}'", name),
+                    vec![name_metadata],
+                ));
+            }
+        };
+
+        let class = self.ast_environment.get_class(&class_type);
+        if !class.methods.is_empty() {
+            return Err((
+                format!("Fields of class '{}' must be declared before its methods", class_type.name),
+                vec![name_metadata],
+            ));
+        }
+        if class.fields.iter().any(|field| field.name == name) {
+            return Err((format!("Field '{}' is already defined", name), vec![name_metadata]));
+        }
+
+        default_value.coerce_literal(&value_type);
+
+        let mut token_vec = Vec::new();
+        match default_value.type_check(&self.ast_environment, &mut token_vec) {
+            Ok(default_type) if default_type.is(&value_type) => {}
+            Ok(default_type) => {
+                default_value.push_to_token_vec(&mut token_vec);
+                return Err((
+                    format!(
+                        "Field '{}' is of type {} but the default value is of type {}",
+                        name,
+                        value_type.to_type_string(),
+                        default_type.to_type_string()
+                    ),
+                    token_vec,
+                ));
+            }
+            Err(e) => {
+                return Err((e, token_vec));
+            }
+        }
+
+        self.ast_environment.get_class_mut(&class_type).add_field(ClassField {
+            name,
+            value_type,
+            is_public,
+            default_value,
+        });
+
+        Ok(())
+    }
+
+    pub fn end_function(&mut self) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let function = self.functions.pop().expect("No function to end");
+
+        let result = if function.return_type.is(&ValueType::Void) || self.panic_mode {
+            Ok(())
+        } else {
+            self.check_function_returns(&function)
+        };
+
+        self.loop_depth = function.outer_loop_depth;
+        self.ast_environment.end_function();
+        self.ast.as_mut().unwrap().end_function();
+
+        result
+    }
+
+    pub fn end_closure(&mut self) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let function = self.functions.pop().expect("No closure to end");
+
+        let result = if function.return_type.is(&ValueType::Void) || self.panic_mode {
+            Ok(())
+        } else {
+            self.check_function_returns(&function)
+        };
+
+        self.loop_depth = function.outer_loop_depth;
+        let captures = self.ast_environment.end_function();
+
+        let ast = self.ast.as_mut().unwrap();
+        ast.end_function();
+
+        let function_stmt = match ast.get_current_scope_mut().forwards_declarations.last_mut() {
+            Some(Stmt::FunctionStmt(function_stmt)) => function_stmt,
+            _ => unreachable!("The closure is the last function declared in the scope"),
+        };
+        function_stmt.captures = captures.clone();
+
+        let function_type = FunctionType {
+            parameters: function_stmt.args
+                .iter()
+                .map(|arg| arg.value_type.clone())
+                .collect(),
+            return_type: Box::new(function.return_type),
+        };
+
+        self.exprs.push(
+            Expr::ClosureExpr(ClosureExpr {
+                function: function_stmt.id,
+                captures,
+                function_type,
+                token_metadata: function.name_metadata,
+            })
+        );
+
+        result
+    }
+
+    // If the body ends with an expression, that expression is returned
+    fn check_function_returns(
+        &mut self,
+        function: &AstFunction
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let body = self.ast.as_mut().unwrap().get_current_scope_mut();
+
+        if body.always_returns() {
+            return Ok(());
+        }
+
+        let mut last_expr = match body.cf_stmts.last() {
+            Some(Stmt::ExprStmt(_)) =>
+                match body.cf_stmts.pop() {
+                    Some(Stmt::ExprStmt(expr)) => expr,
+                    _ => unreachable!(),
+                }
+            _ => {
+                let message = match function.name.is_empty() {
+                    true =>
+                        format!(
+                            "Closure must return a value of type {}",
+                            function.return_type.to_type_string()
+                        ),
+                    false =>
+                        format!(
+                            "Function '{}' must return a value of type {}",
+                            function.name,
+                            function.return_type.to_type_string()
+                        ),
+                };
+
+                return Err((message, vec![function.name_metadata]));
+            }
+        };
+
+        last_expr.coerce_literal(&function.return_type);
+
+        let mut token_vec = Vec::new();
+        let result = match last_expr.type_check(&self.ast_environment, &mut token_vec) {
+            Ok(value_type) if value_type.is(&function.return_type) => Ok(()),
+            Ok(value_type) => {
+                last_expr.push_to_token_vec(&mut token_vec);
+                Err(
+                    format!(
+                        "Expected return value of type {} but got {}",
+                        function.return_type.to_type_string(),
+                        value_type.to_type_string()
+                    )
+                )
+            }
+            Err(e) => Err(e),
+        };
+
+        self.ast
+            .as_mut()
+            .unwrap()
+            .push_stmt(Stmt::ReturnStmt(ReturnStmt::new(Some(last_expr))));
+
+        result.map_err(|e| (e, token_vec))
+    }
+
+    pub fn emit_return(
+        &mut self,
+        return_metadata: TokenMetadata,
+        has_value: bool
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let mut value = if has_value { self.exprs.pop() } else { None };
+
+        let function = match self.functions.last() {
+            Some(function) => function,
+            None => {
+                return Err((
+                    "Cannot use 'return' outside of a function".to_string(),
+                    vec![return_metadata],
+                ));
+            }
+        };
+
+        if let Some(value) = &mut value {
+            value.coerce_literal(&function.return_type);
+        }
+
+        let mut token_vec = Vec::new();
+        let result = match &value {
+            _ if self.panic_mode => Ok(()),
+            Some(value) => {
+                match value.type_check(&self.ast_environment, &mut token_vec) {
+                    Ok(_) if function.return_type.is(&ValueType::Void) => {
+                        value.push_to_token_vec(&mut token_vec);
+                        Err(
+                            format!(
+                                "Function '{}' has no return type, so it cannot return a value",
+                                function.name
+                            )
+                        )
+                    }
+                    Ok(value_type) if !value_type.is(&function.return_type) => {
+                        value.push_to_token_vec(&mut token_vec);
+                        Err(
+                            format!(
+                                "Expected return value of type {} but got {}",
+                                function.return_type.to_type_string(),
+                                value_type.to_type_string()
+                            )
+                        )
+                    }
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(e),
+                }
+            }
+            None if !function.return_type.is(&ValueType::Void) => {
+                token_vec.push(return_metadata);
+                Err(
+                    format!(
+                        "Expected return value of type {}",
+                        function.return_type.to_type_string()
+                    )
+                )
+            }
+            None => Ok(()),
+        };
+
+        // Pushed even on errors, so a missing return isn't reported as well
+        self.ast.as_mut().unwrap().push_stmt(Stmt::ReturnStmt(ReturnStmt::new(value)));
+
+        result.map_err(|e| (e, token_vec))
+    }
+
+    pub fn emit_print(
+        &mut self,
+        print_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        match self.exprs.pop() {
+            Some(value) => self.push_stmt(Stmt::PrintStmt(value)),
+            None => Err(("Expected value to print".to_string(), vec![print_metadata])),
+        }
+    }
+
+    pub fn emit_call(
+        &mut self,
+        arg_count: usize,
+        paren_metadata: TokenMetadata,
+        type_args: Option<Vec<ValueType>>
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let split_index = self.exprs.len().saturating_sub(arg_count);
+        let mut args = self.exprs.split_off(split_index);
+
+        let callee = match self.exprs.pop() {
+            Some(Expr::IdentifierLookup(callee)) if self.ast_environment.get(&callee.lexeme).is_none() => {
+                callee
+            }
+            Some(expr) => {
+                return self.emit_value_call(expr, args, paren_metadata, type_args);
+            }
+            None => {
+                return Err(("Expected function before '('".to_string(), vec![paren_metadata]));
+            }
+        };
+
+        let mut signature = self.ast_environment.get_function(&callee.lexeme);
+        let mut trait_impls = Vec::new();
+
+        if let (None, Some(class_type)) = (&signature, self.get_class_type(&callee.lexeme)) {
+            let class_type = self.specialize_class_type(&callee, class_type, type_args)?;
+            return self.emit_instance(callee, class_type, args);
+        }
+
+        if let Some(function_signature) = &signature {
+            let function_signature = match type_args {
+                _ if function_signature.type_params.is_empty() && type_args.is_some() => {
+                    return Err((
+                        format!("Function '{}' doesn't take type arguments", callee.lexeme),
+                        vec![callee.token_metadata],
+                    ));
+                }
+                _ if function_signature.type_params.is_empty() => function_signature.clone(),
+                type_args => {
+                    let type_args = match type_args {
+                        Some(type_args) => type_args,
+                        None => self.infer_type_args(&callee, function_signature, &args)?,
+                    };
+
+                    let specialized = self.specialize_function_signature(
+                        &callee,
+                        function_signature,
+                        type_args.clone()
+                    )?;
+                    trait_impls = self.get_trait_impls(
+                        GenericId::Function(function_signature.id),
+                        &type_args,
+                        callee.token_metadata
+                    )?;
+                    specialized
+                }
+            };
+
+            Self::coerce_args(&mut args, &function_signature.parameters);
+            signature = Some(function_signature);
+        } else if type_args.is_some() {
+            return Err((
+                format!("'{}' doesn't take type arguments", callee.lexeme),
+                vec![callee.token_metadata],
+            ));
+        }
+
+        match (signature, value_v2::get_builtin(&callee.lexeme)) {
+            (None, Some(builtin)) => {
+                self.coerce_builtin_args(&builtin, &mut args);
+                self.exprs.push(
+                    Expr::BuiltinCallExpr(BuiltinCallExpr {
+                        builtin,
+                        args,
+                        token_metadata: callee.token_metadata,
+                    })
+                );
+            }
+            (signature, _) => {
+                self.exprs.push(Expr::CallExpr(CallExpr::new(callee, args, signature, trait_impls)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn emit_value_call(
+        &mut self,
+        callee: Expr,
+        mut args: Vec<Expr>,
+        paren_metadata: TokenMetadata,
+        type_args: Option<Vec<ValueType>>
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let mut token_vec = Vec::new();
+
+        let function_type = match callee.type_check(&self.ast_environment, &mut token_vec) {
+            Ok(ValueType::Function(function_type)) => function_type,
+            Ok(_) => {
+                let mut token_vec = vec![paren_metadata];
+                callee.push_to_token_vec(&mut token_vec);
+                self.exprs.push(callee);
+                return Err(("Only functions can be called".to_string(), token_vec));
+            }
+            Err(e) => {
+                self.exprs.push(callee);
+                return Err((e, token_vec));
+            }
+        };
+
+        if type_args.is_some() {
+            return Err((
+                "Function values don't take type arguments".to_string(),
+                vec![paren_metadata],
+            ));
+        }
+
+        Self::coerce_args(&mut args, &function_type.parameters);
+
+        self.exprs.push(
+            Expr::CallValueExpr(CallValueExpr {
+                callee: Box::new(callee),
+                args,
+                function_type,
+                paren_metadata,
+            })
+        );
+
+        Ok(())
+    }
+
+    // Number literals only decide type parameters other arguments don't, floats before integers
+    fn infer_type_args(
+        &self,
+        callee: &AstIdentifier,
+        signature: &FunctionSignature,
+        args: &[Expr]
+    ) -> Result<Vec<ValueType>, (String, Vec<TokenMetadata>)> {
+        let mut inferred = vec![None; signature.type_params.len()];
+
+        let (mut literal_args, other_args): (Vec<_>, Vec<_>) = args
+            .iter()
+            .zip(&signature.parameters)
+            .filter_map(|(arg, parameter)| {
+                let arg_type = arg.type_check(&self.ast_environment, &mut Vec::new()).ok()?;
+                Some((parameter, arg_type, arg.is_number_literal()))
+            })
+            .partition(|(_, _, is_number_literal)| *is_number_literal);
+        literal_args.sort_by_key(|(_, arg_type, _)| !arg_type.is_float());
+
+        for (parameter, arg_type, _) in other_args.into_iter().chain(literal_args) {
+            parameter.infer_type_args(&arg_type, &signature.type_params, &mut inferred);
+        }
+
+        inferred
+            .into_iter()
+            .zip(&signature.type_params)
+            .map(|(type_arg, type_param)| {
+                type_arg.ok_or_else(|| {
+                    (
+                        format!(
+                            "Cannot infer type parameter '{}' of '{}', so it needs a type argument like '{}<...>(...)'",
+                            type_param,
+                            callee.lexeme,
+                            callee.lexeme
+                        ),
+                        vec![callee.token_metadata],
+                    )
+                })
+            })
+            .collect()
+    }
+
+    fn specialize_function_signature(
+        &mut self,
+        callee: &AstIdentifier,
+        signature: &FunctionSignature,
+        type_args: Vec<ValueType>
+    ) -> Result<FunctionSignature, (String, Vec<TokenMetadata>)> {
+        if type_args.len() != signature.type_params.len() {
+            return Err((
+                format!(
+                    "Function '{}' expects {} type argument(s) but got {}",
+                    callee.lexeme,
+                    signature.type_params.len(),
+                    type_args.len()
+                ),
+                vec![callee.token_metadata],
+            ));
+        }
+
+        let passed_on = self.ast_environment.check_type_args(
+            GenericId::Function(signature.id),
+            &callee.lexeme,
+            &signature.type_params,
+            &type_args,
+            callee.token_metadata
+        )?;
+        for requirement in passed_on {
+            self.ast_environment.add_requirement(requirement);
+        }
+
+        Ok(signature.substitute(&signature.type_params, &type_args))
+    }
+
+    // Without type arguments, they're taken from the annotation of the variable
+    fn specialize_class_type(
+        &mut self,
+        callee: &AstIdentifier,
+        class_type: ClassType,
+        type_args: Option<Vec<ValueType>>
+    ) -> Result<ClassType, (String, Vec<TokenMetadata>)> {
+        let type_params = self.ast_environment.get_class(&class_type).type_params.clone();
+
+        let type_args = match type_args {
+            Some(type_args) if type_args.len() != type_params.len() => {
+                return Err((
+                    format!(
+                        "Class '{}' expects {} type argument(s) but got {}",
+                        class_type.name,
+                        type_params.len(),
+                        type_args.len()
+                    ),
+                    vec![callee.token_metadata],
+                ));
+            }
+            Some(type_args) => {
+                let passed_on = self.ast_environment.check_type_args(
+                    GenericId::Class(class_type.id),
+                    &class_type.name,
+                    &type_params,
+                    &type_args,
+                    callee.token_metadata
+                )?;
+                for requirement in passed_on {
+                    self.ast_environment.add_requirement(requirement);
+                }
+
+                type_args
+            }
+            None => vec![ValueType::Empty; type_params.len()],
+        };
+
+        Ok(ClassType { type_args, ..class_type })
+    }
+
+    pub fn emit_array(&mut self, element_count: usize, bracket_metadata: TokenMetadata) {
+        let split_index = self.exprs.len().saturating_sub(element_count);
+        let mut elements = self.exprs.split_off(split_index);
+
+        // In `[x, 1]` the literal gets the type of `x`
+        let element_type = elements
+            .iter()
+            .find(|element| !element.is_number_literal())
+            .and_then(|element| element.type_check(&self.ast_environment, &mut Vec::new()).ok());
+        if let Some(element_type) = element_type {
+            for element in elements.iter_mut() {
+                element.coerce_literal(&element_type);
+            }
+        }
+
+        self.exprs.push(
+            Expr::ArrayExpr(ArrayExpr {
+                elements,
+                token_metadata: bracket_metadata,
+            })
+        );
+    }
+
+    pub fn emit_map(&mut self, entry_count: usize, bracket_metadata: TokenMetadata) {
+        let split_index = self.exprs.len().saturating_sub(entry_count * 2);
+        let mut entries = Vec::with_capacity(entry_count);
+
+        let mut exprs = self.exprs.split_off(split_index).into_iter();
+        while let (Some(key), Some(value)) = (exprs.next(), exprs.next()) {
+            entries.push((key, value));
+        }
+
+        // Like in arrays, number literals get the type of the other keys or values
+        let key_type = self.get_non_literal_type(entries.iter().map(|(key, _)| key));
+        let value_type = self.get_non_literal_type(entries.iter().map(|(_, value)| value));
+        for (key, value) in entries.iter_mut() {
+            if let Some(key_type) = &key_type {
+                key.coerce_literal(key_type);
+            }
+            if let Some(value_type) = &value_type {
+                value.coerce_literal(value_type);
+            }
+        }
+
+        self.exprs.push(
+            Expr::MapExpr(MapExpr {
+                entries,
+                token_metadata: bracket_metadata,
+            })
+        );
+    }
+
+    pub fn emit_interpolation(&mut self, part_count: usize, string_metadata: TokenMetadata) {
+        let split_index = self.exprs.len().saturating_sub(part_count);
+        let parts = self.exprs.split_off(split_index);
+
+        self.exprs.push(
+            Expr::InterpolationExpr(InterpolationExpr {
+                parts,
+                token_metadata: string_metadata,
+            })
+        );
+    }
+
+    fn get_non_literal_type<'a>(&self, mut exprs: impl Iterator<Item = &'a Expr>) -> Option<ValueType> {
+        exprs
+            .find(|expr| !expr.is_number_literal())
+            .and_then(|expr| expr.type_check(&self.ast_environment, &mut Vec::new()).ok())
+    }
+
+    pub fn emit_tuple(&mut self, element_count: usize, paren_metadata: TokenMetadata) {
+        let split_index = self.exprs.len().saturating_sub(element_count);
+        let elements = self.exprs.split_off(split_index);
+
+        self.exprs.push(
+            Expr::TupleExpr(TupleExpr {
+                elements,
+                token_metadata: paren_metadata,
+            })
+        );
+    }
+
+    pub fn emit_index(
+        &mut self,
+        bracket_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let (target, mut index) = match (self.exprs.pop(), self.exprs.pop()) {
+            (Some(index), Some(target)) => (target, index),
+            _ => {
+                return Err(("Expected index after '['".to_string(), vec![bracket_metadata]));
+            }
+        };
+
+        // In `map[1]` the literal gets the key type of the map
+        if let Ok(ValueType::Map(key_type, _)) = target.type_check(&self.ast_environment, &mut Vec::new()) {
+            index.coerce_literal(&key_type);
+        }
+
+        self.exprs.push(
+            Expr::IndexExpr(IndexExpr {
+                target: Box::new(target),
+                index: Box::new(index),
+                token_metadata: bracket_metadata,
+            })
+        );
+
+        Ok(())
+    }
+
+    pub fn emit_index_assignment(
+        &mut self,
+        bracket_metadata: TokenMetadata,
+        is_compound: bool
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        if self.panic_mode {
+            return Ok(());
+        }
+
+        let (target, mut value) = match (self.exprs.pop(), self.exprs.pop()) {
+            (Some(value), Some(Expr::IndexExpr(target))) => (target, value),
+            _ => {
+                return Err(("Expected index in assignment".to_string(), vec![bracket_metadata]));
+            }
+        };
+
+        if let Ok(element_type) = target.type_check(&self.ast_environment, &mut Vec::new()) {
+            value.coerce_literal(&element_type);
+        }
+
+        self.push_stmt(Stmt::IndexAssignment(IndexAssignmentStmt::new(target, value, is_compound)))
+    }
+
+    fn emit_instance(
+        &mut self,
+        callee: AstIdentifier,
+        class_type: ClassType,
+        args: Vec<Expr>
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        if !args.is_empty() {
+            let mut token_vec = Vec::new();
+            for arg in args.iter().rev() {
+                arg.push_to_token_vec(&mut token_vec);
+            }
+            callee.push_to_token_vec(&mut token_vec);
+
+            return Err((
+                format!("Class '{}' is constructed without arguments", class_type.name),
+                token_vec,
+            ));
+        }
+
+        // The fields aren't all known until the methods are reached
+        if self.get_method_class_type().map_or(false, |current| current.id == class_type.id) {
+            return Err((
+                format!(
+                    "Class '{}' cannot be constructed in the default value of its own fields",
+                    class_type.name
+                ),
+                vec![callee.token_metadata],
+            ));
+        }
+
+        if self.ast_environment.is_class_declared_only(&class_type) {
+            return Err((AstEnvironment::class_used_before_definition(&class_type), vec![callee.token_metadata]));
+        }
+
+        let class = self.ast_environment.get_class(&class_type);
+
+        self.exprs.push(
+            Expr::InstanceExpr(InstanceExpr {
+                class_type: class_type.clone(),
+                layout: class.layout.clone(),
+                fields: class.fields
+                    .iter()
+                    .map(|field| field.default_value.clone())
+                    .collect(),
+                token_metadata: callee.token_metadata,
+            })
+        );
+
+        Ok(())
+    }
+
+    pub fn emit_cast(
+        &mut self,
+        value_type: ValueType,
+        as_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let value = match self.exprs.pop() {
+            Some(value) => value,
+            None => {
+                return Err(("Expected value before 'as'".to_string(), vec![as_metadata]));
+            }
+        };
+
+        self.exprs.push(
+            Expr::CastExpr(CastExpr {
+                value: Box::new(value),
+                value_type,
+                token_metadata: as_metadata,
+            })
+        );
+
+        Ok(())
+    }
+
+    pub fn emit_member_access(
+        &mut self,
+        member: AstIdentifier
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let target = match self.exprs.pop() {
+            Some(target) => target,
+            None => {
+                return Err(("Expected value before '.'".to_string(), vec![member.token_metadata]));
+            }
+        };
+
+        if let Some((enum_name, enum_type)) = self.get_enum_receiver(&target) {
+            return self.emit_enum_variant_expr(enum_name, enum_type, member, Vec::new());
+        }
+
+        let mut token_vec = Vec::new();
+        let field_index = match self.resolve_field(&target, &member, &mut token_vec) {
+            Ok(field_index) => field_index,
+            Err(e) => {
+                self.exprs.push(target);
+                return Err((e, token_vec));
+            }
+        };
+
+        // Both fields and tuple elements are read with `GetField`
+        self.exprs.push(
+            Expr::MemberAccessExpr(MemberAccessExpr {
+                target: Box::new(target),
+                member,
+                field_index,
+            })
+        );
+
+        Ok(())
+    }
+
+    pub fn emit_method_call(
+        &mut self,
+        method: AstIdentifier,
+        arg_count: usize
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let split_index = self.exprs.len().saturating_sub(arg_count);
+        let mut args = self.exprs.split_off(split_index);
+
+        let receiver = match self.exprs.pop() {
+            Some(receiver) => receiver,
+            None => {
+                return Err(("Expected value before '.'".to_string(), vec![method.token_metadata]));
+            }
+        };
+
+        // `EnumName.Variant(payload)` creates an enum value
+        if let Some((enum_name, enum_type)) = self.get_enum_receiver(&receiver) {
+            return self.emit_enum_variant_expr(enum_name, enum_type, method, args);
+        }
+
+        let mut token_vec = Vec::new();
+        let result = receiver
+            .type_check(&self.ast_environment, &mut token_vec)
+            .and_then(|receiver_type| {
+                let method = self.ast_environment.get_method(&receiver_type, &method.lexeme)?;
+                Ok((receiver_type, method))
+            });
+
+        let (receiver_type, mut class_method) = match result {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                if token_vec.is_empty() {
+                    method.push_to_token_vec(&mut token_vec);
+                }
+                self.exprs.push(receiver);
+                return Err((e, token_vec));
+            }
+        };
+
+        let mut bound_impl = None;
+        let mut trait_impls = Vec::new();
+
+        match &receiver_type {
+            // A method of `Box<T>` called on a `Box<i32>` takes and returns `i32` in place of `T`
+            ValueType::Class(class_type) => {
+                let class = self.ast_environment.get_class(class_type);
+                class_method.signature = class_method.signature.substitute(&class.type_params, &class_type.type_args);
+
+                let generic_id = GenericId::Class(class_type.id);
+                trait_impls = self.get_trait_impls(generic_id, &class_type.type_args, method.token_metadata)?;
+            }
+            ValueType::TypeParam(type_param) => {
+                if let Some(trait_method_id) = class_method.trait_method_id {
+                    bound_impl = Some(Box::new(self.get_bound_impl(type_param, trait_method_id, method.token_metadata)?));
+                }
+            }
+            _ => {}
+        }
+
+        Self::coerce_args(&mut args, &class_method.signature.parameters);
+
+        self.exprs.push(
+            Expr::MethodCallExpr(MethodCallExpr {
+                receiver: Box::new(receiver),
+                method,
+                args,
+                class_method: Box::new(class_method),
+                bound_impl,
+                trait_impls,
+            })
+        );
+
+        Ok(())
+    }
+
+    pub fn emit_field_assignment(
+        &mut self,
+        field: AstIdentifier,
+        is_compound: bool
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        if self.panic_mode {
+            return Ok(());
+        }
+
+        let (target, mut value) = match (self.exprs.pop(), self.exprs.pop()) {
+            (Some(value), Some(target)) => (target, value),
+            _ => {
+                return Err(("Expected field in assignment".to_string(), vec![field.token_metadata]));
+            }
+        };
+
+        if let Ok(ValueType::Tuple(_)) = target.type_check(&self.ast_environment, &mut Vec::new()) {
+            return Err((
+                format!("Cannot assign to element {} of a tuple, since tuples are immutable", field.lexeme),
+                vec![field.token_metadata],
+            ));
+        }
+
+        let mut token_vec = Vec::new();
+        let field_index = match self.resolve_field(&target, &field, &mut token_vec) {
+            Ok(field_index) => field_index,
+            Err(e) => {
+                return Err((e, token_vec));
+            }
+        };
+
+        let field_type = MemberAccessExpr {
+            target: Box::new(target.clone()),
+            member: field.clone(),
+            field_index,
+        }.type_check(&self.ast_environment, &mut Vec::new());
+        if let Ok(field_type) = field_type {
+            value.coerce_literal(&field_type);
+        }
+
+        self.push_stmt(
+            Stmt::VariableAssignment(
+                VariableAssignmentStmt::new_field_assignment(
+                    target,
+                    field,
+                    field_index,
+                    value,
+                    is_compound
+                )
+            )
+        )
+    }
+
+    fn resolve_field(
+        &self,
+        target: &Expr,
+        member: &AstIdentifier,
+        token_vec: &mut Vec<TokenMetadata>
+    ) -> Result<usize, String> {
+        if let Ok(ValueType::Tuple(element_types)) = target.type_check(&self.ast_environment, &mut Vec::new()) {
+            return match member.lexeme.parse::<usize>() {
+                Ok(element_index) if element_index < element_types.len() => Ok(element_index),
+                _ => {
+                    member.push_to_token_vec(token_vec);
+                    Err(
+                        format!(
+                            "A tuple of type {} has no element '{}'",
+                            ValueType::Tuple(element_types).to_type_string(),
+                            member.lexeme
+                        )
+                    )
+                }
+            };
+        }
+
+        let class_type = target.type_check_class(member, &self.ast_environment, token_vec)?;
+
+        match self.ast_environment.get_field(&class_type, &member.lexeme) {
+            Ok((field_index, _)) => Ok(field_index),
+            Err(e) => {
+                member.push_to_token_vec(token_vec);
+                Err(e)
+            }
+        }
+    }
+
+    pub fn start_scope(&mut self) {
+        self.ast_environment.start_scope();
+        self.ast.as_mut().unwrap().start_scope();
+    }
+
+    pub fn start_module(&mut self) {
+        self.ast_environment.start_module();
+        self.ast.as_mut().unwrap().start_scope();
+    }
+
+    pub fn end_module(&mut self, path: PathBuf) -> Vec<(String, Option<PathBuf>)> {
+        self.ast.as_mut().unwrap().end_scope();
+        let exports = self.ast_environment.end_module();
+
+        let conflicts = self.ast_environment.import(&exports, &path);
+        self.modules.insert(path, exports);
+
+        conflicts
+    }
+
+    // The top level statements of a module only run the first time it's imported
+    pub fn import_parsed_module(&mut self, path: &Path) -> Option<Vec<(String, Option<PathBuf>)>> {
+        let exports = self.modules.get(path)?;
+
+        Some(self.ast_environment.import(exports, path))
+    }
+
+    pub fn is_module_top_level(&self) -> bool {
+        self.ast_environment.is_module_top_level()
+    }
+
+    pub fn set_public(&mut self, name: String) {
+        self.ast_environment.set_public(name);
+    }
+
+    pub fn end_scope(&mut self) {
+        self.ast_environment.end_scope();
+        self.ast.as_mut().unwrap().end_scope()
+    }
+
+    // A condition is always returned, so the statement can start even on errors
+    fn pop_condition(
+        &mut self,
+        keyword_metadata: TokenMetadata,
+        keyword: &str
+    ) -> (Expr, Result<(), (String, Vec<TokenMetadata>)>) {
+        let mut token_vec = Vec::new();
+
+        let result = match self.exprs.last() {
+            Some(condition) if !self.panic_mode => {
+                match condition.type_check(&self.ast_environment, &mut token_vec) {
+                    Ok(value_type) if value_type.is(&ValueType::Bool) => Ok(()),
+                    Ok(value_type) => {
+                        condition.push_to_token_vec(&mut token_vec);
+                        Err(
+                            format!(
+                                "Expected condition of type bool but got {}",
+                                value_type.to_type_string()
+                            )
+                        )
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            Some(_) => Ok(()),
+            None => {
+                token_vec.push(keyword_metadata);
+                Err(format!("Expected condition after '{}'", keyword))
+            }
+        };
+
+        let condition = match self.exprs.pop() {
+            Some(condition) => condition,
+            None => Expr::Literal(AstValue::new(Value::Bool(true), keyword_metadata)),
+        };
+
+        (condition, result.map_err(|e| (e, token_vec)))
+    }
+
+    pub fn start_if(
+        &mut self,
+        if_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let (condition, result) = self.pop_condition(if_metadata, "if");
+
+        // The if statement is still started on errors, so the scopes stay balanced
+        self.ast_environment.start_branch(false);
+        self.ast_environment.start_scope();
+        self.ast.as_mut().unwrap().start_if(condition);
+        self.if_branches.push(None);
+
+        result
     }
-}
 
-#[derive(Debug)]
-pub struct AstScope {
-    definitions: HashMap<String, AstVariableValue>,
-}
+    pub fn start_else(&mut self) {
+        let true_branch = self.end_branch();
+        *self.if_branches.last_mut().expect("No if statement to else") = Some(true_branch);
 
-impl AstScope {
-    pub fn new() -> Self {
-        Self {
-            definitions: HashMap::new(),
-        }
+        self.ast_environment.start_branch(false);
+        self.ast_environment.start_scope();
+        self.ast.as_mut().unwrap().start_else();
     }
 
-    pub fn insert(
-        &mut self,
-        lexeme: String,
-        value_type: ValueType,
-        is_mutable: bool,
-        is_initialized: bool
-    ) {
-        self.definitions.insert(lexeme, AstVariableValue {
-            value_type,
-            is_mutable,
-            is_initialized,
-        });
-    }
+    pub fn end_if(&mut self) {
+        let branch = self.end_branch();
 
-    pub fn get(&self, lexeme: &String) -> Option<(ValueType, bool, bool)> {
-        match self.definitions.get(lexeme) {
-            Some(v) => Some(v.to_tuple()),
-            None => None,
+        match self.if_branches.pop().flatten() {
+            Some(true_branch) => self.ast_environment.merge_branches(vec![true_branch, branch], true),
+            None => self.ast_environment.merge_branches(vec![branch], false),
         }
+
+        self.ast.as_mut().unwrap().end_if();
     }
-}
 
-pub struct AstEnvironment {
-    scopes: Vec<AstScope>,
-    scope_depth: usize,
-}
+    fn end_branch(&mut self) -> EndedBranch {
+        let diverges = self.ast.as_mut().unwrap().get_current_scope_mut().diverges();
 
-impl AstEnvironment {
-    pub fn new() -> Self {
-        Self {
-            scopes: vec![AstScope::new()],
-            scope_depth: 0,
-        }
+        self.ast_environment.end_scope();
+        self.ast_environment.end_branch(diverges)
     }
 
-    pub fn get_scope_depth(&self) -> usize {
-        self.scope_depth
+    pub fn start_while(
+        &mut self,
+        while_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let (condition, result) = self.pop_condition(while_metadata, "while");
+
+        self.loop_depth += 1;
+        self.ast_environment.start_branch(true);
+        self.ast_environment.start_scope();
+        self.ast.as_mut().unwrap().start_while(condition);
+
+        result
     }
 
-    pub fn start_scope(&mut self) {
-        self.scope_depth += 1;
-        self.scopes.push(AstScope::new());
+    pub fn end_while(&mut self) {
+        self.loop_depth -= 1;
+
+        // The body might not run, so it doesn't initialize anything for sure
+        let mut body = self.end_branch();
+        body.diverges = false;
+        self.ast_environment.merge_branches(vec![body], false);
+
+        self.ast.as_mut().unwrap().end_scope();
     }
 
-    pub fn end_scope(&mut self) {
-        if self.scope_depth > 0 {
-            self.scope_depth -= 1;
+    pub fn start_for(
+        &mut self,
+        variable: String,
+        for_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let mut token_vec = Vec::new();
+
+        let range_end = self.exprs.pop();
+        let range_start = self.exprs.pop();
+
+        let mut result = Ok(());
+        if !self.panic_mode {
+            for bound in [&range_start, &range_end] {
+                let value_type = match bound {
+                    Some(bound) => bound.type_check(&self.ast_environment, &mut token_vec),
+                    None => {
+                        token_vec.push(for_metadata);
+                        Err("Expected range after 'in'".to_string())
+                    }
+                };
+
+                match value_type {
+                    Ok(value_type) if value_type.is(&ValueType::Int32) => {}
+                    Ok(value_type) => {
+                        bound.as_ref().unwrap().push_to_token_vec(&mut token_vec);
+                        result = Err(
+                            format!(
+                                "Expected range bound of type i32 but got {}",
+                                value_type.to_type_string()
+                            )
+                        );
+                        break;
+                    }
+                    Err(e) => {
+                        result = Err(e);
+                        break;
+                    }
+                }
+            }
         }
-        self.scopes.pop();
+
+        let default_bound = || Expr::Literal(AstValue::new(Value::Int32(0), for_metadata));
+        let iterable = ForIterable::Range(
+            Box::new(range_start.unwrap_or_else(default_bound)),
+            Box::new(range_end.unwrap_or_else(default_bound))
+        );
+
+        self.enter_for(ForStmt::new(variable.clone(), None, iterable), vec![(variable, ValueType::Int32)]);
+
+        result.map_err(|e| (e, token_vec))
     }
 
-    pub fn insert(
+    pub fn start_for_map(
         &mut self,
-        lexeme: String,
-        value_type: ValueType,
-        is_mutable: bool,
-        is_initialized: bool
-    ) {
-        self.scopes[self.scope_depth].insert(lexeme, value_type, is_mutable, is_initialized);
-    }
+        variable: String,
+        value_variable: Option<String>,
+        for_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let mut token_vec = Vec::new();
 
-    pub fn get(&self, lexeme: &String) -> Option<(ValueType, bool, bool)> {
-        for i in (0..self.scope_depth + 1).rev() {
-            match self.scopes[i].get(lexeme) {
-                Some(v) => {
-                    return Some(v);
+        let map = self.exprs.pop();
+
+        let mut result = Ok(());
+        let mut entry_types = (ValueType::Unkown, ValueType::Unkown);
+        if !self.panic_mode {
+            match map.as_ref().map(|map| map.type_check(&self.ast_environment, &mut token_vec)) {
+                Some(Ok(ValueType::Map(key_type, value_type))) => {
+                    entry_types = (*key_type, *value_type);
+                }
+                Some(Ok(value_type)) => {
+                    map.as_ref().unwrap().push_to_token_vec(&mut token_vec);
+                    result = Err(
+                        format!(
+                            "Expected a range or a map after 'in' but got {}",
+                            value_type.to_type_string()
+                        )
+                    );
+                }
+                Some(Err(e)) => {
+                    result = Err(e);
                 }
                 None => {
-                    continue;
+                    token_vec.push(for_metadata);
+                    result = Err("Expected a range or a map after 'in'".to_string());
                 }
             }
         }
 
-        None
-    }
-}
+        let map = map.unwrap_or_else(|| {
+            Expr::MapExpr(MapExpr { entries: Vec::new(), token_metadata: for_metadata })
+        });
 
-pub struct AstGenerator {
-    ast: Option<Ast>,
-    stmts: Vec<Stmt>,
-    exprs: Vec<Expr>,
-    panic_mode: bool,
-    ast_environment: AstEnvironment,
-}
+        let mut variables = vec![(variable.clone(), entry_types.0)];
+        if let Some(value_variable) = &value_variable {
+            variables.push((value_variable.clone(), entry_types.1));
+        }
 
-impl AstGenerator {
-    pub fn new() -> Self {
-        Self {
-            ast: Some(Ast::new()),
-            stmts: Vec::new(),
-            exprs: Vec::new(),
-            panic_mode: false,
-            ast_environment: AstEnvironment::new(),
+        self.enter_for(ForStmt::new(variable, value_variable, ForIterable::Map(Box::new(map))), variables);
+
+        result.map_err(|e| (e, token_vec))
+    }
+
+    fn enter_for(&mut self, for_stmt: ForStmt, variables: Vec<(String, ValueType)>) {
+        self.loop_depth += 1;
+        self.ast_environment.start_branch(true);
+        self.ast_environment.start_scope();
+        for (variable, value_type) in variables {
+            self.ast_environment.insert(variable, value_type, false, true);
         }
+        self.ast.as_mut().unwrap().start_for(for_stmt);
     }
 
-    fn get_scope_depth(&self) -> usize {
-        self.ast_environment.get_scope_depth()
+    pub fn end_for(&mut self) {
+        self.end_while();
     }
 
-    pub fn start_function(
+    pub fn emit_break(
         &mut self,
-        name: String,
-        args: Vec<FunctionArgument>,
-        return_type: Option<ValueType>
-    ) {
-        self.ast.as_mut().unwrap().start_function(name, args, return_type);
-    }
+        break_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        if self.loop_depth == 0 {
+            return Err(("Cannot use 'break' outside of a loop".to_string(), vec![break_metadata]));
+        }
 
-    pub fn end_function(&mut self) {
-        self.ast.as_mut().unwrap().end_function();
+        self.push_stmt(Stmt::BreakStmt)
     }
 
-    pub fn start_scope(&mut self) {
-        self.ast_environment.start_scope();
-        self.ast.as_mut().unwrap().start_scope();
-    }
+    pub fn emit_continue(
+        &mut self,
+        continue_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        if self.loop_depth == 0 {
+            return Err((
+                "Cannot use 'continue' outside of a loop".to_string(),
+                vec![continue_metadata],
+            ));
+        }
 
-    pub fn end_scope(&mut self) {
-        self.ast_environment.end_scope();
-        self.ast.as_mut().unwrap().end_scope()
+        self.push_stmt(Stmt::ContinueStmt)
     }
 
     pub fn free(&mut self) {
@@ -182,15 +3987,101 @@ impl AstGenerator {
         self.exprs.push(Expr::Literal(value));
     }
 
-    pub fn emit_identifier_lookup(&mut self, variable: AstIdentifier) {
-        self.exprs.push(Expr::IdentifierLookup(variable));
+    // `a += b` is `a = a + b`, so the target is needed again as the left operand
+    pub fn emit_compound_target(&mut self) {
+        if let Some(target) = self.exprs.last() {
+            self.exprs.push(target.clone());
+        }
+    }
+
+    // With `as_value`, a function name is a function value unless a variable shadows it
+    pub fn emit_identifier_lookup(
+        &mut self,
+        variable: AstIdentifier,
+        as_value: bool
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        self.ast_environment.capture(&variable.lexeme);
+
+        let signature = match self.ast_environment.get_function(&variable.lexeme) {
+            Some(signature) if as_value && self.ast_environment.get(&variable.lexeme).is_none() => {
+                signature
+            }
+            _ => {
+                self.exprs.push(Expr::IdentifierLookup(variable));
+                return Ok(());
+            }
+        };
+
+        if !signature.type_params.is_empty() {
+            return Err((
+                format!("Generic function '{}' cannot be used as a value", variable.lexeme),
+                vec![variable.token_metadata],
+            ));
+        }
+
+        let closure = Closure {
+            function: signature.id,
+            function_type: FunctionType {
+                parameters: signature.parameters,
+                return_type: Box::new(signature.return_type),
+            },
+            upvalues: Vec::new(),
+        };
+        self.exprs.push(
+            Expr::Literal(AstValue::new(Value::Function(Rc::new(closure)), variable.token_metadata))
+        );
+
+        Ok(())
     }
 
     pub fn emit_type_definition(
         &mut self,
         type_def: TypeDefStmt
     ) -> Result<(), (String, Vec<TokenMetadata>)> {
-        self.push_stmt(Stmt::TypeDefStmt(type_def))
+        let name = &type_def.type_name;
+
+        if ValueType::from_name(name).is_some() {
+            return Err((
+                format!("Cannot redefine the builtin type '{}'", name),
+                vec![type_def.token_metadata],
+            ));
+        }
+        if self.ast_environment.is_type_defined_in_scope(name) {
+            return Err((
+                format!("Type '{}' is already defined in this scope", name),
+                vec![type_def.token_metadata],
+            ));
+        }
+
+        let mut type_params: Vec<Rc<str>> = Vec::new();
+        for type_param in &type_def.type_params {
+            let type_param_name = type_param.lexeme.as_str();
+
+            let message = if type_params.iter().any(|other| &**other == type_param_name) {
+                Some(format!("Type parameter '{}' is declared more than once", type_param_name))
+            } else if ValueType::from_name(type_param_name).is_some() {
+                Some(format!("Cannot use the builtin type '{}' as a type parameter", type_param_name))
+            } else {
+                None
+            };
+
+            if let Some(message) = message {
+                return Err((message, vec![type_param.token_metadata]));
+            }
+            type_params.push(type_param_name.into());
+        }
+
+        // The alias is only needed while parsing, so it doesn't become a statement
+        self.ast_environment.insert_type_alias(type_def.type_name, TypeAlias {
+            type_params,
+            typing: type_def.typing,
+        });
+
+        Ok(())
+    }
+
+    pub fn resolve_typing(&self, typing: &Typing) -> Result<ValueType, (String, Vec<TokenMetadata>)> {
+        self.ast_environment.resolve_typing(typing)
     }
 
     pub fn emit_variable_assignment(
@@ -200,7 +4091,7 @@ impl AstGenerator {
         if self.panic_mode {
             return Ok(());
         }
-        let value = self.exprs.pop().unwrap();
+        let mut value = self.exprs.pop().unwrap();
         let identifier = match self.exprs.pop().unwrap() {
             Expr::IdentifierLookup(v) => v,
             _ => {
@@ -208,6 +4099,10 @@ impl AstGenerator {
             }
         };
 
+        if let Some((value_type, _, _)) = self.ast_environment.get(&identifier.lexeme) {
+            value.coerce_literal(&value_type);
+        }
+
         let variable_assignment = Stmt::VariableAssignment(
             VariableAssignmentStmt::new(None, identifier, value)
         );
@@ -223,7 +4118,11 @@ impl AstGenerator {
         is_mutable: bool,
         last_token_in_definition: TokenMetadata
     ) -> Result<(), (String, Vec<TokenMetadata>)> {
-        let value = self.exprs.pop();
+        let mut value = self.exprs.pop();
+
+        if let (Some(value), Some(value_type)) = (&mut value, &value_type) {
+            value.coerce_literal(value_type);
+        }
 
         if
             !matches!(
@@ -250,20 +4149,55 @@ impl AstGenerator {
         self.push_stmt(variable_definition)
     }
 
+    pub fn emit_destructuring_definition(
+        &mut self,
+        variables: Vec<DestructuredVariable>,
+        paren_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let value = match self.exprs.pop() {
+            Some(value) => value,
+            None => {
+                return Err((
+                    "Missing right hand side of variable definition".to_string(),
+                    vec![paren_metadata],
+                ));
+            }
+        };
+
+        for (i, variable) in variables.iter().enumerate() {
+            let is_repeated = variables[..i]
+                .iter()
+                .any(|other| other.name == variable.name && variable.name != "_");
+
+            if is_repeated {
+                return Err((
+                    format!("Variable '{}' is bound more than once in the definition", variable.name),
+                    vec![variable.token_metadata],
+                ));
+            }
+        }
+
+        let variable_definition = Stmt::VariableDefinition(
+            VariableDefinitionStmt::new_destructuring(variables, value, paren_metadata)
+        );
+
+        self.push_stmt(variable_definition)
+    }
+
     pub fn emit_binary_op(
         &mut self,
         expr_op: BinaryOp
     ) -> Result<(), (String, Vec<TokenMetadata>)> {
-        let popped_left = self.exprs.pop();
         let popped_right = self.exprs.pop();
+        let popped_left = self.exprs.pop();
 
         let (left, right) = match (popped_left, popped_right) {
             (Some(left), Some(right)) => (left, right),
-            (Some(left), None) => {
-                let mut metadata = match left {
-                    Expr::Literal(v) => v.get_token_metadata(),
-                    _ => panic!("This is weird..."),
-                };
+            (None, Some(right)) => {
+                let mut token_vec = Vec::new();
+                right.push_to_token_vec(&mut token_vec);
+
+                let mut metadata = TokenMetadata::span(&token_vec).expect("Expressions have tokens");
                 metadata.increment_length();
                 metadata.increment_length();
 
@@ -278,6 +4212,10 @@ impl AstGenerator {
             }
         };
 
+        let (left, right) = self.coerce_operands(left, right);
+
+        self.add_requirement(&left, Op::BinaryOp(expr_op));
+
         let binary_expr = BinaryExpr {
             left: Box::new(left),
             operator: expr_op,
@@ -299,6 +4237,121 @@ impl AstGenerator {
         Ok(())
     }
 
+    // The other operand of a binary operator must be the same type parameter
+    fn add_requirement(&mut self, operand: &Expr, operation: Op) {
+        if !self.ast_environment.has_type_params() {
+            return;
+        }
+
+        let mut token_vec = Vec::new();
+        if let Ok(ValueType::TypeParam(type_param)) = operand.type_check(&self.ast_environment, &mut token_vec) {
+            operand.push_to_token_vec(&mut token_vec);
+
+            if let Some(token_metadata) = token_vec.last() {
+                self.ast_environment.add_requirement(TypeParamRequirement {
+                    type_param,
+                    operation,
+                    token_metadata: *token_metadata,
+                });
+            }
+        }
+    }
+
+    // Gives a number literal the type of the other operand, so `x + 1` works for any number `x`
+    fn coerce_operands(&self, mut left: Expr, mut right: Expr) -> (Expr, Expr) {
+        match (left.is_number_literal(), right.is_number_literal()) {
+            (true, true) => {
+                if let Ok(left_type) = left.type_check(&self.ast_environment, &mut Vec::new()) {
+                    right.coerce_literal(&left_type);
+                }
+                if let Ok(right_type) = right.type_check(&self.ast_environment, &mut Vec::new()) {
+                    left.coerce_literal(&right_type);
+                }
+            }
+            (true, false) => {
+                if let Ok(right_type) = right.type_check(&self.ast_environment, &mut Vec::new()) {
+                    left.coerce_literal(&right_type);
+                }
+            }
+            (false, true) => {
+                if let Ok(left_type) = left.type_check(&self.ast_environment, &mut Vec::new()) {
+                    right.coerce_literal(&left_type);
+                }
+            }
+            (false, false) => {}
+        }
+
+        (left, right)
+    }
+
+    fn coerce_args(args: &mut [Expr], parameters: &[ValueType]) {
+        for (arg, parameter) in args.iter_mut().zip(parameters) {
+            arg.coerce_literal(parameter);
+        }
+    }
+
+    // Number literals only decide `T` if the other arguments don't, floats before integers
+    fn coerce_builtin_args(&self, builtin: &value_v2::Builtin, args: &mut [Expr]) {
+        let (mut literal_args, other_args): (Vec<_>, Vec<_>) = args
+            .iter()
+            .enumerate()
+            .filter_map(|(index, arg)| {
+                let arg_type = arg.type_check(&self.ast_environment, &mut Vec::new()).ok()?;
+                Some((index, arg_type, arg.is_number_literal()))
+            })
+            .partition(|(_, _, is_number_literal)| *is_number_literal);
+        literal_args.sort_by_key(|(_, arg_type, _)| !arg_type.is_float());
+
+        let arg_types = other_args
+            .iter()
+            .chain(&literal_args)
+            .map(|(index, arg_type, _)| (*index, arg_type));
+
+        let type_arg = builtin.infer_type_arg(arg_types).unwrap_or(ValueType::Empty);
+        Self::coerce_args(args, &builtin.resolve_types(&type_arg).0);
+    }
+
+    pub fn emit_logical_op(
+        &mut self,
+        expr_op: LogicalOp,
+        operator_metadata: TokenMetadata
+    ) -> Result<(), (String, Vec<TokenMetadata>)> {
+        let (left, mut right) = match (self.exprs.pop(), self.exprs.pop()) {
+            (Some(right), Some(left)) => (left, right),
+            (Some(left), None) => {
+                self.exprs.push(left);
+                return Err((
+                    "Expected right-hand side of logical operation".to_string(),
+                    vec![operator_metadata],
+                ));
+            }
+            _ => {
+                return Err((
+                    "Expected operands of logical operation".to_string(),
+                    vec![operator_metadata],
+                ));
+            }
+        };
+
+        if let LogicalOp::Coalesce = expr_op {
+            let mut token_vec = Vec::new();
+            if let Ok(ValueType::Optional(inner)) = left.type_check(&self.ast_environment, &mut token_vec) {
+                right.coerce_literal(&inner);
+            }
+        }
+
+        self.exprs.push(
+            Expr::LogicalExpr(LogicalExpr {
+                left: Box::new(left),
+                operator: expr_op,
+                right: Box::new(right),
+                token_metadata: operator_metadata,
+            })
+        );
+
+        Ok(())
+    }
+
     pub fn emit_unary_op(&mut self, expr_op: UnaryOp) -> Result<(), (String, Vec<TokenMetadata>)> {
         if self.panic_mode {
             return Ok(());
@@ -306,6 +4359,8 @@ impl AstGenerator {
 
         let right = self.exprs.pop().unwrap();
 
+        self.add_requirement(&right, Op::UnaryOp(expr_op));
+
         let unary_expr = UnaryExpr {
             operator: expr_op,
             right: Box::new(right),
@@ -331,7 +4386,10 @@ impl AstGenerator {
             self.exit_panic_mode();
             Ok(())
         } else {
-            match self.exprs.pop() {
+            let outer_expr_count = self.functions.last().map_or(0, |function| function.outer_expr_count);
+            let expr = if self.exprs.len() > outer_expr_count { self.exprs.pop() } else { None };
+
+            match expr {
                 Some(expr) => {
                     match self.push_stmt(Stmt::ExprStmt(expr)) {
                         Ok(_) => Ok(()),
@@ -361,6 +4419,10 @@ impl AstGenerator {
         self.exprs.pop()
     }
 
+    pub fn end_program(&mut self) -> Vec<(String, Vec<TokenMetadata>)> {
+        self.ast_environment.end_program()
+    }
+
     pub fn get_ast(&mut self) -> Ast {
         self.ast.take().unwrap()
     }
@@ -1,6 +1,13 @@
-use crate::{ ast::{ expr::AstIdentifier, stmt::{ FunctionArgument, Typing } }, value::ValueType };
+use std::path::{ Path, PathBuf };
+
+use crate::{
+    ast::{ expr::AstIdentifier, stmt::{ DestructuredVariable, FunctionArgument, Typing, TypingValue } },
+    operations::BinaryOp,
+    value::ValueType,
+};
 
 use super::{
+    ast_generator::{ AstGenerator, TypeParamDefinition },
     precedence::Precedence,
     token::{ token_type::TokenType, Token, TokenMetadata },
     Parser,
@@ -120,16 +127,199 @@ impl<'a> Parser<'a> {
         */
     }
 
+    pub(super) fn block_statements(&mut self, msg: &str) {
+        self.hoist_declarations();
+
+        while
+            !self.is_at_end() &&
+            !matches!(self.get_current().get_ttype(), &TokenType::TokenRightCurlyBrace)
+        {
+            self.statement();
+        }
+        self.consume(TokenType::TokenRightCurlyBrace, msg);
+    }
+
+    // Type names are declared in a first pass, so the signatures hoisted in the second can use them
+    pub(super) fn hoist_declarations(&mut self) {
+        let lexer = self.lexer.clone();
+        let (current, next) = (self.current.clone(), self.next.clone());
+        let previous_count = self.previous_tokens.len();
+        let panic_mode = self.panic_mode;
+
+        self.is_hoisting = true;
+
+        for declares_types in [true, false] {
+            self.hoist_block(declares_types);
+
+            self.lexer = lexer.clone();
+            (self.current, self.next) = (current.clone(), next.clone());
+            self.previous_tokens.truncate(previous_count);
+        }
+
+        self.is_hoisting = false;
+        match panic_mode {
+            true => self.enter_panic_mode(),
+            false => self.exit_panic_mode(),
+        }
+    }
+
+    fn hoist_block(&mut self, declares_types: bool) {
+        let mut block_depth = 0;
+
+        loop {
+            let is_named_definition =
+                block_depth == 0 &&
+                self.get_next().is_some_and(|token| token.get_ttype() == &TokenType::TokenIdentifier);
+
+            match self.get_current().get_ttype() {
+                TokenType::TokenEOF => {
+                    break;
+                }
+                TokenType::TokenLeftCurlyBrace => {
+                    block_depth += 1;
+                }
+                TokenType::TokenRightCurlyBrace if block_depth == 0 => {
+                    break;
+                }
+                TokenType::TokenRightCurlyBrace => {
+                    block_depth -= 1;
+                }
+                TokenType::TokenClass if is_named_definition && declares_types => {
+                    self.advance();
+                    self.advance();
+                    self.hoist_class_declaration();
+                    continue;
+                }
+                TokenType::TokenEnum if is_named_definition && declares_types => {
+                    self.advance();
+                    self.advance();
+                    self.ast_generator.declare_enum(self.get_previous().get_lexeme(self.source));
+                    continue;
+                }
+                TokenType::TokenEnum if is_named_definition => {
+                    self.advance();
+                    self.advance();
+                    self.hoist_enum_variants();
+                    continue;
+                }
+                TokenType::TokenFunction if is_named_definition && !declares_types => {
+                    self.advance();
+                    self.advance();
+                    self.hoist_function_signature();
+                    continue;
+                }
+                _ => {}
+            }
+
+            self.advance();
+        }
+    }
+
+    fn hoist_class_declaration(&mut self) {
+        let name = self.get_previous().get_lexeme(self.source);
+
+        self.exit_panic_mode();
+        let type_params = match self.type_parameters() {
+            Ok(type_params) => type_params,
+            Err(_) => {
+                return;
+            }
+        };
+
+        // A class with invalid type parameters is only known from its definition
+        if self.ast_generator.start_type_params(type_params).is_ok() {
+            self.ast_generator.declare_class(name);
+        }
+        self.ast_generator.end_declared_type_params();
+    }
+
+    // Leaves the parser after the enum body, since variants don't contain braces
+    fn hoist_enum_variants(&mut self) {
+        let name = self.get_previous().get_lexeme(self.source);
+        if
+            self.get_current().get_ttype() != &TokenType::TokenLeftCurlyBrace ||
+            !self.ast_generator.start_declared_enum(&name)
+        {
+            return;
+        }
+        self.advance();
+
+        self.exit_panic_mode();
+        while
+            !self.panic_mode &&
+            !matches!(
+                self.get_current().get_ttype(),
+                &TokenType::TokenRightCurlyBrace | &TokenType::TokenEOF
+            )
+        {
+            self.variant_definition();
+
+            if self.get_current().get_ttype() == &TokenType::TokenComma {
+                self.advance();
+            }
+        }
+        self.ast_generator.end_enum();
+
+        while
+            !matches!(
+                self.get_current().get_ttype(),
+                &TokenType::TokenRightCurlyBrace | &TokenType::TokenEOF
+            )
+        {
+            self.advance();
+        }
+        if self.get_current().get_ttype() == &TokenType::TokenRightCurlyBrace {
+            self.advance();
+        }
+    }
+
+    fn hoist_function_signature(&mut self) {
+        let name = self.get_previous().get_lexeme(self.source);
+
+        self.exit_panic_mode();
+        let type_params = match self.type_parameters() {
+            Ok(type_params) => type_params,
+            Err(_) => {
+                return;
+            }
+        };
+
+        // A function with invalid type parameters is only known from its definition
+        if self.ast_generator.start_type_params(type_params).is_ok() {
+            let signature = self
+                .resolve_function_args()
+                .and_then(|args| Ok((args, self.resolve_function_return_type()?)));
+
+            if let Ok((args, return_type)) = signature {
+                self.ast_generator.hoist_function(name, &args, return_type);
+            }
+        }
+        self.ast_generator.end_declared_type_params();
+    }
+
     pub(super) fn var_assign(&mut self) {
         let (lexeme, token_metadata) = {
             let token = self.get_previous();
             (token.get_lexeme(self.source), token.get_metadata())
         };
 
-        self.ast_generator.emit_identifier_lookup(AstIdentifier::new(lexeme, token_metadata));
+        let result = self.ast_generator.emit_identifier_lookup(
+            AstIdentifier::new(lexeme, token_metadata),
+            false
+        );
+        if let Err((message, token_vec)) = result {
+            self.report_compile_error(message, token_vec);
+            return;
+        }
 
         if !self.is_at_expr_end() {
-            if
+            let compound_op = self.get_current().get_ttype().get_compound_assignment_op();
+
+            let has_operator = if compound_op.is_some() {
+                self.advance();
+                self.ast_generator.emit_compound_target();
+                true
+            } else {
                 self.consume(
                     TokenType::TokenAssign,
                     format!(
@@ -137,9 +327,11 @@ impl<'a> Parser<'a> {
                         self.get_current().get_lexeme(self.source)
                     ).as_str()
                 )
-            {
+            };
+
+            if has_operator {
                 if !self.is_at_expr_end() {
-                    self.parse_precedence(Precedence::PrecAssignment.get_next(), None);
+                    self.assignment_value(compound_op);
                     self.consume_expr_end();
                 } else {
                     self.report_compile_error(
@@ -157,15 +349,27 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // A compound assignment expects its target on the expression stack
+    pub(super) fn assignment_value(&mut self, compound_op: Option<BinaryOp>) {
+        self.parse_precedence(Precedence::PrecAssignment.get_next(), None);
+
+        if let Some(operator) = compound_op {
+            if let Err((message, token_vec)) = self.ast_generator.emit_binary_op(operator) {
+                self.report_compile_error(message, token_vec);
+            }
+        }
+    }
+
     pub(super) fn var_def(&mut self, rule_arg: RuleArg) {
         let is_mutable = rule_arg == RuleArg::MutVar;
 
         let lexeme = self.get_previous().get_lexeme(&self.source);
         let identifier_metadata = self.get_previous().get_metadata();
 
-        let found_type = match self.resolve_type() {
-            Ok(found_type) => found_type,
-            Err(_) => None,
+        // A missing type is inferred from the value
+        let found_type = match self.get_current().get_ttype() {
+            TokenType::TokenDefine => None,
+            _ => self.resolve_type().ok(),
         };
 
         let last_token_ind_definition = self.get_previous().get_metadata();
@@ -205,159 +409,1230 @@ impl<'a> Parser<'a> {
         }
     }
 
-    pub(super) fn resolve_type(&mut self) -> Result<Option<ValueType>, Vec<TokenMetadata>> {
-        let found_type = match self.get_current().get_ttype() {
-            TokenType::TokenIdentifier => {
+    pub(super) fn destructuring_definition(&mut self) {
+        let paren_metadata = self.get_previous().get_metadata();
+        let mut variables = Vec::new();
+
+        loop {
+            let is_mutable = self.get_current().get_ttype() == &TokenType::TokenMutable;
+            if is_mutable {
                 self.advance();
-                let type_lexeme = self.get_previous().get_lexeme(&self.source);
-                match type_lexeme.as_str() {
-                    "i32" => Ok(Some(ValueType::Int32)),
-                    "bool" => Ok(Some(ValueType::Bool)),
-                    _ => Ok(None), // This should make a custom type
-                }
             }
-            _ => Err(vec![self.get_current().get_metadata()]),
-        };
 
-        found_type
-    }
+            if !self.consume(TokenType::TokenIdentifier, "Expected variable name in destructuring definition") {
+                return;
+            }
+            let token = self.get_previous();
+            variables.push(DestructuredVariable {
+                name: token.get_lexeme(self.source),
+                is_mutable,
+                token_metadata: token.get_metadata(),
+            });
 
-    pub(super) fn expression_statement(&mut self) {
-        self.expression();
+            if self.get_current().get_ttype() != &TokenType::TokenComma {
+                break;
+            }
+            self.advance();
+        }
+
+        if
+            !self.consume(TokenType::TokenRightParen, "Expected ')' after the destructured variables") ||
+            !self.consume(TokenType::TokenDefine, "Expected ':=' after the destructured variables")
+        {
+            return;
+        }
+
+        if self.is_at_expr_end() {
+            self.report_compile_error(
+                "Missing right hand side of variable definition".to_string(),
+                vec![self.get_previous().get_metadata()]
+            );
+            return;
+        }
+
+        self.parse_precedence(Precedence::PrecAssignment.get_next(), None);
         self.consume_expr_end();
 
-        match self.ast_generator.push_expr() {
-            Ok(_) => {}
-            Err((message, token)) => {
-                self.report_compile_error(message, token);
-                self.synchronize()
-            }
+        let result = self.ast_generator.emit_destructuring_definition(variables, paren_metadata);
+
+        if let Err((message, token_vec)) = result {
+            self.report_compile_error(message, token_vec);
         }
     }
 
-    pub(super) fn expression(&mut self) {
-        self.parse_precedence(Precedence::PrecAssignment, None);
+    pub(super) fn resolve_type(&mut self) -> Result<ValueType, Vec<TokenMetadata>> {
+        let typing = self.resolve_typing()?;
+
+        match self.ast_generator.resolve_typing(&typing) {
+            Ok(value_type) => Ok(value_type),
+            Err((message, token_vec)) => {
+                self.report_compile_error(message, token_vec.clone());
+                Err(token_vec)
+            }
+        }
     }
 
-    pub(super) fn parse_precedence(&mut self, precedence: Precedence, msg: Option<&str>) {
-        self.advance();
+    // Names aren't resolved here, which is how type aliases are stored
+    pub(super) fn resolve_typing(&mut self) -> Result<Typing, Vec<TokenMetadata>> {
+        let token_metadata = self.get_current().get_metadata();
+        let typing = self.resolve_non_optional_typing()?;
 
-        let parse_rule = self.get_parse_rule(self.get_previous().get_ttype());
+        if self.get_current().get_ttype() == &TokenType::TokenQuestion {
+            self.advance();
+            return Ok(Typing::new_optional(typing, token_metadata));
+        }
 
-        let prefix_rule = parse_rule.get_prefix();
+        Ok(typing)
+    }
 
-        if let Some(prefix_rule) = prefix_rule {
-            prefix_rule(self, RuleArg::Precedence(precedence));
+    fn resolve_non_optional_typing(&mut self) -> Result<Typing, Vec<TokenMetadata>> {
+        let token_metadata = self.get_current().get_metadata();
 
-            loop {
-                let current_ttype = self.get_current().get_ttype();
-                let current_precedence = self.get_parse_rule(current_ttype).get_precedence();
+        match self.get_current().get_ttype() {
+            TokenType::TokenIdentifier => {
+                self.advance();
+                let type_lexeme = self.get_previous().get_lexeme(&self.source);
 
-                if (*current_precedence as usize) < (precedence as usize) {
-                    break;
+                // `Box<i32>`. Whether the type takes type arguments is checked when it's resolved
+                let type_args = match self.get_current().get_ttype() {
+                    TokenType::TokenLess => {
+                        self.advance();
+                        Some(self.typing_arguments()?)
+                    }
+                    _ => None,
+                };
+
+                match ValueType::from_name(&type_lexeme) {
+                    Some(value_type) => {
+                        Ok(Typing::new(TypingValue::ValueType(value_type), token_metadata, type_args))
+                    }
+                    None => {
+                        Ok(Typing::new(TypingValue::Custom(type_lexeme), token_metadata, type_args))
+                    }
                 }
+            }
+            TokenType::TokenLeftSquareBracket => {
+                self.advance();
+                let element_typing = self.resolve_typing()?;
 
-                if self.get_previous().get_line() < self.get_current().get_line() {
-                    break;
+                if !self.consume(TokenType::TokenRightSquareBracket, "Expected ']' after element type") {
+                    return Err(vec![self.get_previous().get_metadata()]);
                 }
 
+                Ok(Typing::new_array(element_typing, token_metadata))
+            }
+            TokenType::TokenLeftCurlyBrace => {
                 self.advance();
+                let key_typing = self.resolve_typing()?;
 
-                let infix_rule = self.get_parse_rule(self.get_previous().get_ttype()).get_infix();
+                if !self.consume(TokenType::TokenColon, "Expected ':' after map key type") {
+                    return Err(vec![self.get_previous().get_metadata()]);
+                }
+                let value_typing = self.resolve_typing()?;
 
-                if let Some(infix_rule) = infix_rule {
-                    infix_rule(self, RuleArg::Precedence(precedence));
+                if !self.consume(TokenType::TokenRightCurlyBrace, "Expected '}' after map value type") {
+                    return Err(vec![self.get_previous().get_metadata()]);
                 }
-            }
-        } else {
-            self.report_compile_error(
-                format!(
-                    "Unexpected token: '{}' (no prefix rule)",
-                    self.get_previous().get_lexeme(self.source)
-                ),
-                vec![self.get_previous().get_metadata()]
-            )
-        }
-    }
 
-    pub(super) fn ident_lookup(&mut self) {
-        let token = self.get_previous();
-        let lexeme = token.get_lexeme(self.source);
+                Ok(Typing::new_map(key_typing, value_typing, token_metadata))
+            }
+            TokenType::TokenLeftParen => {
+                self.advance();
+                let mut element_typings = vec![self.resolve_typing()?];
 
-        self.ast_generator.emit_identifier_lookup(AstIdentifier::new(lexeme, token.get_metadata()));
-    }
+                while self.get_current().get_ttype() == &TokenType::TokenComma {
+                    self.advance();
+                    element_typings.push(self.resolve_typing()?);
+                }
 
-    pub(super) fn resolve_function_args(&mut self) -> Result<Vec<FunctionArgument>, ()> {
-        let mut args = vec![];
+                if !self.consume(TokenType::TokenRightParen, "Expected ')' after tuple element types") {
+                    return Err(vec![self.get_previous().get_metadata()]);
+                }
 
-        self.consume(TokenType::TokenLeftParen, "Expected '(' after function identifier");
+                if element_typings.len() < 2 {
+                    self.report_compile_error(
+                        "A tuple type needs at least two element types".to_string(),
+                        vec![token_metadata]
+                    );
+                    return Err(vec![token_metadata]);
+                }
 
-        while !self.is_at_expr_end() {
-            if self.get_current().get_ttype().is(&TokenType::TokenComma) {
-                self.advance();
-            }
-            if self.get_current().get_ttype().is(&TokenType::TokenRightParen) {
-                break;
+                Ok(Typing::new_tuple(element_typings, token_metadata))
             }
+            TokenType::TokenFunction => {
+                self.advance();
 
-            let is_ok = self.consume(
-                TokenType::TokenIdentifier,
-                format!(
-                    "Expected identifer but got '{}' Syntax: <identifier> <type>",
-                    self.get_current().get_lexeme(self.source)
-                ).as_str()
-            );
-            if !is_ok {
-                return Err(());
-            }
+                if !self.consume(TokenType::TokenLeftParen, "Expected '(' after 'fn' in function type") {
+                    return Err(vec![self.get_previous().get_metadata()]);
+                }
 
-            let ident_lexeme = self.get_previous().get_lexeme(&self.source);
+                let mut parameter_typings = Vec::new();
+                while self.get_current().get_ttype() != &TokenType::TokenRightParen {
+                    parameter_typings.push(self.resolve_typing()?);
 
-            let is_mutable = match self.get_current().get_ttype().is(&TokenType::TokenMutable) {
-                true => {
+                    if self.get_current().get_ttype() != &TokenType::TokenComma {
+                        break;
+                    }
                     self.advance();
-                    true
                 }
-                false => false,
-            };
 
-            let arg_type = match self.resolve_type() {
-                Ok(arg_type) =>
-                    match arg_type {
-                        Some(arg_type) => arg_type,
-                        None => panic!("Custom types not yet supported"),
+                if !self.consume(TokenType::TokenRightParen, "Expected ')' after parameter types") {
+                    return Err(vec![self.get_previous().get_metadata()]);
+                }
+
+                // The return type is left out for functions that don't return anything
+                let return_typing = match self.get_current().get_ttype() {
+                    | TokenType::TokenIdentifier
+                    | TokenType::TokenLeftSquareBracket
+                    | TokenType::TokenLeftParen
+                    | TokenType::TokenFunction
+                    | TokenType::TokenSelfType => self.resolve_typing()?,
+                    TokenType::TokenLeftCurlyBrace if self.is_map_type_ahead() => self.resolve_typing()?,
+                    _ => Typing::new(TypingValue::ValueType(ValueType::Void), token_metadata, None),
+                };
+
+                Ok(Typing::new_function(parameter_typings, return_typing, token_metadata))
+            }
+            TokenType::TokenSelfType => {
+                self.advance();
+
+                match self.ast_generator.get_current_class_type() {
+                    Some(class_type) => {
+                        Ok(
+                            Typing::new(
+                                TypingValue::ValueType(ValueType::Class(class_type)),
+                                token_metadata,
+                                None
+                            )
+                        )
+                    }
+                    None => {
+                        self.report_compile_error(
+                            "'Self' can only be used inside a class".to_string(),
+                            vec![token_metadata]
+                        );
+                        Err(vec![token_metadata])
                     }
-                Err(error_tokens) => {
-                    self.report_compile_error("Invalid type".to_string(), error_tokens);
-                    return Err(());
                 }
-            };
+            }
+            _ => Err(vec![token_metadata]),
+        }
+    }
 
-            args.push(FunctionArgument {
-                is_mutable,
-                name: ident_lexeme,
-                value_type: arg_type,
-            });
+    pub(super) fn typing_arguments(&mut self) -> Result<Vec<Typing>, Vec<TokenMetadata>> {
+        let mut type_args = vec![self.resolve_typing()?];
+
+        while self.get_current().get_ttype() == &TokenType::TokenComma {
+            self.advance();
+            type_args.push(self.resolve_typing()?);
         }
 
-        self.consume(TokenType::TokenRightParen, "Expected a closing ')' after function arguments");
+        if !self.consume_type_args_end("Expected '>' after type arguments") {
+            return Err(vec![self.get_previous().get_metadata()]);
+        }
 
-        Ok(args)
+        Ok(type_args)
     }
 
-    // Temporary functions
-    fn consume_type(&mut self, msg: &str) -> Option<&TokenType> {
-        if matches!(self.get_current().get_ttype(), TokenType::TokenInt32 | TokenType::TokenBool) {
-            Some(self.get_current().get_ttype())
-        } else {
-            self.report_compile_error(msg.to_string(), vec![self.get_current().get_metadata()]);
-            None
+    pub(super) fn type_parameters(&mut self) -> Result<Vec<TypeParamDefinition>, ()> {
+        let mut type_params = Vec::new();
+
+        if self.get_current().get_ttype() != &TokenType::TokenLess {
+            return Ok(type_params);
         }
-    }
+        self.advance();
 
-    fn consume_identifier(&mut self, msg: &str) -> Option<String> {
-        if matches!(self.get_current().get_ttype(), TokenType::TokenIdentifier) {
-            Some(self.get_current().get_lexeme(self.source))
+        loop {
+            if !self.consume(TokenType::TokenIdentifier, "Expected type parameter name") {
+                return Err(());
+            }
+            let token = self.get_previous();
+            let name = AstIdentifier::new(token.get_lexeme(self.source), token.get_metadata());
+
+            let mut bounds = Vec::new();
+            if self.get_current().get_ttype() == &TokenType::TokenColon {
+                loop {
+                    self.advance();
+
+                    if !self.consume(TokenType::TokenIdentifier, "Expected trait name in type parameter bound") {
+                        return Err(());
+                    }
+                    let token = self.get_previous();
+                    bounds.push(AstIdentifier::new(token.get_lexeme(self.source), token.get_metadata()));
+
+                    if self.get_current().get_ttype() != &TokenType::TokenPlus {
+                        break;
+                    }
+                }
+            }
+            type_params.push(TypeParamDefinition { name, bounds });
+
+            if self.get_current().get_ttype() != &TokenType::TokenComma {
+                break;
+            }
+            self.advance();
+        }
+
+        if !self.consume_type_args_end("Expected '>' after type parameters") {
+            return Err(());
+        }
+
+        Ok(type_params)
+    }
+
+    // In `Box<Box<i32>>` the lexer sees '>>', which is split to close both
+    fn consume_type_args_end(&mut self, msg: &str) -> bool {
+        if self.get_current().get_ttype() != &TokenType::TokenGreaterGreater {
+            return self.consume(TokenType::TokenGreater, msg);
+        }
+
+        let metadata = self.get_current().get_metadata();
+        let (start, line) = (metadata.get_start(), metadata.get_line());
+
+        if let Some(current) = self.current.take() {
+            self.previous_tokens.push(current);
+        }
+        self.current = Some(Token::new(TokenType::TokenGreater, start + 1, 1, line, metadata.get_file_id()));
+
+        true
+    }
+
+    // A module that has already been parsed is only imported
+    pub(super) fn import_module(&mut self, module_path: &str, path_metadata: TokenMetadata) {
+        let directory = self.error_handler
+            .get_file_path(self.file_id)
+            .and_then(Path::parent)
+            .unwrap_or(Path::new(""));
+        let path = directory.join(module_path);
+
+        let Ok(canonical_path) = path.canonicalize() else {
+            self.report_compile_error(
+                format!("Cannot find module '{}'", path.display()),
+                vec![path_metadata]
+            );
+            return;
+        };
+
+        if let Some(cycle_start) = self.module_stack.iter().position(|(path, _)| path == &canonical_path) {
+            let mut cycle = self.module_stack[cycle_start..]
+                .iter()
+                .map(|(_, file_id)| self.error_handler.get_file_path(*file_id).unwrap().display().to_string())
+                .collect::<Vec<_>>();
+            cycle.push(cycle[0].clone());
+
+            self.report_compile_error(
+                format!("Import cycle: {}", cycle.join(" -> ")),
+                vec![path_metadata]
+            );
+            return;
+        }
+
+        if let Some(conflicts) = self.ast_generator.import_parsed_module(&canonical_path) {
+            self.report_import_conflicts(conflicts, &canonical_path, path_metadata);
+            return;
+        }
+
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(e) => {
+                self.report_compile_error(
+                    format!("Cannot read module '{}': {}", path.display(), e),
+                    vec![path_metadata]
+                );
+                return;
+            }
+        };
+        let source_chars = source.chars().collect::<Vec<_>>();
+        let file_id = self.error_handler.add_file(path, source);
+
+        let mut module_stack = self.module_stack.clone();
+        module_stack.push((canonical_path.clone(), file_id));
+
+        // The module shares the generator, so its functions and types end up in the same AST
+        let ast_generator = std::mem::replace(&mut self.ast_generator, Box::new(AstGenerator::new()));
+        let mut parser = Parser::new_module(
+            &source_chars,
+            file_id,
+            module_stack,
+            ast_generator,
+            &mut *self.error_handler
+        );
+
+        parser.ast_generator.start_module();
+        parser.parse_statements();
+        let conflicts = parser.ast_generator.end_module(canonical_path.clone());
+
+        self.ast_generator = parser.ast_generator;
+        self.report_import_conflicts(conflicts, &canonical_path, path_metadata);
+    }
+
+    fn report_import_conflicts(
+        &mut self,
+        conflicts: Vec<(String, Option<PathBuf>)>,
+        module_path: &Path,
+        path_metadata: TokenMetadata
+    ) {
+        for (name, import_path) in conflicts {
+            let message = match import_path {
+                Some(import_path) =>
+                    format!(
+                        "'{}' is imported from both '{}' and '{}'",
+                        name,
+                        import_path.display(),
+                        module_path.display()
+                    ),
+                None => {
+                    let file_path = self.error_handler.get_file_path(self.file_id).unwrap_or(Path::new(""));
+
+                    format!(
+                        "'{}' imported from '{}' is already defined in '{}'",
+                        name,
+                        module_path.display(),
+                        file_path.display()
+                    )
+                }
+            };
+
+            self.report_compile_error(message, vec![path_metadata]);
+        }
+    }
+
+    pub(super) fn expression_statement(&mut self) {
+        self.expression();
+        self.consume_expr_end();
+
+        match self.ast_generator.push_expr() {
+            Ok(_) => {}
+            Err((message, token)) => {
+                self.report_compile_error(message, token);
+                self.synchronize()
+            }
+        }
+    }
+
+    pub(super) fn expression(&mut self) {
+        self.parse_precedence(Precedence::PrecAssignment, None);
+    }
+
+    pub(super) fn parse_precedence(&mut self, precedence: Precedence, msg: Option<&str>) {
+        self.advance();
+
+        let parse_rule = self.get_parse_rule(self.get_previous().get_ttype());
+
+        let prefix_rule = parse_rule.get_prefix();
+
+        if let Some(prefix_rule) = prefix_rule {
+            prefix_rule(self, RuleArg::Precedence(precedence));
+
+            loop {
+                let current_ttype = self.get_current().get_ttype();
+                let current_precedence = self.get_parse_rule(current_ttype).get_precedence();
+
+                if (*current_precedence as usize) < (precedence as usize) {
+                    break;
+                }
+
+                if self.get_previous().get_line() < self.get_current().get_line() {
+                    break;
+                }
+
+                // After an error, e.g. in `import`, the rest is skipped when synchronizing
+                if self.panic_mode {
+                    break;
+                }
+
+                self.advance();
+
+                let infix_rule = self.get_parse_rule(self.get_previous().get_ttype()).get_infix();
+
+                if let Some(infix_rule) = infix_rule {
+                    infix_rule(self, RuleArg::Precedence(precedence));
+                }
+            }
+        } else {
+            self.report_compile_error(
+                format!(
+                    "Unexpected token: '{}' (no prefix rule)",
+                    self.get_previous().get_lexeme(self.source)
+                ),
+                vec![self.get_previous().get_metadata()]
+            )
+        }
+    }
+
+    pub(super) fn ident_lookup(&mut self) {
+        let token = self.get_previous();
+        let lexeme = token.get_lexeme(self.source);
+        let is_callee = matches!(
+            self.get_current().get_ttype(),
+            TokenType::TokenLeftParen | TokenType::TokenLess
+        );
+
+        let result = self.ast_generator.emit_identifier_lookup(
+            AstIdentifier::new(lexeme, token.get_metadata()),
+            !is_callee
+        );
+        if let Err((message, token_vec)) = result {
+            self.report_compile_error(message, token_vec);
+        }
+    }
+
+    pub(super) fn resolve_function_args(&mut self) -> Result<Vec<FunctionArgument>, ()> {
+        let mut args = vec![];
+
+        self.consume(TokenType::TokenLeftParen, "Expected '(' after function identifier");
+
+        while !self.is_at_expr_end() {
+            if self.get_current().get_ttype().is(&TokenType::TokenComma) {
+                self.advance();
+            }
+            if self.get_current().get_ttype().is(&TokenType::TokenRightParen) {
+                break;
+            }
+
+            if self.is_self_parameter_ahead() {
+                args.push(self.resolve_self_parameter(args.is_empty())?);
+                continue;
+            }
+
+            let is_ok = self.consume(
+                TokenType::TokenIdentifier,
+                format!(
+                    "Expected identifer but got '{}' Syntax: <identifier> <type>",
+                    self.get_current().get_lexeme(self.source)
+                ).as_str()
+            );
+            if !is_ok {
+                return Err(());
+            }
+
+            let ident_lexeme = self.get_previous().get_lexeme(&self.source);
+            if args.iter().any(|arg: &FunctionArgument| arg.name == ident_lexeme) {
+                self.report_compile_error(
+                    format!("Parameter '{}' is declared more than once", ident_lexeme),
+                    vec![self.get_previous().get_metadata()]
+                );
+                return Err(());
+            }
+
+            let is_mutable = match self.get_current().get_ttype().is(&TokenType::TokenMutable) {
+                true => {
+                    self.advance();
+                    true
+                }
+                false => false,
+            };
+
+            let arg_type = match self.resolve_type() {
+                Ok(arg_type) => arg_type,
+                Err(_) if self.panic_mode => {
+                    return Err(());
+                }
+                Err(error_tokens) => {
+                    self.report_compile_error("Invalid type".to_string(), error_tokens);
+                    return Err(());
+                }
+            };
+
+            args.push(FunctionArgument {
+                is_mutable,
+                name: ident_lexeme,
+                value_type: arg_type,
+            });
+        }
+
+        self.consume(TokenType::TokenRightParen, "Expected a closing ')' after function arguments");
+
+        Ok(args)
+    }
+
+    fn is_self_parameter_ahead(&self) -> bool {
+        match self.get_current().get_ttype() {
+            TokenType::TokenSelfValue => true,
+            TokenType::TokenMutable =>
+                matches!(
+                    self.get_next().map(|token| token.get_ttype()),
+                    Some(TokenType::TokenSelfValue)
+                ),
+            _ => false,
+        }
+    }
+
+    fn resolve_self_parameter(&mut self, is_first: bool) -> Result<FunctionArgument, ()> {
+        let is_mutable = self.get_current().get_ttype() == &TokenType::TokenMutable;
+        if is_mutable {
+            self.advance();
+        }
+        self.advance();
+        let self_metadata = self.get_previous().get_metadata();
+
+        let self_type = match self.ast_generator.get_method_self_type() {
+            Some(self_type) => self_type,
+            None => {
+                self.report_compile_error(
+                    "'self' can only be a parameter of a method".to_string(),
+                    vec![self_metadata]
+                );
+                return Err(());
+            }
+        };
+
+        if !is_first {
+            self.report_compile_error(
+                "'self' must be the first parameter of a method".to_string(),
+                vec![self_metadata]
+            );
+            return Err(());
+        }
+
+        Ok(FunctionArgument {
+            is_mutable,
+            name: "self".to_string(),
+            value_type: self_type,
+        })
+    }
+
+    // `max<i32>(a, b)` or `Box<i32>()`, with '<' next
+    pub(super) fn generic_call(&mut self) {
+        self.ident_lookup();
+        self.advance();
+
+        let typings = match self.typing_arguments() {
+            Ok(typings) => typings,
+            Err(error_tokens) => {
+                if !self.panic_mode {
+                    self.report_compile_error("Invalid type arguments".to_string(), error_tokens);
+                }
+                return;
+            }
+        };
+
+        let mut type_args = Vec::new();
+        for typing in &typings {
+            match self.ast_generator.resolve_typing(typing) {
+                Ok(value_type) => type_args.push(value_type),
+                Err((message, token_vec)) => {
+                    self.report_compile_error(message, token_vec);
+                    return;
+                }
+            }
+        }
+
+        if !self.consume(TokenType::TokenLeftParen, "Expected '(' after type arguments") {
+            return;
+        }
+        let paren_metadata = self.get_previous().get_metadata();
+        let arg_count = self.call_arguments();
+
+        let result = self.ast_generator.emit_call(arg_count, paren_metadata, Some(type_args));
+        if let Err((message, token_vec)) = result {
+            self.report_compile_error(message, token_vec);
+        }
+    }
+
+    pub(super) fn call_arguments(&mut self) -> usize {
+        let mut arg_count = 0;
+
+        if self.get_current().get_ttype() != &TokenType::TokenRightParen {
+            loop {
+                self.expression();
+                arg_count += 1;
+
+                if self.get_current().get_ttype() != &TokenType::TokenComma {
+                    break;
+                }
+                self.advance();
+            }
+        }
+
+        self.consume(TokenType::TokenRightParen, "Expected ')' after arguments");
+
+        arg_count
+    }
+
+    pub(super) fn function_definition(&mut self, is_public: bool) {
+        if !self.consume(TokenType::TokenIdentifier, "Expected function name after 'fn'") {
+            return;
+        }
+        let lexeme = self.get_previous().get_lexeme(self.source);
+        let name_metadata = self.get_previous().get_metadata();
+
+        let is_method = self.ast_generator.get_method_self_type().is_some();
+
+        let type_params = match self.type_parameters() {
+            Ok(type_params) => type_params,
+            Err(_) => {
+                return;
+            }
+        };
+        if is_method && !type_params.is_empty() {
+            self.report_compile_error(
+                "Methods cannot have type parameters of their own, only their class can".to_string(),
+                vec![type_params[0].name.token_metadata]
+            );
+            return;
+        }
+
+        // Invalid type parameters are reported after the body, so the function is still parsed
+        let result = self.ast_generator.start_type_params(type_params);
+        self.function_signature_and_body(lexeme, name_metadata, is_method, is_public);
+        let deferred_errors = self.ast_generator.end_type_params();
+
+        if let Err((message, token_vec)) = result {
+            self.report_compile_error(message, token_vec);
+        }
+        for (message, token_vec) in deferred_errors {
+            self.report_compile_error(message, token_vec);
+        }
+    }
+
+    fn function_signature_and_body(
+        &mut self,
+        lexeme: String,
+        name_metadata: TokenMetadata,
+        is_method: bool,
+        is_public: bool
+    ) {
+        let function_args = match self.resolve_function_args() {
+            Ok(v) => v,
+            Err(_) => {
+                return;
+            }
+        };
+
+        if is_method && function_args.first().map_or(true, |arg| arg.name != "self") {
+            self.report_compile_error(
+                "Methods must take 'self' or 'mut self' as their first parameter".to_string(),
+                vec![name_metadata]
+            );
+            return;
+        }
+
+        let return_type = match self.resolve_function_return_type() {
+            Ok(v) => v,
+            Err(_) => {
+                return;
+            }
+        };
+
+        self.start_function(lexeme, name_metadata, function_args, return_type, is_public);
+
+        self.consume(TokenType::TokenLeftCurlyBrace, "Expected '{' before function body");
+        self.block_statements("Expected '}' after function body");
+
+        self.end_function();
+    }
+
+    pub(super) fn closure(&mut self, fn_metadata: TokenMetadata) {
+        let args = match self.resolve_function_args() {
+            Ok(v) => v,
+            Err(_) => {
+                return;
+            }
+        };
+
+        let return_type = match self.resolve_function_return_type() {
+            Ok(v) => v,
+            Err(_) => {
+                return;
+            }
+        };
+
+        self.ast_generator.start_closure(fn_metadata, args, return_type);
+
+        self.consume(TokenType::TokenLeftCurlyBrace, "Expected '{' before closure body");
+        self.block_statements("Expected '}' after closure body");
+
+        if let Err((message, token_vec)) = self.ast_generator.end_closure() {
+            self.report_compile_error(message, token_vec);
+        }
+    }
+
+    pub(super) fn class_definition(&mut self) {
+        if !self.consume(TokenType::TokenIdentifier, "Expected class name after 'class'") {
+            return;
+        }
+        let name = self.get_previous().get_lexeme(self.source);
+        let name_metadata = self.get_previous().get_metadata();
+
+        let type_params = match self.type_parameters() {
+            Ok(type_params) => type_params,
+            Err(_) => {
+                return;
+            }
+        };
+
+        if !self.consume(TokenType::TokenLeftCurlyBrace, "Expected '{' after class name") {
+            return;
+        }
+
+        let type_params_result = self.ast_generator.start_type_params(type_params);
+        if let Err((message, token_vec)) = self.ast_generator.start_class(name, name_metadata) {
+            self.report_compile_error(message, token_vec);
+        }
+
+        while
+            !self.is_at_end() &&
+            !matches!(self.get_current().get_ttype(), &TokenType::TokenRightCurlyBrace)
+        {
+            let is_public = self.get_current().get_ttype() == &TokenType::TokenPublic;
+            if is_public {
+                self.advance();
+            }
+
+            match self.get_current().get_ttype() {
+                TokenType::TokenFunction => {
+                    self.advance();
+                    self.function_definition(is_public);
+                }
+                TokenType::TokenIdentifier => {
+                    self.advance();
+                    self.field_definition(is_public);
+                }
+                _ => {
+                    self.report_compile_error(
+                        format!(
+                            "Expected a field or method in class body but got '{}'",
+                            self.get_current().get_lexeme(self.source)
+                        ),
+                        vec![self.get_current().get_metadata()]
+                    );
+                    self.advance();
+                }
+            }
+
+            if self.panic_mode {
+                self.synchronize();
+            }
+        }
+
+        self.consume(TokenType::TokenRightCurlyBrace, "Expected '}' after class body");
+
+        self.ast_generator.end_class();
+        let deferred_errors = self.ast_generator.end_type_params();
+
+        if let Err((message, token_vec)) = type_params_result {
+            self.report_compile_error(message, token_vec);
+        }
+        for (message, token_vec) in deferred_errors {
+            self.report_compile_error(message, token_vec);
+        }
+    }
+
+    fn field_definition(&mut self, is_public: bool) {
+        let name = self.get_previous().get_lexeme(self.source);
+        let name_metadata = self.get_previous().get_metadata();
+
+        let value_type = match self.resolve_type() {
+            Ok(value_type) => value_type,
+            Err(_) if self.panic_mode => {
+                return;
+            }
+            Err(error_tokens) => {
+                self.report_compile_error(
+                    format!("Expected type of field '{}'", name),
+                    error_tokens
+                );
+                return;
+            }
+        };
+
+        if !self.consume(TokenType::TokenColon, "Expected ':' and a default value after field type") {
+            return;
+        }
+        if self.is_at_expr_end() {
+            self.report_compile_error(
+                format!("Expected a default value for field '{}'", name),
+                vec![self.get_previous().get_metadata()]
+            );
+            return;
+        }
+        self.parse_precedence(Precedence::PrecAssignment.get_next(), None);
+        self.consume_expr_end();
+
+        if self.panic_mode {
+            return;
+        }
+
+        let result = self.ast_generator.emit_field(name, name_metadata, value_type, is_public);
+        if let Err((message, token_vec)) = result {
+            self.report_compile_error(message, token_vec);
+        }
+    }
+
+    pub(super) fn enum_definition(&mut self) {
+        if !self.consume(TokenType::TokenIdentifier, "Expected enum name after 'enum'") {
+            return;
+        }
+        let name = self.get_previous().get_lexeme(self.source);
+        let name_metadata = self.get_previous().get_metadata();
+
+        if !self.consume(TokenType::TokenLeftCurlyBrace, "Expected '{' after enum name") {
+            return;
+        }
+
+        if let Err((message, token_vec)) = self.ast_generator.start_enum(name, name_metadata) {
+            self.report_compile_error(message, token_vec);
+        }
+
+        while
+            !self.is_at_end() &&
+            !matches!(self.get_current().get_ttype(), &TokenType::TokenRightCurlyBrace)
+        {
+            self.variant_definition();
+
+            if self.get_current().get_ttype() == &TokenType::TokenComma {
+                self.advance();
+            }
+            if self.panic_mode {
+                self.synchronize();
+            }
+        }
+
+        self.consume(TokenType::TokenRightCurlyBrace, "Expected '}' after enum body");
+
+        self.ast_generator.end_enum();
+    }
+
+    pub(super) fn trait_definition(&mut self) {
+        if !self.consume(TokenType::TokenIdentifier, "Expected trait name after 'trait'") {
+            return;
+        }
+        let name = self.get_previous().get_lexeme(self.source);
+        let name_metadata = self.get_previous().get_metadata();
+
+        if !self.consume(TokenType::TokenLeftCurlyBrace, "Expected '{' after trait name") {
+            return;
+        }
+
+        if let Err((message, token_vec)) = self.ast_generator.start_trait(name, name_metadata) {
+            self.report_compile_error(message, token_vec);
+            self.exit_panic_mode();
+        }
+
+        while
+            !self.is_at_end() &&
+            !matches!(self.get_current().get_ttype(), &TokenType::TokenRightCurlyBrace)
+        {
+            match self.get_current().get_ttype() {
+                TokenType::TokenFunction => {
+                    self.advance();
+                    self.trait_method_declaration();
+                }
+                _ => {
+                    self.report_compile_error(
+                        format!(
+                            "Expected a method declaration in trait body but got '{}'",
+                            self.get_current().get_lexeme(self.source)
+                        ),
+                        vec![self.get_current().get_metadata()]
+                    );
+                    self.advance();
+                }
+            }
+
+            if self.panic_mode {
+                self.synchronize();
+            }
+        }
+
+        self.consume(TokenType::TokenRightCurlyBrace, "Expected '}' after trait body");
+
+        self.ast_generator.end_trait();
+    }
+
+    fn trait_method_declaration(&mut self) {
+        if !self.consume(TokenType::TokenIdentifier, "Expected method name after 'fn'") {
+            return;
+        }
+        let name = self.get_previous().get_lexeme(self.source);
+        let name_metadata = self.get_previous().get_metadata();
+
+        if self.get_current().get_ttype() == &TokenType::TokenLess {
+            self.report_compile_error(
+                "Trait methods cannot have type parameters".to_string(),
+                vec![self.get_current().get_metadata()]
+            );
+            return;
+        }
+
+        let args = match self.resolve_function_args() {
+            Ok(args) => args,
+            Err(_) => {
+                return;
+            }
+        };
+
+        if args.first().is_none_or(|arg| arg.name != "self") {
+            self.report_compile_error(
+                "Methods must take 'self' or 'mut self' as their first parameter".to_string(),
+                vec![name_metadata]
+            );
+            return;
+        }
+
+        let is_at_declaration_end =
+            self.is_at_expr_end() ||
+            self.get_current().get_ttype() == &TokenType::TokenRightCurlyBrace;
+
+        let return_type = if is_at_declaration_end {
+            None
+        } else {
+            match self.resolve_function_return_type() {
+                Ok(return_type) => return_type,
+                Err(_) => {
+                    return;
+                }
+            }
+        };
+
+        if self.get_current().get_ttype() == &TokenType::TokenLeftCurlyBrace {
+            self.report_compile_error(
+                "Trait methods are declared without a body".to_string(),
+                vec![self.get_current().get_metadata()]
+            );
+            return;
+        }
+
+        let result = self.ast_generator.emit_trait_method(name, name_metadata, args, return_type);
+        if let Err((message, token_vec)) = result {
+            self.report_compile_error(message, token_vec);
+        }
+    }
+
+    pub(super) fn impl_definition(&mut self) {
+        if !self.consume(TokenType::TokenIdentifier, "Expected trait name after 'impl'") {
+            return;
+        }
+        let token = self.get_previous();
+        let trait_name = AstIdentifier::new(token.get_lexeme(self.source), token.get_metadata());
+
+        if !self.consume(TokenType::TokenFor, "Expected 'for' after trait name") {
+            return;
+        }
+
+        let target_metadata = self.get_current().get_metadata();
+        let self_type = match self.resolve_type() {
+            Ok(self_type) => self_type,
+            Err(_) if self.panic_mode => {
+                return;
+            }
+            Err(error_tokens) => {
+                self.report_compile_error(
+                    "Expected the type implementing the trait".to_string(),
+                    error_tokens
+                );
+                return;
+            }
+        };
+
+        if !self.consume(TokenType::TokenLeftCurlyBrace, "Expected '{' after the implementing type") {
+            return;
+        }
+
+        let result = self.ast_generator.start_impl(trait_name, self_type, target_metadata);
+        if let Err((message, token_vec)) = result {
+            // The methods are still parsed, since the parser isn't out of sync
+            self.report_compile_error(message, token_vec);
+            self.exit_panic_mode();
+        }
+
+        while
+            !self.is_at_end() &&
+            !matches!(self.get_current().get_ttype(), &TokenType::TokenRightCurlyBrace)
+        {
+            match self.get_current().get_ttype() {
+                TokenType::TokenFunction => {
+                    self.advance();
+                    self.function_definition(false);
+                }
+                _ => {
+                    self.report_compile_error(
+                        format!(
+                            "Expected a method in trait implementation but got '{}'",
+                            self.get_current().get_lexeme(self.source)
+                        ),
+                        vec![self.get_current().get_metadata()]
+                    );
+                    self.advance();
+                }
+            }
+
+            if self.panic_mode {
+                self.synchronize();
+            }
+        }
+
+        self.consume(TokenType::TokenRightCurlyBrace, "Expected '}' after trait implementation");
+
+        if let Err((message, token_vec)) = self.ast_generator.end_impl() {
+            self.report_compile_error(message, token_vec);
+        }
+    }
+
+    fn variant_definition(&mut self) {
+        if self.get_current().get_ttype() != &TokenType::TokenIdentifier {
+            self.report_compile_error(
+                format!(
+                    "Expected a variant in enum body but got '{}'",
+                    self.get_current().get_lexeme(self.source)
+                ),
+                vec![self.get_current().get_metadata()]
+            );
+            self.advance();
+            return;
+        }
+        self.advance();
+        let name = self.get_previous().get_lexeme(self.source);
+        let name_metadata = self.get_previous().get_metadata();
+
+        let mut payload_types = Vec::new();
+        if self.get_current().get_ttype() == &TokenType::TokenLeftParen {
+            self.advance();
+
+            loop {
+                match self.resolve_type() {
+                    Ok(value_type) => payload_types.push(value_type),
+                    Err(_) if self.panic_mode => {
+                        return;
+                    }
+                    Err(error_tokens) => {
+                        self.report_compile_error(
+                            format!("Expected a type in the payload of variant '{}'", name),
+                            error_tokens
+                        );
+                        return;
+                    }
+                }
+
+                if self.get_current().get_ttype() != &TokenType::TokenComma {
+                    break;
+                }
+                self.advance();
+            }
+
+            if !self.consume(TokenType::TokenRightParen, "Expected ')' after variant payload") {
+                return;
+            }
+        }
+
+        let result = self.ast_generator.emit_enum_variant(name, name_metadata, payload_types);
+        if let Err((message, token_vec)) = result {
+            self.report_compile_error(message, token_vec);
+        }
+    }
+
+    pub(super) fn match_arm(&mut self) {
+        if self.get_current().get_ttype() != &TokenType::TokenIdentifier {
+            self.report_compile_error(
+                format!(
+                    "Expected a match pattern but got '{}'",
+                    self.get_current().get_lexeme(self.source)
+                ),
+                vec![self.get_current().get_metadata()]
+            );
+            self.advance();
+            return;
+        }
+        self.advance();
+        let pattern_metadata = self.get_previous().get_metadata();
+        let mut variant = {
+            let token = self.get_previous();
+            AstIdentifier::new(token.get_lexeme(self.source), token.get_metadata())
+        };
+
+        let mut enum_name = None;
+        if self.get_current().get_ttype() == &TokenType::TokenDot {
+            self.advance();
+            if !self.consume(TokenType::TokenIdentifier, "Expected variant name after '.'") {
+                return;
+            }
+            let token = self.get_previous();
+            enum_name = Some(variant);
+            variant = AstIdentifier::new(token.get_lexeme(self.source), token.get_metadata());
+        }
+
+        let mut bindings = Vec::new();
+        if self.get_current().get_ttype() == &TokenType::TokenLeftParen {
+            self.advance();
+
+            while self.get_current().get_ttype() != &TokenType::TokenRightParen {
+                if !self.consume(TokenType::TokenIdentifier, "Expected a name to bind the value to") {
+                    return;
+                }
+                let token = self.get_previous();
+                bindings.push(AstIdentifier::new(token.get_lexeme(self.source), token.get_metadata()));
+
+                if self.get_current().get_ttype() != &TokenType::TokenComma {
+                    break;
+                }
+                self.advance();
+            }
+
+            if !self.consume(TokenType::TokenRightParen, "Expected ')' after pattern bindings") {
+                return;
+            }
+        }
+
+        let variant = if enum_name.is_none() && variant.lexeme == "_" && bindings.is_empty() {
+            None
+        } else {
+            Some(variant)
+        };
+
+        let result = self.ast_generator.start_match_arm(
+            enum_name,
+            variant,
+            bindings,
+            pattern_metadata
+        );
+        if let Err((message, token_vec)) = result {
+            self.report_compile_error(message, token_vec);
+        }
+
+        if self.consume(TokenType::TokenFatArrow, "Expected '=>' after match pattern") {
+            self.parse_precedence(Precedence::PrecAssignment.get_next(), None);
+        }
+
+        let result = self.ast_generator.end_match_arm();
+        if let Err((message, token_vec)) = result {
+            if !self.panic_mode {
+                self.report_compile_error(message, token_vec);
+            }
+        }
+    }
+
+    // Also stops at the ',' or '}' ending the arm, since arms can share a line
+    pub(super) fn synchronize_match_arm(&mut self) {
+        self.exit_panic_mode();
+
+        while !self.is_at_end() {
+            if self.get_previous().get_line() < self.get_current().get_line() {
+                return;
+            }
+
+            match self.get_current().get_ttype() {
+                TokenType::TokenComma | TokenType::TokenRightCurlyBrace => {
+                    return;
+                }
+                _ => self.advance(),
+            }
+        }
+    }
+
+    // Temporary functions
+    fn consume_type(&mut self, msg: &str) -> Option<&TokenType> {
+        if matches!(self.get_current().get_ttype(), TokenType::TokenInt32 | TokenType::TokenBool) {
+            Some(self.get_current().get_ttype())
+        } else {
+            self.report_compile_error(msg.to_string(), vec![self.get_current().get_metadata()]);
+            None
+        }
+    }
+
+    fn consume_identifier(&mut self, msg: &str) -> Option<String> {
+        if matches!(self.get_current().get_ttype(), TokenType::TokenIdentifier) {
+            Some(self.get_current().get_lexeme(self.source))
         } else {
             self.report_compile_error(msg.to_string(), vec![self.get_current().get_metadata()]);
             None
@@ -366,12 +1641,21 @@ impl<'a> Parser<'a> {
 
     pub(super) fn resolve_function_return_type(&mut self) -> Result<Option<ValueType>, ()> {
         let return_type = match self.get_current().get_ttype() {
-            TokenType::TokenLeftCurlyBrace => {
+            TokenType::TokenLeftCurlyBrace if !self.is_map_type_ahead() => {
                 return Ok(None);
             }
-            TokenType::TokenIdentifier =>
+            | TokenType::TokenLeftCurlyBrace
+            | TokenType::TokenIdentifier
+            | TokenType::TokenLeftSquareBracket
+            | TokenType::TokenLeftParen
+            | TokenType::TokenFunction
+            | TokenType::TokenSelfType =>
                 match self.resolve_type() {
-                    Ok(return_type) => return_type,
+                    Ok(return_type) => Some(return_type),
+                    // Unknown types are already reported
+                    Err(_) if self.panic_mode => {
+                        return Err(());
+                    }
                     Err(error_tokens) => {
                         self.report_compile_error(
                             "Invalid function return type".to_string(),
@@ -398,6 +1682,9 @@ impl<'a> Parser<'a> {
     pub(super) fn synchronize(&mut self) {
         self.exit_panic_mode();
 
+        // Blocks opened on the line that is skipped
+        let mut block_depth = 0;
+
         while !self.is_at_end() {
             if self.get_previous().get_ttype() == &TokenType::TokenSemicolon {
                 return;
@@ -416,6 +1703,18 @@ impl<'a> Parser<'a> {
                     );
                     self.advance();
                 }
+                TokenType::TokenLeftCurlyBrace => {
+                    block_depth += 1;
+                    self.advance();
+                }
+                // The end of the block the statement is in, like in `fn f() { g() }`
+                TokenType::TokenRightCurlyBrace if block_depth == 0 => {
+                    return;
+                }
+                TokenType::TokenRightCurlyBrace => {
+                    block_depth -= 1;
+                    self.advance();
+                }
                 _ => self.advance(),
             }
         }
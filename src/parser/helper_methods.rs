@@ -4,7 +4,13 @@ use crate::{
     value::ValueType,
 };
 
-use super::{ parse_rule::{ ParseRule, PARSE_RULES }, token::TokenMetadata, Parser };
+use super::{
+    parse_rule::{ ParseRule, PARSE_RULES },
+    precedence::Precedence,
+    token::TokenMetadata,
+    Parser,
+    RuleArg,
+};
 
 impl<'a> Parser<'a> {
     pub(super) fn advance(&mut self) {
@@ -105,7 +111,8 @@ impl<'a> Parser<'a> {
     pub(super) fn consume_expr_end(&mut self) {
         match self.get_current().get_ttype() {
             TokenType::TokenSemicolon => self.advance(),
-            TokenType::TokenEOF => {}
+            // The closing brace of a one-line block, e.g. `if a { break }`
+            TokenType::TokenEOF | TokenType::TokenRightCurlyBrace => {}
             _ => {
                 let prev_line = self.get_previous().get_line();
 
@@ -249,18 +256,163 @@ impl<'a> Parser<'a> {
     pub(super) fn start_function(
         &mut self,
         name: String,
+        name_metadata: TokenMetadata,
         args: Vec<FunctionArgument>,
-        return_type: Option<ValueType>
+        return_type: Option<ValueType>,
+        is_public: bool
     ) {
-        self.ast_generator.start_function(name, args, return_type);
+        let result = self.ast_generator.start_function(name, name_metadata, args, return_type, is_public);
+        if let Err((message, token_vec)) = result {
+            self.report_compile_error(message, token_vec);
+        }
     }
 
     pub(super) fn end_function(&mut self) {
-        self.ast_generator.end_function();
+        if let Err((message, token_vec)) = self.ast_generator.end_function() {
+            self.report_compile_error(message, token_vec);
+        }
+    }
+
+    // Keywords like `if` are prefix rules, but are only valid as statements
+    pub(super) fn check_statement_context(
+        &mut self,
+        rule_arg: &RuleArg,
+        keyword: &str,
+        keyword_metadata: TokenMetadata
+    ) -> bool {
+        if rule_arg != &RuleArg::Precedence(Precedence::PrecAssignment) {
+            self.report_compile_error(
+                format!("'{}' cannot be used as an expression", keyword),
+                vec![keyword_metadata]
+            );
+            return false;
+        }
+
+        true
+    }
+
+    // Tells `a [i32] := []` apart from an index, a call or the block after a condition
+    pub(super) fn is_bracketed_type_ahead(&self) -> bool {
+        let (open, close) = match self.get_current().get_ttype() {
+            TokenType::TokenLeftParen => (TokenType::TokenLeftParen, TokenType::TokenRightParen),
+            // Checking this first avoids scanning every block
+            TokenType::TokenLeftCurlyBrace if !self.is_map_type_ahead() => {
+                return false;
+            }
+            TokenType::TokenLeftCurlyBrace => {
+                (TokenType::TokenLeftCurlyBrace, TokenType::TokenRightCurlyBrace)
+            }
+            _ => (TokenType::TokenLeftSquareBracket, TokenType::TokenRightSquareBracket),
+        };
+
+        let mut lexer = self.lexer.clone();
+        let tokens = self
+            .get_next()
+            .cloned()
+            .into_iter()
+            .chain(std::iter::from_fn(move || lexer.scan_token()));
+
+        Self::is_define_after_close(tokens, open, close)
+    }
+
+    pub(super) fn is_at_interpolation_end(&self) -> bool {
+        let token = self.get_current();
+
+        matches!(token.get_ttype(), TokenType::TokenString | TokenType::TokenStringInterpolation) &&
+            token.get_lexeme(self.source).starts_with('}')
+    }
+
+    // Map keys are named types, and no statement starts with a name followed by ':'
+    pub(super) fn is_map_type_ahead(&self) -> bool {
+        self.get_current().get_ttype() == &TokenType::TokenLeftCurlyBrace &&
+            self.get_next().map(|token| *token.get_ttype()) == Some(TokenType::TokenIdentifier) &&
+            self.lexer.clone().scan_token().map(|token| *token.get_ttype()) == Some(TokenType::TokenColon)
+    }
+
+    pub(super) fn is_destructuring_ahead(&self) -> bool {
+        let mut lexer = self.lexer.clone();
+        let tokens = std::iter
+            ::once(self.get_current().clone())
+            .chain(self.get_next().cloned())
+            .chain(std::iter::from_fn(move || lexer.scan_token()));
+
+        Self::is_define_after_close(tokens, TokenType::TokenLeftParen, TokenType::TokenRightParen)
+    }
+
+    // Scans with a copy of the lexer, so nothing is consumed
+    fn is_define_after_close(
+        mut tokens: impl Iterator<Item = Token>,
+        open: TokenType,
+        close: TokenType
+    ) -> bool {
+        let mut depth = 1;
+
+        while let Some(token) = tokens.next() {
+            match token.get_ttype() {
+                ttype if ttype == &open => {
+                    depth += 1;
+                }
+                ttype if ttype == &close => {
+                    depth -= 1;
+
+                    if depth == 0 {
+                        let mut after = tokens.next();
+                        if matches!(after.as_ref().map(|token| *token.get_ttype()), Some(TokenType::TokenQuestion)) {
+                            after = tokens.next();
+                        }
+
+                        return matches!(
+                            after.as_ref().map(|token| *token.get_ttype()),
+                            Some(TokenType::TokenDefine)
+                        );
+                    }
+                }
+                TokenType::TokenSemicolon | TokenType::TokenEOF => {
+                    return false;
+                }
+                _ => {}
+            }
+        }
+
+        false
+    }
+
+    pub(super) fn resolve_escape_sequences(raw_string: &str) -> Result<String, String> {
+        let mut string = String::with_capacity(raw_string.len());
+        let mut chars = raw_string.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                string.push(c);
+                continue;
+            }
+
+            match chars.next() {
+                Some('n') => string.push('\n'),
+                Some('t') => string.push('\t'),
+                Some('r') => string.push('\r'),
+                Some('0') => string.push('\0'),
+                Some('\\') => string.push('\\'),
+                Some('"') => string.push('"'),
+                // Braces are escaped so they aren't taken for an interpolation
+                Some('{') => string.push('{'),
+                Some('}') => string.push('}'),
+                Some(c) => {
+                    return Err(format!("Invalid escape sequence: '\\{}'", c));
+                }
+                None => {
+                    return Err("Expected escape sequence after '\\'".to_string());
+                }
+            }
+        }
+
+        Ok(string)
     }
 
     pub(super) fn report_compile_error(&mut self, message: String, token: Vec<TokenMetadata>) {
-        self.error_handler.report_compile_error(message, token);
+        if !self.is_hoisting {
+            self.error_handler.report_compile_error(message, token);
+        }
         self.enter_panic_mode();
     }
 }
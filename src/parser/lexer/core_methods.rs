@@ -1,6 +1,6 @@
 use crate::parser::token::{ Token, token_type::TokenType };
 
-use super::Lexer;
+use super::{ lexer_util::is_digit, Lexer };
 
 impl<'a> Lexer<'a> {
     pub(super) fn make_eof_token(&mut self) -> Option<Token> {
@@ -9,30 +9,140 @@ impl<'a> Lexer<'a> {
     }
 
     pub(super) fn make_token(&mut self, ttype: TokenType) -> Option<Token> {
-        Some(Token::new(ttype, self.start, self.current - self.start, self.line))
+        Some(Token::new(ttype, self.start, self.current - self.start, self.line, self.file_id))
+    }
+
+    // A fraction needs a digit after the '.', and none after a lone '.', for `0..10` and `pair.0.1`
+    pub(super) fn make_number_token(&mut self) -> Option<Token> {
+        self.consume_digits();
+
+        let is_tuple_index =
+            self.start > 0 &&
+            self.get_character(self.start - 1) == &'.' &&
+            (self.start < 2 || self.get_character(self.start - 2) != &'.');
+        if is_tuple_index {
+            return self.make_token(TokenType::TokenNumber);
+        }
+
+        if self.is(0, '.') && is_digit(self.peek(1)) {
+            self.advance();
+            self.consume_digits();
+        }
+
+        if self.is(0, 'e') || self.is(0, 'E') {
+            let has_sign = self.is(1, '+') || self.is(1, '-');
+            let digit_offset = if has_sign { 2 } else { 1 };
+
+            if is_digit(self.peek(digit_offset)) {
+                for _ in 0..digit_offset {
+                    self.advance();
+                }
+                self.consume_digits();
+            }
+        }
+
+        self.make_token(TokenType::TokenNumber)
+    }
+
+    fn consume_digits(&mut self) {
+        while !self.is_at_end() && is_digit(self.peek(0)) {
+            self.advance();
+        }
     }
 
     pub(super) fn make_identifier_token(&mut self) -> Option<Token> {
         let ttype = match self.get_character(self.start) {
+            'a' => self.check_keyword(1, 1, "s", TokenType::TokenAs),
             //'b' => self.check_keyword(1, 3, "ool", TokenType::TokenBool),
+            'b' => self.check_keyword(1, 4, "reak", TokenType::TokenBreak),
+            'c' => {
+                if self.current - self.start > 1 {
+                    match self.get_character(self.start + 1) {
+                        'l' => self.check_keyword(2, 3, "ass", TokenType::TokenClass),
+                        'o' => self.check_keyword(2, 6, "ntinue", TokenType::TokenContinue),
+                        _ => TokenType::TokenIdentifier,
+                    }
+                } else {
+                    TokenType::TokenIdentifier
+                }
+            }
+            'e' => {
+                if self.current - self.start > 1 {
+                    match self.get_character(self.start + 1) {
+                        'l' => self.check_keyword(2, 2, "se", TokenType::TokenElse),
+                        'n' => self.check_keyword(2, 2, "um", TokenType::TokenEnum),
+                        _ => TokenType::TokenIdentifier,
+                    }
+                } else {
+                    TokenType::TokenIdentifier
+                }
+            }
             'f' => {
                 if self.current - self.start > 1 {
                     match self.get_character(self.start + 1) {
                         'a' => self.check_keyword(2, 3, "lse", TokenType::TokenFalse),
-                        'n' => TokenType::TokenFunction,
+                        'n' => self.check_keyword(2, 0, "", TokenType::TokenFunction),
+                        'o' => self.check_keyword(2, 1, "r", TokenType::TokenFor),
+                        _ => TokenType::TokenIdentifier,
+                    }
+                } else {
+                    TokenType::TokenIdentifier
+                }
+            }
+            'i' => {
+                if self.current - self.start > 1 {
+                    match self.get_character(self.start + 1) {
+                        'f' => self.check_keyword(2, 0, "", TokenType::TokenIf),
+                        'm' if self.current - self.start > 3 => {
+                            match self.get_character(self.start + 3) {
+                                'l' => self.check_keyword(2, 2, "pl", TokenType::TokenImpl),
+                                'o' => self.check_keyword(2, 4, "port", TokenType::TokenImport),
+                                _ => TokenType::TokenIdentifier,
+                            }
+                        }
+                        'n' => self.check_keyword(2, 0, "", TokenType::TokenIn),
+                        _ => TokenType::TokenIdentifier,
+                    }
+                } else {
+                    TokenType::TokenIdentifier
+                }
+            }
+            'm' => {
+                if self.current - self.start > 1 {
+                    match self.get_character(self.start + 1) {
+                        'a' => self.check_keyword(2, 3, "tch", TokenType::TokenMatch),
+                        'u' => self.check_keyword(2, 1, "t", TokenType::TokenMutable),
+                        _ => TokenType::TokenIdentifier,
+                    }
+                } else {
+                    TokenType::TokenIdentifier
+                }
+            }
+            'n' => self.check_keyword(1, 3, "one", TokenType::TokenNoneValue),
+            'p' => {
+                if self.current - self.start > 1 {
+                    match self.get_character(self.start + 1) {
+                        'r' => self.check_keyword(2, 3, "int", TokenType::TokenPrint),
+                        'u' => self.check_keyword(2, 1, "b", TokenType::TokenPublic),
                         _ => TokenType::TokenIdentifier,
                     }
                 } else {
                     TokenType::TokenIdentifier
                 }
             }
-            //'i' => self.check_keyword(1, 2, "32", TokenType::TokenInt32),
-            'm' => self.check_keyword(1, 2, "ut", TokenType::TokenMutable),
             'r' => self.check_keyword(1, 5, "eturn", TokenType::TokenReturn),
+            's' => self.check_keyword(1, 3, "elf", TokenType::TokenSelfValue),
+            'S' => self.check_keyword(1, 3, "elf", TokenType::TokenSelfType),
             't' => {
                 if self.current - self.start > 1 {
                     match self.get_character(self.start + 1) {
-                        'r' => self.check_keyword(2, 2, "ue", TokenType::TokenTrue),
+                        'r' if self.current - self.start > 2 => {
+                            match self.get_character(self.start + 2) {
+                                'a' => self.check_keyword(3, 2, "it", TokenType::TokenTrait),
+                                'u' => self.check_keyword(3, 1, "e", TokenType::TokenTrue),
+                                _ => TokenType::TokenIdentifier,
+                            }
+                        }
                         'y' => self.check_keyword(2, 2, "pe", TokenType::TokenTyping),
                         _ => TokenType::TokenIdentifier,
                     }
@@ -40,6 +150,7 @@ impl<'a> Lexer<'a> {
                     TokenType::TokenIdentifier
                 }
             }
+            'w' => self.check_keyword(1, 4, "hile", TokenType::TokenWhile),
 
             _ => TokenType::TokenIdentifier,
         };
@@ -47,6 +158,36 @@ impl<'a> Lexer<'a> {
         self.make_token(ttype)
     }
 
+    // A '{' ends the token before an interpolation, whose tokens follow until the closing '}'
+    pub(super) fn make_string_token(&mut self) -> Option<Token> {
+        while !self.is_at_end() && !self.is(0, '"') && !self.is(0, '{') {
+            if self.is(0, '\\') {
+                self.advance();
+                if self.is_at_end() {
+                    break;
+                }
+            }
+            if self.is(0, '\n') {
+                self.line += 1;
+            }
+            self.advance();
+        }
+
+        if self.is_at_end() {
+            return self.make_error_token("Unterminated string".to_string());
+        }
+
+        if self.is(0, '{') {
+            self.advance();
+            self.interpolation_depths.push(0);
+            return self.make_token(TokenType::TokenStringInterpolation);
+        }
+
+        // The closing quote
+        self.advance();
+        self.make_token(TokenType::TokenString)
+    }
+
     pub(super) fn make_error_token(&mut self, message: String) -> Option<Token> {
         Some(
             Token::new_error(
@@ -54,6 +195,7 @@ impl<'a> Lexer<'a> {
                 self.start,
                 self.current - self.start,
                 self.line,
+                self.file_id,
                 message
             )
         )
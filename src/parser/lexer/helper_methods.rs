@@ -45,11 +45,16 @@ impl<'a> Lexer<'a> {
         rest: &str,
         ttype: TokenType
     ) -> TokenType {
+        // The length is checked first, since the keyword may reach past the end of the source
+        if self.current - self.start != start + length {
+            return TokenType::TokenIdentifier;
+        }
+
         let search_lexeme = self.source[self.start + start..self.start + start + length]
             .iter()
             .collect::<String>();
 
-        if self.current - self.start == start + length && search_lexeme == rest {
+        if search_lexeme == rest {
             ttype
         } else {
             TokenType::TokenIdentifier
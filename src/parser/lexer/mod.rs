@@ -6,20 +6,26 @@ mod lexer_util;
 
 use lexer_util::*;
 
+#[derive(Clone)]
 pub struct Lexer<'a> {
     source: &'a Vec<char>,
     start: usize,
     current: usize,
     line: usize,
+    file_id: usize,
+    // The unclosed '{' in each interpolation, so its closing '}' can be told apart
+    interpolation_depths: Vec<usize>,
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(source: &'a Vec<char>) -> Self {
+    pub fn new(source: &'a Vec<char>, file_id: usize) -> Self {
         Self {
             source,
             start: 0,
             current: 0,
             line: 1,
+            file_id,
+            interpolation_depths: Vec::new(),
         }
     }
 
@@ -27,6 +33,7 @@ impl<'a> Lexer<'a> {
         self.start = 0;
         self.current = 0;
         self.line = 1;
+        self.interpolation_depths.clear();
     }
 
     #[profiler::function_tracker]
@@ -55,47 +62,153 @@ impl<'a> Lexer<'a> {
         match c {
             '(' => self.make_token(TokenType::TokenLeftParen),
             ')' => self.make_token(TokenType::TokenRightParen),
-            '{' => self.make_token(TokenType::TokenLeftCurlyBrace),
-            '}' => self.make_token(TokenType::TokenRightCurlyBrace),
+            '{' => {
+                if let Some(depth) = self.interpolation_depths.last_mut() {
+                    *depth += 1;
+                }
+                self.make_token(TokenType::TokenLeftCurlyBrace)
+            }
+            '}' => {
+                match self.interpolation_depths.last_mut() {
+                    // The end of an interpolation, so the rest of the string follows
+                    Some(0) => {
+                        self.interpolation_depths.pop();
+                        self.make_string_token()
+                    }
+                    Some(depth) => {
+                        *depth -= 1;
+                        self.make_token(TokenType::TokenRightCurlyBrace)
+                    }
+                    None => self.make_token(TokenType::TokenRightCurlyBrace),
+                }
+            }
             '[' => self.make_token(TokenType::TokenLeftSquareBracket),
             ']' => self.make_token(TokenType::TokenRightSquareBracket),
-            '+' => self.make_token(TokenType::TokenPlus),
-            '-' => self.make_token(TokenType::TokenMinus),
-            '*' => self.make_token(TokenType::TokenStar),
-            '/' => self.make_token(TokenType::TokenSlash),
+            '+' => {
+                if self.is(0, '=') {
+                    self.advance();
+                    self.make_token(TokenType::TokenPlusEqual)
+                } else {
+                    self.make_token(TokenType::TokenPlus)
+                }
+            }
+            '-' => {
+                if self.is(0, '=') {
+                    self.advance();
+                    self.make_token(TokenType::TokenMinusEqual)
+                } else {
+                    self.make_token(TokenType::TokenMinus)
+                }
+            }
+            '*' => {
+                if self.is(0, '*') {
+                    self.advance();
+                    self.make_token(TokenType::TokenStarStar)
+                } else if self.is(0, '=') {
+                    self.advance();
+                    self.make_token(TokenType::TokenStarEqual)
+                } else {
+                    self.make_token(TokenType::TokenStar)
+                }
+            }
+            '/' => {
+                if self.is(0, '=') {
+                    self.advance();
+                    self.make_token(TokenType::TokenSlashEqual)
+                } else {
+                    self.make_token(TokenType::TokenSlash)
+                }
+            }
+            '%' => self.make_token(TokenType::TokenPercent),
+            '^' => self.make_token(TokenType::TokenCaret),
+            '~' => self.make_token(TokenType::TokenTilde),
             ';' => self.make_token(TokenType::TokenSemicolon),
+            '.' => {
+                if self.is(0, '.') {
+                    self.advance();
+                    self.make_token(TokenType::TokenDotDot)
+                } else {
+                    self.make_token(TokenType::TokenDot)
+                }
+            }
             ',' => self.make_token(TokenType::TokenComma),
+            '"' => self.make_string_token(),
             '=' => {
-                // if self.peek(0).unwrap() == '=' {
-                //     self.advance();
-                //     self.make_token(TokenType::TokenEqualEqual)
-                // } else {
-                self.make_token(TokenType::TokenAssign)
-                // }
+                if self.is(0, '=') {
+                    self.advance();
+                    self.make_token(TokenType::TokenEqualEqual)
+                } else if self.is(0, '>') {
+                    self.advance();
+                    self.make_token(TokenType::TokenFatArrow)
+                } else {
+                    self.make_token(TokenType::TokenAssign)
+                }
+            }
+            '<' => {
+                if self.is(0, '=') {
+                    self.advance();
+                    self.make_token(TokenType::TokenLessEqual)
+                } else if self.is(0, '<') {
+                    self.advance();
+                    self.make_token(TokenType::TokenLessLess)
+                } else {
+                    self.make_token(TokenType::TokenLess)
+                }
+            }
+            '>' => {
+                if self.is(0, '=') {
+                    self.advance();
+                    self.make_token(TokenType::TokenGreaterEqual)
+                } else if self.is(0, '>') {
+                    self.advance();
+                    self.make_token(TokenType::TokenGreaterGreater)
+                } else {
+                    self.make_token(TokenType::TokenGreater)
+                }
             }
             ':' => {
                 if self.is(0, '=') {
                     self.advance();
                     self.make_token(TokenType::TokenDefine)
                 } else {
-                    self.make_error_token(format!("Unexpected character: {}", c))
+                    self.make_token(TokenType::TokenColon)
+                }
+            }
+            '&' => {
+                if self.is(0, '&') {
+                    self.advance();
+                    self.make_token(TokenType::TokenAnd)
+                } else {
+                    self.make_token(TokenType::TokenAmpersand)
+                }
+            }
+            '|' => {
+                if self.is(0, '|') {
+                    self.advance();
+                    self.make_token(TokenType::TokenOr)
+                } else {
+                    self.make_token(TokenType::TokenPipe)
+                }
+            }
+            '?' => {
+                if self.is(0, '?') {
+                    self.advance();
+                    self.make_token(TokenType::TokenQuestionQuestion)
+                } else {
+                    self.make_token(TokenType::TokenQuestion)
                 }
             }
             '!' => {
-                // if self.peek(0).unwrap() == '=' {
-                //     self.advance();
-                //     self.make_token(TokenType::TokenBangEqual)
-                // } else {
-                self.make_token(TokenType::TokenBang)
-                // }
+                if self.is(0, '=') {
+                    self.advance();
+                    self.make_token(TokenType::TokenBangEqual)
+                } else {
+                    self.make_token(TokenType::TokenBang)
+                }
             }
             c => {
                 if is_digit(Some(c)) {
-                    while !self.is_at_end() && is_digit(self.peek(0)) {
-                        self.advance();
-                    }
-
-                    self.make_token(TokenType::TokenNumber)
+                    self.make_number_token()
                 } else if is_alphabetic(Some(c)) {
                     while
                         !self.is_at_end() &&
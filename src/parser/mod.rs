@@ -8,6 +8,8 @@ mod parse_rule_methods;
 pub mod ast_generator;
 mod lexer;
 
+use std::path::PathBuf;
+
 use crate::{ ast::Ast, error_handler::ErrorHandler, parser::{ lexer::Lexer, token::Token } };
 
 use self::{ ast_generator::AstGenerator, precedence::Precedence };
@@ -27,21 +29,40 @@ pub struct Parser<'a> {
     previous_tokens: Vec<Token>,
     had_error: bool,
     panic_mode: bool,
-    ast_generator: AstGenerator,
+    // Errors aren't reported while hoisting, since the definitions are parsed again
+    is_hoisting: bool,
+    file_id: usize,
+    module_stack: Vec<(PathBuf, usize)>,
+    // Boxed, since the generator is handed to the parsers of imported modules
+    ast_generator: Box<AstGenerator>,
     error_handler: &'a mut ErrorHandler,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(source: &'a Vec<char>, error_handler: &'a mut ErrorHandler) -> Self {
+    // Imports are resolved relative to the file, or to the working directory if it isn't registered
+    pub fn new(source: &'a Vec<char>, file_id: usize, error_handler: &'a mut ErrorHandler) -> Self {
+        Self::new_module(source, file_id, Vec::new(), Box::new(AstGenerator::new()), error_handler)
+    }
+
+    fn new_module(
+        source: &'a Vec<char>,
+        file_id: usize,
+        module_stack: Vec<(PathBuf, usize)>,
+        ast_generator: Box<AstGenerator>,
+        error_handler: &'a mut ErrorHandler
+    ) -> Self {
         Self {
-            lexer: Lexer::new(source),
+            lexer: Lexer::new(source, file_id),
             source,
             next: None,
             current: None,
             previous_tokens: Vec::with_capacity(64), // implement function to clear when: consume_expr_end
             had_error: false,
             panic_mode: false,
-            ast_generator: AstGenerator::new(),
+            is_hoisting: false,
+            file_id,
+            module_stack,
+            ast_generator,
             error_handler,
         }
     }
@@ -57,15 +78,17 @@ impl<'a> Parser<'a> {
 
     #[profiler::function_tracker]
     pub fn parse_to_ast(&mut self) -> Ast {
-        self.advance();
-        self.advance();
+        let main_path = self.error_handler
+            .get_file_path(self.file_id)
+            .and_then(|path| path.canonicalize().ok());
+        if let Some(path) = main_path {
+            self.module_stack.push((path, self.file_id));
+        }
 
-        while !self.is_at_end() {
-            self.statement();
+        self.parse_statements();
 
-            if self.panic_mode {
-                self.synchronize();
-            }
+        for (message, token_vec) in self.ast_generator.end_program() {
+            self.report_compile_error(message, token_vec);
         }
 
         let ast = self.ast_generator.get_ast();
@@ -77,4 +100,18 @@ impl<'a> Parser<'a> {
         self.free();
         ast
     }
+
+    fn parse_statements(&mut self) {
+        self.advance();
+        self.advance();
+        self.hoist_declarations();
+
+        while !self.is_at_end() {
+            self.statement();
+
+            if self.panic_mode {
+                self.synchronize();
+            }
+        }
+    }
 }
@@ -4,11 +4,11 @@ use crate::parser::{RuleArg, precedence::Precedence};
 use super::ParseRule;
 lazy_static! {
     pub static ref PARSE_RULES: Vec<ParseRule> = {
-        let mut parse_rules_vec = Vec::with_capacity(28);
+        let mut parse_rules_vec = Vec::with_capacity(74);
         parse_rules_vec.push(ParseRule {
             prefix: (Some(|c, arg| c.grouping(arg))),
-            infix: (None),
-            precedence: Precedence::PrecNone,
+            infix: (Some(|c, arg| c.call(arg))),
+            precedence: Precedence::PrecCall,
         });
         parse_rules_vec.push(ParseRule {
             prefix: (None),
@@ -25,6 +25,11 @@ lazy_static! {
             infix: (None),
             precedence: Precedence::PrecNone,
         });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.array(arg))),
+            infix: (Some(|c, arg| c.index(arg))),
+            precedence: Precedence::PrecCall,
+        });
         parse_rules_vec.push(ParseRule {
             prefix: (None),
             infix: (None),
@@ -35,16 +40,26 @@ lazy_static! {
             infix: (None),
             precedence: Precedence::PrecNone,
         });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.unary(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
         parse_rules_vec.push(ParseRule {
             prefix: (None),
             infix: (None),
             precedence: Precedence::PrecNone,
         });
         parse_rules_vec.push(ParseRule {
-            prefix: (Some(|c, arg| c.unary(arg))),
+            prefix: (None),
             infix: (None),
             precedence: Precedence::PrecNone,
         });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.dot(arg))),
+            precedence: Precedence::PrecCall,
+        });
         parse_rules_vec.push(ParseRule {
             prefix: (None),
             infix: (None),
@@ -75,11 +90,126 @@ lazy_static! {
             infix: (Some(|c, arg| c.binary(arg))),
             precedence: Precedence::PrecFactor,
         });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.binary(arg))),
+            precedence: Precedence::PrecFactor,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.binary(arg))),
+            precedence: Precedence::PrecPower,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.binary(arg))),
+            precedence: Precedence::PrecBitAnd,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.binary(arg))),
+            precedence: Precedence::PrecBitOr,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.binary(arg))),
+            precedence: Precedence::PrecBitXor,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.binary(arg))),
+            precedence: Precedence::PrecShift,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.binary(arg))),
+            precedence: Precedence::PrecShift,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.unary(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.binary(arg))),
+            precedence: Precedence::PrecEquality,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.binary(arg))),
+            precedence: Precedence::PrecEquality,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.binary(arg))),
+            precedence: Precedence::PrecComparison,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.binary(arg))),
+            precedence: Precedence::PrecComparison,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.binary(arg))),
+            precedence: Precedence::PrecComparison,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.binary(arg))),
+            precedence: Precedence::PrecComparison,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.logical(arg))),
+            precedence: Precedence::PrecAnd,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.logical(arg))),
+            precedence: Precedence::PrecOr,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (Some(|c, arg| c.logical(arg))),
+            precedence: Precedence::PrecCoalesce,
+        });
         parse_rules_vec.push(ParseRule {
             prefix: (Some(|c, arg| c.number(arg))),
             infix: (None),
             precedence: Precedence::PrecNone,
         });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.string(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.string_interpolation(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
         parse_rules_vec.push(ParseRule {
             prefix: (Some(|c, arg| c.identifier(arg))),
             infix: (None),
@@ -95,6 +225,21 @@ lazy_static! {
             infix: (None),
             precedence: Precedence::PrecNone,
         });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.literal(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
         parse_rules_vec.push(ParseRule {
             prefix: (None),
             infix: (None),
@@ -125,8 +270,93 @@ lazy_static! {
             infix: (None),
             precedence: Precedence::PrecNone,
         });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.print_statement(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.return_statement(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.if_statement(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.while_statement(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.for_statement(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (None),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
         parse_rules_vec.push(ParseRule {
             prefix: (None),
+            infix: (Some(|c, arg| c.cast(arg))),
+            precedence: Precedence::PrecCast,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.break_statement(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.continue_statement(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.class_statement(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.enum_statement(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.match_expression(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.trait_statement(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.impl_statement(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.import_statement(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.public(arg))),
+            infix: (None),
+            precedence: Precedence::PrecNone,
+        });
+        parse_rules_vec.push(ParseRule {
+            prefix: (Some(|c, arg| c.self_value(arg))),
             infix: (None),
             precedence: Precedence::PrecNone,
         });
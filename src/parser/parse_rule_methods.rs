@@ -1,7 +1,7 @@
 use crate::ast;
 use crate::ast::expr::{ AstIdentifier, AstValue, Expr };
 use crate::ast::stmt::TypeDefStmt;
-use crate::operations::{ BinaryOp, UnaryOp };
+use crate::operations::{ BinaryOp, LogicalOp, UnaryOp };
 use crate::value::Value;
 use crate::parser::token::token_type::TokenType::{ self, * };
 use super::precedence::Precedence::*;
@@ -13,16 +13,99 @@ impl<'a> Parser<'a> {
         let token = self.get_previous();
         let lexeme = token.get_lexeme(self.source);
 
-        match lexeme.parse::<i32>() {
-            Ok(int_value) => {
+        // Integers are i32 unless too large, and can get another type from how they're used
+        let value = if lexeme.contains(['.', 'e', 'E']) {
+            lexeme.parse::<f64>().ok().map(Value::Float64)
+        } else {
+            lexeme
+                .parse::<i32>()
+                .map(Value::Int32)
+                .or_else(|_| lexeme.parse::<i64>().map(Value::Int64))
+                .or_else(|_| lexeme.parse::<u64>().map(Value::UInt64))
+                .ok()
+        };
+
+        match value {
+            Some(value) => {
+                self.ast_generator.emit_constant_literal(AstValue::new(value, token.get_metadata()));
+            }
+            None => {
+                self.report_compile_error(
+                    format!("Number literal '{}' is too large", lexeme),
+                    vec![token.get_metadata()]
+                );
+            }
+        }
+    }
+
+    pub fn string(&mut self, _rule_arg: RuleArg) {
+        let token = self.get_previous();
+        let metadata = token.get_metadata();
+        let lexeme = token.get_lexeme(self.source);
+
+        // The lexeme includes the quotes
+        match Self::resolve_escape_sequences(&lexeme[1..lexeme.len() - 1]) {
+            Ok(string) => {
                 self.ast_generator.emit_constant_literal(
-                    AstValue::new(Value::Int32(int_value), token.get_metadata())
+                    AstValue::new(Value::String(string.into()), metadata)
                 );
             }
-            Err(e) => {
-                eprintln!("Could not parse i32. In c.number(RuleArg): {}", e);
+            Err(message) => self.report_compile_error(message, vec![metadata]),
+        }
+    }
+
+    // The lexer gives the text up to each '{', then the tokens of the interpolated expression
+    pub fn string_interpolation(&mut self, _rule_arg: RuleArg) {
+        let string_metadata = self.get_previous().get_metadata();
+        let mut part_count = 0;
+
+        loop {
+            let token = self.get_previous();
+            let metadata = token.get_metadata();
+            let is_last_part = token.get_ttype() == &TokenString;
+            let lexeme = token.get_lexeme(self.source);
+
+            // The text starts after '"' or '}' and ends before '{' or '"'
+            match Self::resolve_escape_sequences(&lexeme[1..lexeme.len() - 1]) {
+                Ok(string) if string.is_empty() => {}
+                Ok(string) => {
+                    self.ast_generator.emit_constant_literal(
+                        AstValue::new(Value::String(string.into()), metadata)
+                    );
+                    part_count += 1;
+                }
+                Err(message) => {
+                    self.report_compile_error(message, vec![metadata]);
+                    return;
+                }
+            }
+
+            if is_last_part {
+                break;
             }
+
+            if self.is_at_interpolation_end() {
+                self.report_compile_error(
+                    "Expected an expression inside '{}'".to_string(),
+                    vec![self.get_current().get_metadata()]
+                );
+                return;
+            }
+
+            self.expression();
+            part_count += 1;
+
+            if !self.is_at_interpolation_end() {
+                self.report_compile_error(
+                    "Expected '}' after interpolated expression".to_string(),
+                    vec![self.get_current().get_metadata()]
+                );
+                return;
+            }
+            self.advance();
         }
+
+        self.ast_generator.emit_interpolation(part_count, string_metadata);
     }
 
     pub fn mut_var_def(&mut self, rule_arg: RuleArg) {
@@ -33,7 +116,10 @@ impl<'a> Parser<'a> {
                 self.var_def(RuleArg::MutVar);
             }
             TokenFunction => {
-                panic!("Functions cannot be mutable");
+                self.report_compile_error(
+                    "Functions cannot be mutable".to_string(),
+                    vec![self.get_previous().get_metadata()]
+                );
             }
             _ => panic!("Unexpected: {}", self.get_current().get_lexeme(self.source)),
         }
@@ -44,9 +130,18 @@ impl<'a> Parser<'a> {
             true => self.ident_lookup(),
             false => {
                 match self.get_current().get_ttype() {
-                    TokenAssign => self.var_assign(),
-                    TokenIdentifier | TokenDefine => self.var_def(RuleArg::None),
-                    TokenLeftParen => println!("identifier: function"),
+                    TokenAssign | TokenPlusEqual | TokenMinusEqual | TokenStarEqual | TokenSlashEqual => {
+                        self.var_assign()
+                    }
+                    TokenIdentifier | TokenFunction | TokenDefine => self.var_def(RuleArg::None),
+                    TokenLeftSquareBracket | TokenLeftParen | TokenLeftCurlyBrace if
+                        self.is_bracketed_type_ahead()
+                    => {
+                        self.var_def(RuleArg::None)
+                    }
+                    TokenLess if self.ast_generator.is_generic(&self.get_previous().get_lexeme(self.source)) => {
+                        self.generic_call()
+                    }
                     _ => self.ident_lookup(),
                 }
             }
@@ -54,42 +149,203 @@ impl<'a> Parser<'a> {
     }
 
     pub fn block(&mut self, rule_arg: RuleArg) {
+        // Like a match arm `A => { ... }`. The block is still parsed to keep the parser in sync
+        if rule_arg != RuleArg::Precedence(PrecAssignment) {
+            self.report_compile_error(
+                "A block cannot be used as an expression".to_string(),
+                vec![self.get_previous().get_metadata()]
+            );
+            self.exit_panic_mode();
+        }
+
         self.start_scope();
+        self.block_statements("Expected '}' at the end of block");
+        self.end_scope()
+    }
+
+    pub fn if_statement(&mut self, rule_arg: RuleArg) {
+        let if_metadata = self.get_previous().get_metadata();
 
-        while
-            !self.is_at_end() &&
-            !matches!(self.get_current().get_ttype(), &TokenType::TokenRightCurlyBrace)
-        {
-            self.statement();
+        if !self.check_statement_context(&rule_arg, "if", if_metadata) {
+            return;
+        }
+
+        self.expression();
+
+        if let Err((message, token_vec)) = self.ast_generator.start_if(if_metadata) {
+            self.report_compile_error(message, token_vec);
+        }
+
+        self.consume(TokenLeftCurlyBrace, "Expected '{' after if condition");
+        self.block_statements("Expected '}' at the end of if block");
+
+        if self.get_current().get_ttype() == &TokenElse {
+            self.advance();
+            self.ast_generator.start_else();
+
+            if self.get_current().get_ttype() == &TokenIf {
+                self.advance();
+                self.if_statement(rule_arg);
+            } else {
+                self.consume(TokenLeftCurlyBrace, "Expected '{' after 'else'");
+                self.block_statements("Expected '}' at the end of else block");
+            }
+        }
+
+        self.ast_generator.end_if();
+    }
+
+    pub fn while_statement(&mut self, rule_arg: RuleArg) {
+        let while_metadata = self.get_previous().get_metadata();
+
+        if !self.check_statement_context(&rule_arg, "while", while_metadata) {
+            return;
+        }
+
+        self.expression();
+
+        if let Err((message, token_vec)) = self.ast_generator.start_while(while_metadata) {
+            self.report_compile_error(message, token_vec);
+        }
+
+        self.consume(TokenLeftCurlyBrace, "Expected '{' after while condition");
+        self.block_statements("Expected '}' at the end of while block");
+
+        self.ast_generator.end_while();
+    }
+
+    pub fn for_statement(&mut self, rule_arg: RuleArg) {
+        let for_metadata = self.get_previous().get_metadata();
+
+        if !self.check_statement_context(&rule_arg, "for", for_metadata) {
+            return;
+        }
+
+        // `for (key, value) in map`
+        let mut value_variable = None;
+        let is_pair = self.get_current().get_ttype() == &TokenLeftParen;
+        if is_pair {
+            self.advance();
+        }
+
+        if !self.consume(TokenIdentifier, "Expected loop variable after 'for'") {
+            return;
+        }
+        let variable = self.get_previous().get_lexeme(self.source);
+
+        if is_pair {
+            self.consume(TokenComma, "Expected ',' after key variable");
+            if !self.consume(TokenIdentifier, "Expected value variable after ','") {
+                return;
+            }
+            value_variable = Some(self.get_previous().get_lexeme(self.source));
+            self.consume(TokenRightParen, "Expected ')' after value variable");
+        }
+
+        self.consume(TokenIn, "Expected 'in' after loop variable");
+        self.expression();
+
+        // Anything other than a range is looped over as a map
+        let result = if self.get_current().get_ttype() == &TokenDotDot {
+            self.advance();
+            self.expression();
+
+            if value_variable.is_some() {
+                self.report_compile_error(
+                    "Only maps can be looped over with a key and a value variable".to_string(),
+                    vec![for_metadata]
+                );
+            }
+            self.ast_generator.start_for(variable, for_metadata)
+        } else {
+            self.ast_generator.start_for_map(variable, value_variable, for_metadata)
+        };
+
+        if let Err((message, token_vec)) = result {
+            self.report_compile_error(message, token_vec);
+        }
+
+        self.consume(TokenLeftCurlyBrace, "Expected '{' after range or map");
+        self.block_statements("Expected '}' at the end of for block");
+
+        self.ast_generator.end_for();
+    }
+
+    pub fn break_statement(&mut self, rule_arg: RuleArg) {
+        let break_metadata = self.get_previous().get_metadata();
+
+        if !self.check_statement_context(&rule_arg, "break", break_metadata) {
+            return;
+        }
+
+        if let Err((message, token_vec)) = self.ast_generator.emit_break(break_metadata) {
+            self.report_compile_error(message, token_vec);
+        }
+    }
+
+    pub fn continue_statement(&mut self, rule_arg: RuleArg) {
+        let continue_metadata = self.get_previous().get_metadata();
+
+        if !self.check_statement_context(&rule_arg, "continue", continue_metadata) {
+            return;
+        }
+
+        if let Err((message, token_vec)) = self.ast_generator.emit_continue(continue_metadata) {
+            self.report_compile_error(message, token_vec);
         }
-        self.consume(TokenType::TokenRightCurlyBrace, "Expected '}' at the end of block");
-        self.end_scope()
     }
 
     pub fn typing(&mut self, rule_arg: RuleArg) {
-        panic!("Typings not supported yet")
-        /*
-        self.advance();
+        let type_metadata = self.get_previous().get_metadata();
 
+        if !self.check_statement_context(&rule_arg, "type", type_metadata) {
+            return;
+        }
+
+        if !self.consume(TokenIdentifier, "Expected type name after 'type'") {
+            return;
+        }
         let lexeme = self.get_previous().get_lexeme(self.source);
+        let name_metadata = self.get_previous().get_metadata();
 
-        self.consume(
-            TokenType::TokenAssign,
-            format!("Expected '=' after type definition but got '{}'", lexeme).as_str()
-        );
+        let Ok(type_params) = self.type_parameters() else {
+            return;
+        };
+        if let Some(bound) = type_params.iter().find_map(|type_param| type_param.bounds.first()) {
+            self.report_compile_error(
+                "Type parameters of a type alias cannot have bounds".to_string(),
+                vec![bound.token_metadata]
+            );
+            return;
+        }
+
+        if !self.consume(TokenAssign, "Expected '=' after type name") {
+            return;
+        }
 
         let typing = match self.resolve_typing() {
             Ok(typing) => typing,
-            Err(_) => {
+            Err(_) if self.panic_mode => {
+                return;
+            }
+            Err(error_tokens) => {
+                self.report_compile_error(
+                    format!("Expected a type after '=' in definition of type '{}'", lexeme),
+                    error_tokens
+                );
                 return;
             }
         };
-
-        match self.ast_generator.emit_type_definition(TypeDefStmt::new(lexeme, typing)) {
-            Ok(_) => {}
-            Err((msg, token_vec)) => { self.report_compile_error(msg, token_vec) }
+        self.consume_expr_end();
+
+        let type_params = type_params
+            .into_iter()
+            .map(|type_param| type_param.name)
+            .collect();
+        let type_def = TypeDefStmt::new(lexeme, type_params, typing, name_metadata);
+        if let Err((message, token_vec)) = self.ast_generator.emit_type_definition(type_def) {
+            self.report_compile_error(message, token_vec);
         }
-        */
     }
 
     pub fn literal(&mut self, rule_arg: RuleArg) {
@@ -104,49 +360,453 @@ impl<'a> Parser<'a> {
                 self.ast_generator.emit_constant_literal(
                     AstValue::new(Value::Bool(true), token.get_metadata())
                 ),
+            TokenNoneValue =>
+                self.ast_generator.emit_constant_literal(
+                    AstValue::new(Value::None, token.get_metadata())
+                ),
             _ => {}
         }
     }
 
     pub fn function(&mut self, rule_arg: RuleArg) {
-        self.advance();
-        let lexeme = self.get_previous().get_lexeme(self.source);
+        let fn_metadata = self.get_previous().get_metadata();
+
+        // A function without a name is a closure, which is an expression
+        if self.get_current().get_ttype() == &TokenLeftParen {
+            self.closure(fn_metadata);
+            return;
+        }
+
+        if !self.check_statement_context(&rule_arg, "fn", fn_metadata) {
+            return;
+        }
+
+        self.function_definition(false);
+    }
+
+    pub fn class_statement(&mut self, rule_arg: RuleArg) {
+        let class_metadata = self.get_previous().get_metadata();
+
+        if !self.check_statement_context(&rule_arg, "class", class_metadata) {
+            return;
+        }
+
+        self.class_definition();
+    }
+
+    pub fn enum_statement(&mut self, rule_arg: RuleArg) {
+        let enum_metadata = self.get_previous().get_metadata();
+
+        if !self.check_statement_context(&rule_arg, "enum", enum_metadata) {
+            return;
+        }
+
+        self.enum_definition();
+    }
+
+    pub fn trait_statement(&mut self, rule_arg: RuleArg) {
+        let trait_metadata = self.get_previous().get_metadata();
+
+        if !self.check_statement_context(&rule_arg, "trait", trait_metadata) {
+            return;
+        }
+
+        self.trait_definition();
+    }
+
+    pub fn impl_statement(&mut self, rule_arg: RuleArg) {
+        let impl_metadata = self.get_previous().get_metadata();
+
+        if !self.check_statement_context(&rule_arg, "impl", impl_metadata) {
+            return;
+        }
+
+        self.impl_definition();
+    }
+
+    pub fn match_expression(&mut self, _rule_arg: RuleArg) {
+        let match_metadata = self.get_previous().get_metadata();
+
+        self.expression();
+
+        if let Err((message, token_vec)) = self.ast_generator.start_match(match_metadata) {
+            let is_value_parsed = !self.panic_mode;
+            self.report_compile_error(message, token_vec);
+
+            // The arms are still parsed, since a wrong type doesn't put the parser out of sync
+            if is_value_parsed {
+                self.exit_panic_mode();
+            }
+        }
+
+        if self.consume(TokenLeftCurlyBrace, "Expected '{' after match value") {
+            while !self.is_at_end() && self.get_current().get_ttype() != &TokenRightCurlyBrace {
+                self.match_arm();
+
+                if self.panic_mode {
+                    self.synchronize_match_arm();
+                }
+                if self.get_current().get_ttype() == &TokenComma {
+                    self.advance();
+                }
+            }
+
+            self.consume(TokenRightCurlyBrace, "Expected '}' after match arms");
+        }
+
+        if let Err((message, token_vec)) = self.ast_generator.end_match() {
+            self.report_compile_error(message, token_vec);
+        }
+    }
+
+    pub fn public(&mut self, rule_arg: RuleArg) {
+        let pub_metadata = self.get_previous().get_metadata();
 
-        let function_args = match self.resolve_function_args() {
-            Ok(v) => v,
-            Err(_) => {
+        if !self.check_statement_context(&rule_arg, "pub", pub_metadata) {
+            return;
+        }
+
+        let name = self
+            .get_next()
+            .filter(|token| token.get_ttype() == &TokenIdentifier)
+            .map(|token| token.get_lexeme(self.source));
+        if let Some(name) = name {
+            self.ast_generator.set_public(name);
+        }
+
+        match self.get_current().get_ttype() {
+            TokenFunction => {
+                self.advance();
+                self.function_definition(true);
+            }
+            TokenClass => {
+                self.advance();
+                self.class_definition();
+            }
+            TokenEnum => {
+                self.advance();
+                self.enum_definition();
+            }
+            TokenTrait => {
+                self.advance();
+                self.trait_definition();
+            }
+            TokenTyping => {
+                self.advance();
+                self.typing(rule_arg);
+            }
+            _ => {
+                self.report_compile_error(
+                    "Expected 'fn', 'class', 'enum', 'trait' or 'type' after 'pub'".to_string(),
+                    vec![pub_metadata]
+                );
+            }
+        }
+    }
+
+    // `import math` imports `math.vs`, and `import "lib/math.vs"` imports the file at the path
+    pub fn import_statement(&mut self, rule_arg: RuleArg) {
+        let import_metadata = self.get_previous().get_metadata();
+
+        if !self.check_statement_context(&rule_arg, "import", import_metadata) {
+            return;
+        }
+
+        let lexeme = self.get_current().get_lexeme(self.source);
+        let module_path = match self.get_current().get_ttype() {
+            // The lexeme includes the quotes
+            TokenString => lexeme[1..lexeme.len() - 1].to_string(),
+            TokenIdentifier => format!("{}.vs", lexeme),
+            _ => {
+                self.report_compile_error(
+                    "Expected a module name or path after 'import'".to_string(),
+                    vec![import_metadata]
+                );
                 return;
             }
         };
+        self.advance();
+        let path_metadata = self.get_previous().get_metadata();
+
+        // `import mods/rt` would otherwise continue as a division
+        if !self.is_at_expr_end() && self.get_current().get_ttype() != &TokenRightCurlyBrace {
+            self.report_compile_error(
+                "Expected a new line or ';' after the module name. Paths are written as strings".to_string(),
+                vec![self.get_current().get_metadata()]
+            );
+            return;
+        }
+        self.consume_expr_end();
+
+        if !self.ast_generator.is_module_top_level() {
+            self.report_compile_error(
+                "Modules can only be imported at the top level of a file".to_string(),
+                vec![import_metadata]
+            );
+            return;
+        }
 
-        println!("{:#?}", function_args);
+        self.import_module(&module_path, path_metadata);
+    }
+
+    pub fn self_value(&mut self, _rule_arg: RuleArg) {
+        let token = self.get_previous();
+
+        let result = self.ast_generator.emit_identifier_lookup(
+            AstIdentifier::new("self".to_string(), token.get_metadata()),
+            false
+        );
+        if let Err((message, token_vec)) = result {
+            self.report_compile_error(message, token_vec);
+        }
+    }
+
+    pub fn call(&mut self, _rule_arg: RuleArg) {
+        let paren_metadata = self.get_previous().get_metadata();
+        let arg_count = self.call_arguments();
+
+        if let Err((message, token_vec)) = self.ast_generator.emit_call(arg_count, paren_metadata, None) {
+            self.report_compile_error(message, token_vec);
+        }
+    }
+
+    // Also parses map literals, since the braces of `{"a": 1}` would be taken for a block
+    pub fn array(&mut self, _rule_arg: RuleArg) {
+        let bracket_metadata = self.get_previous().get_metadata();
+
+        if self.get_current().get_ttype() == &TokenColon {
+            self.advance();
+            self.consume(TokenRightSquareBracket, "Expected ']' after ':' in empty map");
+            self.ast_generator.emit_map(0, bracket_metadata);
+            return;
+        }
+
+        let mut element_count = 0;
+        let mut is_map = false;
+
+        while self.get_current().get_ttype() != &TokenRightSquareBracket {
+            self.expression();
+
+            // The first element decides whether it's an array or a map
+            if element_count == 0 {
+                is_map = self.get_current().get_ttype() == &TokenColon;
+            }
+            if is_map {
+                self.consume(TokenColon, "Expected ':' after map key");
+                self.expression();
+            }
+            element_count += 1;
+
+            if self.get_current().get_ttype() != &TokenComma {
+                break;
+            }
+            self.advance();
+        }
+
+        if is_map {
+            self.consume(TokenRightSquareBracket, "Expected ']' after map entries");
+            self.ast_generator.emit_map(element_count, bracket_metadata);
+        } else {
+            self.consume(TokenRightSquareBracket, "Expected ']' after array elements");
+            self.ast_generator.emit_array(element_count, bracket_metadata);
+        }
+    }
+
+    pub fn index(&mut self, rule_arg: RuleArg) {
+        let bracket_metadata = self.get_previous().get_metadata();
+
+        self.expression();
+        self.consume(TokenRightSquareBracket, "Expected ']' after index");
+
+        if let Err((message, token_vec)) = self.ast_generator.emit_index(bracket_metadata) {
+            self.report_compile_error(message, token_vec);
+            return;
+        }
+
+        let compound_op = self.get_current().get_ttype().get_compound_assignment_op();
+        if self.get_current().get_ttype() != &TokenAssign && compound_op.is_none() {
+            return;
+        }
+
+        let assign_metadata = self.get_current().get_metadata();
+        if rule_arg != RuleArg::Precedence(PrecAssignment) {
+            self.report_compile_error(
+                "An index assignment cannot be used as an expression".to_string(),
+                vec![assign_metadata]
+            );
+            return;
+        }
+        self.advance();
 
-        let return_type = match self.resolve_function_return_type() {
-            Ok(v) => v,
-            Err(_) => {
+        if self.is_at_expr_end() {
+            self.report_compile_error(
+                "Missing right hand side of index assignment".to_string(),
+                vec![assign_metadata]
+            );
+            return;
+        }
+        if compound_op.is_some() {
+            self.ast_generator.emit_compound_target();
+        }
+        self.assignment_value(compound_op);
+
+        let result = self.ast_generator.emit_index_assignment(
+            bracket_metadata,
+            compound_op.is_some()
+        );
+        if let Err((message, token_vec)) = result {
+            self.report_compile_error(message, token_vec);
+        }
+    }
+
+    pub fn cast(&mut self, _rule_arg: RuleArg) {
+        let as_metadata = self.get_previous().get_metadata();
+
+        let value_type = match self.resolve_type() {
+            Ok(value_type) => value_type,
+            Err(_) if self.panic_mode => {
+                return;
+            }
+            Err(token_vec) => {
+                self.report_compile_error("Expected a type after 'as'".to_string(), token_vec);
                 return;
             }
         };
 
-        println!("Return type: {:?}", return_type);
+        if let Err((message, token_vec)) = self.ast_generator.emit_cast(value_type, as_metadata) {
+            self.report_compile_error(message, token_vec);
+        }
+    }
+
+    pub fn dot(&mut self, rule_arg: RuleArg) {
+        // A number is the index of a tuple element, like `pair.0`
+        if self.get_current().get_ttype() == &TokenNumber {
+            self.advance();
+        } else if !self.consume(TokenIdentifier, "Expected member name after '.'") {
+            return;
+        }
+        let member = {
+            let token = self.get_previous();
+            AstIdentifier::new(token.get_lexeme(self.source), token.get_metadata())
+        };
+
+        match self.get_current().get_ttype() {
+            TokenLeftParen => {
+                self.advance();
+                let arg_count = self.call_arguments();
+
+                if let Err((message, token_vec)) = self.ast_generator.emit_method_call(member, arg_count) {
+                    self.report_compile_error(message, token_vec);
+                }
+            }
+            TokenAssign | TokenPlusEqual | TokenMinusEqual | TokenStarEqual | TokenSlashEqual if
+                rule_arg == RuleArg::Precedence(PrecAssignment)
+            => {
+                let compound_op = self.get_current().get_ttype().get_compound_assignment_op();
+                let assign_metadata = self.get_current().get_metadata();
+                self.advance();
+
+                if self.is_at_expr_end() {
+                    self.report_compile_error(
+                        "Missing right hand side of field assignment".to_string(),
+                        vec![assign_metadata]
+                    );
+                    return;
+                }
+                if compound_op.is_some() {
+                    self.ast_generator.emit_compound_target();
+                    let result = self.ast_generator.emit_member_access(member.clone());
+                    if let Err((message, token_vec)) = result {
+                        self.report_compile_error(message, token_vec);
+                        return;
+                    }
+                }
+                self.assignment_value(compound_op);
+
+                let result = self.ast_generator.emit_field_assignment(member, compound_op.is_some());
+                if let Err((message, token_vec)) = result {
+                    self.report_compile_error(message, token_vec);
+                }
+            }
+            _ => {
+                if let Err((message, token_vec)) = self.ast_generator.emit_member_access(member) {
+                    self.report_compile_error(message, token_vec);
+                }
+            }
+        }
+    }
+
+    pub fn return_statement(&mut self, rule_arg: RuleArg) {
+        let return_metadata = self.get_previous().get_metadata();
+
+        if !self.check_statement_context(&rule_arg, "return", return_metadata) {
+            return;
+        }
+
+        let has_value =
+            !self.is_at_expr_end() && self.get_current().get_ttype() != &TokenRightCurlyBrace;
+        if has_value {
+            self.expression();
+        }
 
-        self.start_function(lexeme, function_args, return_type);
+        let result = self.ast_generator.emit_return(return_metadata, has_value);
+        if let Err((message, token_vec)) = result {
+            self.report_compile_error(message, token_vec);
+        }
+    }
 
-        println!("I run");
+    pub fn print_statement(&mut self, rule_arg: RuleArg) {
+        let print_metadata = self.get_previous().get_metadata();
 
-        self.consume(TokenLeftCurlyBrace, "Expected '{' before function body");
-        while !self.is_at_end() && !matches!(self.get_current().get_ttype(), &TokenRightCurlyBrace) {
-            self.statement();
+        if !self.check_statement_context(&rule_arg, "print", print_metadata) {
+            return;
         }
-        self.consume(TokenRightCurlyBrace, "Expected '}' after function body");
 
-        self.end_function();
+        self.consume(TokenLeftParen, "Expected '(' after 'print'");
+        self.expression();
+        self.consume(TokenRightParen, "Expected ')' after value to print");
+
+        if let Err((message, token_vec)) = self.ast_generator.emit_print(print_metadata) {
+            self.report_compile_error(message, token_vec);
+        }
     }
 
     pub fn grouping(&mut self, rule_arg: RuleArg) {
+        if rule_arg == RuleArg::Precedence(PrecAssignment) && self.is_destructuring_ahead() {
+            self.destructuring_definition();
+            return;
+        }
+
+        let paren_metadata = self.get_previous().get_metadata();
         self.expression();
 
-        self.consume(TokenRightParen, "Expect ')' after expression");
+        if self.get_current().get_ttype() != &TokenComma {
+            self.consume(TokenRightParen, "Expect ')' after expression");
+            return;
+        }
+
+        let mut element_count = 1;
+        while self.get_current().get_ttype() == &TokenComma {
+            self.advance();
+            if self.get_current().get_ttype() == &TokenRightParen {
+                break;
+            }
+
+            self.expression();
+            element_count += 1;
+        }
+
+        self.consume(TokenRightParen, "Expected ')' after tuple elements");
+
+        if element_count < 2 {
+            self.report_compile_error(
+                "A tuple needs at least two elements".to_string(),
+                vec![paren_metadata]
+            );
+            return;
+        }
+
+        self.ast_generator.emit_tuple(element_count, paren_metadata);
     }
 
     pub fn unary(&mut self, rule_arg: RuleArg) {
@@ -157,6 +817,7 @@ impl<'a> Parser<'a> {
         let result = match operator_type {
             TokenMinus => self.ast_generator.emit_unary_op(UnaryOp::Neg),
             TokenBang => self.ast_generator.emit_unary_op(UnaryOp::Truthy),
+            TokenTilde => self.ast_generator.emit_unary_op(UnaryOp::BitNot),
             _ => Ok(()),
         };
 
@@ -170,13 +831,54 @@ impl<'a> Parser<'a> {
 
         let parse_rule = self.get_parse_rule(&operator_type);
 
-        self.parse_precedence(parse_rule.get_precedence().get_next(), None);
+        // `**` is right associative, so `2 ** 3 ** 2` is `2 ** (3 ** 2)`
+        let right_precedence = if operator_type == TokenStarStar {
+            *parse_rule.get_precedence()
+        } else {
+            parse_rule.get_precedence().get_next()
+        };
+        self.parse_precedence(right_precedence, None);
 
         let result = match operator_type {
             TokenPlus => self.ast_generator.emit_binary_op(BinaryOp::Add),
             TokenMinus => self.ast_generator.emit_binary_op(BinaryOp::Sub),
             TokenStar => self.ast_generator.emit_binary_op(BinaryOp::Mul),
             TokenSlash => self.ast_generator.emit_binary_op(BinaryOp::Div),
+            TokenPercent => self.ast_generator.emit_binary_op(BinaryOp::Mod),
+            TokenStarStar => self.ast_generator.emit_binary_op(BinaryOp::Pow),
+            TokenAmpersand => self.ast_generator.emit_binary_op(BinaryOp::BitAnd),
+            TokenPipe => self.ast_generator.emit_binary_op(BinaryOp::BitOr),
+            TokenCaret => self.ast_generator.emit_binary_op(BinaryOp::BitXor),
+            TokenLessLess => self.ast_generator.emit_binary_op(BinaryOp::Shl),
+            TokenGreaterGreater => self.ast_generator.emit_binary_op(BinaryOp::Shr),
+            TokenEqualEqual => self.ast_generator.emit_binary_op(BinaryOp::Equal),
+            TokenBangEqual => self.ast_generator.emit_binary_op(BinaryOp::NotEqual),
+            TokenLess => self.ast_generator.emit_binary_op(BinaryOp::Less),
+            TokenLessEqual => self.ast_generator.emit_binary_op(BinaryOp::LessEqual),
+            TokenGreater => self.ast_generator.emit_binary_op(BinaryOp::Greater),
+            TokenGreaterEqual => self.ast_generator.emit_binary_op(BinaryOp::GreaterEqual),
+            _ => Ok(()),
+        };
+
+        if let Err((message, token)) = result {
+            self.report_compile_error(message, token);
+        }
+    }
+
+    pub fn logical(&mut self, _rule_arg: RuleArg) {
+        let operator_type = { *self.get_previous().get_ttype() };
+        let operator_metadata = self.get_previous().get_metadata();
+
+        let parse_rule = self.get_parse_rule(&operator_type);
+
+        self.parse_precedence(parse_rule.get_precedence().get_next(), None);
+
+        let result = match operator_type {
+            TokenAnd => self.ast_generator.emit_logical_op(LogicalOp::And, operator_metadata),
+            TokenOr => self.ast_generator.emit_logical_op(LogicalOp::Or, operator_metadata),
+            TokenQuestionQuestion => {
+                self.ast_generator.emit_logical_op(LogicalOp::Coalesce, operator_metadata)
+            }
             _ => Ok(()),
         };
 
@@ -2,30 +2,44 @@
 pub enum Precedence {
     PrecNone = 0,
     PrecAssignment = 1,
-    PrecOr = 2,
-    PrecAnd = 3,
-    PrecEquality = 4,
-    PrecComparison = 5,
-    PrecTerm = 6,
-    PrecFactor = 7,
-    PrecUnary = 8,
-    PrecCall = 9,
-    PrecPrimary = 10,
+    PrecCoalesce = 2,
+    PrecOr = 3,
+    PrecAnd = 4,
+    PrecEquality = 5,
+    PrecComparison = 6,
+    PrecBitOr = 7,
+    PrecBitXor = 8,
+    PrecBitAnd = 9,
+    PrecShift = 10,
+    PrecTerm = 11,
+    PrecFactor = 12,
+    PrecCast = 13,
+    PrecUnary = 14,
+    PrecPower = 15,
+    PrecCall = 16,
+    PrecPrimary = 17,
 }
 impl From<usize> for Precedence {
     fn from(value: usize) -> Self {
         match value {
             0 => Precedence::PrecNone,
             1 => Precedence::PrecAssignment,
-            2 => Precedence::PrecOr,
-            3 => Precedence::PrecAnd,
-            4 => Precedence::PrecEquality,
-            5 => Precedence::PrecComparison,
-            6 => Precedence::PrecTerm,
-            7 => Precedence::PrecFactor,
-            8 => Precedence::PrecUnary,
-            9 => Precedence::PrecCall,
-            10 => Precedence::PrecPrimary,
+            2 => Precedence::PrecCoalesce,
+            3 => Precedence::PrecOr,
+            4 => Precedence::PrecAnd,
+            5 => Precedence::PrecEquality,
+            6 => Precedence::PrecComparison,
+            7 => Precedence::PrecBitOr,
+            8 => Precedence::PrecBitXor,
+            9 => Precedence::PrecBitAnd,
+            10 => Precedence::PrecShift,
+            11 => Precedence::PrecTerm,
+            12 => Precedence::PrecFactor,
+            13 => Precedence::PrecCast,
+            14 => Precedence::PrecUnary,
+            15 => Precedence::PrecPower,
+            16 => Precedence::PrecCall,
+            17 => Precedence::PrecPrimary,
             _ => panic!("Invalid precedence value: {}", value),
         }
     }
@@ -35,15 +49,22 @@ impl From<Precedence> for usize {
         match value {
             Precedence::PrecNone => 0,
             Precedence::PrecAssignment => 1,
-            Precedence::PrecOr => 2,
-            Precedence::PrecAnd => 3,
-            Precedence::PrecEquality => 4,
-            Precedence::PrecComparison => 5,
-            Precedence::PrecTerm => 6,
-            Precedence::PrecFactor => 7,
-            Precedence::PrecUnary => 8,
-            Precedence::PrecCall => 9,
-            Precedence::PrecPrimary => 10,
+            Precedence::PrecCoalesce => 2,
+            Precedence::PrecOr => 3,
+            Precedence::PrecAnd => 4,
+            Precedence::PrecEquality => 5,
+            Precedence::PrecComparison => 6,
+            Precedence::PrecBitOr => 7,
+            Precedence::PrecBitXor => 8,
+            Precedence::PrecBitAnd => 9,
+            Precedence::PrecShift => 10,
+            Precedence::PrecTerm => 11,
+            Precedence::PrecFactor => 12,
+            Precedence::PrecCast => 13,
+            Precedence::PrecUnary => 14,
+            Precedence::PrecPower => 15,
+            Precedence::PrecCall => 16,
+            Precedence::PrecPrimary => 17,
         }
     }
 }
@@ -2,21 +2,29 @@ use self::token_type::TokenType;
 
 pub mod token_type;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct TokenMetadata {
     start: usize,
     length: usize,
     line: usize,
     token_type: TokenType,
+    file_id: usize,
 }
 
 impl TokenMetadata {
-    pub fn new(start: usize, length: usize, line: usize, token_type: TokenType) -> Self {
+    pub fn new(
+        start: usize,
+        length: usize,
+        line: usize,
+        token_type: TokenType,
+        file_id: usize
+    ) -> Self {
         Self {
             start,
             length,
             line,
             token_type,
+            file_id,
         }
     }
 
@@ -32,6 +40,25 @@ impl TokenMetadata {
     pub fn get_ttype(&self) -> TokenType {
         self.token_type
     }
+    pub fn get_file_id(&self) -> usize {
+        self.file_id
+    }
+
+    // The tokens are ordered last to first, like `push_to_token_vec` pushes them
+    pub fn span(token_vec: &[TokenMetadata]) -> Option<Self> {
+        let first = token_vec.last()?;
+        let last = token_vec.first()?;
+
+        Some(
+            Self::new(
+                first.get_start(),
+                last.get_start() - first.get_start() + last.get_len(),
+                last.get_line(),
+                last.get_ttype(),
+                last.get_file_id()
+            )
+        )
+    }
 
     pub fn increment_length(&mut self) {
         self.length += 1;
@@ -49,16 +76,24 @@ pub struct Token {
     start: usize,
     length: usize,
     line: usize,
+    file_id: usize,
     optional_message: Option<String>,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, start: usize, length: usize, line: usize) -> Token {
+    pub fn new(
+        token_type: TokenType,
+        start: usize,
+        length: usize,
+        line: usize,
+        file_id: usize
+    ) -> Token {
         Token {
             token_type,
             start,
             length,
             line,
+            file_id,
             optional_message: None,
         }
     }
@@ -69,6 +104,7 @@ impl Token {
             length: self.length,
             line: self.line,
             token_type: self.token_type,
+            file_id: self.file_id,
         }
     }
 
@@ -77,6 +113,7 @@ impl Token {
         start: usize,
         length: usize,
         line: usize,
+        file_id: usize,
         message: String
     ) -> Token {
         Token {
@@ -84,6 +121,7 @@ impl Token {
             start,
             length,
             line,
+            file_id,
             optional_message: Some(message),
         }
     }
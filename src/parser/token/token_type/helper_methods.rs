@@ -1,3 +1,5 @@
+use crate::operations::BinaryOp;
+
 use super::TokenType;
 
 impl TokenType {
@@ -8,6 +10,16 @@ impl TokenType {
         }
     }
 
+    pub fn get_compound_assignment_op(&self) -> Option<BinaryOp> {
+        match self {
+            Self::TokenPlusEqual => Some(BinaryOp::Add),
+            Self::TokenMinusEqual => Some(BinaryOp::Sub),
+            Self::TokenStarEqual => Some(BinaryOp::Mul),
+            Self::TokenSlashEqual => Some(BinaryOp::Div),
+            _ => None,
+        }
+    }
+
     pub fn is(&self, other: &TokenType) -> bool {
         self == other
     }
@@ -11,22 +11,68 @@ pub enum TokenType {
     TokenBang,
     TokenAssign,
     TokenComma,
+    TokenDot,
+    TokenColon,
+    TokenQuestion,
     TokenMinus,
     TokenPlus,
     TokenSlash,
     TokenStar,
+    TokenPercent,
+    TokenStarStar,
+    TokenPlusEqual,
+    TokenMinusEqual,
+    TokenStarEqual,
+    TokenSlashEqual,
+    TokenAmpersand,
+    TokenPipe,
+    TokenCaret,
+    TokenLessLess,
+    TokenGreaterGreater,
+    TokenTilde,
+    TokenEqualEqual,
+    TokenBangEqual,
+    TokenLess,
+    TokenLessEqual,
+    TokenGreater,
+    TokenGreaterEqual,
+    TokenAnd,
+    TokenOr,
+    TokenQuestionQuestion,
     TokenNumber,
+    TokenString,
+    TokenStringInterpolation,
     TokenIdentifier,
     TokenTrue,
     TokenFalse,
+    TokenNoneValue,
     TokenInt32,
     TokenBool,
     TokenDefine,
+    TokenDotDot,
+    TokenFatArrow,
     TokenMutable,
     TokenFunction,
     TokenTyping,
     TokenPrint,
     TokenReturn,
+    TokenIf,
+    TokenElse,
+    TokenWhile,
+    TokenFor,
+    TokenIn,
+    TokenAs,
+    TokenBreak,
+    TokenContinue,
+    TokenClass,
+    TokenEnum,
+    TokenMatch,
+    TokenTrait,
+    TokenImpl,
+    TokenImport,
+    TokenPublic,
+    TokenSelfValue,
+    TokenSelfType,
     TokenError,
     TokenEOF,
 }
@@ -43,24 +89,70 @@ impl From<TokenType> for usize {
             TokenType::TokenBang => 7,
             TokenType::TokenAssign => 8,
             TokenType::TokenComma => 9,
-            TokenType::TokenMinus => 10,
-            TokenType::TokenPlus => 11,
-            TokenType::TokenSlash => 12,
-            TokenType::TokenStar => 13,
-            TokenType::TokenNumber => 14,
-            TokenType::TokenIdentifier => 15,
-            TokenType::TokenTrue => 16,
-            TokenType::TokenFalse => 17,
-            TokenType::TokenInt32 => 18,
-            TokenType::TokenBool => 19,
-            TokenType::TokenDefine => 20,
-            TokenType::TokenMutable => 21,
-            TokenType::TokenFunction => 22,
-            TokenType::TokenTyping => 23,
-            TokenType::TokenPrint => 24,
-            TokenType::TokenReturn => 25,
-            TokenType::TokenError => 26,
-            TokenType::TokenEOF => 27,
+            TokenType::TokenDot => 10,
+            TokenType::TokenColon => 11,
+            TokenType::TokenQuestion => 12,
+            TokenType::TokenMinus => 13,
+            TokenType::TokenPlus => 14,
+            TokenType::TokenSlash => 15,
+            TokenType::TokenStar => 16,
+            TokenType::TokenPercent => 17,
+            TokenType::TokenStarStar => 18,
+            TokenType::TokenPlusEqual => 19,
+            TokenType::TokenMinusEqual => 20,
+            TokenType::TokenStarEqual => 21,
+            TokenType::TokenSlashEqual => 22,
+            TokenType::TokenAmpersand => 23,
+            TokenType::TokenPipe => 24,
+            TokenType::TokenCaret => 25,
+            TokenType::TokenLessLess => 26,
+            TokenType::TokenGreaterGreater => 27,
+            TokenType::TokenTilde => 28,
+            TokenType::TokenEqualEqual => 29,
+            TokenType::TokenBangEqual => 30,
+            TokenType::TokenLess => 31,
+            TokenType::TokenLessEqual => 32,
+            TokenType::TokenGreater => 33,
+            TokenType::TokenGreaterEqual => 34,
+            TokenType::TokenAnd => 35,
+            TokenType::TokenOr => 36,
+            TokenType::TokenQuestionQuestion => 37,
+            TokenType::TokenNumber => 38,
+            TokenType::TokenString => 39,
+            TokenType::TokenStringInterpolation => 40,
+            TokenType::TokenIdentifier => 41,
+            TokenType::TokenTrue => 42,
+            TokenType::TokenFalse => 43,
+            TokenType::TokenNoneValue => 44,
+            TokenType::TokenInt32 => 45,
+            TokenType::TokenBool => 46,
+            TokenType::TokenDefine => 47,
+            TokenType::TokenDotDot => 48,
+            TokenType::TokenFatArrow => 49,
+            TokenType::TokenMutable => 50,
+            TokenType::TokenFunction => 51,
+            TokenType::TokenTyping => 52,
+            TokenType::TokenPrint => 53,
+            TokenType::TokenReturn => 54,
+            TokenType::TokenIf => 55,
+            TokenType::TokenElse => 56,
+            TokenType::TokenWhile => 57,
+            TokenType::TokenFor => 58,
+            TokenType::TokenIn => 59,
+            TokenType::TokenAs => 60,
+            TokenType::TokenBreak => 61,
+            TokenType::TokenContinue => 62,
+            TokenType::TokenClass => 63,
+            TokenType::TokenEnum => 64,
+            TokenType::TokenMatch => 65,
+            TokenType::TokenTrait => 66,
+            TokenType::TokenImpl => 67,
+            TokenType::TokenImport => 68,
+            TokenType::TokenPublic => 69,
+            TokenType::TokenSelfValue => 70,
+            TokenType::TokenSelfType => 71,
+            TokenType::TokenError => 72,
+            TokenType::TokenEOF => 73,
         }
     }
 }
@@ -1 +1,2 @@
 mod vm_tests;
+mod program_tests;
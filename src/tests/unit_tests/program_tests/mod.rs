@@ -0,0 +1,1216 @@
+use crate::{
+    compiler::Compiler,
+    error_handler::ErrorHandler,
+    parser::Parser,
+    vm::{ output::CapturedOutput, runtime_error::RuntimeError, VM },
+};
+
+fn run(src: &str) -> Vec<String> {
+    let (lines, result) = run_with_result(src);
+
+    result.expect("Runtime error");
+
+    lines
+}
+
+// Like `run`, but runtime errors are returned instead of failing the test
+fn run_with_result(src: &str) -> (Vec<String>, Result<(), RuntimeError>) {
+    let error_handler = &mut ErrorHandler::new();
+    let file_id = error_handler.add_file("test.vs".into(), src.to_string());
+
+    let src_chars = src.chars().collect::<Vec<_>>();
+    let mut parser = Parser::new(&src_chars, file_id, error_handler);
+    let ast = parser.parse_to_ast();
+
+    assert!(!error_handler.has_error(), "Failed to parse program");
+
+    let mut compiler = Compiler::new(error_handler);
+    let program = compiler.compile(ast).expect("Failed to compile program");
+
+    let output = CapturedOutput::new();
+    let mut vm = VM::with_output(program, Box::new(output.clone()));
+    let result = vm.run();
+
+    (output.get_lines(), result)
+}
+
+fn has_compile_error(src: &str) -> bool {
+    let error_handler = &mut ErrorHandler::new();
+    let file_id = error_handler.add_file("test.vs".into(), src.to_string());
+
+    let src_chars = src.chars().collect::<Vec<_>>();
+    let ast = Parser::new(&src_chars, file_id, error_handler).parse_to_ast();
+
+    if error_handler.has_error() {
+        return true;
+    }
+
+    Compiler::new(error_handler).compile(ast).is_none()
+}
+
+#[test]
+fn test_print() {
+    let src = "
+        a := 2 + 3 * 4
+        print(a)
+        print(a == 14)
+        print(!true)
+    ";
+
+    assert_eq!(run(src), vec!["14", "true", "false"]);
+}
+
+#[test]
+fn test_print_in_loops() {
+    let src = "
+        for i in 0..10 {
+            if i == 2 {
+                continue
+            }
+            if i == 5 {
+                break
+            }
+            print(i)
+        }
+
+        mut n := 3
+        while n > 0 {
+            print(n)
+            n = n - 1
+        }
+    ";
+
+    assert_eq!(run(src), vec!["0", "1", "3", "4", "3", "2", "1"]);
+}
+
+#[test]
+fn test_print_in_functions() {
+    let src = "
+        fn fib(n i32) i32 {
+            if n < 2 {
+                return n
+            }
+            fib(n - 1) + fib(n - 2)
+        }
+
+        fn greet(n i32) {
+            print(n)
+        }
+
+        print(fib(10))
+        greet(7)
+    ";
+
+    assert_eq!(run(src), vec!["55", "7"]);
+}
+
+#[test]
+fn test_function_declarations() {
+    let src = "
+        print(is_even(10))
+
+        fn is_even(n i32) bool {
+            if n == 0 {
+                return true
+            }
+            is_odd(n - 1)
+        }
+
+        fn is_odd(n i32) bool {
+            if n == 0 {
+                return false
+            }
+            is_even(n - 1)
+        }
+
+        {
+            print(triple(2))
+            fn triple(x i32) i32 { x * 3 }
+        }
+    ";
+
+    assert_eq!(run(src), vec!["true", "6"]);
+
+    assert!(has_compile_error("fn f() {}\nfn f() {}"));
+    assert!(has_compile_error("fn f(a i32, a i32) {}"));
+    assert!(has_compile_error("{\n    fn g() {}\n}\ng()"));
+
+    let src = "
+        class Sq {
+            pub side i32: 3
+        }
+        print(area(Sq()))
+        fn area(s Sq) i32 { s.side * s.side }
+
+        print(id(3))
+        print(id(\"a\"))
+        fn id<T>(x T) T { x }
+    ";
+
+    assert_eq!(run(src), vec!["9", "3", "a"]);
+
+    let twice_src = "fn twice<T>(x T) T { x + x }";
+    assert!(has_compile_error(&format!("print(twice(true))\n{}", twice_src)));
+    assert!(has_compile_error(&format!("fn outer<U>(x U) U {{ twice(x) }}\nprint(outer(true))\n{}", twice_src)));
+
+    let src = "
+        fn forever(n i32) i32 {
+            forever(n + 1)
+        }
+        print(forever(0))
+    ";
+
+    let (_, result) = run_with_result(src);
+
+    assert_eq!(
+        result.map_err(|error| error.message),
+        Err("Stack overflow: calls are nested more than 10000 levels deep".to_string())
+    );
+}
+
+#[test]
+fn test_short_circuit() {
+    let src = "
+        fn check(value bool) bool {
+            print(value)
+            value
+        }
+
+        a := check(false) && check(true)
+        b := check(true) || check(false)
+        c := check(true) && check(false)
+        print(a)
+        print(b)
+        print(c)
+    ";
+
+    assert_eq!(run(src), vec!["false", "true", "true", "false", "false", "true", "false"]);
+
+    // The hidden variables of logical expressions and matches don't run out of registers
+    let mut src = String::from("enum E {\n    A(i32)\n    B\n}\nx := E.A(1)\nt := true\nmut count := 0\n");
+    for _ in 0..300 {
+        src.push_str("if t && !t || t {\n    count += match x { A(v) => v, B => 0 }\n}\n");
+    }
+    src.push_str("print(count)");
+
+    assert_eq!(run(&src), vec!["300"]);
+}
+
+#[test]
+fn test_float_comparisons() {
+    let src = "
+        a := 1.5
+        b f32 := 2.25
+        print(a < 2.0)
+        print(a <= 1.5)
+        print(a >= 1.6)
+        print(b > 2.0)
+        print(b >= 2.25)
+        print(a == 1.5)
+        print(a != 1.5)
+        print(-0.5 < 0.0)
+    ";
+
+    assert_eq!(run(src), vec!["true", "true", "false", "true", "true", "true", "false", "true"]);
+}
+
+#[test]
+fn test_strings() {
+    let src = r#"
+        fn greet(name str) str {
+            "Hello, " + name
+        }
+
+        mut name := ""
+        for i in 0..3 {
+            name = name + "a"
+        }
+
+        print(greet(name))
+        print("tab\there \"quoted\" \\")
+        print(name == "aaa")
+        print(name != "aaa")
+    "#;
+
+    assert_eq!(run(src), vec!["Hello, aaa", "tab\there \"quoted\" \\", "true", "false"]);
+}
+
+#[test]
+fn test_arrays() {
+    let src = r#"
+        fn sum(numbers [i32]) i32 {
+            mut total := 0
+            for i in 0..len(numbers) {
+                total = total + numbers[i]
+            }
+            total
+        }
+
+        mut a := [1, 2, 3]
+        a[1] = 20
+        print(a)
+        print(sum(a))
+
+        mut b := a
+        b[0] = 100
+        print(a[0])
+
+        mut grid [[i32]] := [[1, 2], []]
+        grid[1] = [3]
+        grid[0][1] = grid[1][0]
+        print(grid)
+
+        names [str] := []
+        print(len(names))
+        print(len(["a", "b"]) + len("abc"))
+    "#;
+
+    assert_eq!(run(src), vec!["[1, 20, 3]", "24", "100", "[[1, 3], [3]]", "0", "5"]);
+
+    // Arrays are shared, so an immutable one can't become mutable through another variable
+    assert_eq!(run("b := [1]\nmut c := [b[0]]\nc[0] = 2\nprint(b)"), vec!["[1]"]);
+    assert!(has_compile_error("b := [1]\nmut c := b"));
+    assert!(has_compile_error("b := [1]\nmut c [i32] := []\nc = b"));
+    assert!(has_compile_error("b := [[1]]\nmut c := b[0]"));
+}
+
+#[test]
+fn test_array_index_out_of_bounds() {
+    let src = "
+        a := [1, 2, 3]
+        print(a[2])
+        print(a[3])
+        print(a[0])
+    ";
+
+    let (lines, result) = run_with_result(src);
+
+    assert_eq!(lines, vec!["3"]);
+    assert_eq!(
+        result.map_err(|error| error.message),
+        Err("Index out of bounds: the length is 3 but the index is 3".to_string())
+    );
+}
+
+#[test]
+fn test_classes() {
+    let src = r#"
+        pub class Counter {
+            pub value i32: 0
+            pub label str: "counter"
+            steps i32: 0
+
+            fn step(mut self) {
+                self.steps = self.steps + 1
+            }
+
+            pub fn add(mut self, amount i32) Self {
+                self.value = self.value + amount
+                self.step()
+                self
+            }
+
+            pub fn getSteps(self) i32 {
+                self.steps
+            }
+        }
+
+        mut counter := Counter()
+        counter.add(5).add(10)
+        print(counter.value)
+        print(counter.getSteps())
+
+        alias := counter
+        counter.label = "renamed"
+        print(alias.label)
+
+        print(Counter())
+    "#;
+
+    assert_eq!(run(src), vec![
+        "15",
+        "2",
+        "renamed",
+        "Counter { value: 0, label: \"counter\", steps: 0 }"
+    ]);
+
+    assert!(has_compile_error("class i32 {\n    pub value i32: 0\n}"));
+    assert!(has_compile_error("enum A { B }\nclass A {\n    pub value i32: 0\n}"));
+    assert!(has_compile_error("class A {\n}\nclass A {\n}"));
+
+    let src = "
+        class Parent {
+            pub child Child?: none
+            pub fn getChild(self) Child? { self.child }
+        }
+        class Child {
+            pub age i32: 3
+            pub fn newParent(self) Parent { Parent() }
+        }
+
+        mut parent := Parent()
+        print(parent.getChild() == none)
+        parent.child = Child()
+        print(parent.getChild() == none)
+        print(Child().newParent().child == none)
+    ";
+    assert_eq!(run(src), vec!["true", "false", "true"]);
+
+    assert!(has_compile_error("class A {\n    pub fn b(self) B { B() }\n}\nclass B {\n}"));
+    assert!(has_compile_error("fn f(b B) i32 { b.value }\nclass B {\n    pub value i32: 0\n}"));
+}
+
+#[test]
+fn test_type_aliases() {
+    let src = "
+        type Ids = [Id]
+        type Id = i32
+
+        fn first(ids Ids) Id {
+            ids[0]
+        }
+
+        ids Ids := [7, 8]
+        print(first(ids))
+
+        {
+            type Id = bool
+            flag Id := true
+            print(flag)
+        }
+
+        class Point {
+            pub x i32: 1
+        }
+        type Position = Point
+        position Position := Point()
+        print(position.x)
+
+        type List<T> = [T]
+        type Pair<A, B> = (A, B)
+        pair Pair<str, List<i32>> := (\"a\", [1, 2])
+        print(pair)
+    ";
+
+    assert_eq!(run(src), vec!["7", "true", "1", "(\"a\", [1, 2])"]);
+
+    assert!(has_compile_error("type A = Missing"));
+    assert!(has_compile_error("type A = [A]"));
+    assert!(has_compile_error("type List<T> = [T]\nx List := [1]"));
+    assert!(has_compile_error("type List<T> = [T]\nx List<i32, bool> := [1]"));
+    assert!(has_compile_error("type List<T, T> = [T]"));
+}
+
+#[test]
+fn test_numeric_types() {
+    let src = "
+        big i64 := 5000000000
+        print(big + 1)
+
+        mut byte u8 := 200
+        byte = byte + 55
+        print(byte)
+
+        half := 1.5
+        print(half * 2.0)
+        print(2e3)
+
+        print(7 as f64 / 2.0)
+        print(2.9 as i32)
+        print(300 as u8)
+        print(-1 as i8 as u16)
+        print(true as i64)
+
+        fn scale(value f32, factor f32) f32 {
+            value * factor
+        }
+        print(scale(0.5, 3))
+
+        small i8 := -128
+        print(small < 0)
+
+        for i in 0..2 {
+            print(i)
+        }
+    ";
+
+    assert_eq!(
+        run(src),
+        vec!["5000000001", "255", "3.0", "2000.0", "3.5", "2", "44", "65535", "1", "1.5", "true", "0", "1"]
+    );
+}
+
+#[test]
+fn test_arithmetic_runtime_errors() {
+    let src = "
+        fn divide(a i32, b i32) i32 {
+            a / b
+        }
+        print(divide(6, 3))
+        print(divide(1, 0))
+    ";
+
+    let (lines, result) = run_with_result(src);
+    let error = result.expect_err("Expected a runtime error");
+
+    assert_eq!(lines, vec!["2"]);
+    assert_eq!(error.message, "Division by zero");
+    assert_eq!(error.token_metadata.map(|token_metadata| token_metadata.get_line()), Some(3));
+
+    let src = "
+        mut small i8 := 120
+        for i in 0..10 {
+            small = small + 1
+        }
+    ";
+
+    let (_, result) = run_with_result(src);
+
+    assert_eq!(
+        result.map_err(|error| error.message),
+        Err("Addition of 127 and 1 overflows i8".to_string())
+    );
+}
+
+#[test]
+fn test_modulo_power_and_bitwise_operators() {
+    let src = "
+        x := 17
+        print(x % 5)
+        print(7.5 % 2.0)
+        print(2 ** 3 ** 2)
+        print(-2 ** 2)
+        print(x & 3)
+        print(x | 8)
+        print(x ^ 1)
+        print(1 << 2 + 1)
+        print(x >> 2)
+        print(~x)
+
+        mask u8 := 240
+        print(~mask)
+        print(1 + 2 * 3 & 6 | 1)
+        print(1 & 2 == 0)
+    ";
+
+    assert_eq!(
+        run(src),
+        vec!["2", "1.5", "512", "-4", "1", "25", "16", "8", "4", "-18", "15", "7", "true"]
+    );
+
+    let (_, result) = run_with_result("
+        exponent := -1
+        print(2 ** exponent)
+    ");
+
+    assert_eq!(
+        result.map_err(|error| error.message),
+        Err("Integer exponent -1 is negative".to_string())
+    );
+}
+
+#[test]
+fn test_compound_assignment() {
+    let src = "
+        class Counter {
+            pub value i32: 0
+
+            pub fn add(mut self, value i32) {
+                self.value += value
+            }
+        }
+
+        mut counter := Counter()
+        counter.add(3)
+        counter.add(4)
+        counter.value *= 2
+        print(counter.value)
+
+        mut a := 10
+        a += 5
+        a -= 3
+        a *= 2
+        a /= 4
+        print(a)
+
+        mut numbers := [1, 2, 3]
+        numbers[1] += 10
+        print(numbers)
+
+        mut i := 0
+        while i < 3 {
+            i += 1
+        }
+        print(i)
+
+        fn next(label str, index i32) i32 {
+            print(label)
+            index
+        }
+
+        numbers[next(\"index\", 2)] += 5
+        print(numbers)
+        mut counters := [Counter(), Counter()]
+        counters[next(\"counter\", 1)].value -= 2
+        print(counters[1].value)
+    ";
+
+    assert_eq!(
+        run(src),
+        vec!["14", "6", "[1, 12, 3]", "3", "index", "[1, 12, 8]", "counter", "-2"]
+    );
+}
+
+#[test]
+fn test_enums_and_match() {
+    let src = "
+        enum Shape {
+            Circle(i32)
+            Rect(i32, i32)
+            Empty
+        }
+
+        fn area(shape Shape) i32 {
+            return match shape {
+                Circle(r) => r * r * 3,
+                Shape.Rect(w, h) => w * h,
+                Empty => 0,
+            }
+        }
+
+        shapes := [Shape.Circle(2), Shape.Rect(2, 5), Shape.Empty]
+        for i in 0..3 {
+            print(area(shapes[i]))
+        }
+        print(shapes[1])
+
+        is_empty := match shapes[2] { Empty => true, _ => false }
+        print(is_empty)
+
+        mut sides [i32] := []
+        match shapes[1] {
+            Rect(w, h) => push(sides, w + h),
+            _ => push(sides, 0)
+        }
+        print(sides)
+    ";
+
+    assert_eq!(run(src), vec!["12", "10", "0", "Shape.Rect(2, 5)", "true", "[7]"]);
+
+    let src = "
+        enum E {
+            A(i32)
+            B
+        }
+        x := E.A(4)
+        mut z := 0
+    ";
+    assert!(has_compile_error(&format!("{}match x {{ A(v) => {{ z = v }}, B => {{ z = 1 }} }}", src)));
+    assert!(has_compile_error(&format!("{}match x {{\nA(v) => {{\nprint(v)\n}}\nB => 0\n}}", src)));
+
+    let src = "
+        fn sides(shape Shape) i32 {
+            return match shape {
+                Square => 4,
+                Polygon(n) => n,
+            }
+        }
+        print(sides(Shape.Polygon(6)))
+
+        enum Shape {
+            Square
+            Polygon(i32)
+        }
+        print(sides(Shape.Square))
+    ";
+    assert_eq!(run(src), vec!["6", "4"]);
+}
+
+#[test]
+fn test_optionals_and_initialization() {
+    let src = "
+        fn find(numbers [i32], target i32) i32? {
+            for i in 0..len(numbers) {
+                if numbers[i] == target {
+                    return i
+                }
+            }
+            return none
+        }
+
+        numbers := [4, 8, 15]
+        print(find(numbers, 8))
+        print(find(numbers, 16))
+        print(find(numbers, 16) ?? -1)
+
+        mut a i64? := none
+        print(a == none)
+        a = 5
+        print(a ?? 0)
+
+        b str
+        if numbers[0] > 2 {
+            b = \"big\"
+        } else {
+            b = \"small\"
+        }
+        print(b)
+    ";
+
+    assert_eq!(run(src), vec!["1", "none", "-1", "true", "5", "big"]);
+
+    assert!(has_compile_error("a i32\nprint(a)"));
+    assert!(has_compile_error("mut a i32\nif true {\na = 1\n}\nprint(a)"));
+    assert!(has_compile_error("a i32\nwhile true {\na = 1\n}"));
+    assert!(has_compile_error("a i32? := 2\nprint(a + 1)"));
+    assert!(has_compile_error("x := none"));
+    assert!(has_compile_error("scores := [\"a\": none]"));
+}
+
+#[test]
+fn test_tuples() {
+    let src = "
+        fn divide(a i32, b i32) (i32, i32) {
+            return (a / b, a % b)
+        }
+
+        (quotient, mut remainder) := divide(17, 5)
+        remainder = remainder * 10
+        print(quotient)
+        print(remainder)
+
+        pair := ((1, \"one\"), true)
+        print(pair.0.1)
+        print(pair)
+
+        (_, flag) := pair
+        print(flag)
+
+        big (i64, bool) := (3, false)
+        print(big.0)
+    ";
+
+    assert_eq!(run(src), vec!["3", "20", "one", "((1, \"one\"), true)", "true", "3"]);
+
+    assert!(has_compile_error("(a, b) := (1, 2, 3)"));
+    assert!(has_compile_error("(a, b) := (1, 2)\na = 3"));
+    assert!(has_compile_error("p := (1, 2)\np.0 = 3"));
+    assert!(has_compile_error("p := (1, 2)\nprint(p.2)"));
+
+    // More values than a scope has registers for
+    let definitions = (0..300).map(|i| format!("a{} := {}\n", i, i)).collect::<String>();
+    assert!(has_compile_error(&format!("{}print(a0 + a299)", definitions)));
+}
+
+#[test]
+fn test_generics() {
+    let src = "
+        fn max<T>(a T, b T) T {
+            if a > b {
+                return a
+            }
+            b
+        }
+
+        class Box<T> {
+            pub value T?: none
+
+            pub fn set(mut self, value T) {
+                self.value = value
+            }
+
+            pub fn get(self) T? {
+                self.value
+            }
+        }
+
+        print(max(3, 7))
+        print(max(2.5, 1.5))
+        print(max<i64>(1, 2))
+
+        mut box := Box<i32>()
+        box.set(5)
+        print(box.get())
+
+        mut nested Box<Box<i32>> := Box()
+        nested.set(box)
+        print(nested.get())
+    ";
+
+    assert_eq!(run(src), vec!["7", "2.5", "2", "5", "Box { value: 5 }"]);
+
+    let max_src = "fn max<T>(a T, b T) T {\n    if a > b { return a }\n    b\n}\n";
+    assert!(has_compile_error(&format!("{}max(true, false)", max_src)));
+    assert!(has_compile_error(&format!("{}fn larger<T>(a T, b T) T {{ max(a, b) }}\nlarger(true, false)", max_src)));
+    assert!(has_compile_error("fn make<T>() T? { none }\nx := make()"));
+    assert!(has_compile_error("class Box<T> { pub value T?: none }\nbox := Box()"));
+    assert!(has_compile_error("class Box<T> { pub value T?: none }\nbox := Box<i32, bool>()"));
+
+    let pair_src = "class Pair<T> {\n    pub fn sum(self, a T, b T) T { a + b }\n}\n";
+    let src = format!("fn first(pairs [Pair<i32>]) Pair<i32> {{ pairs[0] }}\n{}print(first([Pair<i32>()]).sum(1, 2))", pair_src);
+    assert_eq!(run(&src), vec!["3"]);
+    assert!(has_compile_error(&format!("fn first(pairs [Pair<bool>]) {{}}\n{}", pair_src)));
+}
+
+#[test]
+fn test_traits() {
+    let src = "
+        trait Show {
+            fn show(self) str
+        }
+
+        trait Counter {
+            fn bump(mut self)
+        }
+
+        class Point {
+            pub x i32: 4
+        }
+
+        impl Show for Point {
+            fn show(self) str {
+                \"point\"
+            }
+        }
+
+        impl Counter for Point {
+            fn bump(mut self) {
+                self.x += 1
+            }
+        }
+
+        impl Show for i32 {
+            fn show(self) str {
+                \"int\"
+            }
+        }
+
+        fn describe<T: Show>(value T) str {
+            value.show()
+        }
+
+        fn describe_twice<T: Show>(value T) str {
+            show := fn() str { value.show() }
+            describe(value) + show()
+        }
+
+        class Labeled<T: Show> {
+            pub prefix str: \"a \"
+
+            pub fn label(self, value T) str {
+                self.prefix + value.show()
+            }
+        }
+
+        mut p := Point()
+        print(p.show())
+        print(describe(p))
+        print(describe(7))
+        print(describe_twice(p))
+        print(Labeled<i32>().label(1))
+        p.bump()
+        print(p.x)
+    ";
+
+    assert_eq!(run(src), vec!["point", "point", "int", "pointpoint", "a int", "5"]);
+
+    let show_src = "trait Show {\n    fn show(self) str\n}\nclass Square {\n    pub side f64: 1.0\n}\n";
+    assert!(has_compile_error(&format!("{}fn describe<T: Show>(value T) str {{ value.show() }}\ndescribe(Square())", show_src)));
+    assert!(has_compile_error(&format!("{}impl Show for Square {{\n}}", show_src)));
+    assert!(has_compile_error(&format!("{}impl Show for Square {{\n    fn show(self) i32 {{ 1 }}\n}}", show_src)));
+    assert!(has_compile_error(&format!("{}fn describe<T>(value T) str {{ value.show() }}", show_src)));
+    assert!(has_compile_error(&format!("{}fn describe<T: Missing>(value T) {{}}", show_src)));
+    assert!(has_compile_error(&format!("{}fn outer<T: Show>(value T) {{\n    fn inner(other T) str {{ other.show() }}\n}}", show_src)));
+
+    // Trait methods share the method table of the type with its builtin methods
+    let src = format!("{}impl Show for f64 {{\n    fn show(self) str {{ \"float\" }}\n}}\nx := 2.5\nprint(x.show())\nprint(x.toString())", show_src);
+    assert_eq!(run(&src), vec!["float", "2.5"]);
+    assert!(has_compile_error("trait Text {\n    fn toString(self) str\n}\nimpl Text for i32 {\n    fn toString(self) str { \"x\" }\n}"));
+}
+
+#[test]
+fn test_builtin_methods() {
+    let src = "
+        x := 5
+        print(x.toBool())
+        print(0.toBool())
+
+        small u8 := 7
+        print(small.max(9))
+        print(x.toString() + \"!\")
+
+        s := \"  Hello  \"
+        print(s.len())
+        print(s.trim().toUpper())
+        print(s.contains(\"ell\"))
+
+        print(2.7.floor())
+        print([1, 2, 3].len())
+    ";
+
+    assert_eq!(run(src), vec!["true", "false", "9", "5!", "9", "HELLO", "true", "2.0", "3"]);
+
+    let (_, result) = run_with_result("n := -1\nprint(\"ab\".repeat(n))");
+    assert!(result.is_err());
+
+    assert!(has_compile_error("x := 5\nx.toBool(1)"));
+    assert!(has_compile_error("x := 5\nx.max(true)"));
+    assert!(has_compile_error("s := \"a\"\ns.reverse()"));
+    assert!(has_compile_error("trait T { fn toBool(self) bool }\nimpl T for i32 { fn toBool(self) bool { false } }"));
+}
+
+#[test]
+fn test_closures() {
+    let src = "
+        fn makeAdder(n i32) fn(i32) i32 {
+            return fn(x i32) i32 { x + n }
+        }
+        addFive := makeAdder(5)
+        print(addFive(10))
+
+        fn makeCounter() fn() i32 {
+            mut count := 0
+            return fn() i32 {
+                count += 1
+                count
+            }
+        }
+        counter := makeCounter()
+        counter()
+        print(counter())
+        print(makeCounter()())
+
+        mut total := 0
+        add := fn(n i32) { total += n }
+        add(4)
+        add(5)
+        print(total)
+
+        fn double(x i32) i32 { x * 2 }
+        fn apply(f fn(i32) i32, x i32) i32 { f(x) }
+        print(apply(double, 21))
+        print(apply(fn(x i32) i32 {
+            y := x * 10
+            y + 1
+        }, 3))
+
+        f fn(i32) i32 := double
+        print(f)
+    ";
+
+    assert_eq!(run(src), vec!["15", "2", "1", "9", "42", "31", "<fn(i32) i32>"]);
+
+    // Each iteration has its own loop variables
+    let src = r#"
+        mut fs [fn() str] := []
+        for i in 0..3 {
+            push(fs, fn() str { "{i}" })
+        }
+        for (key, value) in ["a": 1, "b": 2] {
+            push(fs, fn() str { key + "{value}" })
+        }
+        for f in 0..len(fs) {
+            print(fs[f]())
+        }
+    "#;
+
+    assert_eq!(run(src), vec!["0", "1", "2", "a1", "b2"]);
+
+    assert!(has_compile_error("f := fn(x i32) i32 { x }\nf(true)"));
+    assert!(has_compile_error("f := fn(x i32) i32 { x }\nf(1, 2)"));
+    assert!(has_compile_error("f := fn() i32 { print(1) }"));
+    assert!(has_compile_error("x := 3\nx(1)"));
+    assert!(has_compile_error("fn id<T>(x T) T { x }\nf := id"));
+    assert!(has_compile_error("mut y i32\nf := fn() { y = 3 }"));
+}
+
+#[test]
+fn test_builtins() {
+    let path = std::env::temp_dir().join(format!("viskum_builtins_{}.txt", std::process::id()));
+    let src = format!(
+        "
+        big i64 := -7
+        print(abs(-5))
+        print(abs(big))
+        print(max(big, 2))
+        print(min(2.5, 1.5))
+        print(min(1, 2.5))
+        print(max(1, 2.5))
+        print(pow(2, 10))
+        print(sqrt(16))
+
+        print(split(\"a,b,c\", \",\"))
+        print(trim(\"  hi  \"))
+        print(contains(\"hello\", \"ell\"))
+
+        mut numbers := [3, 1, 2]
+        push(numbers, 0)
+        print(pop(numbers))
+        sort(numbers)
+        print(numbers)
+        mut words := split(\"pear apple\", \" \")
+        sort(words)
+        print(words)
+
+        write(\"{0}\", \"saved\")
+        print(read(\"{0}\"))
+        ",
+        path.display()
+    );
+
+    assert_eq!(
+        run(&src),
+        vec![
+            "5",
+            "7",
+            "2",
+            "1.5",
+            "1.0",
+            "2.5",
+            "1024",
+            "4.0",
+            "[\"a\", \"b\", \"c\"]",
+            "hi",
+            "true",
+            "0",
+            "[1, 2, 3]",
+            "[\"apple\", \"pear\"]",
+            "saved"
+        ]
+    );
+    std::fs::remove_file(path).unwrap();
+
+    let (_, result) = run_with_result("mut empty [i32] := []\nprint(pop(empty))");
+    assert_eq!(
+        result.map_err(|error| error.message),
+        Err("Cannot pop from an empty array".to_string())
+    );
+
+    assert!(has_compile_error("print(len(5))"));
+    assert!(has_compile_error("print(abs(\"x\"))"));
+    assert!(has_compile_error("print(min(1))"));
+    assert!(has_compile_error("mut a := [1]\npush(a, \"s\")"));
+    assert!(has_compile_error("sort([true])"));
+    assert!(has_compile_error("a := [1, 2]\npush(a, 3)"));
+    assert!(has_compile_error("a := [2, 1]\nsort(a)"));
+    assert!(has_compile_error("fn f(a [i32]) {\n    push(a, 1)\n}"));
+    assert!(has_compile_error("fn f(a [i32]) i32 {\n    pop(a)\n}"));
+}
+
+#[test]
+fn test_maps() {
+    let src =
+        "
+        mut ages := [\"bob\": 31, \"alice\": 27]
+        ages[\"carol\"] = 45
+        ages[\"bob\"] += 1
+        print(ages)
+        print(ages[\"alice\"])
+        print(ages.contains(\"bob\"))
+        print(ages.remove(\"alice\"))
+        print(ages.remove(\"alice\"))
+        print(len(ages))
+
+        for (name, age) in ages {
+            print(name)
+            print(age)
+        }
+
+        mut seen {i64: bool} := [:]
+        seen[7] = true
+        for key in seen {
+            print(key)
+        }
+
+        fn count(words [str]) {str: i32} {
+            mut counts {str: i32} := [:]
+            for i in 0..len(words) {
+                if counts.contains(words[i]) {
+                    counts[words[i]] += 1
+                } else {
+                    counts[words[i]] = 1
+                }
+            }
+            return counts
+        }
+        print(count(split(\"b a b\", \" \")))
+        ";
+
+    assert_eq!(
+        run(src),
+        vec![
+            "{\"bob\": 32, \"alice\": 27, \"carol\": 45}",
+            "27",
+            "true",
+            "27",
+            "none",
+            "2",
+            "bob",
+            "32",
+            "carol",
+            "45",
+            "7",
+            "{\"b\": 2, \"a\": 1}"
+        ]
+    );
+
+    let (_, result) = run_with_result("m := [\"a\": 1]\nprint(m[\"b\"])");
+    assert_eq!(
+        result.map_err(|error| error.message),
+        Err("Map has no entry with the key \"b\"".to_string())
+    );
+
+    // The loop stops when the map changes size, instead of looping forever or skipping entries
+    let src = "
+        mut m := [\"a\": 1]
+        for (k, v) in m {
+            print(k)
+            m[k + \"x\"] = 1
+        }
+    ";
+    let (lines, result) = run_with_result(src);
+    assert_eq!(lines, vec!["a"]);
+    assert_eq!(
+        result.map_err(|error| error.message),
+        Err("The map changed size while it was iterated over".to_string())
+    );
+
+    let src = "
+        mut m := [\"a\": 1, \"b\": 2, \"c\": 3]
+        for (k, v) in m {
+            print(k)
+            m.remove(k)
+        }
+    ";
+    let (lines, result) = run_with_result(src);
+    assert_eq!(lines, vec!["a"]);
+    assert_eq!(
+        result.map_err(|error| error.message),
+        Err("The map changed size while it was iterated over".to_string())
+    );
+
+    assert!(has_compile_error("m := [:]"));
+    assert!(has_compile_error("m := [\"a\": 1, 2: 3]"));
+    assert!(has_compile_error("m := [1.5: true]"));
+    assert!(has_compile_error("m := [\"a\": 1]\nprint(m[1])"));
+    assert!(has_compile_error("for (k, v) in 0..3 {\n}"));
+    assert!(has_compile_error("m := [\"a\": 1]\nm.remove(\"a\")"));
+    assert!(has_compile_error("fn f(m {str: i32}) {\n    m.remove(\"a\")\n}"));
+}
+
+#[test]
+fn test_modules() {
+    let dir = std::env::temp_dir().join(format!("viskum_modules_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("shapes")).unwrap();
+
+    let files = [
+        (
+            "math.vs",
+            "
+            print(\"math loaded\")
+            fn helper() i32 { 1 }
+            pub fn add(a i32, b i32) i32 { a + b }
+            pub fn inc(a i32) i32 { a + helper() }
+            type Id = i32
+            pub type Pair<T> = (T, T)
+            pub fn origin() Pair<Id> { (0, 0) }
+            ",
+        ),
+        (
+            "shapes/square.vs",
+            "
+            import \"../math.vs\"
+            pub class Square {
+                pub side i32: 0
+                pub fn perimeter(self) i32 { add(self.side, self.side) * 2 }
+            }
+            ",
+        ),
+        ("outer.vs", "print(x)"),
+        ("one.vs", "pub fn value() i32 { 1 }\npub class Shape {}"),
+        ("two.vs", "pub fn value() i32 { 2 }"),
+        ("cycle_a.vs", "import cycle_b"),
+        ("cycle_b.vs", "import cycle_a"),
+    ];
+    for (name, src) in files {
+        std::fs::write(dir.join(name), src).unwrap();
+    }
+
+    let path = |name: &str| dir.join(name).display().to_string();
+    let src = format!(
+        "
+        import \"{}\"
+        import \"{}\"
+        mut square := Square()
+        square.side = 3
+        print(add(2, 3))
+        print(inc(4))
+        print(square.perimeter())
+        point Pair<i32> := origin()
+        print(point)
+        ",
+        path("math.vs"),
+        path("shapes/square.vs")
+    );
+
+    assert_eq!(run(&src), vec!["math loaded", "5", "5", "12", "(0, 0)"]);
+
+    assert!(has_compile_error(&format!("import \"{}\"\nhelper()", path("math.vs"))));
+    assert!(has_compile_error(&format!("import \"{}\"\nid Id := 1", path("math.vs"))));
+    assert!(has_compile_error("import shapes/square"));
+    assert!(has_compile_error(&format!("x := 1\nimport \"{}\"", path("outer.vs"))));
+    assert!(has_compile_error(&format!("import \"{}\"", path("cycle_a.vs"))));
+    assert!(has_compile_error(&format!("import \"{}\"", path("missing.vs"))));
+    assert!(has_compile_error(&format!("fn f() {{ import \"{}\" }}", path("math.vs"))));
+
+    // Imports never replace what's already declared
+    let src = format!("import \"{}\"\nimport \"{}\"\nprint(value())", path("one.vs"), path("one.vs"));
+    assert_eq!(run(&src), vec!["1"]);
+    assert!(has_compile_error(&format!("import \"{}\"\nimport \"{}\"", path("one.vs"), path("two.vs"))));
+    assert!(has_compile_error(&format!("import \"{}\"\nfn value() i32 {{ 5 }}", path("one.vs"))));
+    assert!(has_compile_error(&format!("fn value() i32 {{ 5 }}\nimport \"{}\"", path("one.vs"))));
+    assert!(has_compile_error(&format!("class Shape {{}}\nimport \"{}\"", path("one.vs"))));
+    assert!(has_compile_error(&format!("import \"{}\"\nclass Shape {{}}", path("one.vs"))));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_string_interpolation() {
+    let src =
+        "
+        x := 3
+        a := 1.5
+        b := 2.0
+        print(\"x = {x}, y = {a + b}\")
+
+        names := [\"bob\": [1, 2]]
+        print(\"{names[\"bob\"]} has {len(names[\"bob\"])} items in {names}\")
+        print(\"{x}{x > 2}\")
+        print(\"{\"nested {x * 2}\"}!\")
+        print(\"\\{x\\} is {x}\")
+
+        fn greet(name str) str {
+            \"Hello, {name}\"
+        }
+        print(greet(\"alice\"))
+        ";
+
+    assert_eq!(
+        run(src),
+        vec![
+            "x = 3, y = 3.5",
+            "[1, 2] has 2 items in {\"bob\": [1, 2]}",
+            "3true",
+            "nested 6!",
+            "{x} is 3",
+            "Hello, alice"
+        ]
+    );
+
+    assert!(has_compile_error("print(\"{y}\")"));
+    assert!(has_compile_error("print(\"{}\")"));
+    assert!(has_compile_error("x := 1\nprint(\"{x + 1\")"));
+    assert!(has_compile_error("fn f() {}\nprint(\"{f()}\")"));
+    assert!(has_compile_error("s := \"{1}\" + 1"));
+}
@@ -74,3 +74,350 @@
 //         assert_eq!(vm._get_register(0), &Value::Int32(2));
 //     }
 // }
+
+use crate::{ compiler::Compiler, error_handler::ErrorHandler, parser::Parser, value::Value, vm::VM };
+
+fn run_to_first_variable(src: &str) -> Value {
+    let error_handler = &mut ErrorHandler::new();
+    let file_id = error_handler.add_file("test.vs".into(), src.to_string());
+
+    let src_chars = src.chars().collect::<Vec<_>>();
+    let mut parser = Parser::new(&src_chars, file_id, error_handler);
+    let ast = parser.parse_to_ast();
+
+    assert!(!error_handler.has_error(), "Failed to parse program");
+
+    let mut compiler = Compiler::new(error_handler);
+    let program = compiler.compile(ast).expect("Failed to compile program");
+
+    let mut vm = VM::new(program);
+    vm.run().expect("Runtime error");
+
+    vm._get_register(0).clone()
+}
+
+fn has_compile_error(src: &str) -> bool {
+    let error_handler = &mut ErrorHandler::new();
+    let file_id = error_handler.add_file("test.vs".into(), src.to_string());
+
+    let src_chars = src.chars().collect::<Vec<_>>();
+    let mut parser = Parser::new(&src_chars, file_id, error_handler);
+    parser.parse_to_ast();
+
+    error_handler.has_error()
+}
+
+#[test]
+fn test_if_else() {
+    let src = "
+        mut result := 0
+        if true {
+            result = 1
+        } else {
+            result = 2
+        }
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(1));
+
+    let src = "
+        mut result := 0
+        if !true {
+            result = 1
+        } else {
+            result = 2
+        }
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(2));
+
+    let src = "
+        mut result := 0
+        if false {
+            result = 1
+        }
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(0));
+}
+
+#[test]
+fn test_else_if_chains() {
+    let src = "
+        mut result := 0
+        if false {
+            result = 1
+        } else if !true {
+            result = 2
+        } else if true {
+            result = 3
+        } else {
+            result = 4
+        }
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(3));
+
+    let src = "
+        mut result := 0
+        if false {
+            result = 1
+        } else if false {
+            result = 2
+        } else {
+            result = 4
+        }
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(4));
+
+    let src = "
+        mut result := 0
+        if false {
+            result = 1
+        } else if false {
+            result = 2
+        }
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(0));
+}
+
+#[test]
+fn test_while_loops() {
+    let src = "
+        mut result := 0
+        mut running := true
+        while running {
+            result = result + 1
+            running = false
+        }
+        while false {
+            result = 10
+        }
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(1));
+
+    let src = "
+        mut result := 0
+        mut again := true
+        while true {
+            result = result + 1
+            if !again {
+                break
+            }
+            again = false
+        }
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(2));
+}
+
+#[test]
+fn test_for_range_loops() {
+    let src = "
+        mut result := 0
+        for i in 0..5 {
+            result = result + i
+        }
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(10));
+
+    let src = "
+        mut result := 0
+        for i in -2..1 {
+            result = result * 10 + i
+        }
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(-210));
+}
+
+#[test]
+fn test_break_and_continue() {
+    let src = "
+        mut result := 0
+        mut skip := true
+        for i in 1..100 {
+            if skip {
+                skip = false
+                continue
+            }
+            result = result + i
+            if !skip {
+                break
+            }
+        }
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(2));
+}
+
+#[test]
+fn test_empty_ranges() {
+    let src = "
+        mut result := 0
+        for i in 5..0 {
+            result = result + 1
+        }
+        for i in 3..3 {
+            result = result + 1
+        }
+        for i in 0..-2 {
+            result = result + 1
+        }
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(0));
+}
+
+#[test]
+fn test_function_calls() {
+    let src = "
+        fn add(a i32, b i32) i32 {
+            a + b
+        }
+
+        fn twice(n i32) i32 {
+            return add(n, n)
+        }
+
+        result := twice(add(1, 2)) * 2
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(12));
+
+    let src = "
+        fn depth(more bool) i32 {
+            if more {
+                return depth(false) + 1
+            }
+            1
+        }
+
+        result := depth(true)
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(2));
+}
+
+#[test]
+fn test_return_from_nested_blocks() {
+    let src = "
+        fn first_set(a bool, b bool) i32 {
+            if a {
+                return 1
+            } else {
+                if b {
+                    return 2
+                }
+            }
+            while true {
+                for i in 0..10 {
+                    return 3
+                }
+            }
+            4
+        }
+
+        result := first_set(false, false) * 100 + first_set(false, true) * 10 + first_set(true, true)
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(321));
+}
+
+#[test]
+fn test_comparisons() {
+    let cases = [
+        ("1 < 2", true),
+        ("2 < 2", false),
+        ("2 <= 2", true),
+        ("3 <= 2", false),
+        ("3 > 2", true),
+        ("2 > 2", false),
+        ("2 >= 2", true),
+        ("1 >= 2", false),
+        ("1 + 1 == 2", true),
+        ("1 != 1", false),
+        ("true == !false", true),
+        ("true != false", true),
+    ];
+
+    for (comparison, expected) in cases {
+        let src = format!("result := {}", comparison);
+        assert_eq!(run_to_first_variable(&src), Value::Bool(expected), "{}", comparison);
+    }
+
+    let src = "
+        mut result := 0
+        for i in 0..10 {
+            if i >= 3 {
+                break
+            }
+            result = result + 1
+        }
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(3));
+
+    assert!(!has_compile_error("result := 1 <= 2 == true"));
+    assert!(has_compile_error("result := 1 < true"));
+    assert!(has_compile_error("result := true <= false"));
+    assert!(has_compile_error("mut result := 0\nresult = 1 == 1"));
+}
+
+#[test]
+fn test_logical_operators() {
+    let cases = [
+        ("true && true", true),
+        ("true && false", false),
+        ("false && true", false),
+        ("false || false", false),
+        ("false || true", true),
+        ("true || false", true),
+        ("false || true && false", false),
+        ("true || false && false", true),
+        ("1 < 2 && 2 < 3", true),
+    ];
+
+    for (expression, expected) in cases {
+        let src = format!("mut result := {}\nresult = {}", !expected, expression);
+        assert_eq!(run_to_first_variable(&src), Value::Bool(expected), "{}", expression);
+    }
+
+    assert!(has_compile_error("result := 1 && true"));
+}
+
+#[test]
+fn test_short_circuit() {
+    // The right operand would divide by zero if it was evaluated
+    let src = "
+        fn divides(zero i32) bool {
+            1 / zero == 0
+        }
+
+        mut result := 0
+        if false && divides(0) {
+            result = 1
+        }
+        if result == 0 || divides(0) {
+            result = 2
+        }
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(2));
+
+    let src = "
+        mut result := 0
+        while result < 5 && result != 2 {
+            result = result + 1
+        }
+        if result == 0 || result == 2 && !(result > 2) {
+            result = result * 10
+        }
+    ";
+
+    assert_eq!(run_to_first_variable(src), Value::Int32(20));
+}
@@ -1,22 +1,420 @@
+use std::{ cell::RefCell, hash::{ Hash, Hasher }, rc::Rc };
+
+use indexmap::IndexMap;
+
 use crate::operations::{ BinaryOp, UnaryOp };
 
-#[derive(Debug, Clone, PartialEq, Copy)]
+// Evaluates to None if the types differ, and Some(None) on overflow. Floats use `$float_body`
+macro_rules! checked_numeric_op {
+    ($left:expr, $right:expr, $checked:ident, | $lhs:ident, $rhs:ident | $float_body:expr) => {
+        match ($left, $right) {
+            (Value::Int8(lhs), Value::Int8(rhs)) => Some(lhs.$checked(*rhs).map(Value::Int8)),
+            (Value::Int16(lhs), Value::Int16(rhs)) => Some(lhs.$checked(*rhs).map(Value::Int16)),
+            (Value::Int32(lhs), Value::Int32(rhs)) => Some(lhs.$checked(*rhs).map(Value::Int32)),
+            (Value::Int64(lhs), Value::Int64(rhs)) => Some(lhs.$checked(*rhs).map(Value::Int64)),
+            (Value::UInt8(lhs), Value::UInt8(rhs)) => Some(lhs.$checked(*rhs).map(Value::UInt8)),
+            (Value::UInt16(lhs), Value::UInt16(rhs)) => Some(lhs.$checked(*rhs).map(Value::UInt16)),
+            (Value::UInt32(lhs), Value::UInt32(rhs)) => Some(lhs.$checked(*rhs).map(Value::UInt32)),
+            (Value::UInt64(lhs), Value::UInt64(rhs)) => Some(lhs.$checked(*rhs).map(Value::UInt64)),
+            (Value::Float32($lhs), Value::Float32($rhs)) => Some(Some(Value::Float32($float_body))),
+            (Value::Float64($lhs), Value::Float64($rhs)) => Some(Some(Value::Float64($float_body))),
+            _ => None,
+        }
+    };
+}
+
+// Evaluates to None if the types differ, and Some(None) if `$body` fails
+macro_rules! checked_integer_op {
+    ($left:expr, $right:expr, | $lhs:ident, $rhs:ident | $body:expr) => {
+        match ($left, $right) {
+            (Value::Int8($lhs), Value::Int8($rhs)) => Some($body.map(Value::Int8)),
+            (Value::Int16($lhs), Value::Int16($rhs)) => Some($body.map(Value::Int16)),
+            (Value::Int32($lhs), Value::Int32($rhs)) => Some($body.map(Value::Int32)),
+            (Value::Int64($lhs), Value::Int64($rhs)) => Some($body.map(Value::Int64)),
+            (Value::UInt8($lhs), Value::UInt8($rhs)) => Some($body.map(Value::UInt8)),
+            (Value::UInt16($lhs), Value::UInt16($rhs)) => Some($body.map(Value::UInt16)),
+            (Value::UInt32($lhs), Value::UInt32($rhs)) => Some($body.map(Value::UInt32)),
+            (Value::UInt64($lhs), Value::UInt64($rhs)) => Some($body.map(Value::UInt64)),
+            _ => None,
+        }
+    };
+}
+
+macro_rules! numeric_compare {
+    ($left:expr, $right:expr, | $lhs:ident, $rhs:ident | $body:expr) => {
+        match ($left, $right) {
+            (Value::Int8($lhs), Value::Int8($rhs)) => Some($body),
+            (Value::Int16($lhs), Value::Int16($rhs)) => Some($body),
+            (Value::Int32($lhs), Value::Int32($rhs)) => Some($body),
+            (Value::Int64($lhs), Value::Int64($rhs)) => Some($body),
+            (Value::UInt8($lhs), Value::UInt8($rhs)) => Some($body),
+            (Value::UInt16($lhs), Value::UInt16($rhs)) => Some($body),
+            (Value::UInt32($lhs), Value::UInt32($rhs)) => Some($body),
+            (Value::UInt64($lhs), Value::UInt64($rhs)) => Some($body),
+            (Value::Float32($lhs), Value::Float32($rhs)) => Some($body),
+            (Value::Float64($lhs), Value::Float64($rhs)) => Some($body),
+            _ => None,
+        }
+    };
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ValueType {
+    Int8,
+    Int16,
     Int32,
+    Int64,
+    UInt8,
+    UInt16,
+    UInt32,
+    UInt64,
+    Float32,
+    Float64,
     Bool,
+    String,
+    Array(Box<ValueType>),
+    Map(Box<ValueType>, Box<ValueType>),
+    Tuple(Vec<ValueType>),
+    Class(ClassType),
+    Enum(EnumType),
+    // `none` itself is an optional of empty
+    Optional(Box<ValueType>),
+    TypeParam(Rc<str>),
+    Function(FunctionType),
     Unkown,
     Empty,
     Void,
 }
 impl ValueType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "i8" => Some(ValueType::Int8),
+            "i16" => Some(ValueType::Int16),
+            "i32" => Some(ValueType::Int32),
+            "i64" => Some(ValueType::Int64),
+            "u8" => Some(ValueType::UInt8),
+            "u16" => Some(ValueType::UInt16),
+            "u32" => Some(ValueType::UInt32),
+            "u64" => Some(ValueType::UInt64),
+            "f32" => Some(ValueType::Float32),
+            "f64" => Some(ValueType::Float64),
+            "bool" => Some(ValueType::Bool),
+            "str" => Some(ValueType::String),
+            _ => None,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            | ValueType::Int8
+            | ValueType::Int16
+            | ValueType::Int32
+            | ValueType::Int64
+            | ValueType::UInt8
+            | ValueType::UInt16
+            | ValueType::UInt32
+            | ValueType::UInt64
+        )
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, ValueType::Float32 | ValueType::Float64)
+    }
+
+    pub fn is_hashable(&self) -> bool {
+        self.is_integer() || matches!(self, ValueType::Bool | ValueType::String)
+    }
+
+    // Arrays, maps and instances are shared rather than copied when assigned
+    pub fn is_shared(&self) -> bool {
+        match self {
+            ValueType::Array(_) | ValueType::Map(_, _) | ValueType::Class(_) => true,
+            ValueType::Tuple(element_types) => element_types.iter().any(|element_type| element_type.is_shared()),
+            ValueType::Optional(inner) => inner.is_shared(),
+            _ => false,
+        }
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.is_integer() || self.is_float()
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            | ValueType::Int8
+            | ValueType::Int16
+            | ValueType::Int32
+            | ValueType::Int64
+            | ValueType::Float32
+            | ValueType::Float64
+        )
+    }
+
     pub fn is(&self, other: &ValueType) -> bool {
-        self == other
+        match (self, other) {
+            // The element type of an empty array literal isn't known, so it fits any array
+            (ValueType::Array(element_type), ValueType::Array(other_element_type)) => {
+                element_type.is(other_element_type) ||
+                    **element_type == ValueType::Empty ||
+                    **other_element_type == ValueType::Empty
+            }
+            // Likewise for the key and value types of an empty map literal
+            (ValueType::Map(key_type, value_type), ValueType::Map(other_key_type, other_value_type)) => {
+                let is_empty = **key_type == ValueType::Empty || **other_key_type == ValueType::Empty;
+
+                is_empty || (key_type.is(other_key_type) && value_type.is(other_value_type))
+            }
+            // `none` fits any optional, and a value of type T fits T?
+            (ValueType::Optional(inner), ValueType::Optional(other_inner)) => {
+                **inner == ValueType::Empty || inner.is(other_inner)
+            }
+            (_, ValueType::Optional(other_inner)) => self.is(other_inner),
+            (ValueType::Tuple(element_types), ValueType::Tuple(other_element_types)) => {
+                element_types.len() == other_element_types.len() &&
+                    element_types
+                        .iter()
+                        .zip(other_element_types)
+                        .all(|(element_type, other_element_type)| element_type.is(other_element_type))
+            }
+            (ValueType::Function(function_type), ValueType::Function(other_function_type)) => {
+                function_type.parameters.len() == other_function_type.parameters.len() &&
+                    function_type.parameters
+                        .iter()
+                        .zip(&other_function_type.parameters)
+                        .all(|(parameter, other_parameter)| parameter.is(other_parameter)) &&
+                    function_type.return_type.is(&other_function_type.return_type)
+            }
+            // Type arguments that couldn't be inferred fit any type, like in an empty array
+            (ValueType::Class(class_type), ValueType::Class(other_class_type)) => {
+                class_type.id == other_class_type.id &&
+                    class_type.type_args
+                        .iter()
+                        .zip(&other_class_type.type_args)
+                        .all(|(type_arg, other_type_arg)| {
+                            type_arg.is(other_type_arg) ||
+                                *type_arg == ValueType::Empty ||
+                                *other_type_arg == ValueType::Empty
+                        })
+            }
+            _ => self == other,
+        }
+    }
+
+    pub fn has_unknown_element_type(&self) -> bool {
+        match self {
+            ValueType::Array(element_type) => {
+                **element_type == ValueType::Empty || element_type.has_unknown_element_type()
+            }
+            ValueType::Map(key_type, value_type) => {
+                **key_type == ValueType::Empty || value_type.has_unknown_element_type()
+            }
+            ValueType::Optional(inner) => **inner == ValueType::Empty || inner.has_unknown_element_type(),
+            ValueType::Tuple(element_types) => {
+                element_types.iter().any(|element_type| element_type.has_unknown_element_type())
+            }
+            ValueType::Class(class_type) => {
+                class_type.type_args
+                    .iter()
+                    .any(|type_arg| *type_arg == ValueType::Empty || type_arg.has_unknown_element_type())
+            }
+            _ => false,
+        }
+    }
+
+    pub fn substitute(&self, type_params: &[Rc<str>], type_args: &[ValueType]) -> ValueType {
+        match self {
+            ValueType::TypeParam(name) => {
+                match type_params.iter().position(|type_param| type_param == name) {
+                    Some(index) => type_args.get(index).cloned().unwrap_or(ValueType::Empty),
+                    None => self.clone(),
+                }
+            }
+            ValueType::Array(element_type) => {
+                ValueType::Array(Box::new(element_type.substitute(type_params, type_args)))
+            }
+            ValueType::Map(key_type, value_type) => {
+                ValueType::Map(
+                    Box::new(key_type.substitute(type_params, type_args)),
+                    Box::new(value_type.substitute(type_params, type_args))
+                )
+            }
+            ValueType::Optional(inner) => {
+                // `T?` with T being an optional is still just an optional
+                match inner.substitute(type_params, type_args) {
+                    inner @ ValueType::Optional(_) => inner,
+                    inner => ValueType::Optional(Box::new(inner)),
+                }
+            }
+            ValueType::Tuple(element_types) => {
+                ValueType::Tuple(
+                    element_types
+                        .iter()
+                        .map(|element_type| element_type.substitute(type_params, type_args))
+                        .collect()
+                )
+            }
+            ValueType::Class(class_type) => {
+                ValueType::Class(ClassType {
+                    id: class_type.id,
+                    name: class_type.name.clone(),
+                    type_args: class_type.type_args
+                        .iter()
+                        .map(|type_arg| type_arg.substitute(type_params, type_args))
+                        .collect(),
+                })
+            }
+            ValueType::Function(function_type) => {
+                ValueType::Function(FunctionType {
+                    parameters: function_type.parameters
+                        .iter()
+                        .map(|parameter| parameter.substitute(type_params, type_args))
+                        .collect(),
+                    return_type: Box::new(function_type.return_type.substitute(type_params, type_args)),
+                })
+            }
+            value_type => value_type.clone(),
+        }
+    }
+
+    // Type parameters already inferred are kept, so a mismatch is reported by the type check
+    pub fn infer_type_args(
+        &self,
+        value_type: &ValueType,
+        type_params: &[Rc<str>],
+        inferred: &mut [Option<ValueType>]
+    ) {
+        match (self, value_type) {
+            (_, ValueType::Empty | ValueType::Unkown) => {}
+            (ValueType::TypeParam(name), value_type) => {
+                if let Some(index) = type_params.iter().position(|type_param| type_param == name) {
+                    if inferred[index].is_none() {
+                        inferred[index] = Some(value_type.clone());
+                    }
+                }
+            }
+            (ValueType::Array(element_type), ValueType::Array(value_element_type)) => {
+                element_type.infer_type_args(value_element_type, type_params, inferred);
+            }
+            (ValueType::Map(key_type, value_type), ValueType::Map(value_key_type, value_value_type)) => {
+                key_type.infer_type_args(value_key_type, type_params, inferred);
+                value_type.infer_type_args(value_value_type, type_params, inferred);
+            }
+            (ValueType::Optional(inner), ValueType::Optional(value_inner)) => {
+                inner.infer_type_args(value_inner, type_params, inferred);
+            }
+            (ValueType::Optional(inner), value_type) => {
+                inner.infer_type_args(value_type, type_params, inferred);
+            }
+            (ValueType::Tuple(element_types), ValueType::Tuple(value_element_types)) => {
+                for (element_type, value_element_type) in element_types.iter().zip(value_element_types) {
+                    element_type.infer_type_args(value_element_type, type_params, inferred);
+                }
+            }
+            (ValueType::Class(class_type), ValueType::Class(value_class_type)) => {
+                for (type_arg, value_type_arg) in class_type.type_args.iter().zip(&value_class_type.type_args) {
+                    type_arg.infer_type_args(value_type_arg, type_params, inferred);
+                }
+            }
+            (ValueType::Function(function_type), ValueType::Function(value_function_type)) => {
+                let parameters = function_type.parameters.iter().zip(&value_function_type.parameters);
+                for (parameter, value_parameter) in parameters {
+                    parameter.infer_type_args(value_parameter, type_params, inferred);
+                }
+                function_type.return_type.infer_type_args(
+                    &value_function_type.return_type,
+                    type_params,
+                    inferred
+                );
+            }
+            _ => {}
+        }
+    }
+
+    // The type arguments of a class aren't part of it, since they aren't known at runtime
+    pub fn get_impl_target(&self) -> Option<ImplTarget> {
+        match self {
+            ValueType::Class(class_type) => Some(ImplTarget::Class(class_type.id)),
+            ValueType::Enum(enum_type) => Some(ImplTarget::Enum(enum_type.id)),
+            | ValueType::Int8
+            | ValueType::Int16
+            | ValueType::Int32
+            | ValueType::Int64
+            | ValueType::UInt8
+            | ValueType::UInt16
+            | ValueType::UInt32
+            | ValueType::UInt64
+            | ValueType::Float32
+            | ValueType::Float64
+            | ValueType::Bool
+            | ValueType::String => Some(ImplTarget::Builtin(self.to_type_string().into())),
+            _ => None,
+        }
+    }
+
+    pub fn contains_type_param(&self) -> bool {
+        match self {
+            ValueType::TypeParam(_) => true,
+            ValueType::Array(inner) | ValueType::Optional(inner) => inner.contains_type_param(),
+            ValueType::Map(key_type, value_type) => {
+                key_type.contains_type_param() || value_type.contains_type_param()
+            }
+            ValueType::Tuple(element_types) => {
+                element_types.iter().any(|element_type| element_type.contains_type_param())
+            }
+            ValueType::Class(class_type) => {
+                class_type.type_args.iter().any(|type_arg| type_arg.contains_type_param())
+            }
+            ValueType::Function(function_type) => {
+                function_type.parameters.iter().any(|parameter| parameter.contains_type_param()) ||
+                    function_type.return_type.contains_type_param()
+            }
+            _ => false,
+        }
     }
 
     pub fn to_type_string(&self) -> String {
         match self {
+            ValueType::Int8 => "i8".to_string(),
+            ValueType::Int16 => "i16".to_string(),
             ValueType::Int32 => "i32".to_string(),
+            ValueType::Int64 => "i64".to_string(),
+            ValueType::UInt8 => "u8".to_string(),
+            ValueType::UInt16 => "u16".to_string(),
+            ValueType::UInt32 => "u32".to_string(),
+            ValueType::UInt64 => "u64".to_string(),
+            ValueType::Float32 => "f32".to_string(),
+            ValueType::Float64 => "f64".to_string(),
             ValueType::Bool => "bool".to_string(),
+            ValueType::String => "str".to_string(),
+            ValueType::Array(element_type) => format!("[{}]", element_type.to_type_string()),
+            ValueType::Map(key_type, value_type) => {
+                format!("{{{}: {}}}", key_type.to_type_string(), value_type.to_type_string())
+            }
+            ValueType::Tuple(element_types) => {
+                let element_types = element_types
+                    .iter()
+                    .map(|element_type| element_type.to_type_string())
+                    .collect::<Vec<_>>();
+
+                format!("({})", element_types.join(", "))
+            }
+            ValueType::Class(class_type) if class_type.type_args.is_empty() => {
+                class_type.name.to_string()
+            }
+            ValueType::Class(class_type) => {
+                let type_args = class_type.type_args
+                    .iter()
+                    .map(|type_arg| type_arg.to_type_string())
+                    .collect::<Vec<_>>();
+
+                format!("{}<{}>", class_type.name, type_args.join(", "))
+            }
+            ValueType::Enum(enum_type) => enum_type.name.to_string(),
+            ValueType::Optional(inner) => format!("{}?", inner.to_type_string()),
+            ValueType::TypeParam(name) => name.to_string(),
+            ValueType::Function(function_type) => function_type.to_type_string(),
             ValueType::Unkown => "unknown".to_string(),
             ValueType::Empty => "empty".to_string(),
             ValueType::Void => "void".to_string(),
@@ -24,24 +422,49 @@ impl ValueType {
     }
 
     pub fn type_check_binary(&self, other: &ValueType, op: BinaryOp) -> Result<ValueType, String> {
+        // Whether the type argument supports the operator is checked where the generic is used
+        if let (ValueType::TypeParam(_), ValueType::TypeParam(_)) = (self, other) {
+            if self == other {
+                return Ok(if op.is_comparison() { ValueType::Bool } else { self.clone() });
+            }
+        }
+
         match op {
             BinaryOp::Add => self.try_add(other),
             BinaryOp::Mul => self.try_mul(other),
             BinaryOp::Div => self.try_div(other),
             BinaryOp::Sub => self.try_sub(other),
+            BinaryOp::Mod | BinaryOp::Pow => self.try_numeric(other, op),
+            | BinaryOp::BitAnd
+            | BinaryOp::BitOr
+            | BinaryOp::BitXor
+            | BinaryOp::Shl
+            | BinaryOp::Shr => self.try_bitwise(other, op),
+            | BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::Less
+            | BinaryOp::LessEqual
+            | BinaryOp::Greater
+            | BinaryOp::GreaterEqual => self.try_compare(other, op),
         }
     }
 
     pub fn type_check_unary(&self, op: UnaryOp) -> Result<ValueType, String> {
+        if let ValueType::TypeParam(_) = self {
+            return Ok(self.clone());
+        }
+
         match op {
             UnaryOp::Neg => self.try_neg(),
-            UnaryOp::Truthy => Ok(*self),
+            UnaryOp::Truthy => Ok(self.clone()),
+            UnaryOp::BitNot => self.try_bit_not(),
         }
     }
 
     pub fn try_add(&self, other: &ValueType) -> Result<ValueType, String> {
         match (self, other) {
-            (ValueType::Int32, ValueType::Int32) => Ok(ValueType::Int32),
+            (lhs, rhs) if lhs.is_numeric() && lhs == rhs => Ok(lhs.clone()),
+            (ValueType::String, ValueType::String) => Ok(ValueType::String),
             _ =>
                 Err(
                     format!(
@@ -55,7 +478,7 @@ impl ValueType {
 
     pub fn try_mul(&self, other: &ValueType) -> Result<ValueType, String> {
         match (self, other) {
-            (ValueType::Int32, ValueType::Int32) => Ok(ValueType::Int32),
+            (lhs, rhs) if lhs.is_numeric() && lhs == rhs => Ok(lhs.clone()),
             _ =>
                 Err(
                     format!(
@@ -69,7 +492,7 @@ impl ValueType {
 
     pub fn try_div(&self, other: &ValueType) -> Result<ValueType, String> {
         match (self, other) {
-            (ValueType::Int32, ValueType::Int32) => Ok(ValueType::Int32),
+            (lhs, rhs) if lhs.is_numeric() && lhs == rhs => Ok(lhs.clone()),
             _ =>
                 Err(
                     format!(
@@ -83,7 +506,7 @@ impl ValueType {
 
     pub fn try_sub(&self, other: &ValueType) -> Result<ValueType, String> {
         match (self, other) {
-            (ValueType::Int32, ValueType::Int32) => Ok(ValueType::Int32),
+            (lhs, rhs) if lhs.is_numeric() && lhs == rhs => Ok(lhs.clone()),
             _ =>
                 Err(
                     format!(
@@ -95,60 +518,547 @@ impl ValueType {
         }
     }
 
+    pub fn try_numeric(&self, other: &ValueType, op: BinaryOp) -> Result<ValueType, String> {
+        match (self, other) {
+            (lhs, rhs) if lhs.is_numeric() && lhs == rhs => Ok(lhs.clone()),
+            _ =>
+                Err(
+                    format!(
+                        "{} is not defined for {} and {}",
+                        op.to_op_string(),
+                        self.to_type_string(),
+                        other.to_type_string()
+                    )
+                ),
+        }
+    }
+
+    pub fn try_bitwise(&self, other: &ValueType, op: BinaryOp) -> Result<ValueType, String> {
+        match (self, other) {
+            (lhs, rhs) if lhs.is_integer() && lhs == rhs => Ok(lhs.clone()),
+            _ =>
+                Err(
+                    format!(
+                        "{} is not defined for {} and {}",
+                        op.to_op_string(),
+                        self.to_type_string(),
+                        other.to_type_string()
+                    )
+                ),
+        }
+    }
+
+    pub fn try_compare(&self, other: &ValueType, op: BinaryOp) -> Result<ValueType, String> {
+        let is_equality = matches!(op, BinaryOp::Equal | BinaryOp::NotEqual);
+
+        match (self, other) {
+            (lhs, rhs) if lhs.is_numeric() && lhs == rhs => Ok(ValueType::Bool),
+            (ValueType::Bool, ValueType::Bool) if is_equality => Ok(ValueType::Bool),
+            (ValueType::String, ValueType::String) if is_equality => Ok(ValueType::Bool),
+            // An optional can be compared to `none` and to values of its inner type
+            (ValueType::Optional(_), _) | (_, ValueType::Optional(_)) if
+                is_equality && (self.is(other) || other.is(self))
+            => Ok(ValueType::Bool),
+            _ =>
+                Err(
+                    format!(
+                        "{} is not defined for {} and {}",
+                        op.to_op_string(),
+                        self.to_type_string(),
+                        other.to_type_string()
+                    )
+                ),
+        }
+    }
+
     pub fn try_neg(&self) -> Result<ValueType, String> {
-        if self == &ValueType::Int32 {
-            Ok(*self)
+        if self.is_signed() {
+            Ok(self.clone())
         } else {
             Err(format!("Negation is not defined for {}", self.to_type_string()))
         }
     }
+
+    pub fn try_bit_not(&self) -> Result<ValueType, String> {
+        if self.is_integer() {
+            Ok(self.clone())
+        } else {
+            Err(format!("Bitwise not is not defined for {}", self.to_type_string()))
+        }
+    }
+
+    pub fn try_cast(&self, value_type: &ValueType) -> Result<ValueType, String> {
+        let is_valid =
+            self == value_type ||
+            (self.is_numeric() && value_type.is_numeric()) ||
+            (self == &ValueType::Bool && value_type.is_integer());
+
+        if is_valid {
+            Ok(value_type.clone())
+        } else {
+            Err(
+                format!(
+                    "Cannot cast {} to {}",
+                    self.to_type_string(),
+                    value_type.to_type_string()
+                )
+            )
+        }
+    }
+}
+
+// Identified by id, since classes in different scopes can share a name
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassType {
+    pub id: usize,
+    pub name: Rc<str>,
+    pub type_args: Vec<ValueType>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionType {
+    pub parameters: Vec<ValueType>,
+    pub return_type: Box<ValueType>,
+}
+
+impl FunctionType {
+    pub fn to_type_string(&self) -> String {
+        let parameters = self.parameters
+            .iter()
+            .map(|parameter| parameter.to_type_string())
+            .collect::<Vec<_>>();
+
+        match *self.return_type {
+            ValueType::Void => format!("fn({})", parameters.join(", ")),
+            ref return_type => format!("fn({}) {}", parameters.join(", "), return_type.to_type_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ImplTarget {
+    Builtin(Rc<str>),
+    Class(usize),
+    Enum(usize),
+}
+
+// Fields are accessed by index, so the names are only used for printing
+#[derive(Debug, PartialEq)]
+pub struct ClassLayout {
+    pub id: usize,
+    pub name: Rc<str>,
+    pub field_names: Vec<Rc<str>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Instance {
+    pub layout: Rc<ClassLayout>,
+    pub fields: Vec<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumType {
+    pub id: usize,
+    pub name: Rc<str>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct EnumLayout {
+    pub id: usize,
+    pub name: Rc<str>,
+    pub variant_names: Vec<Rc<str>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct EnumVariant {
+    pub layout: Rc<EnumLayout>,
+    pub tag: usize,
+    pub payload: Vec<Value>,
+}
+
+// Reads the variable's register until its scope ends, then keeps the value itself
+#[derive(Debug, PartialEq)]
+pub enum Upvalue {
+    Open {
+        frame: usize,
+        scope: usize,
+        register: usize,
+    },
+    Closed(Value),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Closure {
+    pub function: usize,
+    pub function_type: FunctionType,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapKey(Value);
+
+impl MapKey {
+    pub fn new(value: &Value) -> Result<Self, String> {
+        if value.to_value_type().is_hashable() {
+            Ok(Self(value.clone()))
+        } else {
+            Err(format!("{} cannot be used as a map key", value.to_value_type().to_type_string()))
+        }
+    }
+
+    pub fn get_value(&self) -> &Value {
+        &self.0
+    }
+}
+
+impl Eq for MapKey {}
+
+impl Hash for MapKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match &self.0 {
+            Value::Int8(int) => int.hash(state),
+            Value::Int16(int) => int.hash(state),
+            Value::Int32(int) => int.hash(state),
+            Value::Int64(int) => int.hash(state),
+            Value::UInt8(int) => int.hash(state),
+            Value::UInt16(int) => int.hash(state),
+            Value::UInt32(int) => int.hash(state),
+            Value::UInt64(int) => int.hash(state),
+            Value::Bool(bool) => bool.hash(state),
+            Value::String(string) => string.hash(state),
+            _ => {}
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+// Strings, arrays, maps and instances are reference counted, so writes are shared
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
+    Int8(i8),
+    Int16(i16),
     Int32(i32),
+    Int64(i64),
+    UInt8(u8),
+    UInt16(u16),
+    UInt32(u32),
+    UInt64(u64),
+    Float32(f32),
+    Float64(f64),
     Bool(bool),
+    String(Rc<str>),
+    Array(Rc<RefCell<Vec<Value>>>),
+    // Keeps the insertion order, so iterating a map is deterministic
+    Map(Rc<RefCell<IndexMap<MapKey, Value>>>),
+    Tuple(Rc<Vec<Value>>),
+    Instance(Rc<RefCell<Instance>>),
+    Enum(Rc<EnumVariant>),
+    Function(Rc<Closure>),
+    None,
     Empty,
 }
 
 impl Value {
     pub fn to_string(&self) -> String {
         match self {
+            Value::Int8(int) => int.to_string(),
+            Value::Int16(int) => int.to_string(),
             Value::Int32(int32) => int32.to_string(),
+            Value::Int64(int) => int.to_string(),
+            Value::UInt8(int) => int.to_string(),
+            Value::UInt16(int) => int.to_string(),
+            Value::UInt32(int) => int.to_string(),
+            Value::UInt64(int) => int.to_string(),
+            // Debug formatting keeps the decimal point, so `1.0` isn't printed as `1`
+            Value::Float32(float) => format!("{:?}", float),
+            Value::Float64(float) => format!("{:?}", float),
             Value::Bool(bool) => bool.to_string(),
+            Value::String(string) => string.to_string(),
+            Value::Array(elements) => {
+                let elements = elements
+                    .borrow()
+                    .iter()
+                    .map(|element| element.to_nested_string())
+                    .collect::<Vec<_>>();
+
+                format!("[{}]", elements.join(", "))
+            }
+            Value::Map(entries) => {
+                let entries = entries
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| {
+                        format!("{}: {}", key.0.to_nested_string(), value.to_nested_string())
+                    })
+                    .collect::<Vec<_>>();
+
+                format!("{{{}}}", entries.join(", "))
+            }
+            Value::Tuple(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|element| element.to_nested_string())
+                    .collect::<Vec<_>>();
+
+                format!("({})", elements.join(", "))
+            }
+            Value::Instance(instance) => {
+                let instance = instance.borrow();
+
+                let fields = instance.layout.field_names
+                    .iter()
+                    .zip(&instance.fields)
+                    .map(|(name, value)| format!("{}: {}", name, value.to_nested_string()))
+                    .collect::<Vec<_>>();
+
+                if fields.is_empty() {
+                    format!("{} {{}}", instance.layout.name)
+                } else {
+                    format!("{} {{ {} }}", instance.layout.name, fields.join(", "))
+                }
+            }
+            Value::Enum(variant) => {
+                let name = &variant.layout.variant_names[variant.tag];
+
+                if variant.payload.is_empty() {
+                    format!("{}.{}", variant.layout.name, name)
+                } else {
+                    let payload = variant.payload
+                        .iter()
+                        .map(|value| value.to_nested_string())
+                        .collect::<Vec<_>>();
+
+                    format!("{}.{}({})", variant.layout.name, name, payload.join(", "))
+                }
+            }
+            Value::Function(closure) => format!("<{}>", closure.function_type.to_type_string()),
+            Value::None => "none".to_string(),
             Value::Empty => "empty".to_string(),
         }
     }
 
+    fn to_nested_string(&self) -> String {
+        match self {
+            Value::String(string) => format!("{:?}", string),
+            value => value.to_string(),
+        }
+    }
+
     pub fn to_value_type(&self) -> ValueType {
         match self {
+            Value::Int8(_) => ValueType::Int8,
+            Value::Int16(_) => ValueType::Int16,
             Value::Int32(_) => ValueType::Int32,
+            Value::Int64(_) => ValueType::Int64,
+            Value::UInt8(_) => ValueType::UInt8,
+            Value::UInt16(_) => ValueType::UInt16,
+            Value::UInt32(_) => ValueType::UInt32,
+            Value::UInt64(_) => ValueType::UInt64,
+            Value::Float32(_) => ValueType::Float32,
+            Value::Float64(_) => ValueType::Float64,
             Value::Bool(_) => ValueType::Bool,
+            Value::String(_) => ValueType::String,
+            Value::Array(elements) => {
+                let element_type = match elements.borrow().first() {
+                    Some(element) => element.to_value_type(),
+                    None => ValueType::Empty,
+                };
+
+                ValueType::Array(Box::new(element_type))
+            }
+            Value::Map(entries) => {
+                let (key_type, value_type) = match entries.borrow().first() {
+                    Some((key, value)) => (key.0.to_value_type(), value.to_value_type()),
+                    None => (ValueType::Empty, ValueType::Empty),
+                };
+
+                ValueType::Map(Box::new(key_type), Box::new(value_type))
+            }
+            Value::Tuple(elements) => {
+                ValueType::Tuple(elements.iter().map(|element| element.to_value_type()).collect())
+            }
+            Value::Instance(instance) => {
+                let layout = &instance.borrow().layout;
+
+                // The type arguments aren't known at runtime
+                ValueType::Class(ClassType {
+                    id: layout.id,
+                    name: layout.name.clone(),
+                    type_args: Vec::new(),
+                })
+            }
+            Value::Enum(variant) => {
+                let layout = &variant.layout;
+
+                ValueType::Enum(EnumType { id: layout.id, name: layout.name.clone() })
+            }
+            Value::Function(closure) => ValueType::Function(closure.function_type.clone()),
+            Value::None => ValueType::Optional(Box::new(ValueType::Empty)),
             Value::Empty => ValueType::Empty,
         }
     }
 
+    pub fn new_array(elements: Vec<Value>) -> Self {
+        Value::Array(Rc::new(RefCell::new(elements)))
+    }
+
+    pub fn new_map(entries: IndexMap<MapKey, Value>) -> Self {
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    pub fn new_tuple(elements: Vec<Value>) -> Self {
+        Value::Tuple(Rc::new(elements))
+    }
+
+    pub fn new_instance(layout: Rc<ClassLayout>, fields: Vec<Value>) -> Self {
+        Value::Instance(Rc::new(RefCell::new(Instance { layout, fields })))
+    }
+
+    pub fn new_enum(layout: Rc<EnumLayout>, tag: usize, payload: Vec<Value>) -> Self {
+        Value::Enum(Rc::new(EnumVariant { layout, tag, payload }))
+    }
+
     pub fn add(&self, other: &Value) -> Result<Self, String> {
-        match (self, other) {
-            (Value::Int32(lhs), Value::Int32(rhs)) => Ok(Value::Int32(lhs + rhs)),
-            _ =>
-                Err(
-                    format!(
-                        "Addition is not defined for {} and {}",
-                        self.to_value_type().to_type_string(),
-                        other.to_value_type().to_type_string()
-                    )
-                ),
+        if let (Value::String(lhs), Value::String(rhs)) = (self, other) {
+            return Ok(Value::String(format!("{}{}", lhs, rhs).into()));
         }
+
+        let result = checked_numeric_op!(self, other, checked_add, |lhs, rhs| lhs + rhs);
+        self.arithmetic_result(other, BinaryOp::Add, result)
     }
 
     pub fn mul(&self, other: &Value) -> Result<Self, String> {
-        match (self, other) {
-            (Value::Int32(lhs), Value::Int32(rhs)) => Ok(Value::Int32(lhs * rhs)),
-            _ =>
+        let result = checked_numeric_op!(self, other, checked_mul, |lhs, rhs| lhs * rhs);
+        self.arithmetic_result(other, BinaryOp::Mul, result)
+    }
+
+    pub fn div(&self, other: &Value) -> Result<Self, String> {
+        let result = checked_numeric_op!(self, other, checked_div, |lhs, rhs| lhs / rhs);
+        self.arithmetic_result(other, BinaryOp::Div, result)
+    }
+
+    pub fn sub(&self, other: &Value) -> Result<Self, String> {
+        let result = checked_numeric_op!(self, other, checked_sub, |lhs, rhs| lhs - rhs);
+        self.arithmetic_result(other, BinaryOp::Sub, result)
+    }
+
+    pub fn rem(&self, other: &Value) -> Result<Self, String> {
+        let result = checked_numeric_op!(self, other, checked_rem, |lhs, rhs| lhs % rhs);
+        self.arithmetic_result(other, BinaryOp::Mod, result)
+    }
+
+    // Integer exponents must be positive, since the result would be a fraction otherwise
+    pub fn pow(&self, other: &Value) -> Result<Self, String> {
+        let result = match (self, other) {
+            (Value::Float32(lhs), Value::Float32(rhs)) => Some(Some(Value::Float32(lhs.powf(*rhs)))),
+            (Value::Float64(lhs), Value::Float64(rhs)) => Some(Some(Value::Float64(lhs.powf(*rhs)))),
+            _ => {
+                checked_integer_op!(self, other, |lhs, rhs| {
+                    u32::try_from(*rhs as i128)
+                        .ok()
+                        .and_then(|exponent| lhs.checked_pow(exponent))
+                })
+            }
+        };
+        self.arithmetic_result(other, BinaryOp::Pow, result)
+    }
+
+    pub fn bit_and(&self, other: &Value) -> Result<Self, String> {
+        let result = checked_integer_op!(self, other, |lhs, rhs| Some(lhs & rhs));
+        self.arithmetic_result(other, BinaryOp::BitAnd, result)
+    }
+
+    pub fn bit_or(&self, other: &Value) -> Result<Self, String> {
+        let result = checked_integer_op!(self, other, |lhs, rhs| Some(lhs | rhs));
+        self.arithmetic_result(other, BinaryOp::BitOr, result)
+    }
+
+    pub fn bit_xor(&self, other: &Value) -> Result<Self, String> {
+        let result = checked_integer_op!(self, other, |lhs, rhs| Some(lhs ^ rhs));
+        self.arithmetic_result(other, BinaryOp::BitXor, result)
+    }
+
+    pub fn shl(&self, other: &Value) -> Result<Self, String> {
+        let result = checked_integer_op!(self, other, |lhs, rhs| {
+            u32::try_from(*rhs as i128)
+                .ok()
+                .and_then(|amount| lhs.checked_shl(amount))
+        });
+        self.arithmetic_result(other, BinaryOp::Shl, result)
+    }
+
+    pub fn shr(&self, other: &Value) -> Result<Self, String> {
+        let result = checked_integer_op!(self, other, |lhs, rhs| {
+            u32::try_from(*rhs as i128)
+                .ok()
+                .and_then(|amount| lhs.checked_shr(amount))
+        });
+        self.arithmetic_result(other, BinaryOp::Shr, result)
+    }
+
+    pub fn binary_op(&self, other: &Value, op: BinaryOp) -> Result<Self, String> {
+        match op {
+            BinaryOp::Add => self.add(other),
+            BinaryOp::Sub => self.sub(other),
+            BinaryOp::Mul => self.mul(other),
+            BinaryOp::Div => self.div(other),
+            BinaryOp::Mod => self.rem(other),
+            BinaryOp::Pow => self.pow(other),
+            BinaryOp::BitAnd => self.bit_and(other),
+            BinaryOp::BitOr => self.bit_or(other),
+            BinaryOp::BitXor => self.bit_xor(other),
+            BinaryOp::Shl => self.shl(other),
+            BinaryOp::Shr => self.shr(other),
+            | BinaryOp::Equal
+            | BinaryOp::NotEqual
+            | BinaryOp::Less
+            | BinaryOp::LessEqual
+            | BinaryOp::Greater
+            | BinaryOp::GreaterEqual => self.compare(other, op),
+        }
+    }
+
+    fn arithmetic_result(
+        &self,
+        other: &Value,
+        op: BinaryOp,
+        result: Option<Option<Value>>
+    ) -> Result<Self, String> {
+        match result {
+            Some(Some(value)) => Ok(value),
+            // Integer division also fails when the result overflows, as in `-128 / -1` for i8
+            Some(None) if matches!(op, BinaryOp::Div) && other.is_integer_zero() => {
+                Err("Division by zero".to_string())
+            }
+            Some(None) if matches!(op, BinaryOp::Mod) && other.is_integer_zero() => {
+                Err("Modulo by zero".to_string())
+            }
+            Some(None) if matches!(op, BinaryOp::Shl | BinaryOp::Shr) => {
                 Err(
                     format!(
-                        "Multiplication is not defined for {} and {}",
+                        "Shift amount {} is out of range for {}",
+                        other.to_string(),
+                        self.to_value_type().to_type_string()
+                    )
+                )
+            }
+            Some(None) if matches!(op, BinaryOp::Pow) && other.is_negative() => {
+                Err(format!("Integer exponent {} is negative", other.to_string()))
+            }
+            Some(None) =>
+                Err(
+                    format!(
+                        "{} of {} and {} overflows {}",
+                        op.to_op_string(),
+                        self.to_string(),
+                        other.to_string(),
+                        self.to_value_type().to_type_string()
+                    )
+                ),
+            None =>
+                Err(
+                    format!(
+                        "{} is not defined for {} and {}",
+                        op.to_op_string(),
                         self.to_value_type().to_type_string(),
                         other.to_value_type().to_type_string()
                     )
@@ -156,38 +1066,184 @@ impl Value {
         }
     }
 
-    pub fn div(&self, other: &Value) -> Result<Self, String> {
-        match (self, other) {
-            (Value::Int32(lhs), Value::Int32(rhs)) => Ok(Value::Int32(lhs / rhs)),
+    fn is_integer_zero(&self) -> bool {
+        matches!(Number::from_value(self), Some(Number::Integer(0)))
+    }
+
+    fn is_negative(&self) -> bool {
+        matches!(Number::from_value(self), Some(Number::Integer(int)) if int < 0)
+    }
+
+    pub fn compare(&self, other: &Value, op: BinaryOp) -> Result<Self, String> {
+        let result = match op {
+            BinaryOp::Equal if self.is_comparable_to(other) => Some(self == other),
+            BinaryOp::NotEqual if self.is_comparable_to(other) => Some(self != other),
+            BinaryOp::Less => numeric_compare!(self, other, |lhs, rhs| lhs < rhs),
+            BinaryOp::LessEqual => numeric_compare!(self, other, |lhs, rhs| lhs <= rhs),
+            BinaryOp::Greater => numeric_compare!(self, other, |lhs, rhs| lhs > rhs),
+            BinaryOp::GreaterEqual => numeric_compare!(self, other, |lhs, rhs| lhs >= rhs),
+            _ => None,
+        };
+
+        result.map(Value::Bool).ok_or_else(|| {
+            format!(
+                "{} is not defined for {} and {}",
+                op.to_op_string(),
+                self.to_value_type().to_type_string(),
+                other.to_value_type().to_type_string()
+            )
+        })
+    }
+
+    fn is_comparable_to(&self, other: &Value) -> bool {
+        let is_same_number = numeric_compare!(self, other, |_lhs, _rhs| true).is_some();
+
+        is_same_number ||
+            matches!(
+                (self, other),
+                | (Value::Bool(_), Value::Bool(_))
+                | (Value::String(_), Value::String(_))
+                | (Value::None, _)
+                | (_, Value::None)
+            )
+    }
+
+    pub fn get_index(&self, index: &Value) -> Result<Self, String> {
+        match (self, index) {
+            (Value::Map(entries), key) => {
+                match entries.borrow().get(&MapKey::new(key)?) {
+                    Some(value) => Ok(value.clone()),
+                    None => Err(format!("Map has no entry with the key {}", key.to_nested_string())),
+                }
+            }
+            (Value::Array(elements), Value::Int32(index)) => {
+                let elements = elements.borrow();
+
+                match usize::try_from(*index).ok().and_then(|index| elements.get(index)) {
+                    Some(element) => Ok(element.clone()),
+                    None => Err(Self::index_out_of_bounds(elements.len(), *index)),
+                }
+            }
             _ =>
                 Err(
                     format!(
-                        "Division is not defined for {} and {}",
+                        "Cannot index into {} with {}",
                         self.to_value_type().to_type_string(),
-                        other.to_value_type().to_type_string()
+                        index.to_value_type().to_type_string()
                     )
                 ),
         }
     }
 
-    pub fn sub(&self, other: &Value) -> Result<Self, String> {
-        match (self, other) {
-            (Value::Int32(lhs), Value::Int32(rhs)) => Ok(Value::Int32(lhs - rhs)),
+    pub fn set_index(&self, index: &Value, value: Value) -> Result<(), String> {
+        match (self, index) {
+            (Value::Map(entries), key) => {
+                entries.borrow_mut().insert(MapKey::new(key)?, value);
+                Ok(())
+            }
+            (Value::Array(elements), Value::Int32(index)) => {
+                let mut elements = elements.borrow_mut();
+                let length = elements.len();
+
+                match usize::try_from(*index).ok().and_then(|index| elements.get_mut(index)) {
+                    Some(element) => {
+                        *element = value;
+                        Ok(())
+                    }
+                    None => Err(Self::index_out_of_bounds(length, *index)),
+                }
+            }
             _ =>
                 Err(
                     format!(
-                        "Subtraction is not defined for {} and {}",
+                        "Cannot index into {} with {}",
                         self.to_value_type().to_type_string(),
-                        other.to_value_type().to_type_string()
+                        index.to_value_type().to_type_string()
                     )
                 ),
         }
     }
 
+    fn index_out_of_bounds(length: usize, index: i32) -> String {
+        format!("Index out of bounds: the length is {} but the index is {}", length, index)
+    }
+
+    pub fn get_field(&self, field: usize) -> Result<Self, String> {
+        match self {
+            Value::Instance(instance) => Ok(instance.borrow().fields[field].clone()),
+            Value::Tuple(elements) => Ok(elements[field].clone()),
+            v => Err(format!("{} has no fields", v.to_value_type().to_type_string())),
+        }
+    }
+
+    pub fn set_field(&self, field: usize, value: Value) -> Result<(), String> {
+        match self {
+            Value::Instance(instance) => {
+                instance.borrow_mut().fields[field] = value;
+                Ok(())
+            }
+            v => Err(format!("{} has no fields", v.to_value_type().to_type_string())),
+        }
+    }
+
+    pub fn get_tag(&self) -> Result<Self, String> {
+        match self {
+            Value::Enum(variant) => Ok(Value::Int32(variant.tag as i32)),
+            v => Err(format!("{} is not an enum", v.to_value_type().to_type_string())),
+        }
+    }
+
+    pub fn get_payload(&self, index: usize) -> Result<Self, String> {
+        match self {
+            Value::Enum(variant) => Ok(variant.payload[index].clone()),
+            v => Err(format!("{} is not an enum", v.to_value_type().to_type_string())),
+        }
+    }
+
+    pub fn len(&self) -> Result<Self, String> {
+        match self {
+            Value::Array(elements) => Ok(Value::Int32(elements.borrow().len() as i32)),
+            Value::Map(entries) => Ok(Value::Int32(entries.borrow().len() as i32)),
+            Value::String(string) => Ok(Value::Int32(string.chars().count() as i32)),
+            v => Err(format!("Length is not defined for {}", v.to_value_type().to_type_string())),
+        }
+    }
+
     pub fn neg(&self) -> Result<Self, String> {
+        let result = match self {
+            Value::Int8(int) => int.checked_neg().map(Value::Int8),
+            Value::Int16(int) => int.checked_neg().map(Value::Int16),
+            Value::Int32(int32) => int32.checked_neg().map(Value::Int32),
+            Value::Int64(int) => int.checked_neg().map(Value::Int64),
+            Value::Float32(float) => Some(Value::Float32(-float)),
+            Value::Float64(float) => Some(Value::Float64(-float)),
+            v => {
+                return Err(
+                    format!("Negation is not defined for {}", v.to_value_type().to_type_string())
+                );
+            }
+        };
+
+        result.ok_or_else(|| {
+            format!(
+                "Negation of {} overflows {}",
+                self.to_string(),
+                self.to_value_type().to_type_string()
+            )
+        })
+    }
+
+    pub fn bit_not(&self) -> Result<Self, String> {
         match self {
-            Value::Int32(int32) => Ok(Value::Int32(-int32)),
-            v => Err(format!("Negation is not defined for {}", v.to_value_type().to_type_string())),
+            Value::Int8(int) => Ok(Value::Int8(!int)),
+            Value::Int16(int) => Ok(Value::Int16(!int)),
+            Value::Int32(int) => Ok(Value::Int32(!int)),
+            Value::Int64(int) => Ok(Value::Int64(!int)),
+            Value::UInt8(int) => Ok(Value::UInt8(!int)),
+            Value::UInt16(int) => Ok(Value::UInt16(!int)),
+            Value::UInt32(int) => Ok(Value::UInt32(!int)),
+            Value::UInt64(int) => Ok(Value::UInt64(!int)),
+            v => Err(format!("Bitwise not is not defined for {}", v.to_value_type().to_type_string())),
         }
     }
 
@@ -195,7 +1251,117 @@ impl Value {
         match self {
             Value::Bool(bool) => Value::Bool(!*bool),
             Value::Int32(int32) => Value::Bool(!*int32 != 0),
+            Value::Int8(int) => Value::Bool(*int == 0),
+            Value::Int16(int) => Value::Bool(*int == 0),
+            Value::Int64(int) => Value::Bool(*int == 0),
+            Value::UInt8(int) => Value::Bool(*int == 0),
+            Value::UInt16(int) => Value::Bool(*int == 0),
+            Value::UInt32(int) => Value::Bool(*int == 0),
+            Value::UInt64(int) => Value::Bool(*int == 0),
+            Value::Float32(float) => Value::Bool(*float == 0.0),
+            Value::Float64(float) => Value::Bool(*float == 0.0),
+            Value::String(string) => Value::Bool(string.is_empty()),
+            Value::Array(elements) => Value::Bool(elements.borrow().is_empty()),
+            Value::Map(entries) => Value::Bool(entries.borrow().is_empty()),
+            Value::Tuple(_) | Value::Instance(_) | Value::Enum(_) | Value::Function(_) => {
+                Value::Bool(false)
+            }
+            Value::None => Value::Bool(true),
             Value::Empty => Value::Empty,
         }
     }
+
+    // Follows the truncating and saturating rules of Rust's `as`
+    pub fn cast(&self, value_type: &ValueType) -> Result<Self, String> {
+        if &self.to_value_type() == value_type {
+            return Ok(self.clone());
+        }
+
+        let number = match self {
+            Value::Bool(bool) => Some(Number::Integer(*bool as i128)),
+            value => Number::from_value(value),
+        };
+
+        number.and_then(|number| number.to_value(value_type)).ok_or_else(|| {
+            format!(
+                "Cannot cast {} to {}",
+                self.to_value_type().to_type_string(),
+                value_type.to_type_string()
+            )
+        })
+    }
+
+    // Only if the literal fits without loss, so `x u8 := 200` type checks
+    pub fn convert_literal(&self, value_type: &ValueType) -> Option<Self> {
+        match Number::from_value(self)? {
+            Number::Integer(int) if value_type.is_integer() => {
+                let converted = Number::Integer(int).to_value(value_type)?;
+
+                match Number::from_value(&converted)? {
+                    Number::Integer(result) if result == int => Some(converted),
+                    _ => None,
+                }
+            }
+            Number::Integer(int) if value_type.is_float() => {
+                Number::Float(int as f64).to_value(value_type)
+            }
+            Number::Float(float) if value_type.is_float() => {
+                Number::Float(float).to_value(value_type)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Number {
+    Integer(i128),
+    Float(f64),
+}
+
+impl Number {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Int8(int) => Some(Number::Integer(*int as i128)),
+            Value::Int16(int) => Some(Number::Integer(*int as i128)),
+            Value::Int32(int) => Some(Number::Integer(*int as i128)),
+            Value::Int64(int) => Some(Number::Integer(*int as i128)),
+            Value::UInt8(int) => Some(Number::Integer(*int as i128)),
+            Value::UInt16(int) => Some(Number::Integer(*int as i128)),
+            Value::UInt32(int) => Some(Number::Integer(*int as i128)),
+            Value::UInt64(int) => Some(Number::Integer(*int as i128)),
+            Value::Float32(float) => Some(Number::Float(*float as f64)),
+            Value::Float64(float) => Some(Number::Float(*float)),
+            _ => None,
+        }
+    }
+
+    fn to_value(self, value_type: &ValueType) -> Option<Value> {
+        macro_rules! convert {
+            ($variant:ident, $ty:ty) => {
+                match self {
+                    Number::Integer(int) => Value::$variant(int as $ty),
+                    Number::Float(float) => Value::$variant(float as $ty),
+                }
+            };
+        }
+
+        let value = match value_type {
+            ValueType::Int8 => convert!(Int8, i8),
+            ValueType::Int16 => convert!(Int16, i16),
+            ValueType::Int32 => convert!(Int32, i32),
+            ValueType::Int64 => convert!(Int64, i64),
+            ValueType::UInt8 => convert!(UInt8, u8),
+            ValueType::UInt16 => convert!(UInt16, u16),
+            ValueType::UInt32 => convert!(UInt32, u32),
+            ValueType::UInt64 => convert!(UInt64, u64),
+            ValueType::Float32 => convert!(Float32, f32),
+            ValueType::Float64 => convert!(Float64, f64),
+            _ => {
+                return None;
+            }
+        };
+
+        Some(value)
+    }
 }
@@ -0,0 +1,218 @@
+use std::{ cmp::Ordering, io::BufRead, rc::Rc };
+
+use crate::{ operations::BinaryOp, value::{ Value, ValueType } };
+
+use super::{ max, min, Builtin, TypeBound };
+
+fn type_param() -> ValueType {
+    ValueType::TypeParam("T".into())
+}
+
+fn array_of(element_type: ValueType) -> ValueType {
+    ValueType::Array(Box::new(element_type))
+}
+
+pub(super) fn get_builtins() -> Vec<Builtin> {
+    vec![
+        // Math
+        Builtin::new("abs", TypeBound::Number, &[type_param()], type_param(), true, abs),
+        Builtin::new("min", TypeBound::Number, &[type_param(), type_param()], type_param(), true, |args| {
+            min(&args[0], &args[1..])
+        }),
+        Builtin::new("max", TypeBound::Number, &[type_param(), type_param()], type_param(), true, |args| {
+            max(&args[0], &args[1..])
+        }),
+        Builtin::new("pow", TypeBound::Number, &[type_param(), type_param()], type_param(), true, |args| {
+            args[0].pow(&args[1])
+        }),
+        Builtin::new("sqrt", TypeBound::Any, &[ValueType::Float64], ValueType::Float64, true, sqrt),
+
+        // Strings
+        Builtin::new("len", TypeBound::Sized, &[type_param()], ValueType::Int32, true, |args| args[0].len()),
+        Builtin::new(
+            "split",
+            TypeBound::Any,
+            &[ValueType::String, ValueType::String],
+            array_of(ValueType::String),
+            true,
+            split
+        ),
+        Builtin::new("trim", TypeBound::Any, &[ValueType::String], ValueType::String, true, |args| {
+            Ok(Value::String(get_string(&args[0])?.trim().into()))
+        }),
+        Builtin::new(
+            "contains",
+            TypeBound::Any,
+            &[ValueType::String, ValueType::String],
+            ValueType::Bool,
+            true,
+            |args| Ok(Value::Bool(get_string(&args[0])?.contains(&**get_string(&args[1])?)))
+        ),
+
+        // Arrays
+        Builtin::new(
+            "push",
+            TypeBound::Any,
+            &[array_of(type_param()), type_param()],
+            ValueType::Void,
+            false,
+            push
+        ).mutating(),
+        Builtin::new("pop", TypeBound::Any, &[array_of(type_param())], type_param(), false, pop).mutating(),
+        Builtin::new(
+            "sort",
+            TypeBound::Comparable,
+            &[array_of(type_param())],
+            ValueType::Void,
+            false,
+            sort
+        ).mutating(),
+
+        // Input and output
+        Builtin::new("read_line", TypeBound::Any, &[], ValueType::String, false, read_line),
+        Builtin::new("read", TypeBound::Any, &[ValueType::String], ValueType::String, false, read),
+        Builtin::new(
+            "write",
+            TypeBound::Any,
+            &[ValueType::String, ValueType::String],
+            ValueType::Void,
+            false,
+            write
+        )
+    ]
+}
+
+fn get_string(value: &Value) -> Result<&Rc<str>, String> {
+    match value {
+        Value::String(string) => Ok(string),
+        value => Err(format!("{} is not a string", value.to_value_type().to_type_string())),
+    }
+}
+
+fn abs(args: &[Value]) -> Result<Value, String> {
+    let zero = Value::Int8(0).cast(&args[0].to_value_type())?;
+
+    match args[0].compare(&zero, BinaryOp::Less)? {
+        Value::Bool(true) => args[0].neg(),
+        _ => Ok(args[0].clone()),
+    }
+}
+
+fn sqrt(args: &[Value]) -> Result<Value, String> {
+    match &args[0] {
+        Value::Float64(float) if *float >= 0.0 => Ok(Value::Float64(float.sqrt())),
+        Value::Float64(float) => {
+            Err(format!("Cannot take the square root of a negative number ({})", float))
+        }
+        value => Err(format!("{} is not a f64", value.to_value_type().to_type_string())),
+    }
+}
+
+// An empty separator splits the string into its characters
+fn split(args: &[Value]) -> Result<Value, String> {
+    let string = get_string(&args[0])?;
+    let separator = get_string(&args[1])?;
+
+    let parts = if separator.is_empty() {
+        string
+            .chars()
+            .map(|char| Value::String(char.to_string().into()))
+            .collect()
+    } else {
+        string
+            .split(&**separator)
+            .map(|part| Value::String(part.into()))
+            .collect()
+    };
+
+    Ok(Value::new_array(parts))
+}
+
+fn push(args: &[Value]) -> Result<Value, String> {
+    match &args[0] {
+        Value::Array(elements) => {
+            elements.borrow_mut().push(args[1].clone());
+            Ok(Value::Empty)
+        }
+        value => Err(format!("Cannot push to {}", value.to_value_type().to_type_string())),
+    }
+}
+
+fn pop(args: &[Value]) -> Result<Value, String> {
+    match &args[0] {
+        Value::Array(elements) => {
+            elements
+                .borrow_mut()
+                .pop()
+                .ok_or_else(|| "Cannot pop from an empty array".to_string())
+        }
+        value => Err(format!("Cannot pop from {}", value.to_value_type().to_type_string())),
+    }
+}
+
+fn sort(args: &[Value]) -> Result<Value, String> {
+    let Value::Array(elements) = &args[0] else {
+        return Err(format!("Cannot sort {}", args[0].to_value_type().to_type_string()));
+    };
+
+    let mut error = None;
+    elements.borrow_mut().sort_by(|a, b| {
+        match compare(a, b) {
+            Ok(ordering) => ordering,
+            Err(e) => {
+                error.get_or_insert(e);
+                Ordering::Equal
+            }
+        }
+    });
+
+    match error {
+        Some(e) => Err(e),
+        None => Ok(Value::Empty),
+    }
+}
+
+fn compare(a: &Value, b: &Value) -> Result<Ordering, String> {
+    if let (Value::String(a), Value::String(b)) = (a, b) {
+        return Ok(a.cmp(b));
+    }
+
+    if a.compare(b, BinaryOp::Less)? == Value::Bool(true) {
+        Ok(Ordering::Less)
+    } else if a.compare(b, BinaryOp::Greater)? == Value::Bool(true) {
+        Ok(Ordering::Greater)
+    } else {
+        Ok(Ordering::Equal)
+    }
+}
+
+fn read_line(_: &[Value]) -> Result<Value, String> {
+    let mut line = String::new();
+
+    std::io
+        ::stdin()
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| format!("Cannot read from the standard input: {}", e))?;
+
+    Ok(Value::String(line.trim_end_matches(['\n', '\r']).into()))
+}
+
+fn read(args: &[Value]) -> Result<Value, String> {
+    let path = get_string(&args[0])?;
+
+    std::fs
+        ::read_to_string(&**path)
+        .map(|content| Value::String(content.into()))
+        .map_err(|e| format!("Cannot read file '{}': {}", path, e))
+}
+
+fn write(args: &[Value]) -> Result<Value, String> {
+    let path = get_string(&args[0])?;
+    let content = get_string(&args[1])?;
+
+    std::fs
+        ::write(&**path, content.as_bytes())
+        .map(|_| Value::Empty)
+        .map_err(|e| format!("Cannot write file '{}': {}", path, e))
+}
@@ -0,0 +1,26 @@
+use crate::value::{ Value, ValueType };
+
+use super::{ max, min, self_type, to_string, Method };
+
+macro_rules! map_float {
+    ($value:expr, | $float:ident | $body:expr) => {
+        match $value {
+            Value::Float32($float) => Ok(Value::Float32($body)),
+            Value::Float64($float) => Ok(Value::Float64($body)),
+            value => Err(format!("{} is not a float", value.to_value_type().to_type_string())),
+        }
+    };
+}
+
+pub(super) fn get_methods() -> Vec<Method> {
+    vec![
+        Method::new("toString", &[], ValueType::String, to_string),
+        Method::new("abs", &[], self_type(), |receiver, _| map_float!(receiver, |float| float.abs())),
+        Method::new("floor", &[], self_type(), |receiver, _| map_float!(receiver, |float| float.floor())),
+        Method::new("ceil", &[], self_type(), |receiver, _| map_float!(receiver, |float| float.ceil())),
+        Method::new("round", &[], self_type(), |receiver, _| map_float!(receiver, |float| float.round())),
+        Method::new("sqrt", &[], self_type(), |receiver, _| map_float!(receiver, |float| float.sqrt())),
+        Method::new("min", &[("other", self_type())], self_type(), min),
+        Method::new("max", &[("other", self_type())], self_type(), max)
+    ]
+}
@@ -0,0 +1,18 @@
+use crate::{ operations::BinaryOp, value::{ Value, ValueType } };
+
+use super::{ max, min, self_type, to_string, Method };
+
+pub(super) fn get_methods() -> Vec<Method> {
+    vec![
+        Method::new("toBool", &[], ValueType::Bool, to_bool),
+        Method::new("toString", &[], ValueType::String, to_string),
+        Method::new("min", &[("other", self_type())], self_type(), min),
+        Method::new("max", &[("other", self_type())], self_type(), max)
+    ]
+}
+
+fn to_bool(receiver: &Value, _: &[Value]) -> Result<Value, String> {
+    let zero = Value::Int8(0).cast(&receiver.to_value_type())?;
+
+    receiver.compare(&zero, BinaryOp::NotEqual)
+}
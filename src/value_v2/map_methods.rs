@@ -0,0 +1,75 @@
+use crate::value::{ MapKey, Value, ValueType };
+
+use super::{ len, Method };
+
+fn key_type() -> ValueType {
+    ValueType::TypeParam("K".into())
+}
+
+fn value_type() -> ValueType {
+    ValueType::TypeParam("V".into())
+}
+
+pub(super) fn get_methods() -> Vec<Method> {
+    vec![
+        Method::new("len", &[], ValueType::Int32, len),
+        Method::new("contains", &[("key", key_type())], ValueType::Bool, contains),
+        Method::new(
+            "remove",
+            &[("key", key_type())],
+            ValueType::Optional(Box::new(value_type())),
+            remove
+        ).mutating()
+    ]
+}
+
+fn contains(receiver: &Value, args: &[Value]) -> Result<Value, String> {
+    match receiver {
+        Value::Map(entries) => Ok(Value::Bool(entries.borrow().contains_key(&MapKey::new(&args[0])?))),
+        value => Err(format!("{} is not a map", value.to_value_type().to_type_string())),
+    }
+}
+
+fn remove(receiver: &Value, args: &[Value]) -> Result<Value, String> {
+    match receiver {
+        Value::Map(entries) => {
+            Ok(entries.borrow_mut().shift_remove(&MapKey::new(&args[0])?).unwrap_or(Value::None))
+        }
+        value => Err(format!("{} is not a map", value.to_value_type().to_type_string())),
+    }
+}
+
+// The loop can't continue if the map's size has changed since it started
+pub(super) fn has_entry_at(args: &[Value]) -> Result<Value, String> {
+    match (&args[0], &args[1], &args[2]) {
+        (Value::Map(entries), Value::Int32(index), Value::Int32(size)) => {
+            if entries.borrow().len() != *size as usize {
+                return Err("The map changed size while it was iterated over".to_string());
+            }
+
+            Ok(Value::Bool(index < size))
+        }
+        (value, _, _) => Err(format!("{} is not a map", value.to_value_type().to_type_string())),
+    }
+}
+
+pub(super) fn key_at(args: &[Value]) -> Result<Value, String> {
+    entry_at(args).map(|(key, _)| key)
+}
+
+pub(super) fn value_at(args: &[Value]) -> Result<Value, String> {
+    entry_at(args).map(|(_, value)| value)
+}
+
+fn entry_at(args: &[Value]) -> Result<(Value, Value), String> {
+    match (&args[0], &args[1]) {
+        (Value::Map(entries), Value::Int32(index)) => {
+            entries
+                .borrow()
+                .get_index(*index as usize)
+                .map(|(key, value)| (key.get_value().clone(), value.clone()))
+                .ok_or_else(|| format!("Map has no entry at index {}", index))
+        }
+        (value, _) => Err(format!("{} is not a map", value.to_value_type().to_type_string())),
+    }
+}
@@ -1,362 +1,340 @@
+use std::rc::Rc;
+
 use ahash::AHashMap;
-use lazy_static::lazy_static;
 
-use crate::{ ast::stmt::FunctionArgument, operations::{ BinaryOp, UnaryOp } };
+use crate::{ ast::stmt::FunctionArgument, operations::BinaryOp, value::{ Value, ValueType } };
 
-#[derive(Debug, Clone)]
-pub struct ValueHolder {
-    pub value_type: ValueType,
-    pub value: Value,
+mod integer_methods;
+mod float_methods;
+mod string_methods;
+mod map_methods;
+mod builtins;
+
+// The receiver isn't part of the arguments
+#[derive(Debug, Clone, Copy)]
+pub struct NativeMethod {
+    pub name: &'static str,
+    pub function: fn(&Value, &[Value]) -> Result<Value, String>,
 }
 
-impl ValueHolder {
-    pub fn new_bool(bool: bool) -> Self {
-        Self {
-            value: Value::Bool(bool),
-            value_type: ValueType::Bool(&VALUE_TYPE_BOOL),
-        }
+impl NativeMethod {
+    pub fn call(&self, receiver: &Value, args: &[Value]) -> Result<Value, String> {
+        (self.function)(receiver, args)
     }
+}
 
-    pub fn new_int32(i32: i32) -> Self {
-        Self {
-            value: Value::Int32(i32),
-            value_type: ValueType::Int32(&VALUE_TYPE_INT),
-        }
-    }
+#[derive(Debug, Clone, Copy)]
+pub enum MethodImpl {
+    Native(NativeMethod),
+    Function(usize),
 }
 
 #[derive(Debug, Clone)]
-pub enum Value {
-    Empty,
-    Int32(i32),
-    Bool(bool),
-    // Custom {
-    //     name: usize,
-    //     value: Box<Value>,
-    // },
-    // String(String),
-    // Vector(Vec<Value>),
-    // HashMap(AHashMap<String, Value>),
+pub struct Method {
+    // `Self` is the type of the receiver, and `K` and `V` the key and value types of a map
+    pub args: Vec<FunctionArgument>,
+    pub return_type: ValueType,
+    pub implementation: MethodImpl,
+    pub is_self_mutable: bool,
 }
 
-impl Value {
-    pub fn to_type_string(&self) -> String {
-        (
-            match self {
-                Value::Int32(_) => "Int32",
-                Value::Bool(_) => "booli32",
-                Value::Empty => "empty",
-                // Value::Custom { .. } => panic!("String should be a usize if I want to impl Copy"),
-            }
-        ).to_string()
-    }
+impl Method {
+    fn new(
+        name: &'static str,
+        args: &[(&str, ValueType)],
+        return_type: ValueType,
+        function: fn(&Value, &[Value]) -> Result<Value, String>
+    ) -> Self {
+        let args = args
+            .iter()
+            .map(|(name, value_type)| FunctionArgument {
+                name: name.to_string(),
+                value_type: value_type.clone(),
+                is_mutable: false,
+            })
+            .collect();
 
-    pub fn to_string(&self) -> String {
-        match self {
-            Self::Empty => "EMPTY".to_string(),
-            Self::Int32(i32) => i32.to_string(),
-            Self::Bool(bool) => bool.to_string(),
-            // Self::Custom { name, value } => (*value).to_string(),
+        Self {
+            args,
+            return_type,
+            implementation: MethodImpl::Native(NativeMethod { name, function }),
+            is_self_mutable: false,
         }
     }
 
-    pub fn add(&self, other: &Value) -> Result<Self, String> {
-        let op = BinaryOp::Add;
-
-        match (self, other) {
-            (Value::Int32(lhs), Value::Int32(rhs)) => Ok(Value::Int32(lhs + rhs)),
-            _ =>
-                Err(
-                    format!(
-                        "{} is not defined for {} and {}",
-                        op.to_op_string(),
-                        self.to_type_string(),
-                        other.to_type_string()
-                    )
-                ),
-        }
+    pub fn from_function(
+        id: usize,
+        args: Vec<FunctionArgument>,
+        return_type: ValueType,
+        is_self_mutable: bool
+    ) -> Self {
+        Self { args, return_type, implementation: MethodImpl::Function(id), is_self_mutable }
     }
 
-    pub fn mul(&self, other: &Value) -> Result<Self, String> {
-        let op = BinaryOp::Mul;
-
-        match (self, other) {
-            (Value::Int32(lhs), Value::Int32(rhs)) => Ok(Value::Int32(lhs * rhs)),
-            _ =>
-                Err(
-                    format!(
-                        "{} is not defined for {} and {}",
-                        op.to_op_string(),
-                        self.to_type_string(),
-                        other.to_type_string()
-                    )
-                ),
-        }
+    fn mutating(self) -> Self {
+        Self { is_self_mutable: true, ..self }
     }
 
-    pub fn div(&self, other: &Value) -> Result<Self, String> {
-        let op = BinaryOp::Div;
-
-        match (self, other) {
-            (Value::Int32(lhs), Value::Int32(rhs)) => Ok(Value::Int32(lhs / rhs)),
-            _ =>
-                Err(
-                    format!(
-                        "{} is not defined for {} and {}",
-                        op.to_op_string(),
-                        self.to_type_string(),
-                        other.to_type_string()
-                    )
-                ),
-        }
+    pub fn resolve_types(&self, receiver_type: &ValueType) -> (Vec<ValueType>, ValueType) {
+        let (key_type, value_type) = match receiver_type {
+            ValueType::Map(key_type, value_type) => ((**key_type).clone(), (**value_type).clone()),
+            _ => (ValueType::Empty, ValueType::Empty),
+        };
+
+        let type_params = [Rc::from("Self"), Rc::from("K"), Rc::from("V")];
+        let type_args = [receiver_type.clone(), key_type, value_type];
+
+        let args = self.args
+            .iter()
+            .map(|arg| arg.value_type.substitute(&type_params, &type_args))
+            .collect();
+
+        (args, self.return_type.substitute(&type_params, &type_args))
     }
+}
 
-    pub fn sub(&self, other: &Value) -> Result<Self, String> {
-        let op = BinaryOp::Sub;
-
-        match (self, other) {
-            (Value::Int32(lhs), Value::Int32(rhs)) => Ok(Value::Int32(lhs - rhs)),
-            _ =>
-                Err(
-                    format!(
-                        "{} is not defined for {} and {}",
-                        op.to_op_string(),
-                        self.to_type_string(),
-                        other.to_type_string()
-                    )
-                ),
-        }
+#[derive(Debug, Clone, Copy)]
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub function: fn(&[Value]) -> Result<Value, String>,
+    // Calls with constant arguments are evaluated while compiling
+    pub is_pure: bool,
+}
+
+impl NativeFunction {
+    pub fn call(&self, args: &[Value]) -> Result<Value, String> {
+        (self.function)(args)
     }
+}
 
-    pub fn neg(&self) -> Result<Self, String> {
-        let op = UnaryOp::Neg;
+// The condition of `for (key, value) in map`, which fails if the map's size has changed
+pub const MAP_HAS_ENTRY_AT: NativeFunction = NativeFunction {
+    name: "@map_has_entry_at",
+    function: map_methods::has_entry_at,
+    is_pure: false,
+};
+
+// Not a valid identifier, so programs can't call it
+pub const MAP_KEY_AT: NativeFunction = NativeFunction {
+    name: "@map_key_at",
+    function: map_methods::key_at,
+    is_pure: false,
+};
+
+pub const MAP_VALUE_AT: NativeFunction = NativeFunction {
+    name: "@map_value_at",
+    function: map_methods::value_at,
+    is_pure: false,
+};
+
+pub const TO_STRING: NativeFunction = NativeFunction {
+    name: "@to_string",
+    function: |args| Ok(Value::String(args[0].to_string().into())),
+    is_pure: true,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub enum TypeBound {
+    Any,
+    Number,
+    Comparable,
+    Sized,
+}
 
+impl TypeBound {
+    pub fn allows(&self, value_type: &ValueType) -> bool {
         match self {
-            Value::Int32(int32) => Ok(Value::Int32(-int32)),
-            v => Err(format!("{} is not defined for {}", op.to_op_string(), v.to_type_string())),
+            TypeBound::Any => true,
+            TypeBound::Number => value_type.is_numeric(),
+            TypeBound::Comparable => value_type.is_numeric() || value_type == &ValueType::String,
+            TypeBound::Sized => {
+                matches!(value_type, ValueType::Array(_) | ValueType::Map(_, _) | ValueType::String)
+            }
         }
     }
 
-    pub fn not(&self) -> Result<Self, String> {
-        let op = UnaryOp::Truthy;
-
+    pub fn get_description(&self) -> &'static str {
         match self {
-            Value::Bool(bool) => Ok(Value::Bool(!*bool)),
-            Value::Int32(int32) => Ok(Value::Bool(!*int32 != 0)),
-            v => Err(format!("{} is not defined for {}", op.to_op_string(), v.to_type_string())),
-            // Value::Empty => Value::Empty,
+            TypeBound::Any => "any type",
+            TypeBound::Number => "a number",
+            TypeBound::Comparable => "a number or a str",
+            TypeBound::Sized => "an array, a map or a str",
         }
     }
 }
 
-// pub enum BaseValueType {
-//     Int32,
-//     String,
-//     Bool,
-//     Vector,
-//     HashMap,
-// }
-
+// A user defined function with the same name takes precedence
 #[derive(Debug, Clone)]
-pub enum ValueType {
-    Empty,
-    Unkown,
-    Int32(&'static ValueTypeAttrs),
-    Bool(&'static ValueTypeAttrs),
-    // Custom {
-    //     name: String,
-    //     attrs: &'static ValueTypeAttrs,
-    // },
-    // String(&'static ValueTypeAttrs),
-    // Vector(&'static ValueTypeAttrs),
-    // HashMap(&'static ValueTypeAttrs),
+pub struct Builtin {
+    pub type_bound: TypeBound,
+    pub parameters: Vec<ValueType>,
+    pub return_type: ValueType,
+    pub native: NativeFunction,
+    pub mutates_first_arg: bool,
 }
 
-impl ValueType {
-    pub fn new_int32() -> Self {
-        Self::Int32(&VALUE_TYPE_INT)
+impl Builtin {
+    fn new(
+        name: &'static str,
+        type_bound: TypeBound,
+        parameters: &[ValueType],
+        return_type: ValueType,
+        is_pure: bool,
+        function: fn(&[Value]) -> Result<Value, String>
+    ) -> Self {
+        Self {
+            type_bound,
+            parameters: parameters.to_vec(),
+            return_type,
+            native: NativeFunction { name, function, is_pure },
+            mutates_first_arg: false,
+        }
     }
 
-    pub fn new_bool() -> Self {
-        Self::Bool(&VALUE_TYPE_BOOL)
+    fn mutating(self) -> Self {
+        Self { mutates_first_arg: true, ..self }
     }
 
-    pub fn is(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Self::Empty, Self::Empty) => true,
-            (Self::Unkown, Self::Unkown) => true,
-            (Self::Int32(_), Self::Int32(_)) => true,
-            (Self::Bool(_), Self::Bool(_)) => true,
-            _ => false,
-        }
+    pub fn get_name(&self) -> &'static str {
+        self.native.name
     }
 
-    pub fn to_type_string(&self) -> String {
-        (
-            match self {
-                Self::Int32(_) => "i32",
-                Self::Bool(_) => "bool",
-                Self::Empty => "empty",
-                Self::Unkown => "unkown",
-                // Self::Custom { name, .. } => name,
+    // The first argument `T` is inferred from decides it, so the caller orders the arguments
+    pub fn infer_type_arg<'a>(
+        &self,
+        arg_types: impl Iterator<Item = (usize, &'a ValueType)>
+    ) -> Option<ValueType> {
+        let type_params = [Rc::from("T")];
+        let mut inferred = [None];
+
+        for (index, arg_type) in arg_types {
+            if let Some(parameter) = self.parameters.get(index) {
+                parameter.infer_type_args(arg_type, &type_params, &mut inferred);
             }
-        ).to_string()
-    }
-
-    pub fn type_check_binary(&self, other: &ValueType, op: BinaryOp) -> Result<Self, String> {
-        match op {
-            BinaryOp::Add => self.try_add(other),
-            BinaryOp::Mul => self.try_mul(other),
-            BinaryOp::Div => self.try_div(other),
-            BinaryOp::Sub => self.try_sub(other),
         }
-    }
 
-    pub fn type_check_unary(&self, op: UnaryOp) -> Result<Self, String> {
-        match op {
-            UnaryOp::Neg => self.try_neg(),
-            UnaryOp::Truthy => Ok(self.clone()),
-        }
+        inferred[0].take()
     }
 
-    pub fn try_add(&self, other: &Self) -> Result<Self, String> {
-        let op = BinaryOp::Add;
-        match (self, other) {
-            (Self::Int32(_), Self::Int32(_)) => Ok(Self::Int32(&VALUE_TYPE_INT)),
-            _ =>
-                Err(
-                    format!(
-                        "{} is not defined for {} and {}",
-                        op.to_op_string(),
-                        self.to_type_string(),
-                        other.to_type_string()
-                    )
-                ),
-        }
-    }
+    pub fn resolve_types(&self, type_arg: &ValueType) -> (Vec<ValueType>, ValueType) {
+        let type_params = [Rc::from("T")];
+        let type_args = [type_arg.clone()];
 
-    pub fn try_mul(&self, other: &Self) -> Result<Self, String> {
-        let op = BinaryOp::Mul;
-        match (self, other) {
-            (Self::Int32(_), Self::Int32(_)) => Ok(Self::Int32(&VALUE_TYPE_INT)),
-            _ =>
-                Err(
-                    format!(
-                        "{} is not defined for {} and {}",
-                        op.to_op_string(),
-                        self.to_type_string(),
-                        other.to_type_string()
-                    )
-                ),
-        }
+        let parameters = self.parameters
+            .iter()
+            .map(|parameter| parameter.substitute(&type_params, &type_args))
+            .collect();
+
+        (parameters, self.return_type.substitute(&type_params, &type_args))
     }
+}
 
-    pub fn try_sub(&self, other: &Self) -> Result<Self, String> {
-        let op = BinaryOp::Sub;
-        match (self, other) {
-            (Self::Int32(_), Self::Int32(_)) => Ok(Self::Int32(&VALUE_TYPE_INT)),
-            _ =>
-                Err(
-                    format!(
-                        "{} is not defined for {} and {}",
-                        op.to_op_string(),
-                        self.to_type_string(),
-                        other.to_type_string()
-                    )
-                ),
+#[derive(Debug, Clone, Default)]
+pub struct ValueTypeAttrs {
+    pub methods: AHashMap<Rc<str>, Method>,
+}
+
+impl ValueTypeAttrs {
+    fn new(methods: Vec<Method>) -> Self {
+        Self {
+            methods: methods
+                .into_iter()
+                .map(|method| {
+                    let name = match method.implementation {
+                        MethodImpl::Native(native) => native.name,
+                        MethodImpl::Function(_) => unreachable!("Builtin methods are native"),
+                    };
+                    (name.into(), method)
+                })
+                .collect(),
         }
     }
 
-    pub fn try_div(&self, other: &Self) -> Result<Self, String> {
-        let op = BinaryOp::Div;
-        match (self, other) {
-            (Self::Int32(_), Self::Int32(_)) => Ok(Self::Int32(&VALUE_TYPE_INT)),
-            _ =>
-                Err(
-                    format!(
-                        "{} is not defined for {} and {}",
-                        op.to_op_string(),
-                        self.to_type_string(),
-                        other.to_type_string()
-                    )
-                ),
+    // Returns false if the type already has a method with this name, which is kept
+    pub fn insert_method(&mut self, name: &str, method: Method) -> bool {
+        if self.methods.contains_key(name) {
+            return false;
         }
+        self.methods.insert(name.into(), method);
+
+        true
     }
+}
 
-    pub fn try_neg(&self) -> Result<Self, String> {
-        let op = UnaryOp::Neg;
+fn self_type() -> ValueType {
+    ValueType::TypeParam("Self".into())
+}
 
-        match self {
-            Self::Int32(_) => Ok(Self::Int32(&VALUE_TYPE_INT)),
-            v => Err(format!("{} is not defined for {}", op.to_op_string(), v.to_type_string())),
-        }
-    }
+fn to_string(receiver: &Value, _: &[Value]) -> Result<Value, String> {
+    Ok(Value::String(receiver.to_string().into()))
+}
 
-    pub fn try_truthy(&self) -> Result<Self, String> {
-        let op = UnaryOp::Truthy;
+fn len(receiver: &Value, _: &[Value]) -> Result<Value, String> {
+    receiver.len()
+}
 
-        match self {
-            Self::Int32(_) => Ok(Self::Int32(&VALUE_TYPE_INT)),
-            v => Err(format!("{} is not defined for {}", op.to_op_string(), v.to_type_string())),
-        }
+fn min(receiver: &Value, args: &[Value]) -> Result<Value, String> {
+    match receiver.compare(&args[0], BinaryOp::LessEqual)? {
+        Value::Bool(true) => Ok(receiver.clone()),
+        _ => Ok(args[0].clone()),
     }
 }
-#[derive(Debug, Clone)]
-pub struct ValueTypeAttrs {
-    pub operations: ValueTypeOperations,
-    pub methods: AHashMap<&'static str, Method>,
+
+fn max(receiver: &Value, args: &[Value]) -> Result<Value, String> {
+    match receiver.compare(&args[0], BinaryOp::GreaterEqual)? {
+        Value::Bool(true) => Ok(receiver.clone()),
+        _ => Ok(args[0].clone()),
+    }
 }
-#[derive(Debug, Clone)]
-pub struct ValueTypeOperations {
-    pub add: Option<fn(Value, Value) -> Result<Value, String>>,
-    pub sub: Option<fn(Value, Value) -> Result<Value, String>>,
-    pub mul: Option<fn(Value, Value) -> Result<Value, String>>,
-    pub div: Option<fn(Value, Value) -> Result<Value, String>>,
-
-    pub neg: Option<fn(Value) -> Result<Value, String>>,
-    pub not: Option<fn(Value) -> Result<Value, String>>,
+
+thread_local! {
+    static VALUE_TYPE_BOOL: Rc<ValueTypeAttrs> = Rc::new(
+        ValueTypeAttrs::new(vec![Method::new("toString", &[], ValueType::String, to_string)])
+    );
+
+    static VALUE_TYPE_INT: Rc<ValueTypeAttrs> = Rc::new(
+        ValueTypeAttrs::new(integer_methods::get_methods())
+    );
+
+    static VALUE_TYPE_FLOAT: Rc<ValueTypeAttrs> = Rc::new(
+        ValueTypeAttrs::new(float_methods::get_methods())
+    );
+
+    static VALUE_TYPE_STRING: Rc<ValueTypeAttrs> = Rc::new(
+        ValueTypeAttrs::new(string_methods::get_methods())
+    );
+
+    static VALUE_TYPE_ARRAY: Rc<ValueTypeAttrs> = Rc::new(
+        ValueTypeAttrs::new(vec![Method::new("len", &[], ValueType::Int32, len)])
+    );
+
+    static VALUE_TYPE_MAP: Rc<ValueTypeAttrs> = Rc::new(
+        ValueTypeAttrs::new(map_methods::get_methods())
+    );
+
+    static BUILTINS: AHashMap<&'static str, Builtin> = builtins
+        ::get_builtins()
+        .into_iter()
+        .map(|builtin| (builtin.get_name(), builtin))
+        .collect();
 }
-#[derive(Debug, Clone)]
-pub struct Method {
-    pub args: Option<Vec<FunctionArgument>>,
-    pub return_type: ValueType,
+
+pub fn get_builtin(name: &str) -> Option<Builtin> {
+    BUILTINS.with(|builtins| builtins.get(name).cloned())
 }
 
-lazy_static! {
-    pub static ref VALUE_TYPE_BOOL: ValueTypeAttrs = ValueTypeAttrs {
-        operations: ValueTypeOperations {
-            add: None,
-            div: None,
-            mul: None,
-            sub: None,
-
-            neg: None,
-            not: Some(|value| value.not()),
-        },
-        methods: {
-            let methods = AHashMap::new();
-
-            methods
-        },
+pub fn get_type_attrs(value_type: &ValueType) -> Option<Rc<ValueTypeAttrs>> {
+    let attrs = match value_type {
+        ValueType::Bool => &VALUE_TYPE_BOOL,
+        ValueType::String => &VALUE_TYPE_STRING,
+        ValueType::Array(_) => &VALUE_TYPE_ARRAY,
+        ValueType::Map(_, _) => &VALUE_TYPE_MAP,
+        value_type if value_type.is_integer() => &VALUE_TYPE_INT,
+        value_type if value_type.is_float() => &VALUE_TYPE_FLOAT,
+        _ => {
+            return None;
+        }
     };
 
-    pub static ref VALUE_TYPE_INT: ValueTypeAttrs = ValueTypeAttrs {
-        operations: ValueTypeOperations {
-            add: Some(|lhs, rhs| lhs.add(&rhs)),
-            sub: Some(|lhs, rhs| lhs.sub(&rhs)),
-            mul: Some(|lhs, rhs| lhs.mul(&rhs)),
-            div: Some(|lhs, rhs| lhs.div(&rhs)),
-            neg: Some(|value| value.neg()),
-            not: Some(|value| value.not()),
-        },
-        methods: {
-            let mut methods = AHashMap::new();
-            methods.insert("toBool", Method {
-                args: None,
-                return_type: ValueType::Bool(&VALUE_TYPE_BOOL),
-            });
-
-            methods
-        },
-    };
+    Some(attrs.with(Rc::clone))
 }
@@ -0,0 +1,69 @@
+use crate::value::{ Value, ValueType };
+
+use super::{ len, Method };
+
+macro_rules! with_strings {
+    ($value:expr, | $string:ident | $body:expr) => {
+        match $value {
+            Value::String($string) => Ok($body),
+            value => Err(format!("{} is not a string", value.to_value_type().to_type_string())),
+        }
+    };
+    ($value:expr, $arg:expr, | $string:ident, $other:ident | $body:expr) => {
+        match ($value, $arg) {
+            (Value::String($string), Value::String($other)) => Ok($body),
+            (value, arg) =>
+                Err(
+                    format!(
+                        "Expected two strings, but got {} and {}",
+                        value.to_value_type().to_type_string(),
+                        arg.to_value_type().to_type_string()
+                    )
+                ),
+        }
+    };
+}
+
+pub(super) fn get_methods() -> Vec<Method> {
+    vec![
+        Method::new("len", &[], ValueType::Int32, len),
+        Method::new("toUpper", &[], ValueType::String, |receiver, _| {
+            with_strings!(receiver, |string| Value::String(string.to_uppercase().into()))
+        }),
+        Method::new("toLower", &[], ValueType::String, |receiver, _| {
+            with_strings!(receiver, |string| Value::String(string.to_lowercase().into()))
+        }),
+        Method::new("trim", &[], ValueType::String, |receiver, _| {
+            with_strings!(receiver, |string| Value::String(string.trim().into()))
+        }),
+        Method::new("contains", &[("other", ValueType::String)], ValueType::Bool, |receiver, args| {
+            with_strings!(receiver, &args[0], |string, other| Value::Bool(string.contains(&**other)))
+        }),
+        Method::new("startsWith", &[("prefix", ValueType::String)], ValueType::Bool, |receiver, args| {
+            with_strings!(receiver, &args[0], |string, prefix| Value::Bool(string.starts_with(&**prefix)))
+        }),
+        Method::new("endsWith", &[("suffix", ValueType::String)], ValueType::Bool, |receiver, args| {
+            with_strings!(receiver, &args[0], |string, suffix| Value::Bool(string.ends_with(&**suffix)))
+        }),
+        Method::new("repeat", &[("count", ValueType::Int32)], ValueType::String, repeat)
+    ]
+}
+
+fn repeat(receiver: &Value, args: &[Value]) -> Result<Value, String> {
+    match (receiver, &args[0]) {
+        (Value::String(string), Value::Int32(count)) if *count >= 0 => {
+            Ok(Value::String(string.repeat(*count as usize).into()))
+        }
+        (Value::String(_), Value::Int32(count)) => {
+            Err(format!("Cannot repeat a string a negative number of times ({})", count))
+        }
+        (value, arg) =>
+            Err(
+                format!(
+                    "Cannot repeat {} by {}",
+                    value.to_value_type().to_type_string(),
+                    arg.to_value_type().to_type_string()
+                )
+            ),
+    }
+}
@@ -1,7 +1,17 @@
-use super::{ instructions::Instruction, VM };
+use crate::value::Value;
+
+use super::{ instructions::{ Instruction, InstructionSrc }, VM };
 
 impl VM {
     pub(super) fn get_instruction(&self) -> &Instruction {
-        &self.program[self.pc]
+        &self.program.instructions[self.pc]
+    }
+
+    // Borrowed like registers are, so heap values are only cloned when stored
+    pub(super) fn get_src<'a>(&'a self, src: &'a InstructionSrc) -> &'a Value {
+        match src {
+            InstructionSrc::Register(register) => self.get_register(*register),
+            InstructionSrc::Constant(value) => value,
+        }
     }
 }
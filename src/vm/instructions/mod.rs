@@ -1,4 +1,11 @@
-use crate::{ operations::{ BinaryOp, UnaryOp }, value::Value };
+use std::rc::Rc;
+
+use crate::{
+    operations::{ BinaryOp, UnaryOp },
+    parser::token::TokenMetadata,
+    value::{ ClassLayout, EnumLayout, FunctionType, Value, ValueType },
+    value_v2::{ NativeFunction, NativeMethod },
+};
 
 #[derive(Debug, Clone, Copy)]
 pub struct InstructionRegister {
@@ -27,16 +34,88 @@ impl InstructionSrc {
     pub fn dissassemble(&self) -> String {
         match self {
             Self::Register(value) => { format!("{}", value.dissassemble()) }
+            Self::Constant(Value::String(string)) => { format!("{:?}", string) }
             Self::Constant(value) => { format!("{}", value.to_string()) }
         }
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub enum Capture {
+    Local(InstructionRegister),
+    Upvalue(usize),
+}
+
+impl Capture {
+    pub fn dissassemble(&self) -> String {
+        match self {
+            Self::Local(register) => register.dissassemble(),
+            Self::Upvalue(index) => format!("U{}", index),
+        }
+    }
+}
+
+// The source locations point at the code causing a runtime error
+#[derive(Debug, Default)]
+pub struct Bytecode {
+    pub instructions: Vec<Instruction>,
+    locations: Vec<Option<TokenMetadata>>,
+}
+
+impl Bytecode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, instruction: Instruction) {
+        self.push_with_location(instruction, None);
+    }
+
+    pub fn push_with_location(
+        &mut self,
+        instruction: Instruction,
+        token_metadata: Option<TokenMetadata>
+    ) {
+        self.instructions.push(instruction);
+        self.locations.push(token_metadata);
+    }
+
+    pub fn extend(&mut self, other: Bytecode) {
+        self.instructions.extend(other.instructions);
+        self.locations.extend(other.locations);
+    }
+
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    pub fn get_location(&self, index: usize) -> Option<TokenMetadata> {
+        self.locations.get(index).copied().flatten()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Instruction {
     Halt,
     StartScope,
     EndScope,
+    EndScopes {
+        count: usize,
+    },
+    Jump {
+        target: usize,
+    },
+    JumpIfFalse {
+        src: InstructionSrc,
+        target: usize,
+    },
+    Print {
+        src: InstructionSrc,
+    },
     Load {
         reg: InstructionRegister,
         src: InstructionSrc,
@@ -61,10 +140,79 @@ pub enum Instruction {
         src1: InstructionSrc,
         src2: InstructionSrc,
     },
+    Mod {
+        dest: InstructionRegister,
+        src1: InstructionSrc,
+        src2: InstructionSrc,
+    },
+    Pow {
+        dest: InstructionRegister,
+        src1: InstructionSrc,
+        src2: InstructionSrc,
+    },
+    BitAnd {
+        dest: InstructionRegister,
+        src1: InstructionSrc,
+        src2: InstructionSrc,
+    },
+    BitOr {
+        dest: InstructionRegister,
+        src1: InstructionSrc,
+        src2: InstructionSrc,
+    },
+    BitXor {
+        dest: InstructionRegister,
+        src1: InstructionSrc,
+        src2: InstructionSrc,
+    },
+    Shl {
+        dest: InstructionRegister,
+        src1: InstructionSrc,
+        src2: InstructionSrc,
+    },
+    Shr {
+        dest: InstructionRegister,
+        src1: InstructionSrc,
+        src2: InstructionSrc,
+    },
+    Equal {
+        dest: InstructionRegister,
+        src1: InstructionSrc,
+        src2: InstructionSrc,
+    },
+    NotEqual {
+        dest: InstructionRegister,
+        src1: InstructionSrc,
+        src2: InstructionSrc,
+    },
+    Less {
+        dest: InstructionRegister,
+        src1: InstructionSrc,
+        src2: InstructionSrc,
+    },
+    LessEqual {
+        dest: InstructionRegister,
+        src1: InstructionSrc,
+        src2: InstructionSrc,
+    },
+    Greater {
+        dest: InstructionRegister,
+        src1: InstructionSrc,
+        src2: InstructionSrc,
+    },
+    GreaterEqual {
+        dest: InstructionRegister,
+        src1: InstructionSrc,
+        src2: InstructionSrc,
+    },
     Neg {
         dest: InstructionRegister,
         src: InstructionSrc,
     },
+    BitNot {
+        dest: InstructionRegister,
+        src: InstructionSrc,
+    },
     Truthy {
         dest: InstructionRegister,
         src: InstructionSrc,
@@ -77,19 +225,266 @@ pub enum Instruction {
         dest: InstructionRegister,
         src: InstructionSrc,
     },
-    Function {
+    Call {
+        dest: InstructionRegister,
+        function: usize,
+        args: Vec<InstructionSrc>,
+    },
+    CallNative {
+        dest: InstructionRegister,
+        method: NativeMethod,
+        receiver: InstructionSrc,
+        args: Vec<InstructionSrc>,
+    },
+    CallValue {
+        dest: InstructionRegister,
+        callee: InstructionSrc,
+        args: Vec<InstructionSrc>,
+    },
+    Return {
+        src: Option<InstructionSrc>,
+    },
+    NewClosure {
         dest: InstructionRegister,
-        instructions_count: u16,
+        function: usize,
+        function_type: FunctionType,
+        captures: Vec<Capture>,
+    },
+    GetUpvalue {
+        dest: InstructionRegister,
+        index: usize,
+    },
+    SetUpvalue {
+        index: usize,
+        src: InstructionSrc,
+    },
+    NewArray {
+        dest: InstructionRegister,
+        elements: Vec<InstructionSrc>,
+    },
+    NewMap {
+        dest: InstructionRegister,
+        entries: Vec<(InstructionSrc, InstructionSrc)>,
+    },
+    NewTuple {
+        dest: InstructionRegister,
+        elements: Vec<InstructionSrc>,
+    },
+    Index {
+        dest: InstructionRegister,
+        src: InstructionSrc,
+        index: InstructionSrc,
+    },
+    SetIndex {
+        array: InstructionSrc,
+        index: InstructionSrc,
+        src: InstructionSrc,
+    },
+    CallBuiltin {
+        dest: InstructionRegister,
+        function: NativeFunction,
+        args: Vec<InstructionSrc>,
+    },
+    Cast {
+        dest: InstructionRegister,
+        src: InstructionSrc,
+        value_type: ValueType,
+    },
+    NewInstance {
+        dest: InstructionRegister,
+        layout: Rc<ClassLayout>,
+        fields: Vec<InstructionSrc>,
+    },
+    GetField {
+        dest: InstructionRegister,
+        src: InstructionSrc,
+        field: usize,
+    },
+    SetField {
+        object: InstructionSrc,
+        field: usize,
+        src: InstructionSrc,
+    },
+    NewEnum {
+        dest: InstructionRegister,
+        layout: Rc<EnumLayout>,
+        tag: usize,
+        payload: Vec<InstructionSrc>,
+    },
+    GetTag {
+        dest: InstructionRegister,
+        src: InstructionSrc,
+    },
+    GetPayload {
+        dest: InstructionRegister,
+        src: InstructionSrc,
+        index: usize,
     },
 }
 
 impl Instruction {
     pub fn dissassemble(&self) -> String {
         match self {
-            Self::Function { dest, instructions_count } => panic!("DISSASSEMBLE NOT IMPLEMENTED"),
+            Self::Call { dest, function, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.dissassemble())
+                    .collect::<Vec<_>>();
+
+                format!("CALL {} {} ({})", dest.dissassemble(), function, args.join(", "))
+            }
+            Self::CallNative { dest, method, receiver, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.dissassemble())
+                    .collect::<Vec<_>>();
+
+                format!(
+                    "CALLNATIVE {} {} {} ({})",
+                    dest.dissassemble(),
+                    method.name,
+                    receiver.dissassemble(),
+                    args.join(", ")
+                )
+            }
+            Self::CallValue { dest, callee, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.dissassemble())
+                    .collect::<Vec<_>>();
+
+                format!(
+                    "CALLVALUE {} {} ({})",
+                    dest.dissassemble(),
+                    callee.dissassemble(),
+                    args.join(", ")
+                )
+            }
+            Self::NewClosure { dest, function, captures, .. } => {
+                let captures = captures
+                    .iter()
+                    .map(|capture| capture.dissassemble())
+                    .collect::<Vec<_>>();
+
+                format!("NEWCLOSURE {} {} [{}]", dest.dissassemble(), function, captures.join(", "))
+            }
+            Self::GetUpvalue { dest, index } => {
+                format!("GETUPVALUE {} U{}", dest.dissassemble(), index)
+            }
+            Self::SetUpvalue { index, src } => {
+                format!("SETUPVALUE U{} {}", index, src.dissassemble())
+            }
+            Self::Return { src } => {
+                match src {
+                    Some(src) => format!("RETURN {}", src.dissassemble()),
+                    None => "RETURN".to_string(),
+                }
+            }
+            Self::NewArray { dest, elements } => {
+                let elements = elements
+                    .iter()
+                    .map(|element| element.dissassemble())
+                    .collect::<Vec<_>>();
+
+                format!("NEWARRAY {} [{}]", dest.dissassemble(), elements.join(", "))
+            }
+            Self::NewMap { dest, entries } => {
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key.dissassemble(), value.dissassemble()))
+                    .collect::<Vec<_>>();
+
+                format!("NEWMAP {} [{}]", dest.dissassemble(), entries.join(", "))
+            }
+            Self::NewTuple { dest, elements } => {
+                let elements = elements
+                    .iter()
+                    .map(|element| element.dissassemble())
+                    .collect::<Vec<_>>();
+
+                format!("NEWTUPLE {} ({})", dest.dissassemble(), elements.join(", "))
+            }
+            Self::Index { dest, src, index } => {
+                format!(
+                    "INDEX {} {} {}",
+                    dest.dissassemble(),
+                    src.dissassemble(),
+                    index.dissassemble()
+                )
+            }
+            Self::SetIndex { array, index, src } => {
+                format!(
+                    "SETINDEX {} {} {}",
+                    array.dissassemble(),
+                    index.dissassemble(),
+                    src.dissassemble()
+                )
+            }
+            Self::CallBuiltin { dest, function, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.dissassemble())
+                    .collect::<Vec<_>>();
+
+                format!("CALLBUILTIN {} {} ({})", dest.dissassemble(), function.name, args.join(", "))
+            }
+            Self::Cast { dest, src, value_type } => {
+                format!(
+                    "CAST {} {} {}",
+                    dest.dissassemble(),
+                    src.dissassemble(),
+                    value_type.to_type_string()
+                )
+            }
+            Self::NewInstance { dest, layout, fields } => {
+                let fields = fields
+                    .iter()
+                    .map(|field| field.dissassemble())
+                    .collect::<Vec<_>>();
+
+                format!(
+                    "NEWINSTANCE {} {} {{{}}}",
+                    dest.dissassemble(),
+                    layout.name,
+                    fields.join(", ")
+                )
+            }
+            Self::GetField { dest, src, field } => {
+                format!("GETFIELD {} {} {}", dest.dissassemble(), src.dissassemble(), field)
+            }
+            Self::SetField { object, field, src } => {
+                format!("SETFIELD {} {} {}", object.dissassemble(), field, src.dissassemble())
+            }
+            Self::NewEnum { dest, layout, tag, payload } => {
+                let payload = payload
+                    .iter()
+                    .map(|value| value.dissassemble())
+                    .collect::<Vec<_>>();
+
+                format!(
+                    "NEWENUM {} {}.{} ({})",
+                    dest.dissassemble(),
+                    layout.name,
+                    layout.variant_names[*tag],
+                    payload.join(", ")
+                )
+            }
+            Self::GetTag { dest, src } => {
+                format!("GETTAG {} {}", dest.dissassemble(), src.dissassemble())
+            }
+            Self::GetPayload { dest, src, index } => {
+                format!("GETPAYLOAD {} {} {}", dest.dissassemble(), src.dissassemble(), index)
+            }
             Self::Halt => { "HALT".to_string() }
             Self::StartScope => { "STARTSCOPE".to_string() }
             Self::EndScope => { "ENDSCOPE".to_string() }
+            Self::EndScopes { count } => { format!("ENDSCOPES {}", count) }
+            Self::Jump { target } => { format!("JUMP {}", target) }
+            Self::JumpIfFalse { src, target } => {
+                format!("JUMPIFFALSE {} {}", src.dissassemble(), target)
+            }
+
+            Self::Print { src } => { format!("PRINT {}", src.dissassemble()) }
 
             Self::Load { reg, src } => {
                 format!("LOAD {} {}", reg.dissassemble(), src.dissassemble())
@@ -126,9 +521,116 @@ impl Instruction {
                     src2.dissassemble()
                 )
             }
+            Self::Mod { dest, src1, src2 } => {
+                format!(
+                    "MOD {} {} {}",
+                    dest.dissassemble(),
+                    src1.dissassemble(),
+                    src2.dissassemble()
+                )
+            }
+            Self::Pow { dest, src1, src2 } => {
+                format!(
+                    "POW {} {} {}",
+                    dest.dissassemble(),
+                    src1.dissassemble(),
+                    src2.dissassemble()
+                )
+            }
+            Self::BitAnd { dest, src1, src2 } => {
+                format!(
+                    "BITAND {} {} {}",
+                    dest.dissassemble(),
+                    src1.dissassemble(),
+                    src2.dissassemble()
+                )
+            }
+            Self::BitOr { dest, src1, src2 } => {
+                format!(
+                    "BITOR {} {} {}",
+                    dest.dissassemble(),
+                    src1.dissassemble(),
+                    src2.dissassemble()
+                )
+            }
+            Self::BitXor { dest, src1, src2 } => {
+                format!(
+                    "BITXOR {} {} {}",
+                    dest.dissassemble(),
+                    src1.dissassemble(),
+                    src2.dissassemble()
+                )
+            }
+            Self::Shl { dest, src1, src2 } => {
+                format!(
+                    "SHL {} {} {}",
+                    dest.dissassemble(),
+                    src1.dissassemble(),
+                    src2.dissassemble()
+                )
+            }
+            Self::Shr { dest, src1, src2 } => {
+                format!(
+                    "SHR {} {} {}",
+                    dest.dissassemble(),
+                    src1.dissassemble(),
+                    src2.dissassemble()
+                )
+            }
+            Self::Equal { dest, src1, src2 } => {
+                format!(
+                    "EQUAL {} {} {}",
+                    dest.dissassemble(),
+                    src1.dissassemble(),
+                    src2.dissassemble()
+                )
+            }
+            Self::NotEqual { dest, src1, src2 } => {
+                format!(
+                    "NOTEQUAL {} {} {}",
+                    dest.dissassemble(),
+                    src1.dissassemble(),
+                    src2.dissassemble()
+                )
+            }
+            Self::Less { dest, src1, src2 } => {
+                format!(
+                    "LESS {} {} {}",
+                    dest.dissassemble(),
+                    src1.dissassemble(),
+                    src2.dissassemble()
+                )
+            }
+            Self::LessEqual { dest, src1, src2 } => {
+                format!(
+                    "LESSEQUAL {} {} {}",
+                    dest.dissassemble(),
+                    src1.dissassemble(),
+                    src2.dissassemble()
+                )
+            }
+            Self::Greater { dest, src1, src2 } => {
+                format!(
+                    "GREATER {} {} {}",
+                    dest.dissassemble(),
+                    src1.dissassemble(),
+                    src2.dissassemble()
+                )
+            }
+            Self::GreaterEqual { dest, src1, src2 } => {
+                format!(
+                    "GREATEREQUAL {} {} {}",
+                    dest.dissassemble(),
+                    src1.dissassemble(),
+                    src2.dissassemble()
+                )
+            }
             Self::Neg { dest, src } => {
                 format!("NEG {} {}", dest.dissassemble(), src.dissassemble())
             }
+            Self::BitNot { dest, src } => {
+                format!("BITNOT {} {}", dest.dissassemble(), src.dissassemble())
+            }
             Self::Truthy { dest, src } => {
                 format!("TRUTHY {} {}", dest.dissassemble(), src.dissassemble())
             }
@@ -152,6 +654,19 @@ impl Instruction {
             BinaryOp::Sub => Self::Sub { dest, src1, src2 },
             BinaryOp::Mul => Self::Mul { dest, src1, src2 },
             BinaryOp::Div => Self::Div { dest, src1, src2 },
+            BinaryOp::Mod => Self::Mod { dest, src1, src2 },
+            BinaryOp::Pow => Self::Pow { dest, src1, src2 },
+            BinaryOp::BitAnd => Self::BitAnd { dest, src1, src2 },
+            BinaryOp::BitOr => Self::BitOr { dest, src1, src2 },
+            BinaryOp::BitXor => Self::BitXor { dest, src1, src2 },
+            BinaryOp::Shl => Self::Shl { dest, src1, src2 },
+            BinaryOp::Shr => Self::Shr { dest, src1, src2 },
+            BinaryOp::Equal => Self::Equal { dest, src1, src2 },
+            BinaryOp::NotEqual => Self::NotEqual { dest, src1, src2 },
+            BinaryOp::Less => Self::Less { dest, src1, src2 },
+            BinaryOp::LessEqual => Self::LessEqual { dest, src1, src2 },
+            BinaryOp::Greater => Self::Greater { dest, src1, src2 },
+            BinaryOp::GreaterEqual => Self::GreaterEqual { dest, src1, src2 },
         }
     }
 
@@ -167,6 +682,7 @@ impl Instruction {
         match operation {
             UnaryOp::Neg => Self::Neg { dest, src },
             UnaryOp::Truthy => Self::Truthy { dest, src },
+            UnaryOp::BitNot => Self::BitNot { dest, src },
         }
     }
 
@@ -1,9 +1,23 @@
-use crate::{ constants::REGISTERS, value::Value };
+use std::{ cell::RefCell, rc::Rc };
+
+use indexmap::IndexMap;
+
+use crate::{
+    constants::{ MAX_CALL_DEPTH, REGISTERS },
+    operations::BinaryOp,
+    value::{ Closure, MapKey, Upvalue, Value },
+};
 
 pub mod instructions;
+pub mod output;
+pub mod runtime_error;
 mod helper_methods;
 
-use self::instructions::{ InstructionRegister, Instruction, InstructionSrc };
+use self::{
+    instructions::{ Bytecode, Capture, InstructionRegister, Instruction },
+    output::{ OutputSink, StdoutSink },
+    runtime_error::RuntimeError,
+};
 
 pub struct Registers {
     registers: Vec<Vec<Value>>,
@@ -41,6 +55,10 @@ impl Registers {
         self.registers.push(registers);
     }
 
+    pub fn get_scope_depth(&self) -> usize {
+        self.registers.len() - 1
+    }
+
     pub fn end_scope(&mut self) {
         // for i in 0..REGISTERS {
         //     let scope = self.registers.len() - 1;
@@ -59,22 +77,44 @@ impl Registers {
 
 pub struct VMFunction {
     registers: Registers,
-    instructions: Vec<Instruction>,
+    instructions: Rc<Bytecode>,
     ip: usize,
+    upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    result_register: InstructionRegister,
+}
+
+pub struct Program {
+    pub instructions: Bytecode,
+    pub functions: Vec<Bytecode>,
 }
 
 pub struct VM {
     registers: Registers,
-    program: Vec<Instruction>,
+    program: Rc<Bytecode>,
     pc: usize,
+    functions: Vec<Rc<Bytecode>>,
+    call_stack: Vec<VMFunction>,
+    upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    // Shared by closures capturing the same variable
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    output: Box<dyn OutputSink>,
 }
 
 impl VM {
-    pub fn new(program: Vec<Instruction>) -> VM {
+    pub fn new(program: Program) -> VM {
+        Self::with_output(program, Box::new(StdoutSink))
+    }
+
+    pub fn with_output(program: Program, output: Box<dyn OutputSink>) -> VM {
         VM {
             registers: Registers::new(),
-            program,
+            program: Rc::new(program.instructions),
             pc: 0,
+            functions: program.functions.into_iter().map(Rc::new).collect(),
+            call_stack: Vec::new(),
+            upvalues: Vec::new(),
+            open_upvalues: Vec::new(),
+            output,
         }
     }
 
@@ -86,20 +126,214 @@ impl VM {
         self.registers.get_mut(instruction_register.register, instruction_register.scope)
     }
 
+    #[cfg(test)]
+    pub fn _get_register(&self, register: usize) -> &Value {
+        self.registers.get(register, 0)
+    }
+
     fn start_scope(&mut self) {
         self.registers.start_scope();
     }
 
     fn end_scope(&mut self) {
+        self.close_upvalues(self.registers.get_scope_depth());
         self.registers.end_scope();
     }
 
+    fn get_frame(&self) -> usize {
+        self.call_stack.len()
+    }
+
+    fn get_frame_registers_mut(&mut self, frame: usize) -> &mut Registers {
+        match self.call_stack.get_mut(frame) {
+            Some(function) => &mut function.registers,
+            None => &mut self.registers,
+        }
+    }
+
+    fn get_upvalue(&mut self, index: usize) -> Value {
+        let upvalue = self.upvalues[index].clone();
+        let upvalue = upvalue.borrow();
+
+        match &*upvalue {
+            Upvalue::Open { frame, scope, register } => {
+                self.get_frame_registers_mut(*frame).get(*register, *scope).clone()
+            }
+            Upvalue::Closed(value) => value.clone(),
+        }
+    }
+
+    fn set_upvalue(&mut self, index: usize, value: Value) {
+        let upvalue = self.upvalues[index].clone();
+        let mut upvalue = upvalue.borrow_mut();
+
+        match &mut *upvalue {
+            Upvalue::Open { frame, scope, register } => {
+                *self.get_frame_registers_mut(*frame).get_mut(*register, *scope) = value;
+            }
+            Upvalue::Closed(closed_value) => {
+                *closed_value = value;
+            }
+        }
+    }
+
+    fn capture_register(&mut self, instruction_register: InstructionRegister) -> Rc<RefCell<Upvalue>> {
+        let captured = Upvalue::Open {
+            frame: self.get_frame(),
+            scope: instruction_register.scope,
+            register: instruction_register.register,
+        };
+
+        if let Some(upvalue) = self.open_upvalues.iter().find(|upvalue| *upvalue.borrow() == captured) {
+            return upvalue.clone();
+        }
+
+        let upvalue = Rc::new(RefCell::new(captured));
+        self.open_upvalues.push(upvalue.clone());
+        upvalue
+    }
+
+    // Moves captured values out of the registers of the scopes being discarded
+    fn close_upvalues(&mut self, scope_depth: usize) {
+        let current_frame = self.get_frame();
+        let mut i = 0;
+
+        while i < self.open_upvalues.len() {
+            let value = match &*self.open_upvalues[i].borrow() {
+                Upvalue::Open { frame, scope, register } if
+                    *frame == current_frame &&
+                    *scope >= scope_depth
+                => {
+                    self.registers.get(*register, *scope).clone()
+                }
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+
+            *self.open_upvalues.swap_remove(i).borrow_mut() = Upvalue::Closed(value);
+        }
+    }
+
+    fn call_function(
+        &mut self,
+        function: usize,
+        args: Vec<Value>,
+        upvalues: Vec<Rc<RefCell<Upvalue>>>,
+        result_register: InstructionRegister
+    ) -> Result<(), String> {
+        if self.call_stack.len() >= MAX_CALL_DEPTH {
+            return Err(format!("Stack overflow: calls are nested more than {} levels deep", MAX_CALL_DEPTH));
+        }
+
+        // The parameters are the first variables in the function, so they get the first registers
+        let mut registers = Registers::new();
+        for (register, arg) in args.into_iter().enumerate() {
+            *registers.get_mut(register, 0) = arg;
+        }
+
+        let instructions = self.functions[function].clone();
+
+        self.call_stack.push(VMFunction {
+            registers: std::mem::replace(&mut self.registers, registers),
+            instructions: std::mem::replace(&mut self.program, instructions),
+            ip: self.pc,
+            upvalues: std::mem::replace(&mut self.upvalues, upvalues),
+            result_register,
+        });
+        self.pc = 0;
+
+        Ok(())
+    }
+
+    fn return_from_function(&mut self, value: Value) {
+        self.close_upvalues(0);
+
+        let caller = self.call_stack.pop().expect("Cannot return from the main program");
+
+        self.registers = caller.registers;
+        self.program = caller.instructions;
+        self.pc = caller.ip + 1;
+        self.upvalues = caller.upvalues;
+
+        *self.get_register_mut(caller.result_register) = value;
+    }
+
     #[profiler::function_tracker("vm-execution")]
-    pub fn run(&mut self) {
-        while self.pc < self.program.len() {
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        // The tracked body must not return early, so errors come from a separate function
+        self.execute().map_err(|message| {
+            RuntimeError::new(message, self.program.get_location(self.pc))
+        })
+    }
+
+    fn execute(&mut self) -> Result<(), String> {
+        while self.pc < self.program.instructions.len() {
             let instruction = self.get_instruction();
             match instruction {
-                Instruction::Function { dest, instructions_count } => panic!("NOT IMPLEMENTED"),
+                Instruction::Call { dest, function, args } => {
+                    let args = args
+                        .iter()
+                        .map(|arg| self.get_src(arg).clone())
+                        .collect::<Vec<_>>();
+
+                    let (dest, function) = (*dest, *function);
+                    self.call_function(function, args, Vec::new(), dest)?;
+                    continue;
+                }
+                Instruction::CallValue { dest, callee, args } => {
+                    let closure = match self.get_src(callee) {
+                        Value::Function(closure) => closure.clone(),
+                        callee => {
+                            return Err(format!("Cannot call a value of type {}", callee.to_value_type().to_type_string()));
+                        }
+                    };
+
+                    let args = args
+                        .iter()
+                        .map(|arg| self.get_src(arg).clone())
+                        .collect::<Vec<_>>();
+
+                    let dest = *dest;
+                    self.call_function(closure.function, args, closure.upvalues.clone(), dest)?;
+                    continue;
+                }
+                Instruction::NewClosure { dest, function, function_type, captures } => {
+                    let (dest, function, function_type) = (*dest, *function, function_type.clone());
+
+                    let upvalues = captures
+                        .clone()
+                        .into_iter()
+                        .map(|capture| {
+                            match capture {
+                                Capture::Local(register) => self.capture_register(register),
+                                Capture::Upvalue(index) => self.upvalues[index].clone(),
+                            }
+                        })
+                        .collect();
+
+                    let closure = Closure { function, function_type, upvalues };
+                    *self.get_register_mut(dest) = Value::Function(Rc::new(closure));
+                }
+                Instruction::GetUpvalue { dest, index } => {
+                    let (dest, index) = (*dest, *index);
+                    *self.get_register_mut(dest) = self.get_upvalue(index);
+                }
+                Instruction::SetUpvalue { index, src } => {
+                    let index = *index;
+                    let value = self.get_src(src).clone();
+                    self.set_upvalue(index, value);
+                }
+                Instruction::Return { src } => {
+                    let value = match src {
+                        Some(src) => self.get_src(src).clone(),
+                        None => Value::Empty,
+                    };
+
+                    self.return_from_function(value);
+                    continue;
+                }
                 Instruction::Halt => {
                     // #[cfg(debug_assertions)]
                     // {
@@ -123,98 +357,275 @@ impl VM {
                 Instruction::EndScope => {
                     self.end_scope();
                 }
+                Instruction::EndScopes { count } => {
+                    for _ in 0..*count {
+                        self.end_scope();
+                    }
+                }
+                Instruction::Jump { target } => {
+                    self.pc = *target;
+                    continue;
+                }
+                Instruction::JumpIfFalse { src, target } => {
+                    let src = self.get_src(src);
+
+                    if let Value::Bool(false) = src {
+                        self.pc = *target;
+                        continue;
+                    }
+                }
+                Instruction::Print { src } => {
+                    let line = self.get_src(src).to_string();
+
+                    self.output.write_line(&line);
+                }
                 Instruction::Load { reg, src } => {
-                    let src = match src {
-                        InstructionSrc::Register(_) => self.get_register(*reg),
-                        InstructionSrc::Constant(value) => value,
-                    };
+                    let src = self.get_src(src);
 
                     *self.get_register_mut(*reg) = src.clone();
                 }
                 Instruction::Add { dest, src1, src2 } => {
-                    let src1 = match src1 {
-                        InstructionSrc::Register(register) => self.get_register(*register),
-                        InstructionSrc::Constant(value) => value,
-                    };
+                    let src1 = self.get_src(src1);
 
-                    let src2 = match src2 {
-                        InstructionSrc::Register(register) => self.get_register(*register),
-                        InstructionSrc::Constant(value) => value,
-                    };
+                    let src2 = self.get_src(src2);
 
-                    *self.get_register_mut(*dest) = src1.add(&src2).unwrap();
+                    *self.get_register_mut(*dest) = src1.add(&src2)?;
                 }
                 Instruction::Define { dest, src } => {
-                    let src = match src {
-                        InstructionSrc::Register(register) => self.get_register(*register),
-                        InstructionSrc::Constant(value) => value,
-                    };
+                    let src = self.get_src(src);
                     *self.get_register_mut(*dest) = src.clone();
                 }
                 Instruction::Assign { dest, src } => {
-                    let src = match src {
-                        InstructionSrc::Register(register) => self.get_register(*register),
-                        InstructionSrc::Constant(value) => value,
-                    };
+                    let src = self.get_src(src);
                     *self.get_register_mut(*dest) = src.clone();
                 }
                 Instruction::Sub { dest, src1, src2 } => {
-                    let src1 = match src1 {
-                        InstructionSrc::Register(register) => self.get_register(*register),
-                        InstructionSrc::Constant(value) => value,
-                    };
+                    let src1 = self.get_src(src1);
 
-                    let src2 = match src2 {
-                        InstructionSrc::Register(register) => self.get_register(*register),
-                        InstructionSrc::Constant(value) => value,
-                    };
+                    let src2 = self.get_src(src2);
 
-                    *self.get_register_mut(*dest) = src1.sub(&src2).unwrap();
+                    *self.get_register_mut(*dest) = src1.sub(&src2)?;
                 }
                 Instruction::Mul { dest, src1, src2 } => {
-                    let src1 = match src1 {
-                        InstructionSrc::Register(register) => self.get_register(*register),
-                        InstructionSrc::Constant(value) => value,
-                    };
+                    let src1 = self.get_src(src1);
 
-                    let src2 = match src2 {
-                        InstructionSrc::Register(register) => self.get_register(*register),
-                        InstructionSrc::Constant(value) => value,
-                    };
+                    let src2 = self.get_src(src2);
 
-                    *self.get_register_mut(*dest) = src1.mul(&src2).unwrap();
+                    *self.get_register_mut(*dest) = src1.mul(&src2)?;
                 }
                 Instruction::Div { dest, src1, src2 } => {
-                    let src1 = match src1 {
-                        InstructionSrc::Register(register) => self.get_register(*register),
-                        InstructionSrc::Constant(value) => value,
-                    };
+                    let src1 = self.get_src(src1);
 
-                    let src2 = match src2 {
-                        InstructionSrc::Register(register) => self.get_register(*register),
-                        InstructionSrc::Constant(value) => value,
-                    };
+                    let src2 = self.get_src(src2);
+
+                    *self.get_register_mut(*dest) = src1.div(&src2)?;
+                }
+                Instruction::Mod { dest, src1, src2 } => {
+                    let src1 = self.get_src(src1);
+
+                    let src2 = self.get_src(src2);
+
+                    *self.get_register_mut(*dest) = src1.rem(src2)?;
+                }
+                Instruction::Pow { dest, src1, src2 } => {
+                    let src1 = self.get_src(src1);
+
+                    let src2 = self.get_src(src2);
+
+                    *self.get_register_mut(*dest) = src1.pow(src2)?;
+                }
+                Instruction::BitAnd { dest, src1, src2 } => {
+                    let src1 = self.get_src(src1);
+
+                    let src2 = self.get_src(src2);
+
+                    *self.get_register_mut(*dest) = src1.bit_and(src2)?;
+                }
+                Instruction::BitOr { dest, src1, src2 } => {
+                    let src1 = self.get_src(src1);
+
+                    let src2 = self.get_src(src2);
+
+                    *self.get_register_mut(*dest) = src1.bit_or(src2)?;
+                }
+                Instruction::BitXor { dest, src1, src2 } => {
+                    let src1 = self.get_src(src1);
+
+                    let src2 = self.get_src(src2);
 
-                    *self.get_register_mut(*dest) = src1.div(&src2).unwrap();
+                    *self.get_register_mut(*dest) = src1.bit_xor(src2)?;
+                }
+                Instruction::Shl { dest, src1, src2 } => {
+                    let src1 = self.get_src(src1);
+
+                    let src2 = self.get_src(src2);
+
+                    *self.get_register_mut(*dest) = src1.shl(src2)?;
+                }
+                Instruction::Shr { dest, src1, src2 } => {
+                    let src1 = self.get_src(src1);
+
+                    let src2 = self.get_src(src2);
+
+                    *self.get_register_mut(*dest) = src1.shr(src2)?;
+                }
+                Instruction::Equal { dest, src1, src2 } => {
+                    let src1 = self.get_src(src1);
+
+                    let src2 = self.get_src(src2);
+
+                    *self.get_register_mut(*dest) = src1.compare(&src2, BinaryOp::Equal)?;
+                }
+                Instruction::NotEqual { dest, src1, src2 } => {
+                    let src1 = self.get_src(src1);
+
+                    let src2 = self.get_src(src2);
+
+                    *self.get_register_mut(*dest) = src1.compare(&src2, BinaryOp::NotEqual)?;
+                }
+                Instruction::Less { dest, src1, src2 } => {
+                    let src1 = self.get_src(src1);
+
+                    let src2 = self.get_src(src2);
+
+                    *self.get_register_mut(*dest) = src1.compare(&src2, BinaryOp::Less)?;
+                }
+                Instruction::LessEqual { dest, src1, src2 } => {
+                    let src1 = self.get_src(src1);
+
+                    let src2 = self.get_src(src2);
+
+                    *self.get_register_mut(*dest) = src1.compare(&src2, BinaryOp::LessEqual)?;
+                }
+                Instruction::Greater { dest, src1, src2 } => {
+                    let src1 = self.get_src(src1);
+
+                    let src2 = self.get_src(src2);
+
+                    *self.get_register_mut(*dest) = src1.compare(&src2, BinaryOp::Greater)?;
+                }
+                Instruction::GreaterEqual { dest, src1, src2 } => {
+                    let src1 = self.get_src(src1);
+
+                    let src2 = self.get_src(src2);
+
+                    *self.get_register_mut(*dest) = src1.compare(&src2, BinaryOp::GreaterEqual)?;
                 }
                 Instruction::Neg { dest, src } => {
-                    let src = match src {
-                        InstructionSrc::Register(register) => self.get_register(*register),
-                        InstructionSrc::Constant(value) => value,
-                    };
+                    let src = self.get_src(src);
 
-                    *self.get_register_mut(*dest) = src.neg().unwrap();
+                    *self.get_register_mut(*dest) = src.neg()?;
+                }
+                Instruction::BitNot { dest, src } => {
+                    let src = self.get_src(src);
+
+                    *self.get_register_mut(*dest) = src.bit_not()?;
                 }
                 Instruction::Truthy { dest, src } => {
-                    let src = match src {
-                        InstructionSrc::Register(register) => self.get_register(*register),
-                        InstructionSrc::Constant(value) => value,
-                    };
+                    let src = self.get_src(src);
 
                     *self.get_register_mut(*dest) = src.not();
                 }
+                Instruction::NewArray { dest, elements } => {
+                    let elements = elements
+                        .iter()
+                        .map(|element| self.get_src(element).clone())
+                        .collect::<Vec<_>>();
+
+                    *self.get_register_mut(*dest) = Value::new_array(elements);
+                }
+                Instruction::NewMap { dest, entries } => {
+                    let mut map = IndexMap::with_capacity(entries.len());
+                    for (key, value) in entries {
+                        map.insert(MapKey::new(self.get_src(key))?, self.get_src(value).clone());
+                    }
+
+                    *self.get_register_mut(*dest) = Value::new_map(map);
+                }
+                Instruction::NewTuple { dest, elements } => {
+                    let elements = elements
+                        .iter()
+                        .map(|element| self.get_src(element).clone())
+                        .collect::<Vec<_>>();
+
+                    *self.get_register_mut(*dest) = Value::new_tuple(elements);
+                }
+                Instruction::Index { dest, src, index } => {
+                    let src = self.get_src(src);
+
+                    let index = self.get_src(index);
+
+                    *self.get_register_mut(*dest) = src.get_index(index)?;
+                }
+                Instruction::SetIndex { array, index, src } => {
+                    let array = self.get_src(array);
+
+                    let index = self.get_src(index);
+
+                    array.set_index(index, self.get_src(src).clone())?;
+                }
+                Instruction::CallNative { dest, method, receiver, args } => {
+                    let args = args
+                        .iter()
+                        .map(|arg| self.get_src(arg).clone())
+                        .collect::<Vec<_>>();
+
+                    let result = method.call(self.get_src(receiver), &args)?;
+                    *self.get_register_mut(*dest) = result;
+                }
+                Instruction::CallBuiltin { dest, function, args } => {
+                    let args = args
+                        .iter()
+                        .map(|arg| self.get_src(arg).clone())
+                        .collect::<Vec<_>>();
+
+                    *self.get_register_mut(*dest) = function.call(&args)?;
+                }
+                Instruction::Cast { dest, src, value_type } => {
+                    let src = self.get_src(src);
+
+                    *self.get_register_mut(*dest) = src.cast(value_type)?;
+                }
+                Instruction::NewInstance { dest, layout, fields } => {
+                    let fields = fields
+                        .iter()
+                        .map(|field| self.get_src(field).clone())
+                        .collect::<Vec<_>>();
+
+                    *self.get_register_mut(*dest) = Value::new_instance(layout.clone(), fields);
+                }
+                Instruction::GetField { dest, src, field } => {
+                    let src = self.get_src(src);
+
+                    *self.get_register_mut(*dest) = src.get_field(*field)?;
+                }
+                Instruction::SetField { object, field, src } => {
+                    let object = self.get_src(object);
+
+                    object.set_field(*field, self.get_src(src).clone())?;
+                }
+                Instruction::NewEnum { dest, layout, tag, payload } => {
+                    let payload = payload
+                        .iter()
+                        .map(|value| self.get_src(value).clone())
+                        .collect::<Vec<_>>();
+
+                    *self.get_register_mut(*dest) = Value::new_enum(layout.clone(), *tag, payload);
+                }
+                Instruction::GetTag { dest, src } => {
+                    let src = self.get_src(src);
+
+                    *self.get_register_mut(*dest) = src.get_tag()?;
+                }
+                Instruction::GetPayload { dest, src, index } => {
+                    let src = self.get_src(src);
+
+                    *self.get_register_mut(*dest) = src.get_payload(*index)?;
+                }
             }
             self.pc += 1;
         }
+
+        Ok(())
     }
 }
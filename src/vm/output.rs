@@ -0,0 +1,40 @@
+use std::io::Write;
+#[cfg(test)]
+use std::{ cell::RefCell, rc::Rc };
+
+pub trait OutputSink {
+    fn write_line(&mut self, line: &str);
+}
+
+pub struct StdoutSink;
+
+impl OutputSink for StdoutSink {
+    fn write_line(&mut self, line: &str) {
+        writeln!(std::io::stdout(), "{}", line).expect("Failed to write to stdout");
+    }
+}
+
+// Clones share the same lines, so a clone can be handed to the VM
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct CapturedOutput {
+    lines: Rc<RefCell<Vec<String>>>,
+}
+
+#[cfg(test)]
+impl CapturedOutput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_lines(&self) -> Vec<String> {
+        self.lines.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+impl OutputSink for CapturedOutput {
+    fn write_line(&mut self, line: &str) {
+        self.lines.borrow_mut().push(line.to_string());
+    }
+}
@@ -0,0 +1,13 @@
+use crate::parser::token::TokenMetadata;
+
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    pub message: String,
+    pub token_metadata: Option<TokenMetadata>,
+}
+
+impl RuntimeError {
+    pub fn new(message: String, token_metadata: Option<TokenMetadata>) -> Self {
+        Self { message, token_metadata }
+    }
+}